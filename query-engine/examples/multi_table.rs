@@ -2,7 +2,10 @@
 //!
 //! Demonstrates working with multiple tables in the same database.
 
-use query_engine::{Database, col, lit, int_column, varchar_column, Value};
+use query_engine::{
+    Database, JoinType, avg, col, count_star, lit, int_column, partition_by, row_number,
+    scalar_subquery, sum, varchar_column, Value,
+};
 use storage_engine::tuple::Schema;
 
 fn main() -> Result<(), Box<dyn std::error::Error>> {
@@ -132,6 +135,124 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
     }
     println!();
 
+    // Join customers with their orders
+    println!("Customers and Their Orders:");
+    println!("{:-<60}", "");
+    let customer_orders = db.table("customers")?
+        .join(
+            db.table("orders")?,
+            col("customers.customer_id").eq(col("orders.customer_id")),
+            JoinType::Inner,
+        )?
+        .select(&["name", "order_id", "amount", "status"]);
+    println!("{}", customer_orders.explain()?);
+    let rows = customer_orders.collect()?;
+    println!("Found {} orders with their customer:", rows.len());
+    for row in rows.iter() {
+        println!("  {:?}", row.values);
+    }
+    println!();
+
+    // Customers without orders show up with NULL order columns
+    println!("All Customers with Their Orders (left join):");
+    println!("{:-<60}", "");
+    let report = db.table("customers")?
+        .join(
+            db.table("orders")?,
+            col("customers.customer_id").eq(col("orders.customer_id")),
+            JoinType::Left,
+        )?
+        .select(&["name", "order_id"])
+        .collect()?;
+    for row in report.iter() {
+        println!("  {:?}", row.values);
+    }
+    println!();
+
+    // Aggregate the orders of each customer
+    println!("Order Totals per Customer (largest first):");
+    println!("{:-<60}", "");
+    let totals = db.table("orders")?
+        .group_by(&[col("customer_id")])
+        .agg(&[count_star().alias("orders"), sum(col("amount")).alias("total")])?
+        .order_by(&[col("total").desc()])
+        .collect()?;
+    for row in totals.iter() {
+        println!("  {:?}", row.values);
+    }
+    println!();
+
+    // Each customer's largest order
+    println!("Largest Order per Customer:");
+    println!("{:-<60}", "");
+    let largest = db.table("orders")?
+        .with_window(
+            "rn",
+            row_number().over(
+                partition_by(&[col("customer_id")]).order_by(&[col("amount").desc()]),
+            ),
+        )?
+        .filter(col("rn").eq(lit(1)))
+        .select(&["customer_id", "order_id", "amount"])
+        .collect()?;
+    for row in largest.iter() {
+        println!("  {:?}", row.values);
+    }
+    println!();
+
+    // Orders larger than the average of the same customer's orders
+    println!("Orders Above the Customer's Average:");
+    println!("{:-<60}", "");
+    let customer_average = db.table("orders")?
+        .alias("o")
+        .filter(col("o.customer_id").eq(col("orders.customer_id")))
+        .group_by(&[])
+        .agg(&[avg(col("amount"))])?;
+    let above_average = db.table("orders")?
+        .filter(col("amount").gt(scalar_subquery(customer_average)))
+        .select(&["customer_id", "order_id", "amount"])
+        .collect()?;
+    for row in above_average.iter() {
+        println!("  {:?}", row.values);
+    }
+    println!();
+
+    // Customers none of whose orders has completed yet
+    println!("Customers Without a Completed Order:");
+    println!("{:-<60}", "");
+    let waiting = db.table("customers")?
+        .select(&["customer_id"])
+        .except(
+            db.table("orders")?
+                .filter(col("status").eq(query_engine::lit_str("completed")))
+                .select(&["customer_id"]),
+        )?
+        .collect()?;
+    for row in waiting.iter() {
+        println!("  {:?}", row.values);
+    }
+    println!();
+
+    // Completed orders, named once as a common table and joined to their customers
+    println!("Completed Orders by Customer:");
+    println!("{:-<60}", "");
+    let completed = db.table("orders")?
+        .filter(col("status").eq(query_engine::lit_str("completed")))
+        .select(&["order_id", "customer_id", "amount"])
+        .cte("completed")?;
+    let completed_by_customer = db.table("customers")?
+        .join(
+            completed.table(),
+            col("customers.customer_id").eq(col("completed.customer_id")),
+            JoinType::Inner,
+        )?
+        .select(&["name", "order_id", "amount"])
+        .collect()?;
+    for row in completed_by_customer.iter() {
+        println!("  {:?}", row.values);
+    }
+    println!();
+
     // Query high-value orders
     println!("High-Value Orders (> $100):");
     println!("{:-<60}", "");
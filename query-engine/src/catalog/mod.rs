@@ -3,26 +3,115 @@
 //! The catalog keeps track of all tables, their schemas, and associated TableHeap instances.
 //! Metadata is persisted to a binary file (`<db_dir>/catalog`) using binary serialization.
 
+pub mod system;
+pub mod temp;
+
+use crate::diagnostics::closest_match;
 use crate::{QueryError, Result};
-use buffer_pool_manager::api::{make_page_id, BufferPoolManager};
+use buffer_pool_manager::api::{make_page_id, BufferPoolManager, INVALID_PAGE_ID};
 use buffer_pool_manager::disk_manager::DiskManager;
+use buffer_pool_manager::page::SlottedPage;
 use storage_engine::table::TableHeap;
+use temp::{TempTable, TEMP_TABLE_PREFIX};
 use storage_engine::tuple::{Column, Schema, Type};
 use std::collections::HashMap;
-use std::sync::{Arc, RwLock};
+use std::io::Write;
+use std::ops::DerefMut;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, OnceLock, RwLock};
+
+/// Prefix of the data directories of rewritten tables.
+const HEAP_DIR_PREFIX: &str = "rose_heap_";
+
+/// Suffix of the scratch file next to the catalog file, which lists the data directories to
+/// remove on the next open unless a table uses them.
+const SCRATCH_FILE_SUFFIX: &str = ".scratch";
+
+/// Row count of a table whose rows have not been counted yet.
+const UNKNOWN_ROWS: usize = usize::MAX;
 
 /// Information about a table in the database.
-#[derive(Clone)]
+///
+/// Once a table has been dropped or rewritten, its `TableInfo` is retired: the segment files
+/// are removed when the last `Arc` to it is dropped, so scans already reading them finish.
 pub struct TableInfo {
     pub table_id: u32,
     pub name: String,
     pub schema: Schema,
     pub table_heap: Arc<TableHeap>,
+    /// Number of rows, or `UNKNOWN_ROWS` until they are first counted
+    rows: AtomicUsize,
+    /// Set once the table has left the catalog, to remove its segment files on drop
+    retired: OnceLock<Arc<DiskManager>>,
 }
 
 impl TableInfo {
     pub fn new(table_id: u32, name: String, schema: Schema, table_heap: Arc<TableHeap>) -> Self {
-        Self { table_id, name, schema, table_heap }
+        Self {
+            table_id,
+            name,
+            schema,
+            table_heap,
+            rows: AtomicUsize::new(UNKNOWN_ROWS),
+            retired: OnceLock::new(),
+        }
+    }
+
+    /// Marks the table as no longer in the catalog, so that its segment files are removed
+    /// through `disk_manager` once nothing reads it any more.
+    fn retire(&self, disk_manager: Arc<DiskManager>) {
+        let _ = self.retired.set(disk_manager);
+    }
+
+    /// Returns the number of rows in the table.
+    ///
+    /// The rows are counted by walking the table's pages, without decoding any tuples, the
+    /// first time; after that the count is kept up to date by
+    /// [`record_inserts`](Self::record_inserts). Rows inserted straight into the heap while
+    /// it is first counted may be missed, so treat the count as an estimate.
+    pub fn row_count(&self) -> Result<usize> {
+        let rows = self.rows.load(Ordering::Relaxed);
+        if rows != UNKNOWN_ROWS {
+            return Ok(rows);
+        }
+
+        let bpm = self.table_heap.bpm();
+        let mut rows = 0;
+        let mut page_id = self.table_heap.first_page_id();
+        while page_id != INVALID_PAGE_ID {
+            let mut page_guard = bpm.fetch_page(page_id)?;
+            let header = SlottedPage::new(page_guard.deref_mut()).header();
+            rows += header.slot_count as usize;
+            page_id = header.next_page_id;
+        }
+        // Keep a count that inserts have already updated
+        match self
+            .rows
+            .compare_exchange(UNKNOWN_ROWS, rows, Ordering::Relaxed, Ordering::Relaxed)
+        {
+            Ok(_) => Ok(rows),
+            Err(current) => Ok(current),
+        }
+    }
+
+    /// Adds `rows` newly inserted rows to the table's row count, if it has been counted.
+    pub fn record_inserts(&self, rows: usize) {
+        let _ = self
+            .rows
+            .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |current| {
+                (current != UNKNOWN_ROWS).then(|| current + rows)
+            });
+    }
+}
+
+impl Drop for TableInfo {
+    fn drop(&mut self) {
+        // Nothing useful can be done about a failure here; the directory is swept on the
+        // next open instead
+        if let Some(disk_manager) = self.retired.get() {
+            let _ = disk_manager.drop_table(self.table_id);
+        }
     }
 }
 
@@ -33,11 +122,35 @@ impl TableInfo {
 ///
 /// A single global `BufferPoolManager` and `DiskManager` are shared across all tables.
 /// The disk manager routes page I/O to per-table segment files using composite page IDs.
+///
+/// A table's segment files live in a data directory named after the table, until the table
+/// is rewritten: the rewritten heap is stored under `rose_heap_<table_id>` instead, so that it
+/// can be filled while the old directory is still in place. A table created while a
+/// directory of its name exists, e.g. one of a dropped table that is still being read, is
+/// stored under such a directory too.
+///
+/// Directories that may be left behind by a crash, those of temporary tables, of heaps being
+/// filled and of tables that have left the catalog, are listed in a scratch file before
+/// they are created or given up. Opening the catalog removes the listed directories that
+/// no table uses; nothing else is ever removed.
+///
+/// Statements that write to a table hold its write lock (see [`write_table`](Self::write_table)),
+/// so that an insert cannot land in a heap that an update or delete is about to replace.
 pub struct Catalog {
     tables: RwLock<HashMap<String, Arc<TableInfo>>>,
+    /// Write locks of the tables, by name, created on first use
+    write_locks: Mutex<HashMap<String, Arc<Mutex<()>>>>,
+    /// Data directories of rewritten tables, by table id. Guarded by the `tables` lock.
+    data_dirs: RwLock<HashMap<u32, String>>,
     next_table_id: RwLock<u32>,
+    /// Held while a data directory is picked and registered, so two tables never get the same
+    dir_lock: Mutex<()>,
+    /// Held while appending to the scratch file
+    scratch_lock: Mutex<()>,
     bpm: Arc<dyn BufferPoolManager>,
     disk_manager: Arc<DiskManager>,
+    /// The directory holding the catalog file and the tables' data directories
+    db_dir: PathBuf,
     catalog_path: String,
 }
 
@@ -46,18 +159,32 @@ impl Catalog {
     ///
     /// If the catalog file exists, deserializes it and reconstructs each table's `TableHeap`
     /// by registering it with the disk manager and opening the existing segment files.
-    /// If the file does not exist, starts empty (fresh database).
+    /// If the file does not exist, starts empty (fresh database). The data directories
+    /// listed in the scratch file that no table uses are deleted. Fails if a table has the
+    /// name of a system table, which was allowed before system tables existed and would now
+    /// be hidden by it.
     pub fn open(
         bpm: Arc<dyn BufferPoolManager>,
         disk_manager: Arc<DiskManager>,
         catalog_path: String,
     ) -> Result<Self> {
-        let (next_id, tables_map) = if std::path::Path::new(&catalog_path).exists() {
+        let (next_id, tables_map, data_dirs) = if std::path::Path::new(&catalog_path).exists() {
             let bytes = std::fs::read(&catalog_path)?;
-            let (next_id, entries) = deserialize_catalog(&bytes);
+            let (next_id, entries, data_dirs) = deserialize_catalog(&bytes);
+            if let Some(entry) = entries
+                .iter()
+                .find(|entry| system::SystemTable::from_name(&entry.name).is_some())
+            {
+                return Err(QueryError::ExecutionError(format!(
+                    "Cannot open catalog: table '{}' has the name of a system table",
+                    entry.name
+                )));
+            }
+            remove_scratch_dirs(&catalog_path, &entries, &data_dirs)?;
             let mut map: HashMap<String, Arc<TableInfo>> = HashMap::new();
             for entry in entries {
-                disk_manager.register_table(entry.table_id, &entry.name)?;
+                let data_dir = data_dirs.get(&entry.table_id).unwrap_or(&entry.name);
+                disk_manager.register_table(entry.table_id, data_dir)?;
                 // Each table's first page is always local page 1 (local page 0 is reserved).
                 let first_page_id = make_page_id(entry.table_id, 1);
                 let table_heap = Arc::new(TableHeap::open(
@@ -74,22 +201,39 @@ impl Catalog {
                 ));
                 map.insert(entry.name, table_info);
             }
-            (next_id, map)
+            (next_id, map, data_dirs)
         } else {
-            (1, HashMap::new())
+            remove_scratch_dirs(&catalog_path, &[], &HashMap::new())?;
+            (1, HashMap::new(), HashMap::new())
         };
 
+        let db_dir = std::path::Path::new(&catalog_path)
+            .parent()
+            .map(std::path::Path::to_path_buf)
+            .unwrap_or_default();
         Ok(Self {
             tables: RwLock::new(tables_map),
+            write_locks: Mutex::new(HashMap::new()),
+            data_dirs: RwLock::new(data_dirs),
             next_table_id: RwLock::new(next_id),
+            dir_lock: Mutex::new(()),
+            scratch_lock: Mutex::new(()),
             bpm,
             disk_manager,
+            db_dir,
             catalog_path,
         })
     }
 
     /// Creates a new table in the catalog.
     pub fn create_table(&self, name: String, schema: Schema) -> Result<Arc<TableInfo>> {
+        if system::SystemTable::from_name(&name).is_some() {
+            return Err(QueryError::ExecutionError(format!(
+                "Table name '{}' is reserved for a system table",
+                name
+            )));
+        }
+
         let mut tables = self.tables.write().unwrap();
 
         if tables.contains_key(&name) {
@@ -99,12 +243,10 @@ impl Catalog {
             )));
         }
 
-        let mut next_id = self.next_table_id.write().unwrap();
-        let table_id = *next_id;
-        *next_id += 1;
-        drop(next_id);
-
-        self.disk_manager.register_table(table_id, &name)?;
+        let (table_id, data_dir) = self.register_data_dir(Some(&name), HEAP_DIR_PREFIX)?;
+        if data_dir != name {
+            self.data_dirs.write().unwrap().insert(table_id, data_dir);
+        }
         let table_heap = Arc::new(TableHeap::new(self.bpm.clone(), schema.clone(), table_id));
         let table_info = Arc::new(TableInfo::new(table_id, name.clone(), schema, table_heap));
         tables.insert(name, table_info.clone());
@@ -119,7 +261,21 @@ impl Catalog {
         tables
             .get(name)
             .cloned()
-            .ok_or_else(|| QueryError::TableNotFound(name.to_string()))
+            .ok_or_else(|| table_not_found(&tables, name))
+    }
+
+    /// Looks up the current version of `bound`, a table a query was bound to, which may have
+    /// been rewritten since. Fails if the table has been dropped, or replaced by one with
+    /// different columns, which the query's expressions no longer fit.
+    pub fn current_table(&self, bound: &TableInfo) -> Result<Arc<TableInfo>> {
+        let current = self.get_table(&bound.name)?;
+        if !same_columns(&current.schema, &bound.schema) {
+            return Err(QueryError::ExecutionError(format!(
+                "Table '{}' changed since the query was prepared",
+                bound.name
+            )));
+        }
+        Ok(current)
     }
 
     /// Returns all table names in the catalog.
@@ -128,14 +284,185 @@ impl Catalog {
         tables.keys().cloned().collect()
     }
 
-    /// Drops a table from the catalog and removes its data directory.
+    /// Returns every table in the catalog, in no particular order.
+    pub fn tables(&self) -> Vec<Arc<TableInfo>> {
+        let tables = self.tables.read().unwrap();
+        tables.values().cloned().collect()
+    }
+
+    /// Drops a table from the catalog. Its data directory is removed once no scan reads it.
     pub fn drop_table(&self, name: &str) -> Result<()> {
         let mut tables = self.tables.write().unwrap();
         let table_info = tables
             .remove(name)
-            .ok_or_else(|| QueryError::TableNotFound(name.to_string()))?;
-        self.disk_manager.drop_table(table_info.table_id)?;
-        self.save_locked(&tables)?;
+            .ok_or_else(|| table_not_found(&tables, name))?;
+        let data_dir = self.data_dirs.write().unwrap().remove(&table_info.table_id);
+        let saved = self
+            .record_scratch_dir(data_dir.as_deref().unwrap_or(name))
+            .and_then(|()| self.save_locked(&tables));
+        if let Err(e) = saved {
+            if let Some(data_dir) = data_dir {
+                self.data_dirs.write().unwrap().insert(table_info.table_id, data_dir);
+            }
+            tables.insert(name.to_string(), table_info);
+            return Err(e);
+        }
+        table_info.retire(self.disk_manager.clone());
+        Ok(())
+    }
+
+    /// Runs `write` on the [current version](Self::current_table) of `bound` while holding
+    /// the table's write lock, so that statements writing to the same table run one at a time.
+    pub fn write_table<T>(
+        &self,
+        bound: &TableInfo,
+        write: impl FnOnce(Arc<TableInfo>) -> Result<T>,
+    ) -> Result<T> {
+        let lock = self
+            .write_locks
+            .lock()
+            .unwrap()
+            .entry(bound.name.clone())
+            .or_default()
+            .clone();
+        let _writing = lock.lock().unwrap();
+        write(self.current_table(bound)?)
+    }
+
+    /// Replaces the contents of `table_info`, the current version of a table, with the
+    /// tuples `fill` inserts into an empty heap. `fill` returns whether the table changed;
+    /// if not, the new heap is discarded.
+    ///
+    /// The storage engine only appends to a `TableHeap`, so rows are removed or changed by
+    /// rebuilding the heap: a fresh heap, registered under a new table id and data directory,
+    /// is filled and flushed, then swapped into the catalog. The old `TableInfo` is retired
+    /// only once the catalog has been saved, so a failure or crash before then leaves the
+    /// table as it was, and its segment files stay until the scans still reading the old
+    /// heap are done. Call this from [`write_table`](Self::write_table), which keeps other
+    /// writes out while `table_info` is read; if the table has been replaced anyway, e.g.
+    /// dropped and created again, the rewrite fails instead of losing the change. Look the
+    /// table up again to see the new heap.
+    ///
+    /// `fill` returns the number of rows it wrote, which becomes the table's row count, or
+    /// `None` if the table is unchanged and the new heap can be thrown away.
+    pub fn rewrite_table(
+        &self,
+        table_info: &Arc<TableInfo>,
+        fill: impl FnOnce(&TableHeap) -> Result<Option<usize>>,
+    ) -> Result<()> {
+        let (table_id, data_dir) = self.register_data_dir(None, HEAP_DIR_PREFIX)?;
+        let table_heap = Arc::new(TableHeap::new(
+            self.bpm.clone(),
+            table_info.schema.clone(),
+            table_id,
+        ));
+        let filled = fill(&table_heap).and_then(|rows| {
+            if rows.is_some() {
+                self.flush_all_pages()?;
+            }
+            Ok(rows)
+        });
+        let rows = match filled {
+            Ok(Some(rows)) => rows,
+            other => {
+                self.disk_manager.drop_table(table_id)?;
+                return other.map(|_| ());
+            }
+        };
+
+        let mut tables = self.tables.write().unwrap();
+        let replaced = !tables
+            .get(&table_info.name)
+            .is_some_and(|current| Arc::ptr_eq(current, table_info));
+        if replaced {
+            drop(tables);
+            self.disk_manager.drop_table(table_id)?;
+            return Err(QueryError::ExecutionError(format!(
+                "Table '{}' changed while it was being rewritten",
+                table_info.name
+            )));
+        }
+
+        let new_info = Arc::new(TableInfo::new(
+            table_id,
+            table_info.name.clone(),
+            table_info.schema.clone(),
+            table_heap,
+        ));
+        new_info.rows.store(rows, Ordering::Relaxed);
+        tables.insert(table_info.name.clone(), new_info);
+        let old_dir = self.data_dirs.write().unwrap().remove(&table_info.table_id);
+        self.data_dirs.write().unwrap().insert(table_id, data_dir);
+        let saved = self
+            .record_scratch_dir(old_dir.as_deref().unwrap_or(&table_info.name))
+            .and_then(|()| self.save_locked(&tables));
+        if let Err(e) = saved {
+            let mut data_dirs = self.data_dirs.write().unwrap();
+            data_dirs.remove(&table_id);
+            if let Some(old_dir) = old_dir {
+                data_dirs.insert(table_info.table_id, old_dir);
+            }
+            drop(data_dirs);
+            tables.insert(table_info.name.clone(), table_info.clone());
+            self.disk_manager.drop_table(table_id)?;
+            return Err(e);
+        }
+
+        table_info.retire(self.disk_manager.clone());
+        Ok(())
+    }
+
+    /// Creates an empty temporary table for spilling intermediate results.
+    ///
+    /// The table gets its own table id and data directory but is not listed in the catalog.
+    pub fn create_temp_table(&self, schema: Schema) -> Result<TempTable> {
+        let (table_id, name) = self.register_data_dir(None, TEMP_TABLE_PREFIX)?;
+        let stored_schema = temp::storage_schema(&schema);
+        let table_heap = Arc::new(TableHeap::new(
+            self.bpm.clone(),
+            stored_schema.clone(),
+            table_id,
+        ));
+        let table_info = Arc::new(TableInfo::new(table_id, name, stored_schema, table_heap));
+        Ok(TempTable::new(table_info, schema, self.disk_manager.clone()))
+    }
+
+    /// Registers a new table id with the disk manager under a data directory that does not
+    /// exist yet: `preferred` if it is free, or else `<prefix><table_id>`. The directory is
+    /// listed in the scratch file first, so that it is removed on the next open if a crash
+    /// leaves it behind before a table uses it.
+    fn register_data_dir(&self, preferred: Option<&str>, prefix: &str) -> Result<(u32, String)> {
+        let _registering = self.dir_lock.lock().unwrap();
+        let mut preferred = preferred.filter(|name| !self.db_dir.join(name).exists());
+        loop {
+            let mut next_id = self.next_table_id.write().unwrap();
+            let table_id = *next_id;
+            *next_id += 1;
+            drop(next_id);
+
+            let data_dir = match preferred.take() {
+                Some(name) => name.to_string(),
+                None => format!("{}{}", prefix, table_id),
+            };
+            // A user table may have been given the name
+            if self.db_dir.join(&data_dir).exists() {
+                continue;
+            }
+            self.record_scratch_dir(&data_dir)?;
+            self.disk_manager.register_table(table_id, &data_dir)?;
+            return Ok((table_id, data_dir));
+        }
+    }
+
+    /// Appends `data_dir` to the scratch file, to be removed on the next open unless a
+    /// table uses it by then.
+    fn record_scratch_dir(&self, data_dir: &str) -> Result<()> {
+        let _appending = self.scratch_lock.lock().unwrap();
+        let mut file = std::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(format!("{}{}", self.catalog_path, SCRATCH_FILE_SUFFIX))?;
+        writeln!(file, "{}", data_dir)?;
         Ok(())
     }
 
@@ -148,7 +475,7 @@ impl Catalog {
     /// Atomically writes the catalog to disk.
     fn save_locked(&self, tables: &HashMap<String, Arc<TableInfo>>) -> Result<()> {
         let next_id = *self.next_table_id.read().unwrap();
-        let bytes = serialize_catalog(next_id, tables);
+        let bytes = serialize_catalog(next_id, tables, &self.data_dirs.read().unwrap());
         let tmp_path = format!("{}.tmp", self.catalog_path);
         std::fs::write(&tmp_path, &bytes)?;
         std::fs::rename(&tmp_path, &self.catalog_path)?;
@@ -156,6 +483,47 @@ impl Catalog {
     }
 }
 
+/// Deletes the data directories listed in the scratch file next to `catalog_path` that no
+/// restored table uses or is named after: those of temporary tables, of heaps a crash left
+/// half filled, and of dropped or rewritten tables. The scratch file then starts afresh.
+fn remove_scratch_dirs(
+    catalog_path: &str,
+    entries: &[RestoredEntry],
+    data_dirs: &HashMap<u32, String>,
+) -> Result<()> {
+    let scratch_path = format!("{}{}", catalog_path, SCRATCH_FILE_SUFFIX);
+    let listed = match std::fs::read_to_string(&scratch_path) {
+        Ok(listed) => listed,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(()),
+        Err(e) => return Err(e.into()),
+    };
+    let db_dir = std::path::Path::new(catalog_path)
+        .parent()
+        .unwrap_or(std::path::Path::new(""));
+    let in_use = |dir: &str| {
+        entries.iter().any(|entry| {
+            entry.name == dir || data_dirs.get(&entry.table_id).is_some_and(|used| used == dir)
+        })
+    };
+    for dir in listed.lines().filter(|dir| !dir.is_empty() && !in_use(dir)) {
+        let path = db_dir.join(dir);
+        if path.is_dir() {
+            std::fs::remove_dir_all(path)?;
+        }
+    }
+    std::fs::remove_file(&scratch_path)?;
+    Ok(())
+}
+
+/// Builds the error for a missing table, suggesting a similarly named one.
+fn table_not_found(tables: &HashMap<String, Arc<TableInfo>>, name: &str) -> QueryError {
+    QueryError::TableNotFound {
+        name: name.to_string(),
+        suggestion: closest_match(name, tables.keys().map(String::as_str)),
+        location: None,
+    }
+}
+
 // ---------------------------------------------------------------------------
 // Binary serialization
 // ---------------------------------------------------------------------------
@@ -173,8 +541,18 @@ impl Catalog {
 //       [col_name_len] col_name (UTF-8)
 //       [1]          type_tag  (0 = Integer, 1 = Varchar)
 //       [4]          length
-
-fn serialize_catalog(next_id: u32, tables: &HashMap<String, Arc<TableInfo>>) -> Vec<u8> {
+//   Optionally (absent from catalogs written before tables could be relocated):
+//   [4] data_dir_count
+//   For each rewritten table:
+//     [4]            table_id
+//     [4]            data_dir_len
+//     [data_dir_len] data_dir (UTF-8)
+
+fn serialize_catalog(
+    next_id: u32,
+    tables: &HashMap<String, Arc<TableInfo>>,
+    data_dirs: &HashMap<u32, String>,
+) -> Vec<u8> {
     let mut buf = Vec::new();
 
     buf.extend_from_slice(&next_id.to_ne_bytes());
@@ -202,6 +580,13 @@ fn serialize_catalog(next_id: u32, tables: &HashMap<String, Arc<TableInfo>>) ->
         }
     }
 
+    buf.extend_from_slice(&(data_dirs.len() as u32).to_ne_bytes());
+    for (table_id, data_dir) in data_dirs {
+        buf.extend_from_slice(&table_id.to_ne_bytes());
+        buf.extend_from_slice(&(data_dir.len() as u32).to_ne_bytes());
+        buf.extend_from_slice(data_dir.as_bytes());
+    }
+
     buf
 }
 
@@ -211,7 +596,7 @@ struct RestoredEntry {
     schema: Schema,
 }
 
-fn deserialize_catalog(bytes: &[u8]) -> (u32, Vec<RestoredEntry>) {
+fn deserialize_catalog(bytes: &[u8]) -> (u32, Vec<RestoredEntry>, HashMap<u32, String>) {
     let mut offset = 0;
 
     let next_id = u32::from_ne_bytes(bytes[offset..offset + 4].try_into().unwrap());
@@ -257,14 +642,30 @@ fn deserialize_catalog(bytes: &[u8]) -> (u32, Vec<RestoredEntry>) {
         entries.push(RestoredEntry { table_id, name, schema: Schema { columns } });
     }
 
-    (next_id, entries)
+    let mut data_dirs = HashMap::new();
+    if offset < bytes.len() {
+        let dir_count = u32::from_ne_bytes(bytes[offset..offset + 4].try_into().unwrap()) as usize;
+        offset += 4;
+        for _ in 0..dir_count {
+            let table_id = u32::from_ne_bytes(bytes[offset..offset + 4].try_into().unwrap());
+            offset += 4;
+            let dir_len = u32::from_ne_bytes(bytes[offset..offset + 4].try_into().unwrap()) as usize;
+            offset += 4;
+            let data_dir = String::from_utf8(bytes[offset..offset + dir_len].to_vec()).unwrap();
+            offset += dir_len;
+            data_dirs.insert(table_id, data_dir);
+        }
+    }
+
+    (next_id, entries, data_dirs)
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::executor::{Executor, SeqScanExecutor};
     use buffer_pool_manager::concurrent::ConcurrentBufferPoolManager;
-    use storage_engine::tuple::{Column, Type};
+    use storage_engine::tuple::{Column, Tuple, Type, Value};
     use std::path::Path;
 
     fn make_catalog(dir: &str) -> (Arc<DiskManager>, Catalog) {
@@ -309,7 +710,108 @@ mod tests {
         let (_dm, catalog) = make_catalog(dir);
 
         let result = catalog.get_table("nonexistent");
-        assert!(matches!(result, Err(QueryError::TableNotFound(_))));
+        assert!(matches!(result, Err(QueryError::TableNotFound { .. })));
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_catalog_rewrite_rejects_replaced_table() {
+        let dir = "test_catalog_rewrite_dir";
+        let _ = std::fs::remove_dir_all(dir);
+        let (_dm, catalog) = make_catalog(dir);
+
+        let schema = Schema { columns: vec![crate::int_column("id")] };
+        let scanned = catalog.create_table("users".to_string(), schema.clone()).unwrap();
+        catalog.drop_table("users").unwrap();
+        let current = catalog.create_table("users".to_string(), schema).unwrap();
+
+        // Rewriting the dropped version must not clobber the new table
+        let result = catalog.rewrite_table(&scanned, |_| Ok(Some(0)));
+        assert!(result.is_err());
+        assert!(Arc::ptr_eq(&catalog.get_table("users").unwrap(), &current));
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_catalog_dropped_table_outlives_readers() {
+        let dir = "test_catalog_drop_dir";
+        let _ = std::fs::remove_dir_all(dir);
+        let (_dm, catalog) = make_catalog(dir);
+
+        let schema = Schema { columns: vec![crate::int_column("id")] };
+        let users = catalog.create_table("users".to_string(), schema.clone()).unwrap();
+        users.table_heap.insert_tuple(&Tuple { values: vec![Value::Integer(1)] });
+        catalog.drop_table("users").unwrap();
+
+        // A scan of the dropped table still reads its rows
+        let mut scan = SeqScanExecutor::new(users.clone());
+        scan.init().unwrap();
+        assert!(scan.next().unwrap().is_some());
+        drop(scan);
+
+        // The name can be reused while the old directory is still held
+        let recreated = catalog.create_table("users".to_string(), schema).unwrap();
+        assert!(Path::new(dir).join("users").exists());
+        drop(users);
+        assert!(!Path::new(dir).join("users").exists());
+        assert_eq!(recreated.row_count().unwrap(), 0);
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_catalog_open_removes_only_scratch_dirs() {
+        let dir = "test_catalog_scratch_dir";
+        let _ = std::fs::remove_dir_all(dir);
+        {
+            let (_dm, catalog) = make_catalog(dir);
+            let schema = Schema { columns: vec![crate::int_column("id")] };
+            // Left behind as if the process had crashed
+            std::mem::forget(catalog.create_temp_table(schema).unwrap());
+        }
+        // Named like a temporary table's directory, but not created as one
+        std::fs::create_dir_all(Path::new(dir).join("rose_temp_old")).unwrap();
+        let dirs = || {
+            std::fs::read_dir(dir)
+                .unwrap()
+                .filter(|entry| entry.as_ref().unwrap().path().is_dir())
+                .count()
+        };
+        assert_eq!(dirs(), 2);
+
+        let (_dm, _catalog) = make_catalog(dir);
+        assert_eq!(dirs(), 1);
+        assert!(Path::new(dir).join("rose_temp_old").exists());
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_catalog_rejects_system_table_names() {
+        let dir = "test_catalog_system_names_dir";
+        let _ = std::fs::remove_dir_all(dir);
+        let schema = Schema { columns: vec![crate::int_column("id")] };
+        {
+            let (_dm, catalog) = make_catalog(dir);
+            assert!(catalog.create_table("rose_tables".to_string(), schema.clone()).is_err());
+            // Other names with the prefix are ordinary tables
+            catalog.create_table("rose_tablex".to_string(), schema).unwrap();
+        }
+
+        // A catalog written before system tables existed may hold one of their names
+        let catalog_path = Path::new(dir).join("catalog");
+        let bytes = std::fs::read(&catalog_path).unwrap();
+        let at = bytes.windows(11).position(|name| name == b"rose_tablex").unwrap();
+        let mut renamed = bytes.clone();
+        renamed[at..at + 11].copy_from_slice(b"rose_tables");
+        std::fs::write(&catalog_path, renamed).unwrap();
+        let disk_manager = Arc::new(DiskManager::new(Path::new(dir), false).unwrap());
+        let bpm: Arc<dyn BufferPoolManager> =
+            Arc::new(ConcurrentBufferPoolManager::new(10, disk_manager.clone()));
+        let path = catalog_path.to_string_lossy().into_owned();
+        assert!(Catalog::open(bpm, disk_manager, path).is_err());
 
         std::fs::remove_dir_all(dir).unwrap();
     }
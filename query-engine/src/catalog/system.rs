@@ -0,0 +1,96 @@
+//! Read-only system tables that expose the catalog through ordinary queries.
+//!
+//! - `rose_tables(table_id, name)`: one row per user table
+//! - `rose_columns(table_name, column_name, type, length, ordinal)`: one row per column,
+//!   with `ordinal` counting from 1
+//!
+//! Rows are generated from the in-memory catalog each time a scan starts, so they always
+//! reflect the current set of tables. System tables do not list themselves.
+
+use super::Catalog;
+use crate::{int_column, varchar_column};
+use storage_engine::tuple::{Schema, Type, Value};
+
+/// Maximum length reported for name columns.
+const NAME_LENGTH: u32 = 255;
+
+/// A virtual table backed by the catalog.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SystemTable {
+    /// `rose_tables`
+    Tables,
+    /// `rose_columns`
+    Columns,
+}
+
+impl SystemTable {
+    /// Every system table.
+    pub const ALL: [SystemTable; 2] = [SystemTable::Tables, SystemTable::Columns];
+
+    /// Looks up a system table by name.
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|table| table.name() == name)
+    }
+
+    /// Returns the table's name.
+    pub fn name(&self) -> &'static str {
+        match self {
+            SystemTable::Tables => "rose_tables",
+            SystemTable::Columns => "rose_columns",
+        }
+    }
+
+    /// Returns the table's schema.
+    pub fn schema(&self) -> Schema {
+        let columns = match self {
+            SystemTable::Tables => vec![
+                int_column("table_id"),
+                varchar_column("name", NAME_LENGTH),
+            ],
+            SystemTable::Columns => vec![
+                varchar_column("table_name", NAME_LENGTH),
+                varchar_column("column_name", NAME_LENGTH),
+                varchar_column("type", 16),
+                int_column("length"),
+                int_column("ordinal"),
+            ],
+        };
+        Schema { columns }
+    }
+
+    /// Builds the table's current rows from the catalog.
+    pub fn rows(&self, catalog: &Catalog) -> Vec<Vec<Value>> {
+        let mut tables = catalog.tables();
+        tables.sort_by(|a, b| a.name.cmp(&b.name));
+
+        match self {
+            SystemTable::Tables => tables
+                .iter()
+                .map(|table| {
+                    vec![
+                        Value::Integer(table.table_id as i32),
+                        Value::Varchar(table.name.clone()),
+                    ]
+                })
+                .collect(),
+            SystemTable::Columns => tables
+                .iter()
+                .flat_map(|table| {
+                    table.schema.columns.iter().enumerate().map(|(i, column)| {
+                        let type_name = match column.column_type {
+                            Type::Integer => "INTEGER",
+                            Type::Varchar => "VARCHAR",
+                        };
+                        vec![
+                            Value::Varchar(table.name.clone()),
+                            Value::Varchar(column.name.clone()),
+                            Value::Varchar(type_name.to_string()),
+                            Value::Integer(column.length as i32),
+                            Value::Integer(i as i32 + 1),
+                        ]
+                    })
+                })
+                .collect(),
+        }
+    }
+}
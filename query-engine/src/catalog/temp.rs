@@ -0,0 +1,210 @@
+//! Scratch tables for operators whose working set does not fit in memory.
+//!
+//! A temporary table is an ordinary `TableHeap` in the shared buffer pool, so its pages are
+//! cached and evicted like any other. It never appears in the catalog, and its segment
+//! files are removed as soon as the operator that created it drops it. Leftovers from a
+//! crash are cleaned up the next time the database is opened.
+//!
+//! The storage engine cannot store NULL, so each stored tuple starts with bitmask columns
+//! marking which values are NULL, and a NULL is written as a placeholder of its column's
+//! type.
+
+use super::TableInfo;
+use crate::executor::{Executor, SeqScanExecutor};
+use crate::types::{Row, Value};
+use crate::{int_column, QueryError, Result};
+use buffer_pool_manager::disk_manager::DiskManager;
+use std::sync::Arc;
+use storage_engine::tuple::{Schema, Tuple, Type, Value as StorageValue};
+
+/// Prefix of the data directories of temporary tables. A name a user table's directory
+/// already has is skipped.
+pub const TEMP_TABLE_PREFIX: &str = "rose_temp_";
+
+/// Number of columns whose NULL flags fit in one bitmask column.
+const FLAGS_PER_MASK: usize = 32;
+
+/// Returns the number of bitmask columns needed for `columns` columns.
+fn mask_columns(columns: usize) -> usize {
+    columns.div_ceil(FLAGS_PER_MASK)
+}
+
+/// Returns the schema tuples are stored with: the bitmask columns, then `schema`'s columns.
+pub(super) fn storage_schema(schema: &Schema) -> Schema {
+    let masks = (0..mask_columns(schema.columns.len())).map(|i| int_column(&format!("nulls{}", i)));
+    Schema {
+        columns: masks.chain(schema.columns.iter().cloned()).collect(),
+    }
+}
+
+/// An append-only table holding intermediate rows, deleted on drop.
+pub struct TempTable {
+    /// The underlying heap, whose schema includes the bitmask columns
+    info: Arc<TableInfo>,
+    schema: Schema,
+    disk_manager: Arc<DiskManager>,
+    rows: usize,
+}
+
+impl TempTable {
+    pub(super) fn new(
+        info: Arc<TableInfo>,
+        schema: Schema,
+        disk_manager: Arc<DiskManager>,
+    ) -> Self {
+        Self {
+            info,
+            schema,
+            disk_manager,
+            rows: 0,
+        }
+    }
+
+    /// Appends a row.
+    pub fn insert(&mut self, row: &Row) -> Result<()> {
+        let mut masks = vec![0u32; mask_columns(self.schema.columns.len())];
+        let mut values = Vec::with_capacity(self.info.schema.columns.len());
+        for (index, (value, column)) in row.values.iter().zip(&self.schema.columns).enumerate() {
+            let stored = match (value.to_storage(), &column.column_type) {
+                (Some(stored), _) => stored,
+                (None, column_type) => {
+                    masks[index / FLAGS_PER_MASK] |= 1 << (index % FLAGS_PER_MASK);
+                    match column_type {
+                        Type::Integer => StorageValue::Integer(0),
+                        Type::Varchar => StorageValue::Varchar(String::new()),
+                    }
+                }
+            };
+            values.push(stored);
+        }
+        let masks = masks
+            .into_iter()
+            .map(|mask| StorageValue::Integer(mask as i32));
+        let tuple = Tuple {
+            values: masks.chain(values).collect(),
+        };
+
+        self.info.table_heap.insert_tuple(&tuple).ok_or_else(|| {
+            QueryError::ExecutionError(format!(
+                "Failed to write to temporary table '{}'",
+                self.info.name
+            ))
+        })?;
+        self.rows += 1;
+        Ok(())
+    }
+
+    /// Returns the number of rows written so far.
+    pub fn len(&self) -> usize {
+        self.rows
+    }
+
+    /// Returns true if nothing has been written.
+    pub fn is_empty(&self) -> bool {
+        self.rows == 0
+    }
+
+    /// Returns the schema of the stored rows.
+    pub fn schema(&self) -> &Schema {
+        &self.schema
+    }
+
+    /// Returns an executor reading the rows back in insertion order.
+    ///
+    /// The scan must not outlive this table.
+    pub fn scan(&self) -> TempTableScan {
+        TempTableScan {
+            scan: SeqScanExecutor::new(self.info.clone()),
+            schema: self.schema.clone(),
+        }
+    }
+}
+
+impl Drop for TempTable {
+    fn drop(&mut self) {
+        // Nothing useful can be done about a failure here; the directory is swept on the
+        // next open instead
+        let _ = self.disk_manager.drop_table(self.info.table_id);
+    }
+}
+
+/// Reads the rows of a [`TempTable`], restoring their NULLs.
+pub struct TempTableScan {
+    scan: SeqScanExecutor,
+    schema: Schema,
+}
+
+impl Executor for TempTableScan {
+    fn schema(&self) -> &Schema {
+        &self.schema
+    }
+
+    fn init(&mut self) -> Result<()> {
+        self.scan.init()
+    }
+
+    fn next(&mut self) -> Result<Option<Row>> {
+        let Some(stored) = self.scan.next()? else {
+            return Ok(None);
+        };
+        let (masks, values) = stored
+            .values
+            .split_at(mask_columns(self.schema.columns.len()));
+        let values = values
+            .iter()
+            .enumerate()
+            .map(|(index, value)| {
+                let mask = match masks[index / FLAGS_PER_MASK] {
+                    Value::Integer(mask) => mask as u32,
+                    _ => 0,
+                };
+                if mask & (1 << (index % FLAGS_PER_MASK)) != 0 {
+                    Value::Null
+                } else {
+                    value.clone()
+                }
+            })
+            .collect();
+        Ok(Some(Row::new(values)))
+    }
+
+    fn describe(&self) -> String {
+        self.scan.describe()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::executor::tests::collect_results;
+    use crate::{varchar_column, Database};
+
+    #[test]
+    fn test_temp_table_round_trips_nulls() {
+        let path = "test_temp_table_dir";
+        let _ = std::fs::remove_dir_all(path);
+        let db = Database::open(path).unwrap();
+
+        // Enough columns to need a second bitmask column
+        let mut columns: Vec<_> = (0..40).map(|i| int_column(&format!("c{}", i))).collect();
+        columns.push(varchar_column("name", 10));
+        let mut table = db.catalog().create_temp_table(Schema { columns }).unwrap();
+
+        let mut values: Vec<Value> = (0..40).map(Value::Integer).collect();
+        values.push(Value::Varchar("x".to_string()));
+        let full = Row::new(values.clone());
+        values[3] = Value::Null;
+        values[35] = Value::Null;
+        values[40] = Value::Null;
+        let sparse = Row::new(values);
+        table.insert(&full).unwrap();
+        table.insert(&sparse).unwrap();
+
+        let mut scan = table.scan();
+        assert_eq!(scan.schema().columns.len(), 41);
+        assert_eq!(collect_results(&mut scan).unwrap(), vec![full, sparse]);
+
+        drop(table);
+        std::fs::remove_dir_all(path).unwrap();
+    }
+}
@@ -1,14 +1,20 @@
 //! Database struct - the main entry point for query execution.
 
+use crate::catalog::system::SystemTable;
 use crate::catalog::Catalog;
 use crate::dataframe::DataFrame;
-use crate::Result;
+use crate::logical_plan::LogicalPlan;
+use crate::prepared::PreparedQuery;
+use crate::sql::ast::Statement;
+use crate::sql::StatementResult;
+use crate::types::{Row, Value};
+use crate::{QueryError, Result, ScriptError};
 use buffer_pool_manager::api::BufferPoolManager;
 use buffer_pool_manager::concurrent::ConcurrentBufferPoolManager;
 use buffer_pool_manager::disk_manager::DiskManager;
 use std::path::Path;
 use std::sync::Arc;
-use storage_engine::tuple::Schema;
+use storage_engine::tuple::{Schema, Type};
 
 /// The main database interface.
 ///
@@ -35,6 +41,12 @@ impl Database {
         Ok(Self { catalog })
     }
 
+    /// Returns the catalog shared by every DataFrame of this database.
+    #[cfg(test)]
+    pub(crate) fn catalog(&self) -> &Arc<Catalog> {
+        &self.catalog
+    }
+
     /// Creates a new table in the database.
     pub fn create_table(&self, name: &str, schema: Schema) -> Result<()> {
         self.catalog.create_table(name.to_string(), schema)?;
@@ -43,8 +55,126 @@ impl Database {
 
     /// Returns a DataFrame for querying the specified table.
     pub fn table(&self, name: &str) -> Result<DataFrame> {
-        let table_info = self.catalog.get_table(name)?;
-        Ok(DataFrame::new(table_info, None, None, None))
+        let plan = match SystemTable::from_name(name) {
+            Some(table) => LogicalPlan::SystemScan(table, table.schema()),
+            None => LogicalPlan::Scan(self.catalog.get_table(name)?),
+        };
+        Ok(DataFrame::new(self.catalog.clone(), plan))
+    }
+
+    /// Parses and runs a SQL statement, collecting all result rows.
+    ///
+    /// Queries are lowered to the equivalent DataFrame calls, so
+    /// `db.sql("SELECT name FROM users WHERE age > 25 LIMIT 10")` runs the same plan as
+    /// `db.table("users")?.filter(...).select(&["name"]).limit(10)`.
+    /// Statements that produce no rows return an empty vector.
+    pub fn sql(&self, sql: &str) -> Result<Vec<Row>> {
+        match self.execute(sql)? {
+            StatementResult::Query { rows, .. } => Ok(rows),
+            _ => Ok(Vec::new()),
+        }
+    }
+
+    /// Parses and runs a SQL statement, reporting what it did.
+    pub fn execute(&self, sql: &str) -> Result<StatementResult> {
+        self.execute_with(sql, &[])
+    }
+
+    /// Parses and runs a SQL statement with `args` as the values of `$1`, `$2`, ...
+    ///
+    /// Arguments are substituted as values after parsing, so they never need quoting:
+    /// ```ignore
+    /// db.execute_with("SELECT * FROM users WHERE name = $1", &[Value::Varchar(name)])?;
+    /// ```
+    pub fn execute_with(&self, sql: &str, args: &[Value]) -> Result<StatementResult> {
+        let mut statement = crate::sql::parse(sql)?;
+        statement.bind_parameters(args)?;
+        match statement {
+            Statement::Select(select) => {
+                let (schema, rows) =
+                    crate::sql::plan_select(self, sql, &select)?.collect_with_schema()?;
+                Ok(StatementResult::Query { schema, rows })
+            }
+            Statement::CreateTable(create) => {
+                let schema = crate::sql::plan_create_table(&create)?;
+                if !(create.if_not_exists && self.catalog.get_table(&create.name).is_ok()) {
+                    self.create_table(&create.name, schema)?;
+                }
+                Ok(StatementResult::CreateTable)
+            }
+            Statement::DropTable { name, if_exists } => match self.drop_table(&name) {
+                Err(QueryError::TableNotFound { .. }) if if_exists => Ok(StatementResult::DropTable),
+                result => result.map(|_| StatementResult::DropTable),
+            },
+            Statement::Insert(insert) => {
+                crate::sql::execute_insert(self, sql, &insert).map(StatementResult::Insert)
+            }
+            Statement::Update(update) => {
+                crate::sql::execute_update(self, sql, &update).map(StatementResult::Update)
+            }
+            Statement::Delete { table, selection } => {
+                crate::sql::execute_delete(self, sql, &table, selection.as_ref())
+                    .map(StatementResult::Delete)
+            }
+        }
+    }
+
+    /// Runs a script of `;`-separated SQL statements in order, returning each statement's result.
+    ///
+    /// Stops at the first statement that fails and reports where it is in the script.
+    /// Statements before it stay applied. Like [`execute`](Self::execute), this does not flush;
+    /// call [`flush`](Self::flush) to make the changes durable.
+    pub fn execute_script(
+        &self,
+        script: &str,
+    ) -> std::result::Result<Vec<StatementResult>, ScriptError> {
+        let mut results = Vec::new();
+        for (index, (offset, sql)) in crate::sql::split_statements(script).into_iter().enumerate() {
+            match self.execute(sql) {
+                Ok(result) => results.push(result),
+                Err(error) => {
+                    let (line, _) = crate::sql::lexer::line_col(script, offset);
+                    return Err(ScriptError { index, line, error });
+                }
+            }
+        }
+        Ok(results)
+    }
+
+    /// Binds and plans a SQL query once so it can be run many times with different arguments.
+    ///
+    /// Only `SELECT` statements can be prepared; use [`execute_with`](Self::execute_with)
+    /// to pass arguments to other statements.
+    pub fn prepare(&self, sql: &str) -> Result<PreparedQuery> {
+        match crate::sql::parse(sql)? {
+            Statement::Select(select) => crate::sql::plan_select(self, sql, &select)?.prepare(),
+            _ => Err(QueryError::ExecutionError(
+                "Only SELECT statements can be prepared".to_string(),
+            )),
+        }
+    }
+
+    /// Infers the type of each `$n` parameter in a SQL statement from the column or literal
+    /// it is compared with, assigned to or inserted into. Untyped parameters are `None`.
+    pub fn parameter_types(&self, sql: &str) -> Result<Vec<Option<Type>>> {
+        crate::sql::parameter_types(self, &crate::sql::parse(sql)?)
+    }
+
+    /// Returns the columns a SQL statement would produce, without running it.
+    ///
+    /// Statements that produce no rows return `None`.
+    pub fn describe(&self, sql: &str) -> Result<Option<Schema>> {
+        match crate::sql::parse(sql)? {
+            Statement::Select(select) => {
+                Ok(Some(crate::sql::plan_select(self, sql, &select)?.schema()?))
+            }
+            _ => Ok(None),
+        }
+    }
+
+    /// Returns the schema of the specified table.
+    pub fn table_schema(&self, name: &str) -> Result<Schema> {
+        Ok(self.table(name)?.input_schema().clone())
     }
 
     /// Lists all tables in the database.
@@ -66,7 +196,9 @@ impl Database {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use storage_engine::tuple::{Tuple, Value};
+    use crate::expression::{col, lit_str};
+    use crate::types::Value;
+    use storage_engine::tuple::{Tuple, Value as StorageValue};
 
     #[test]
     fn test_database_create_and_list_tables() {
@@ -91,6 +223,292 @@ mod tests {
         std::fs::remove_dir_all(path).unwrap();
     }
 
+    #[test]
+    fn test_sql_select() {
+        let path = "test_sql_select_dir";
+        let _ = std::fs::remove_dir_all(path);
+        let db = Database::open(path).unwrap();
+
+        let schema = crate::Schema {
+            columns: vec![
+                crate::int_column("id"),
+                crate::varchar_column("name", 50),
+                crate::int_column("age"),
+            ],
+        };
+        db.create_table("users", schema).unwrap();
+
+        let users = db.table("users").unwrap();
+        for (id, name, age) in [(1, "Alice", 30), (2, "Bob", 20), (3, "Carol", 40)] {
+            users
+                .insert(&[
+                    crate::Value::Integer(id),
+                    crate::Value::Varchar(name.to_string()),
+                    crate::Value::Integer(age),
+                ])
+                .unwrap();
+        }
+        db.flush().unwrap();
+
+        let results = db
+            .sql("SELECT name, age + 1 AS next_age FROM users WHERE age > 25 LIMIT 10")
+            .unwrap();
+        assert_eq!(results.len(), 2);
+        assert_eq!(results[0].values[0], Value::Varchar("Alice".to_string()));
+        assert_eq!(results[0].values[1], Value::Integer(31));
+        assert_eq!(results[1].values[0], Value::Varchar("Carol".to_string()));
+
+        let results = db.sql("SELECT * FROM users u WHERE u.name = 'Bob'").unwrap();
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].values.len(), 3);
+
+        assert!(matches!(
+            db.sql("SELECT * FROM missing"),
+            Err(crate::QueryError::TableNotFound { .. })
+        ));
+
+        std::fs::remove_dir_all(path).unwrap();
+    }
+
+    #[test]
+    fn test_sql_describe() {
+        let path = "test_sql_describe_dir";
+        let _ = std::fs::remove_dir_all(path);
+        let db = Database::open(path).unwrap();
+
+        db.execute("CREATE TABLE users (id INTEGER, name VARCHAR(50), age INTEGER)").unwrap();
+
+        let described = db.describe("SELECT name, age + 1 FROM users").unwrap().unwrap();
+        assert_eq!(described.columns[0].name, "name");
+        assert!(matches!(described.columns[0].column_type, crate::Type::Varchar));
+        assert_eq!(described.columns[0].length, 50);
+        assert!(matches!(described.columns[1].column_type, crate::Type::Integer));
+        assert!(db.describe("DELETE FROM users").unwrap().is_none());
+
+        std::fs::remove_dir_all(path).unwrap();
+    }
+
+    #[test]
+    fn test_sql_diagnostics() {
+        let path = "test_sql_diagnostics_dir";
+        let _ = std::fs::remove_dir_all(path);
+        let db = Database::open(path).unwrap();
+
+        db.execute("CREATE TABLE users (id INTEGER, name VARCHAR(50), age INTEGER)").unwrap();
+
+        // Unknown names point at the reference and suggest a close match
+        let err = db.sql("SELECT name FROM users WHERE agee > 25").unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "column `agee` not found at line 1, col 30; did you mean `age`?"
+        );
+        assert_eq!(
+            err.location().unwrap().snippet,
+            "SELECT name FROM users WHERE agee > 25\n                             ^^^^"
+        );
+        let err = db.sql("SELECT u.name FROM usres u").unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "table `usres` not found at line 1, col 20; did you mean `users`?"
+        );
+        let err = db.sql("SELECT x.name FROM users u").unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "column `x.name` not found at line 1, col 8; did you mean `u.name`?"
+        );
+
+        std::fs::remove_dir_all(path).unwrap();
+    }
+
+    #[test]
+    fn test_sql_create_and_drop_table() {
+        let path = "test_sql_ddl_dir";
+        let _ = std::fs::remove_dir_all(path);
+        let db = Database::open(path).unwrap();
+
+        db.execute("CREATE TABLE t (id INTEGER, name VARCHAR(50))").unwrap();
+        assert!(db.list_tables().contains(&"t".to_string()));
+
+        let table_info = db.catalog.get_table("t").unwrap();
+        assert_eq!(table_info.schema.columns.len(), 2);
+        assert_eq!(table_info.schema.columns[1].name, "name");
+        assert_eq!(table_info.schema.columns[1].length, 50);
+
+        // Creating again fails unless IF NOT EXISTS is given
+        assert!(matches!(
+            db.execute("CREATE TABLE t (id INTEGER)"),
+            Err(QueryError::ExecutionError(_))
+        ));
+        db.execute("CREATE TABLE IF NOT EXISTS t (id INTEGER)").unwrap();
+
+        db.execute("DROP TABLE t").unwrap();
+        assert!(!db.list_tables().contains(&"t".to_string()));
+        assert!(matches!(
+            db.execute("DROP TABLE t"),
+            Err(QueryError::TableNotFound { .. })
+        ));
+        db.execute("DROP TABLE IF EXISTS t").unwrap();
+
+        std::fs::remove_dir_all(path).unwrap();
+    }
+
+    #[test]
+    fn test_sql_insert_update_delete() {
+        let path = "test_sql_dml_dir";
+        let _ = std::fs::remove_dir_all(path);
+        let db = Database::open(path).unwrap();
+
+        db.execute("CREATE TABLE users (id INTEGER, name VARCHAR(20), age INTEGER)").unwrap();
+        db.execute("CREATE TABLE archive (id INTEGER, name VARCHAR(20), age INTEGER)").unwrap();
+
+        let inserted = db
+            .execute("INSERT INTO users VALUES (1, 'Alice', 30), (2, 'Bob', 20), (3, 'Carol', 40)")
+            .unwrap();
+        assert!(matches!(inserted, StatementResult::Insert(3)));
+
+        // Column list in a different order than the table
+        db.execute("INSERT INTO users (name, age, id) VALUES ('Dave', 25, 4)").unwrap();
+        let rows = db.sql("SELECT id FROM users WHERE name = 'Dave'").unwrap();
+        assert_eq!(rows[0].values[0], Value::Integer(4));
+
+        let copied = db
+            .execute("INSERT INTO archive SELECT * FROM users WHERE age >= 30")
+            .unwrap();
+        assert!(matches!(copied, StatementResult::Insert(2)));
+
+        let updated = db
+            .execute("UPDATE users SET age = age + 1, name = 'Bobby' WHERE id = 2")
+            .unwrap();
+        assert!(matches!(updated, StatementResult::Update(1)));
+        let rows = db.sql("SELECT name, age FROM users WHERE id = 2").unwrap();
+        assert_eq!(rows[0].values[0], Value::Varchar("Bobby".to_string()));
+        assert_eq!(rows[0].values[1], Value::Integer(21));
+
+        let deleted = db.execute("DELETE FROM users WHERE age > 25").unwrap();
+        assert!(matches!(deleted, StatementResult::Delete(2)));
+        assert_eq!(db.sql("SELECT * FROM users").unwrap().len(), 2);
+
+        // Rejected rows leave the table untouched
+        assert!(db.execute("INSERT INTO users VALUES (5, 'Eve', 1), (6, 7, 8)").is_err());
+        assert!(db.execute("INSERT INTO users (id) VALUES (5)").is_err());
+        assert_eq!(db.sql("SELECT * FROM users").unwrap().len(), 2);
+
+        let deleted = db.execute("DELETE FROM users").unwrap();
+        assert!(matches!(deleted, StatementResult::Delete(2)));
+        assert!(db.sql("SELECT * FROM users").unwrap().is_empty());
+        assert_eq!(db.sql("SELECT * FROM archive").unwrap().len(), 2);
+
+        std::fs::remove_dir_all(path).unwrap();
+    }
+
+    #[test]
+    fn test_sql_parameters() {
+        let path = "test_sql_parameters_dir";
+        let _ = std::fs::remove_dir_all(path);
+        let db = Database::open(path).unwrap();
+
+        db.execute("CREATE TABLE users (id INTEGER, name VARCHAR(20), age INTEGER)").unwrap();
+
+        // Parameters are bound as values and typed from their context
+        let sql = "INSERT INTO users (name, id, age) VALUES ($1, $2, $2 + 1)";
+        let types = db.parameter_types(sql).unwrap();
+        assert!(matches!(types[..], [Some(crate::Type::Varchar), Some(crate::Type::Integer)]));
+        let args = [
+            crate::Value::Varchar("O'Brien".to_string()),
+            crate::Value::Integer(7),
+        ];
+        let inserted = db.execute_with(sql, &args).unwrap();
+        assert!(matches!(inserted, StatementResult::Insert(1)));
+        let rows = db
+            .execute_with("SELECT age FROM users WHERE name = $1", &args[..1])
+            .unwrap();
+        assert!(matches!(rows, StatementResult::Query { rows, .. } if rows[0].values[0] == Value::Integer(8)));
+        assert!(db.execute("SELECT * FROM users WHERE id = $1").is_err());
+
+        std::fs::remove_dir_all(path).unwrap();
+    }
+
+    #[test]
+    fn test_execute_script() {
+        let path = "test_execute_script_dir";
+        let _ = std::fs::remove_dir_all(path);
+        let db = Database::open(path).unwrap();
+
+        let results = db
+            .execute_script(
+                "CREATE TABLE users (id INTEGER, name VARCHAR(50));\n\
+                 INSERT INTO users VALUES (1, 'Alice'), (2, 'Bob');\n\
+                 SELECT name FROM users WHERE id = 2;",
+            )
+            .unwrap();
+        assert_eq!(results.len(), 3);
+        assert!(matches!(results[1], StatementResult::Insert(2)));
+        match &results[2] {
+            StatementResult::Query { rows, .. } => {
+                assert_eq!(rows[0].values[0], Value::Varchar("Bob".to_string()))
+            }
+            _ => panic!("expected rows"),
+        }
+
+        // Execution stops at the failing statement; earlier ones stay applied
+        let Err(err) = db.execute_script(
+                "INSERT INTO users VALUES (3, 'Carol');\n\
+             -- typo below\n\
+             SELECT * FROM usres;\n\
+             INSERT INTO users VALUES (4, 'Dan');",
+        ) else {
+            panic!("script should fail");
+        };
+        assert_eq!(err.index, 1);
+        assert_eq!(err.line, 3);
+        assert!(matches!(err.error, QueryError::TableNotFound { .. }));
+        assert!(err.to_string().starts_with("statement 2 (line 3): "));
+        assert_eq!(db.sql("SELECT * FROM users").unwrap().len(), 3);
+
+        std::fs::remove_dir_all(path).unwrap();
+    }
+
+    #[test]
+    fn test_system_tables() {
+        let path = "test_system_tables_dir";
+        let _ = std::fs::remove_dir_all(path);
+        let db = Database::open(path).unwrap();
+
+        db.execute("CREATE TABLE users (id INTEGER, name VARCHAR(50), age INTEGER)")
+            .unwrap();
+        db.execute("CREATE TABLE orders (id INTEGER)").unwrap();
+
+        let tables = db.table("rose_tables").unwrap().collect().unwrap();
+        assert_eq!(tables.len(), 2);
+        assert_eq!(tables[0].values[1], Value::Varchar("orders".to_string()));
+        assert_eq!(tables[1].values[1], Value::Varchar("users".to_string()));
+
+        let columns = db
+            .table("rose_columns")
+            .unwrap()
+            .filter(col("table_name").eq(lit_str("users")))
+            .collect()
+            .unwrap();
+        assert_eq!(columns.len(), 3);
+        assert_eq!(columns[1].values[1], Value::Varchar("name".to_string()));
+        assert_eq!(columns[1].values[2], Value::Varchar("VARCHAR".to_string()));
+        assert_eq!(columns[1].values[3], Value::Integer(50));
+        assert_eq!(columns[1].values[4], Value::Integer(2));
+
+        // SQL sees the same rows, and dropped tables disappear
+        db.execute("DROP TABLE orders").unwrap();
+        let results = db.sql("SELECT name FROM rose_tables").unwrap();
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].values[0], Value::Varchar("users".to_string()));
+
+        // System tables are read-only and their names are reserved
+        assert!(db.execute("DELETE FROM rose_tables").is_err());
+        assert!(db.execute("CREATE TABLE rose_columns (id INTEGER)").is_err());
+        db.execute("CREATE TABLE rose_x (id INTEGER)").unwrap();
+
+        std::fs::remove_dir_all(path).unwrap();
+    }
+
     #[test]
     fn test_catalog_persists_across_reopen() {
         let path = "test_persistence_dir";
@@ -109,7 +527,10 @@ mod tests {
             db.create_table("users", schema).unwrap();
             let table_info = db.catalog.get_table("users").unwrap();
             table_info.table_heap.insert_tuple(&Tuple {
-                values: vec![Value::Integer(42), Value::Varchar("Alice".to_string())],
+                values: vec![
+                    StorageValue::Integer(42),
+                    StorageValue::Varchar("Alice".to_string()),
+                ],
             });
             db.flush().unwrap();
         }
@@ -128,10 +549,38 @@ mod tests {
             });
             assert!(tuple.is_some(), "row should persist after reopen");
             let tuple = tuple.unwrap();
-            assert_eq!(tuple.values[0], Value::Integer(42));
-            assert_eq!(tuple.values[1], Value::Varchar("Alice".to_string()));
+            assert_eq!(tuple.values[0], StorageValue::Integer(42));
+            assert_eq!(tuple.values[1], StorageValue::Varchar("Alice".to_string()));
         }
 
         std::fs::remove_dir_all(path).unwrap();
     }
+
+    #[test]
+    fn test_rewritten_table_persists_across_reopen() {
+        let path = "test_rewrite_persistence_dir";
+        let _ = std::fs::remove_dir_all(path);
+
+        {
+            let db = Database::open(path).unwrap();
+            db.execute("CREATE TABLE users (id INTEGER)").unwrap();
+            db.execute("INSERT INTO users VALUES (1), (2), (3)").unwrap();
+            db.execute("DELETE FROM users WHERE id = 2").unwrap();
+            db.execute("UPDATE users SET id = id * 10").unwrap();
+        }
+
+        // Only the latest heap's data directory is left
+        let dirs = std::fs::read_dir(path)
+            .unwrap()
+            .filter(|entry| entry.as_ref().unwrap().path().is_dir())
+            .count();
+        assert_eq!(dirs, 1);
+
+        let db = Database::open(path).unwrap();
+        let results = db.table("users").unwrap().collect().unwrap();
+        let ids: Vec<_> = results.iter().map(|row| row.values[0].clone()).collect();
+        assert_eq!(ids, vec![Value::Integer(10), Value::Integer(30)]);
+
+        std::fs::remove_dir_all(path).unwrap();
+    }
 }
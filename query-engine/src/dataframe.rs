@@ -2,16 +2,63 @@
 //!
 //! Provides a fluent, method-chaining interface inspired by Polars and DataFusion.
 
-use crate::catalog::TableInfo;
+use crate::catalog::{Catalog, TableInfo};
+use crate::diagnostics::closest_match;
+use crate::executor::explain::format_plan;
 use crate::executor::{
-    BoxedExecutor, FilterExecutor, LimitExecutor, ProjectionExecutor, SeqScanExecutor,
+    BoxedExecutor, BuildSide, CteExecutor, CteRows, CteScanExecutor, DistinctExecutor, Executor,
+    FilterExecutor, HashAggregateExecutor, HashJoinExecutor, InstrumentedExecutor, JoinType,
+    LimitExecutor, MergeJoinExecutor, NestedLoopJoinExecutor, ProjectionExecutor,
+    RecursiveCteExecutor, SeqScanExecutor, SetOperation, SetOperationExecutor, SortExecutor,
+    SubqueryExecutor, SystemScanExecutor, TopNExecutor, WindowExecutor,
 };
-use crate::expression::{col, Expression};
-use crate::types::Value;
+use crate::executor::{hash_aggregate, set_operation, sort, window};
+use crate::executor::hash_join::EquiJoinKeys;
+use crate::expression::{
+    col, column_index, AggregateExpr, AggregateFunction, BinaryOperator, Expression,
+    ParameterValues, SortKey, Subquery, SubqueryRows, UnaryOperator, WindowExpr,
+};
+use crate::logical_plan::{
+    Aggregate, CommonTableDefinition, CommonTableInfo, Join, LogicalPlan, RecursiveInputs,
+    SetOperationInputs, Window,
+};
+use crate::prepared::PreparedQuery;
+use crate::types::{Row, Value};
 use crate::{QueryError, Result};
-use std::sync::Arc;
-use storage_engine::table::RowId;
-use storage_engine::tuple::Tuple;
+use std::cell::RefCell;
+use std::collections::HashSet;
+use std::sync::{Arc, RwLock};
+use std::time::Instant;
+use storage_engine::table::{RowId, TableHeap};
+use storage_engine::tuple::{Column, Schema, Tuple, Type};
+
+/// The algorithm used to execute a join.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum JoinStrategy {
+    /// Merge join when both inputs already arrive sorted on the join keys, hash join for
+    /// other equality conditions, and nested loop join for everything else
+    #[default]
+    Auto,
+    /// Compare every pair of rows; works with any condition
+    NestedLoop,
+    /// Build a hash table on the smaller input; needs an equality condition
+    Hash,
+    /// Sort both inputs on the join keys where needed and merge them; needs an equality
+    /// condition
+    SortMerge,
+}
+
+/// A join whose inputs have been planned, waiting for its algorithm to be chosen.
+struct PlannedJoin<'a> {
+    left: BoxedExecutor,
+    right: BoxedExecutor,
+    /// The condition, bound against `schema`
+    on: Expression,
+    join_type: JoinType,
+    strategy: JoinStrategy,
+    /// Columns of both sides
+    schema: &'a Schema,
+}
 
 /// A lazy query builder that produces an execution plan.
 ///
@@ -22,38 +69,63 @@ use storage_engine::tuple::Tuple;
 ///   .limit(10)
 ///   .collect()
 /// ```
+///
+/// Each method applies to the rows produced by the ones called before it, so
+/// `.limit(10).filter(...)` filters the first 10 rows while `.filter(...).limit(10)` returns
+/// the first 10 rows passing the filter.
 pub struct DataFrame {
-    table_info: Arc<TableInfo>,
-    filter_expr: Option<Expression>,
-    projection_exprs: Option<Vec<(Expression, String)>>, // (expr, output_name)
-    limit: Option<usize>,
+    catalog: Arc<Catalog>,
+    plan: LogicalPlan,
 }
 
 impl DataFrame {
-    /// Creates a new DataFrame from a table.
-    pub(crate) fn new(
-        table_info: Arc<TableInfo>,
-        filter_expr: Option<Expression>,
-        projection_exprs: Option<Vec<(Expression, String)>>,
-        limit: Option<usize>,
-    ) -> Self {
-        Self {
-            table_info,
-            filter_expr,
-            projection_exprs,
-            limit,
+    /// Creates a new DataFrame producing the rows of `plan`.
+    pub(crate) fn new(catalog: Arc<Catalog>, plan: LogicalPlan) -> Self {
+        Self { catalog, plan }
+    }
+
+    /// Returns the logical plan of the query.
+    pub(crate) fn plan(&self) -> &LogicalPlan {
+        &self.plan
+    }
+
+    /// Returns the schema of the underlying table, before any projection.
+    pub(crate) fn input_schema(&self) -> &Schema {
+        self.plan.source_schema()
+    }
+
+    /// Returns a DataFrame whose plan is the node `node` builds over this one's plan.
+    fn then(self, node: impl FnOnce(Box<LogicalPlan>) -> LogicalPlan) -> Self {
+        DataFrame {
+            plan: node(Box::new(self.plan)),
+            catalog: self.catalog,
         }
     }
 
     /// Adds a filter (WHERE clause) to the query.
     ///
+    /// Filtering again keeps only the rows passing both filters.
+    ///
     /// # Example
     /// ```ignore
     /// df.filter(col("age").gt(25))
     /// ```
-    pub fn filter(mut self, predicate: Expression) -> Self {
-        self.filter_expr = Some(predicate);
-        self
+    pub fn filter(self, predicate: Expression) -> Self {
+        match self.plan {
+            // Consecutive filters become a single one over the conjunction of their predicates
+            LogicalPlan::Filter {
+                input,
+                predicate: first,
+            } => DataFrame {
+                catalog: self.catalog,
+                plan: LogicalPlan::Filter {
+                    input,
+                    predicate: first.and(predicate),
+                },
+            },
+            plan => DataFrame::new(self.catalog, plan)
+                .then(|input| LogicalPlan::Filter { input, predicate }),
+        }
     }
 
     /// Projects specific columns (SELECT clause).
@@ -62,13 +134,12 @@ impl DataFrame {
     /// ```ignore
     /// df.select(&["name", "email"])
     /// ```
-    pub fn select(mut self, columns: &[&str]) -> Self {
+    pub fn select(self, columns: &[&str]) -> Self {
         let exprs = columns
             .iter()
             .map(|col_name| (col(col_name), col_name.to_string()))
             .collect();
-        self.projection_exprs = Some(exprs);
-        self
+        self.then(|input| LogicalPlan::Projection { input, exprs })
     }
 
     /// Projects with custom expressions.
@@ -80,13 +151,102 @@ impl DataFrame {
     ///     (col("age").add(lit(1)), "age_plus_one"),
     /// ])
     /// ```
-    pub fn select_exprs(mut self, exprs: &[(Expression, &str)]) -> Self {
+    pub fn select_exprs(self, exprs: &[(Expression, &str)]) -> Self {
         let exprs = exprs
             .iter()
             .map(|(expr, name)| (expr.clone(), name.to_string()))
             .collect();
-        self.projection_exprs = Some(exprs);
-        self
+        self.then(|input| LogicalPlan::Projection { input, exprs })
+    }
+
+    /// Sorts the results (ORDER BY clause), most significant key first.
+    ///
+    /// Keys are evaluated against the rows at this point of the chain, so after
+    /// [`select_exprs`](Self::select_exprs) they can use the names it gives. As in SQL, a
+    /// query can also sort by a column it does not select: keys reading a column the
+    /// projection just before drops are evaluated before that projection, or, if other keys
+    /// use the projection's names, carried through it until after the sort. Ascending keys
+    /// put NULLs last and descending keys put them first, unless
+    /// [`nulls_first`](SortKey::nulls_first) or [`nulls_last`](SortKey::nulls_last) says
+    /// otherwise. Rows with equal keys keep their input order.
+    ///
+    /// # Example
+    /// ```ignore
+    /// df.order_by(&[col("age").desc().nulls_last(), col("name").asc()])
+    /// ```
+    pub fn order_by(self, keys: &[SortKey]) -> Self {
+        let keys = keys.to_vec();
+        match self.plan {
+            LogicalPlan::Projection { input, exprs } if !projects_sort_keys(&exprs, &keys) => {
+                let plan = if input.schema().is_ok_and(|schema| reads_only(&schema, &keys)) {
+                    LogicalPlan::Projection {
+                        input: Box::new(LogicalPlan::Sort { input, keys }),
+                        exprs,
+                    }
+                } else {
+                    // Keys mixing the projection's names with columns it drops: carry those
+                    // columns through the projection, and drop them after the sort
+                    let mut carried = exprs.clone();
+                    for key in &keys {
+                        for name in key.expr.column_names() {
+                            if !carried.iter().any(|(_, output)| output == name) {
+                                carried.push((col(name), name.to_string()));
+                            }
+                        }
+                    }
+                    let projection = LogicalPlan::Projection {
+                        input,
+                        exprs: carried,
+                    };
+                    let sort = LogicalPlan::Sort {
+                        input: Box::new(projection),
+                        keys,
+                    };
+                    LogicalPlan::Projection {
+                        input: Box::new(sort),
+                        exprs: exprs
+                            .into_iter()
+                            .enumerate()
+                            .map(|(i, (_, name))| (Expression::BoundColumn(i), name))
+                            .collect(),
+                    }
+                };
+                DataFrame::new(self.catalog, plan)
+            }
+            plan => DataFrame::new(self.catalog, plan)
+                .then(|input| LogicalPlan::Sort { input, keys }),
+        }
+    }
+
+    /// Removes duplicate rows (SELECT DISTINCT), keeping the first of each.
+    ///
+    /// Rows are compared as they are at this point of the chain, e.g. after
+    /// [`select`](Self::select), and NULLs count as equal to each other.
+    ///
+    /// # Example
+    /// ```ignore
+    /// df.select(&["city"]).distinct()
+    /// ```
+    pub fn distinct(self) -> Self {
+        self.then(|input| LogicalPlan::Distinct {
+            input,
+            on: Vec::new(),
+        })
+    }
+
+    /// Keeps only the first row for each distinct combination of `columns` (DISTINCT ON).
+    ///
+    /// The columns are looked up in the rows at this point of the chain. Which row comes
+    /// first is decided by [`order_by`](Self::order_by), or by the input order without one.
+    ///
+    /// # Example
+    /// ```ignore
+    /// // Each customer's largest order
+    /// orders.order_by(&[col("amount").desc()]).distinct_on(&["customer_id"])
+    /// ```
+    pub fn distinct_on(self, columns: &[&str]) -> Self {
+        let on = columns.iter().map(|name| col(name)).collect();
+        self.then(|input| LogicalPlan::Distinct { input, on })
     }
 
     /// Limits the number of results (LIMIT clause).
@@ -95,9 +255,395 @@ impl DataFrame {
     /// ```ignore
     /// df.limit(10)
     /// ```
-    pub fn limit(mut self, n: usize) -> Self {
-        self.limit = Some(n);
-        self
+    pub fn limit(self, n: usize) -> Self {
+        match self.plan {
+            LogicalPlan::Limit { input, skip, fetch } => DataFrame::new(
+                self.catalog,
+                LogicalPlan::Limit {
+                    input,
+                    skip,
+                    fetch: Some(fetch.map_or(n, |fetch| fetch.min(n))),
+                },
+            ),
+            plan => DataFrame::new(self.catalog, plan).then(|input| LogicalPlan::Limit {
+                input,
+                skip: 0,
+                fetch: Some(n),
+            }),
+        }
+    }
+
+    /// Skips the first `n` results (OFFSET clause).
+    ///
+    /// Without [`order_by`](Self::order_by), which rows come first is unspecified. The rows
+    /// are skipped from those produced so far, so `.limit(10).offset(3)` returns 7 rows
+    /// while `.offset(3).limit(10)` returns 10.
+    ///
+    /// # Example
+    /// ```ignore
+    /// df.order_by(&[col("id").asc()]).offset(20).limit(10)
+    /// ```
+    pub fn offset(self, n: usize) -> Self {
+        match self.plan {
+            LogicalPlan::Limit { input, skip, fetch } => DataFrame::new(
+                self.catalog,
+                LogicalPlan::Limit {
+                    input,
+                    skip: skip.saturating_add(n),
+                    fetch: fetch.map(|fetch| fetch.saturating_sub(n)),
+                },
+            ),
+            plan => DataFrame::new(self.catalog, plan).then(|input| LogicalPlan::Limit {
+                input,
+                skip: n,
+                fetch: None,
+            }),
+        }
+    }
+
+    /// Returns `len` results starting at `offset`, e.g. one page of a paginated listing.
+    ///
+    /// Same as `.offset(offset).limit(len)`.
+    ///
+    /// # Example
+    /// ```ignore
+    /// // The third page of 10
+    /// df.order_by(&[col("id").asc()]).slice(20, 10)
+    /// ```
+    pub fn slice(self, offset: usize, len: usize) -> Self {
+        self.offset(offset).limit(len)
+    }
+
+    /// Groups the rows by the values of `keys` (GROUP BY clause), to be folded into one row
+    /// per group with [`GroupedDataFrame::agg`].
+    ///
+    /// # Example
+    /// ```ignore
+    /// orders.group_by(&[col("customer_id")])
+    ///     .agg(&[count_star(), sum(col("amount")).alias("total")])?
+    ///     .filter(col("total").gt(lit(100))) // HAVING total > 100
+    /// ```
+    pub fn group_by(self, keys: &[Expression]) -> GroupedDataFrame {
+        GroupedDataFrame {
+            df: self,
+            group_by: keys.to_vec(),
+        }
+    }
+
+    /// Joins this DataFrame with `right`, keeping the pairs of rows for which `on` is true.
+    ///
+    /// The result has this DataFrame's columns followed by `right`'s, each named
+    /// `table.column` after the table it came from, or after the name given with
+    /// [`alias`](Self::alias). Unqualified names still work wherever they are unambiguous.
+    ///
+    /// Outer joins ([`JoinType::Left`], [`JoinType::Right`] and [`JoinType::Full`]) also
+    /// keep the rows of the preserved side that matched nothing, with NULL in the other
+    /// side's columns.
+    ///
+    /// # Example
+    /// ```ignore
+    /// customers
+    ///     .join(orders, col("customers.id").eq(col("customer_id")), JoinType::Inner)?
+    ///     .select(&["name", "amount"])
+    /// ```
+    pub fn join(self, right: DataFrame, on: Expression, join_type: JoinType) -> Result<DataFrame> {
+        self.join_with(right, on, join_type, JoinStrategy::Auto)
+    }
+
+    /// Returns the rows of this DataFrame that have at least one match in `right`, each once
+    /// and with only this DataFrame's columns, like `WHERE EXISTS (...)` or `IN (subquery)`.
+    ///
+    /// # Example
+    /// ```ignore
+    /// // Customers who have placed an order
+    /// customers.semi_join(orders, col("customers.id").eq(col("orders.customer_id")))?
+    /// ```
+    pub fn semi_join(self, right: DataFrame, on: Expression) -> Result<DataFrame> {
+        self.join(right, on, JoinType::Semi)
+    }
+
+    /// Returns the rows of this DataFrame that have no match in `right`, with only this
+    /// DataFrame's columns, like `WHERE NOT EXISTS (...)`.
+    ///
+    /// Rows for which `on` is NULL count as unmatched; use
+    /// [`null_aware_anti_join`](Self::null_aware_anti_join) for `NOT IN` semantics.
+    pub fn anti_join(self, right: DataFrame, on: Expression) -> Result<DataFrame> {
+        self.join(right, on, JoinType::Anti)
+    }
+
+    /// Returns the rows of this DataFrame for which `on` is FALSE against every row of
+    /// `right`, like `WHERE x NOT IN (subquery)`.
+    ///
+    /// Following SQL's three-valued logic, a row is left out when `on` is NULL for any row of
+    /// `right`: `x NOT IN (1, NULL)` is unknown rather than true, and a NULL `x` qualifies
+    /// only when `right` is empty.
+    ///
+    /// # Example
+    /// ```ignore
+    /// // Customers not in the blocklist
+    /// customers.null_aware_anti_join(blocked, col("customers.id").eq(col("blocked.id")))?
+    /// ```
+    pub fn null_aware_anti_join(self, right: DataFrame, on: Expression) -> Result<DataFrame> {
+        self.join(right, on, JoinType::NullAwareAnti)
+    }
+
+    /// Like [`join`](Self::join), but executes the join with the given algorithm instead of
+    /// letting the planner choose.
+    ///
+    /// # Example
+    /// ```ignore
+    /// customers.join_with(orders, on, JoinType::Inner, JoinStrategy::SortMerge)?
+    /// ```
+    pub fn join_with(
+        self,
+        right: DataFrame,
+        on: Expression,
+        join_type: JoinType,
+        strategy: JoinStrategy,
+    ) -> Result<DataFrame> {
+        let mut columns = self.qualified_schema()?.columns;
+        for column in right.qualified_schema()?.columns {
+            if columns.iter().any(|other| other.name == column.name) {
+                return Err(QueryError::ExecutionError(format!(
+                    "Both sides of the join have a column named '{}'; use alias() to tell them apart",
+                    column.name
+                )));
+            }
+            columns.push(column);
+        }
+        let schema = Schema { columns };
+
+        // Bind once up front so a bad predicate is reported here rather than at collect()
+        on.bind(&schema)?;
+
+        let left_len = self.schema()?.columns.len();
+        let join = Join {
+            left: self.plan,
+            right: right.plan,
+            on,
+            join_type,
+            strategy,
+            output_schema: join_type.output_schema(&schema, left_len),
+            schema,
+        };
+        Ok(DataFrame::new(self.catalog, LogicalPlan::Join(Box::new(join))))
+    }
+
+    /// Combines this DataFrame's rows with `other`'s (UNION), returning each distinct row
+    /// once. Rows are compared as a whole, with NULLs equal to each other.
+    ///
+    /// Both DataFrames must have the same number of columns, with the same types in the same
+    /// order; the result takes this DataFrame's column names.
+    ///
+    /// # Example
+    /// ```ignore
+    /// db.table("orders")?.select(&["customer_id"])
+    ///     .union(db.table("archived_orders")?.select(&["customer_id"]))?
+    /// ```
+    pub fn union(self, other: DataFrame) -> Result<DataFrame> {
+        self.set_operation(other, SetOperation::Union)
+    }
+
+    /// Like [`union`](Self::union), but keeps duplicate rows (UNION ALL): this DataFrame's
+    /// rows followed by `other`'s.
+    ///
+    /// # Example
+    /// ```ignore
+    /// // Active and archived orders, which have the same columns
+    /// db.table("orders")?.union_all(db.table("archived_orders")?)?
+    /// ```
+    pub fn union_all(self, other: DataFrame) -> Result<DataFrame> {
+        self.set_operation(other, SetOperation::UnionAll)
+    }
+
+    /// Returns each distinct row that is in both this DataFrame and `other` (INTERSECT).
+    ///
+    /// The inputs must match as for [`union`](Self::union).
+    pub fn intersect(self, other: DataFrame) -> Result<DataFrame> {
+        self.set_operation(other, SetOperation::Intersect)
+    }
+
+    /// Returns each distinct row of this DataFrame that is not in `other` (EXCEPT).
+    ///
+    /// The inputs must match as for [`union`](Self::union).
+    ///
+    /// # Example
+    /// ```ignore
+    /// // Customers who have never ordered
+    /// customers.select(&["id"]).except(orders.select(&["customer_id"]))?
+    /// ```
+    pub fn except(self, other: DataFrame) -> Result<DataFrame> {
+        self.set_operation(other, SetOperation::Except)
+    }
+
+    fn set_operation(self, other: DataFrame, operation: SetOperation) -> Result<DataFrame> {
+        // Check the schemas up front so a mismatch is reported here rather than at collect()
+        let schema = set_operation::output_schema(&self.schema()?, &other.schema()?, operation)?;
+        let inputs = SetOperationInputs {
+            left: self.plan,
+            right: other.plan,
+            operation,
+            schema,
+        };
+        Ok(DataFrame::new(
+            self.catalog,
+            LogicalPlan::SetOperation(Box::new(inputs)),
+        ))
+    }
+
+    /// Adds a column named `name` holding the value of a window function for each row, e.g.
+    /// its position within its partition or a running total.
+    ///
+    /// Filtering the result afterwards sees the new column, e.g. to keep the first row of
+    /// each partition. Rows come out grouped by partition and in window order within each.
+    ///
+    /// # Example
+    /// ```ignore
+    /// // Each customer's orders numbered from the largest, with a running total
+    /// let by_amount = partition_by(&[col("customer_id")]).order_by(&[col("amount").desc()]);
+    /// orders
+    ///     .with_window("rn", row_number().over(by_amount.clone()))?
+    ///     .with_window("running_total", sum(col("amount")).over(by_amount))?
+    /// ```
+    pub fn with_window(self, name: &str, window: WindowExpr) -> Result<DataFrame> {
+        // Bind once up front so a bad column or frame is reported here rather than at collect()
+        let input_schema = self.schema()?;
+        let bound = window.bind(&input_schema)?;
+        if input_schema.columns.iter().any(|column| column.name == name) {
+            return Err(QueryError::ExecutionError(format!(
+                "There is already a column named '{}'",
+                name
+            )));
+        }
+
+        let window = Window {
+            schema: window::output_schema(&input_schema, &bound, name),
+            input: self.plan,
+            window,
+            name: name.to_string(),
+        };
+        Ok(DataFrame::new(self.catalog, LogicalPlan::Window(Box::new(window))))
+    }
+
+    /// Names this query as a common table (`WITH name AS (...)`), whose rows are computed once
+    /// per execution of a query however many times it reads them.
+    ///
+    /// Each call to [`CommonTable::table`] returns a DataFrame reading those rows, with its
+    /// columns qualified by `name` in joins.
+    ///
+    /// # Example
+    /// ```ignore
+    /// // Customers whose total is above the average total, computing the totals once
+    /// let totals = orders
+    ///     .group_by(&[col("customer_id")])
+    ///     .agg(&[sum(col("amount")).alias("total")])?
+    ///     .cte("totals")?;
+    /// let average = totals.table().group_by(&[]).agg(&[avg(col("total"))])?;
+    /// totals.table().filter(col("total").gt(scalar_subquery(average)))
+    /// ```
+    pub fn cte(self, name: &str) -> Result<CommonTable> {
+        let schema = self.schema()?;
+        Ok(CommonTable(Arc::new(CommonTableInfo {
+            catalog: self.catalog,
+            rows: CteRows::new(name),
+            schema,
+            definition: CommonTableDefinition::Query(RwLock::new(self.plan)),
+        })))
+    }
+
+    /// Builds a recursive common table (`WITH RECURSIVE name AS (... UNION ...)`): this
+    /// DataFrame's rows, followed by the rows `step` derives from them, then those it derives
+    /// from the rows it derived, and so on until it derives no new rows.
+    ///
+    /// `step` is given a DataFrame named `name` reading the rows added by the previous
+    /// round, and returns one with the same columns. Rows already in the result are dropped,
+    /// so the recursion ends even when following the cycles of a graph.
+    ///
+    /// # Example
+    /// ```ignore
+    /// // Everyone reporting to employee 1, directly or not
+    /// let reports = employees.filter(col("id").eq(lit(1))).select(&["id", "name"])
+    ///     .recursive_union("reports", |reports| {
+    ///         Ok(db.table("employees")?
+    ///             .join(reports, col("manager_id").eq(col("reports.id")), JoinType::Inner)?
+    ///             .select(&["employees.id", "employees.name"]))
+    ///     })?;
+    /// reports.table().collect()
+    /// ```
+    pub fn recursive_union(
+        self,
+        name: &str,
+        step: impl FnOnce(DataFrame) -> Result<DataFrame>,
+    ) -> Result<CommonTable> {
+        self.recursive_cte(name, step, SetOperation::Union)
+    }
+
+    /// Builds a recursive common table like [`recursive_union`](Self::recursive_union), but
+    /// keeping every row `step` derives (`UNION ALL`).
+    ///
+    /// This saves remembering the rows added so far, but never ends if `step` keeps deriving
+    /// rows, e.g. on a graph with cycles.
+    pub fn recursive_union_all(
+        self,
+        name: &str,
+        step: impl FnOnce(DataFrame) -> Result<DataFrame>,
+    ) -> Result<CommonTable> {
+        self.recursive_cte(name, step, SetOperation::UnionAll)
+    }
+
+    fn recursive_cte(
+        self,
+        name: &str,
+        step: impl FnOnce(DataFrame) -> Result<DataFrame>,
+        operation: SetOperation,
+    ) -> Result<CommonTable> {
+        let base_schema = self.schema()?;
+        let working = CommonTable(Arc::new(CommonTableInfo {
+            catalog: self.catalog.clone(),
+            rows: CteRows::new(name),
+            schema: base_schema.clone(),
+            definition: CommonTableDefinition::Working {
+                estimated_rows: self.plan.estimated_rows(&self.catalog)?,
+            },
+        }));
+        let step = step(working.table())?;
+
+        // Check the schemas up front so a mismatch is reported here rather than at collect()
+        let schema = set_operation::output_schema(&base_schema, &step.schema()?, operation)?;
+        let inputs = RecursiveInputs {
+            base: self.plan,
+            step: step.plan,
+            working: working.0.rows.clone(),
+            operation,
+            schema: schema.clone(),
+        };
+        Ok(CommonTable(Arc::new(CommonTableInfo {
+            catalog: self.catalog,
+            rows: CteRows::new(name),
+            schema,
+            definition: CommonTableDefinition::Query(RwLock::new(LogicalPlan::Recursive(
+                Box::new(inputs),
+            ))),
+        })))
+    }
+
+    /// Names this DataFrame for qualifying its columns in a join, e.g. to join a table with
+    /// itself.
+    ///
+    /// # Example
+    /// ```ignore
+    /// db.table("employees")?.alias("e")
+    ///     .join(db.table("employees")?.alias("m"), col("e.manager_id").eq(col("m.id")), JoinType::Inner)?
+    /// ```
+    pub fn alias(self, name: &str) -> Self {
+        let name = name.to_string();
+        self.then(|input| LogicalPlan::Alias { input, name })
+    }
+
+    /// Returns the output schema with every unqualified column name prefixed by the alias or
+    /// table name, as seen from the other side of a join.
+    fn qualified_schema(&self) -> Result<Schema> {
+        Ok(self.plan.qualify(self.schema()?))
     }
 
     /// Inserts a tuple into the table.
@@ -107,31 +653,184 @@ impl DataFrame {
     /// df.insert(&[Value::Integer(1), Value::Varchar("Alice".to_string())])
     /// ```
     pub fn insert(&self, values: &[Value]) -> Result<RowId> {
-        // Convert query_engine Values to storage Values
-        let storage_values: Vec<_> = values
-            .iter()
-            .map(|v| {
-                v.to_storage().ok_or_else(|| {
-                    QueryError::ExecutionError("Cannot insert NULL values (not supported yet)".to_string())
-                })
-            })
-            .collect::<Result<Vec<_>>>()?;
+        let bound = self.plan.writable_table("insert into")?;
+        self.catalog.write_table(bound, |table_info| {
+            let tuple = to_storage_tuple(&table_info.schema, values)?;
+            let row_id = insert_tuple(&table_info.table_heap, &tuple)?;
+            table_info.record_inserts(1);
+            Ok(row_id)
+        })
+    }
 
-        let tuple = Tuple {
-            values: storage_values,
-        };
+    /// Inserts several rows, returning how many were written.
+    ///
+    /// Every row is validated against the schema before any of them is written.
+    pub fn insert_many(&self, rows: &[Vec<Value>]) -> Result<usize> {
+        let bound = self.plan.writable_table("insert into")?;
+        self.catalog.write_table(bound, |table_info| {
+            let tuples = rows
+                .iter()
+                .map(|values| to_storage_tuple(&table_info.schema, values))
+                .collect::<Result<Vec<_>>>()?;
+
+            for tuple in &tuples {
+                insert_tuple(&table_info.table_heap, tuple)?;
+                table_info.record_inserts(1);
+            }
+
+            Ok(tuples.len())
+        })
+    }
+
+    /// Updates the rows matching this DataFrame's filters, returning how many changed.
+    ///
+    /// Each assignment expression is evaluated against the original row.
+    ///
+    /// # Example
+    /// ```ignore
+    /// df.filter(col("id").eq(lit(1)))
+    ///   .update(&[("age", col("age").add(lit(1)))])
+    /// ```
+    pub fn update(self, assignments: &[(&str, Expression)]) -> Result<usize> {
+        let filter = self.modified_rows("update")?;
+        let bound = self.plan.writable_table("update")?;
+        self.catalog.write_table(bound, |table_info| {
+            let schema = &table_info.schema;
+
+            let mut bound_assignments = Vec::with_capacity(assignments.len());
+            for (name, expr) in assignments {
+                let index = schema
+                    .columns
+                    .iter()
+                    .position(|column| &column.name == name)
+                    .ok_or_else(|| QueryError::ColumnNotFound {
+                        name: name.to_string(),
+                        suggestion: closest_match(
+                            name,
+                            schema.columns.iter().map(|column| column.name.as_str()),
+                        ),
+                        location: None,
+                    })?;
+                bound_assignments.push((index, expr.bind(schema)?));
+            }
+
+            let (mut updated, mut written) = (0, 0);
+            self.catalog.rewrite_table(&table_info, |heap| {
+                scan_matching(&self.catalog, &table_info, filter.as_ref(), |row, matched| {
+                    let tuple = if matched {
+                        updated += 1;
+                        let mut values = row.values.clone();
+                        for (index, expr) in &bound_assignments {
+                            values[*index] = expr.evaluate(&row)?;
+                        }
+                        to_storage_tuple(schema, &values)?
+                    } else {
+                        to_storage_tuple(schema, &row.values)?
+                    };
+                    written += 1;
+                    insert_tuple(heap, &tuple).map(|_| ())
+                })?;
+                Ok((updated > 0).then_some(written))
+            })?;
+            Ok(updated)
+        })
+    }
+
+    /// Deletes the rows matching this DataFrame's filters, returning how many were removed.
+    ///
+    /// Without a filter, every row is deleted.
+    pub fn delete(self) -> Result<usize> {
+        let filter = self.modified_rows("delete")?;
+        let bound = self.plan.writable_table("delete from")?;
+        self.catalog.write_table(bound, |table_info| {
+            let (mut deleted, mut kept) = (0, 0);
+            self.catalog.rewrite_table(&table_info, |heap| {
+                scan_matching(&self.catalog, &table_info, filter.as_ref(), |row, matched| {
+                    if matched {
+                        deleted += 1;
+                        return Ok(());
+                    }
+                    kept += 1;
+                    let tuple = to_storage_tuple(&table_info.schema, &row.values)?;
+                    insert_tuple(heap, &tuple).map(|_| ())
+                })?;
+                Ok((deleted > 0).then_some(kept))
+            })?;
+            Ok(deleted)
+        })
+    }
 
-        self.table_info
-            .table_heap
-            .insert_tuple(&tuple)
-            .ok_or_else(|| QueryError::ExecutionError("Failed to insert tuple".to_string()))
+    /// Returns the conjunction of the filters between this DataFrame and its table, which
+    /// the rows to modify must pass, or `None` without any filter.
+    ///
+    /// Sorting and removing duplicates do not change which rows pass, but modifications are
+    /// rejected on DataFrames that project or limit their rows.
+    fn modified_rows(&self, operation: &str) -> Result<Option<Expression>> {
+        let mut filters = Vec::new();
+        let mut plan = &self.plan;
+        loop {
+            match plan {
+                LogicalPlan::Filter { input, predicate } => {
+                    filters.push(predicate.clone());
+                    plan = input;
+                }
+                LogicalPlan::Sort { input, .. }
+                | LogicalPlan::Distinct { input, .. }
+                | LogicalPlan::Alias { input, .. } => plan = input,
+                LogicalPlan::Projection { .. } | LogicalPlan::Limit { .. } => {
+                    return Err(QueryError::ExecutionError(format!(
+                        "{}() only supports filtered tables, not select(), limit() or offset()",
+                        operation
+                    )))
+                }
+                _ => return Ok(filters.into_iter().rev().reduce(Expression::and)),
+            }
+        }
     }
 
     /// Builds the executor tree and executes the query, collecting all results.
     ///
     /// This is the terminal operation that actually runs the query.
-    pub fn collect(self) -> Result<Vec<Tuple>> {
-        let mut executor = self.build_executor()?;
+    pub fn collect(self) -> Result<Vec<Row>> {
+        let (_, results) = self.collect_with_schema()?;
+        Ok(results)
+    }
+
+    /// Returns the schema of the rows this query produces, without running it.
+    pub fn schema(&self) -> Result<Schema> {
+        self.plan.schema()
+    }
+
+    /// Binds and plans the query once so it can be executed many times.
+    ///
+    /// Use [`param`](crate::param) placeholders wherever a value should be supplied per run:
+    /// ```ignore
+    /// let mut by_age = db.table("users")?.filter(col("age").gt(param(1))).prepare()?;
+    /// let over_25 = by_age.execute(&[Value::Integer(25)])?;
+    /// let over_40 = by_age.execute(&[Value::Integer(40)])?;
+    /// ```
+    pub fn prepare(self) -> Result<PreparedQuery> {
+        PreparedQuery::new(self)
+    }
+
+    /// Returns the number of parameters the query takes, i.e. the highest `param(n)` used.
+    pub(crate) fn parameter_count(&self) -> usize {
+        self.plan.parameter_count()
+    }
+
+    /// Points the DataFrame at the tables' current heaps if they have been rewritten since
+    /// the DataFrame was created. Returns whether anything changed.
+    pub(crate) fn refresh(&mut self) -> Result<bool> {
+        self.plan.refresh(&self.catalog)
+    }
+
+    /// Executes the query, returning the output schema alongside the results.
+    ///
+    /// The schema is the plan's, as [`schema`](Self::schema) reports it: the executors may
+    /// name columns differently, e.g. qualified by their table after an IN subquery's join.
+    pub(crate) fn collect_with_schema(self) -> Result<(Schema, Vec<Row>)> {
+        let schema = self.plan.schema()?;
+        let mut executor = self.build_executor(&ParameterValues::default())?;
         executor.init()?;
 
         let mut results = Vec::new();
@@ -139,42 +838,86 @@ impl DataFrame {
             results.push(tuple);
         }
 
-        Ok(results)
+        Ok((schema, results))
     }
 
     /// Builds the executor tree for this DataFrame.
-    fn build_executor(&self) -> Result<BoxedExecutor> {
-        // Start with sequential scan
-        let mut executor: BoxedExecutor = Box::new(SeqScanExecutor::new(self.table_info.clone()));
+    ///
+    /// Parameter placeholders read their values from `params` when the tree runs.
+    pub(crate) fn build_executor(&self, params: &ParameterValues) -> Result<BoxedExecutor> {
+        self.build_query(params, false)
+    }
 
-        // Apply filter if present
-        if let Some(ref filter_expr) = self.filter_expr {
-            // Bind the expression to the current schema
-            let bound_expr = filter_expr.bind(&self.table_info.schema)?;
-            executor = Box::new(FilterExecutor::new(executor, bound_expr));
+    /// Builds the executor tree of a whole query: its plan, run once the rows of every
+    /// common table it reads have been computed.
+    fn build_query(&self, params: &ParameterValues, instrument: bool) -> Result<BoxedExecutor> {
+        let slots = CommonTableSlots::default();
+        let executor =
+            Planner::new(&self.catalog, params, &slots, instrument).build_root(&self.plan)?;
+        let mut tables = Vec::new();
+        self.plan.common_tables(&mut tables);
+        if tables.is_empty() {
+            return Ok(executor);
         }
 
-        // Apply projection if present
-        if let Some(ref proj_exprs) = self.projection_exprs {
-            let (exprs, names): (Vec<_>, Vec<_>) = proj_exprs
-                .iter()
-                .map(|(expr, name)| {
-                    (
-                        expr.bind(&self.table_info.schema).unwrap(),
-                        name.clone(),
-                    )
-                })
-                .unzip();
-
-            executor = Box::new(ProjectionExecutor::new(executor, exprs, names)?);
+        let mut plans = Vec::with_capacity(tables.len());
+        for table in &tables {
+            if let CommonTableDefinition::Query(definition) = &table.definition {
+                let planner = Planner::new(&self.catalog, params, &slots, instrument);
+                let plan = planner.build_root(&definition.read().unwrap())?;
+                plans.push((plan, slots.get(&table.rows)));
+            }
         }
-
-        // Apply limit if present
-        if let Some(limit_val) = self.limit {
-            executor = Box::new(LimitExecutor::new(executor, limit_val));
+        let executor: BoxedExecutor =
+            Box::new(CteExecutor::new(executor, plans, self.catalog.clone()));
+        if instrument {
+            Ok(Box::new(InstrumentedExecutor::new(executor)))
+        } else {
+            Ok(executor)
         }
+    }
 
-        Ok(executor)
+    /// Returns the executor tree this query would run, one operator per line.
+    ///
+    /// # Example
+    /// ```ignore
+    /// println!("{}", df.explain()?);
+    /// // Limit(10)
+    /// //   -> Projection[name]
+    /// //     -> Filter(age > 25)
+    /// //       -> SeqScan(users)
+    /// ```
+    pub fn explain(&self) -> Result<String> {
+        let executor = self.build_executor(&ParameterValues::default())?;
+        Ok(format_plan(executor.as_ref()))
+    }
+
+    /// Runs the query and returns its plan annotated with what each operator did.
+    ///
+    /// Every operator reports the rows it returned, how many times its `next()` was called
+    /// and the time spent inside it (including its children). The results are discarded.
+    ///
+    /// # Example
+    /// ```ignore
+    /// println!("{}", df.explain_analyze()?);
+    /// // Limit(10)  (rows=2 next_calls=3 time=0.091 ms)
+    /// //   -> Filter(age > 25)  (rows=2 next_calls=3 time=0.084 ms)
+    /// //     -> SeqScan(users)  (rows=3 next_calls=4 time=0.062 ms)
+    /// // Execution time: 0.104 ms
+    /// ```
+    pub fn explain_analyze(self) -> Result<String> {
+        let start = Instant::now();
+        let mut executor = self.build_query(&ParameterValues::default(), true)?;
+        executor.init()?;
+        while executor.next()?.is_some() {}
+        let elapsed = start.elapsed();
+
+        let mut plan = format_plan(executor.as_ref());
+        plan.push_str(&format!(
+            "Execution time: {:.3} ms\n",
+            elapsed.as_secs_f64() * 1000.0
+        ));
+        Ok(plan)
     }
 
     /// Executes the query and prints results (for debugging/demo).
@@ -190,26 +933,1159 @@ impl DataFrame {
     }
 }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use crate::Database;
-    use storage_engine::tuple::Type;
+/// A DataFrame whose rows have been grouped by [`DataFrame::group_by`], waiting for the
+/// aggregates to compute for each group.
+pub struct GroupedDataFrame {
+    df: DataFrame,
+    group_by: Vec<Expression>,
+}
 
-    #[test]
-    fn test_dataframe_insert_and_collect() {
-        let path = "test_dataframe_dir";
-        let _ = std::fs::remove_dir_all(path);
-        let db = Database::open(path).unwrap();
+impl GroupedDataFrame {
+    /// Computes `aggregates` over each group, returning a DataFrame with one row per group:
+    /// the group-by values followed by the aggregates.
+    ///
+    /// Aggregates skip NULLs, apart from [`count_star`](crate::count_star), and all NULL
+    /// keys fall into the same group. Without any group-by keys the whole input is a single
+    /// group, so even an empty input produces one row. Filtering the result acts as HAVING.
+    ///
+    /// # Example
+    /// ```ignore
+    /// orders.group_by(&[col("customer_id")])
+    ///     .agg(&[count(col("id")), sum(col("amount"))])?
+    /// ```
+    pub fn agg(self, aggregates: &[AggregateExpr]) -> Result<DataFrame> {
+        // Name the aggregates after the expressions as written, before binding qualifies
+        // their column names
+        let aggregates: Vec<AggregateExpr> = aggregates
+            .iter()
+            .map(|aggregate| aggregate.clone().alias(&aggregate.output_name()))
+            .collect();
 
-        let schema = storage_engine::tuple::Schema {
-            columns: vec![
-                crate::int_column("id"),
-                crate::int_column("age"),
-            ],
+        // Bind once up front so a bad column is reported here rather than at collect()
+        let input_schema = self.df.schema()?;
+        let group_by = self
+            .group_by
+            .iter()
+            .map(|expr| expr.bind(&input_schema))
+            .collect::<Result<Vec<_>>>()?;
+        let bound = aggregates
+            .iter()
+            .map(|aggregate| aggregate.bind(&input_schema))
+            .collect::<Result<Vec<_>>>()?;
+        let schema = hash_aggregate::output_schema(&input_schema, &group_by, &bound);
+        for (i, column) in schema.columns.iter().enumerate() {
+            if schema.columns[..i].iter().any(|other| other.name == column.name) {
+                return Err(QueryError::ExecutionError(format!(
+                    "Aggregation has two columns named '{}'; use alias() to tell them apart",
+                    column.name
+                )));
+            }
+        }
+
+        let aggregate = Aggregate {
+            input: self.df.plan,
+            group_by: self.group_by,
+            aggregates,
+            schema,
         };
+        Ok(DataFrame::new(
+            self.df.catalog,
+            LogicalPlan::Aggregate(Box::new(aggregate)),
+        ))
+    }
+}
 
-        db.create_table("users", schema).unwrap();
+/// A named query whose rows are computed once per execution of a query reading them,
+/// created by [`DataFrame::cte`] or [`DataFrame::recursive_union`].
+///
+/// Cloning shares the same common table.
+#[derive(Clone)]
+pub struct CommonTable(Arc<CommonTableInfo>);
+
+impl CommonTable {
+    /// Returns a DataFrame reading the common table's rows.
+    ///
+    /// Every DataFrame returned reads the same rows, so a query reading the common table
+    /// several times, e.g. joining it with itself, computes them only once.
+    pub fn table(&self) -> DataFrame {
+        DataFrame::new(
+            self.0.catalog.clone(),
+            LogicalPlan::CommonTableScan(self.0.clone()),
+        )
+    }
+
+    /// Returns the common table's name.
+    pub fn name(&self) -> &str {
+        self.0.rows.name()
+    }
+
+    /// Returns the columns of the common table's rows.
+    pub fn schema(&self) -> &Schema {
+        &self.0.schema
+    }
+}
+
+/// The rows of the common tables one executor tree reads, each in a slot of its own.
+///
+/// The logical plan's [`CteRows`] only identify the common tables: every executor tree gets
+/// fresh slots, so trees of the same plans running at the same time never overwrite each
+/// other's rows.
+#[derive(Default)]
+struct CommonTableSlots(RefCell<Vec<(CteRows, CteRows)>>);
+
+impl CommonTableSlots {
+    /// Returns the slot of the executor tree for the common table `rows` identifies.
+    fn get(&self, rows: &CteRows) -> CteRows {
+        let mut slots = self.0.borrow_mut();
+        if let Some((_, slot)) = slots.iter().find(|(table, _)| table.same_table(rows)) {
+            return slot.clone();
+        }
+        let slot = CteRows::new(rows.name());
+        slots.push((rows.clone(), slot.clone()));
+        slot
+    }
+}
+
+/// Turns a logical plan into the executor tree running it.
+///
+/// Every node becomes the executor of its operation, except that a limit over a sort, with
+/// at most projections in between, becomes a top-N. Filters, sorts and projections with
+/// correlated subqueries join the columns those read to their input's rows; the node above
+/// drops them again once nothing needs them.
+struct Planner<'a> {
+    catalog: &'a Arc<Catalog>,
+    params: &'a ParameterValues,
+    /// Where the common tables the executor tree reads keep their rows
+    slots: &'a CommonTableSlots,
+    /// Whether to wrap every operator in an [`InstrumentedExecutor`] to collect EXPLAIN
+    /// ANALYZE statistics
+    instrument: bool,
+    /// Plans of the uncorrelated subqueries met so far, with the rows each fills in
+    uncorrelated: Vec<(BoxedExecutor, SubqueryRows)>,
+}
+
+impl<'a> Planner<'a> {
+    fn new(
+        catalog: &'a Arc<Catalog>,
+        params: &'a ParameterValues,
+        slots: &'a CommonTableSlots,
+        instrument: bool,
+    ) -> Self {
+        Self {
+            catalog,
+            params,
+            slots,
+            instrument,
+            uncorrelated: Vec::new(),
+        }
+    }
+
+    /// Returns a planner with the same settings for another query, e.g. a subquery.
+    fn nested(&self) -> Planner<'a> {
+        Planner::new(self.catalog, self.params, self.slots, self.instrument)
+    }
+
+    fn wrap(&self, executor: BoxedExecutor) -> BoxedExecutor {
+        if self.instrument {
+            Box::new(InstrumentedExecutor::new(executor))
+        } else {
+            executor
+        }
+    }
+
+    /// Builds the executor tree of a whole query, under a [`SubqueryExecutor`] fetching the
+    /// rows of its uncorrelated subqueries if it has any.
+    fn build_root(mut self, plan: &LogicalPlan) -> Result<BoxedExecutor> {
+        let executor = self.build(plan)?;
+        if self.uncorrelated.is_empty() {
+            return Ok(executor);
+        }
+        let uncorrelated = std::mem::take(&mut self.uncorrelated);
+        Ok(self.wrap(Box::new(SubqueryExecutor::new(executor, uncorrelated))))
+    }
+
+    /// Builds the executor tree for `plan`, producing exactly the plan's columns.
+    fn build(&mut self, plan: &LogicalPlan) -> Result<BoxedExecutor> {
+        let executor: BoxedExecutor = match plan {
+            // Look the table up again: the plan's heap is gone if the table has been rewritten
+            LogicalPlan::Scan(table_info) => {
+                Box::new(SeqScanExecutor::new(self.catalog.current_table(table_info)?))
+            }
+            LogicalPlan::SystemScan(table, _) => {
+                Box::new(SystemScanExecutor::new(self.catalog.clone(), *table))
+            }
+            LogicalPlan::CommonTableScan(table) => Box::new(CteScanExecutor::new(
+                self.slots.get(&table.rows),
+                table.schema.clone(),
+            )),
+            LogicalPlan::Filter { .. }
+            | LogicalPlan::Sort { .. }
+            | LogicalPlan::Limit { .. }
+            | LogicalPlan::Alias { .. } => {
+                let (executor, width) = self.build_wide(plan)?;
+                return self.prune(executor, width);
+            }
+            LogicalPlan::Projection { input, exprs } => {
+                let (executor, width) = self.build_wide(input)?;
+                return self.project(executor, width, input, exprs);
+            }
+            LogicalPlan::Distinct { input, on } => {
+                let input = self.build(input)?;
+                let input_schema = input.schema();
+                let keys = if on.is_empty() {
+                    (0..input_schema.columns.len())
+                        .map(Expression::BoundColumn)
+                        .collect()
+                } else {
+                    on.iter()
+                        .map(|expr| expr.bind(input_schema))
+                        .collect::<Result<Vec<_>>>()?
+                };
+                Box::new(DistinctExecutor::new(input, keys, self.catalog.clone()))
+            }
+            LogicalPlan::Join(join) => {
+                let planned = PlannedJoin {
+                    left: self.build(&join.left)?,
+                    right: self.build(&join.right)?,
+                    on: join.on.bind(&join.schema)?.bind_parameters(self.params),
+                    join_type: join.join_type,
+                    strategy: join.strategy,
+                    schema: &join.schema,
+                };
+                let left_is_smaller = || {
+                    Ok(join.left.estimated_rows(self.catalog)?
+                        < join.right.estimated_rows(self.catalog)?)
+                };
+                self.join_executor(planned, left_is_smaller)?
+            }
+            LogicalPlan::Aggregate(aggregate) => {
+                let input = self.build(&aggregate.input)?;
+                let input_schema = input.schema();
+                let group_by = aggregate
+                    .group_by
+                    .iter()
+                    .map(|expr| Ok(expr.bind(input_schema)?.bind_parameters(self.params)))
+                    .collect::<Result<Vec<_>>>()?;
+                let aggregates = aggregate
+                    .aggregates
+                    .iter()
+                    .map(|aggregate| {
+                        Ok(aggregate.bind(input_schema)?.bind_parameters(self.params))
+                    })
+                    .collect::<Result<Vec<_>>>()?;
+                Box::new(HashAggregateExecutor::new(input, group_by, aggregates))
+            }
+            LogicalPlan::SetOperation(inputs) => Box::new(SetOperationExecutor::new(
+                self.build(&inputs.left)?,
+                self.build(&inputs.right)?,
+                inputs.operation,
+            )?),
+            LogicalPlan::Window(window) => {
+                let input = self.build(&window.input)?;
+                let expr = window
+                    .window
+                    .bind(input.schema())?
+                    .bind_parameters(self.params);
+
+                // Bring each partition together, in window order
+                let keys: Vec<SortKey> = expr
+                    .window
+                    .partition_by
+                    .iter()
+                    .cloned()
+                    .map(SortKey::asc)
+                    .chain(expr.window.order_by.iter().cloned())
+                    .collect();
+                let sorted = keys
+                    .iter()
+                    .map(SortKey::ascending_column)
+                    .collect::<Option<Vec<_>>>()
+                    .is_some_and(|columns| input.sort_order().starts_with(&columns));
+                let input = if sorted {
+                    input
+                } else {
+                    self.wrap(Box::new(SortExecutor::new(
+                        input,
+                        keys,
+                        self.catalog.clone(),
+                    )))
+                };
+                Box::new(WindowExecutor::new(input, expr, &window.name))
+            }
+            LogicalPlan::Recursive(inputs) => Box::new(RecursiveCteExecutor::new(
+                self.build(&inputs.base)?,
+                // The step's subqueries may read the working table, so they are fetched
+                // again on every run
+                self.nested().build_root(&inputs.step)?,
+                self.slots.get(&inputs.working),
+                inputs.operation,
+                self.catalog.clone(),
+            )?),
+        };
+        Ok(self.wrap(executor))
+    }
+
+    /// Builds the executor tree for `plan`, returning it with the number of columns the plan
+    /// produces. Filters and sorts with correlated subqueries leave the columns joined in for
+    /// them after those, for the node above to use or drop.
+    fn build_wide(&mut self, plan: &LogicalPlan) -> Result<(BoxedExecutor, usize)> {
+        match plan {
+            LogicalPlan::Filter { input, predicate } => {
+                let (executor, width) = self.build_wide(input)?;
+                Ok((self.filter(executor, width, input, predicate)?, width))
+            }
+            LogicalPlan::Sort { input, keys } => {
+                let (executor, width) = self.build_wide(input)?;
+                Ok((self.sort(executor, width, input, keys, None)?, width))
+            }
+            LogicalPlan::Limit { input, skip, fetch } => {
+                // Over a sort, only the first rows are kept while sorting (a top-N). Rows
+                // skipped by an offset must be kept too, and are dropped by a limit executor
+                // after it. A top-N holds all its rows in memory, so when more than a sort
+                // would are wanted, the sort runs in full and may spill instead.
+                let kept = fetch.and_then(|fetch| fetch.checked_add(*skip));
+                let top_n = match kept {
+                    Some(kept) if kept <= sort::DEFAULT_MEMORY_LIMIT => {
+                        self.build_top_n(input, kept)?
+                    }
+                    _ => None,
+                };
+                let sorted = top_n.is_some();
+                let (executor, width) = match top_n {
+                    Some(top_n) => top_n,
+                    None => self.build_wide(input)?,
+                };
+                let executor = match (fetch, skip) {
+                    (Some(_), 0) if sorted => return Ok((executor, width)),
+                    (Some(fetch), skip) => LimitExecutor::new(executor, *fetch).with_offset(*skip),
+                    (None, 0) => return Ok((executor, width)),
+                    (None, skip) => LimitExecutor::offset(executor, *skip),
+                };
+                Ok((self.wrap(Box::new(executor)), width))
+            }
+            LogicalPlan::Alias { input, .. } => self.build_wide(input),
+            plan => {
+                let executor = self.build(plan)?;
+                let width = executor.schema().columns.len();
+                Ok((executor, width))
+            }
+        }
+    }
+
+    /// Builds the executor tree for `plan` keeping only its first `limit` rows if it sorts
+    /// them, with at most projections after the sort, which do not change which rows come
+    /// first: a top-N then takes the place of the sort. Returns `None`, having built
+    /// nothing, for any other plan.
+    fn build_top_n(
+        &mut self,
+        plan: &LogicalPlan,
+        limit: usize,
+    ) -> Result<Option<(BoxedExecutor, usize)>> {
+        match plan {
+            LogicalPlan::Sort { input, keys } => {
+                let (executor, width) = self.build_wide(input)?;
+                let executor = self.sort(executor, width, input, keys, Some(limit))?;
+                Ok(Some((executor, width)))
+            }
+            LogicalPlan::Projection { input, exprs } => {
+                let Some((executor, width)) = self.build_top_n(input, limit)? else {
+                    return Ok(None);
+                };
+                let executor = self.project(executor, width, input, exprs)?;
+                let width = executor.schema().columns.len();
+                Ok(Some((executor, width)))
+            }
+            LogicalPlan::Alias { input, .. } => self.build_top_n(input, limit),
+            _ => Ok(None),
+        }
+    }
+
+    /// Builds the executor tree for `input` followed by `filter`, as the input of a join
+    /// with a correlated subquery's outer query.
+    fn build_filtered(
+        &mut self,
+        input: &LogicalPlan,
+        filter: Option<&Expression>,
+    ) -> Result<BoxedExecutor> {
+        let (executor, width) = self.build_wide(input)?;
+        let executor = match filter {
+            Some(filter) => self.filter(executor, width, input, filter)?,
+            None => executor,
+        };
+        self.prune(executor, width)
+    }
+
+    /// Drops the columns joined in for correlated subqueries after the first `width`.
+    fn prune(&self, executor: BoxedExecutor, width: usize) -> Result<BoxedExecutor> {
+        let schema = executor.schema();
+        if schema.columns.len() == width {
+            return Ok(executor);
+        }
+        let exprs = (0..width).map(Expression::BoundColumn).collect();
+        let names = schema.columns[..width]
+            .iter()
+            .map(|c| c.name.clone())
+            .collect();
+        Ok(self.wrap(Box::new(ProjectionExecutor::new(executor, exprs, names)?)))
+    }
+
+    /// Puts `predicate` over `executor`, the rows of `input` in its first `width` columns.
+    ///
+    /// IN and NOT IN subqueries that every row must satisfy become semi and anti joins;
+    /// other subqueries are replaced by a [`SubqueryPlanner`].
+    fn filter(
+        &mut self,
+        mut executor: BoxedExecutor,
+        width: usize,
+        input: &LogicalPlan,
+        predicate: &Expression,
+    ) -> Result<BoxedExecutor> {
+        let params = self.params;
+        let input_schema = leading_columns(executor.as_ref(), width);
+        let mut subqueries = SubqueryPlanner::new(self, input, &input_schema, executor.as_ref());
+
+        let conjuncts = predicate.conjuncts();
+        let mut residual = Vec::with_capacity(conjuncts.len());
+        for &conjunct in &conjuncts {
+            match in_subquery_join(conjunct) {
+                Some((expr, subquery, join_type)) => {
+                    let join = subqueries.semi_join(executor, expr, subquery, join_type)?;
+                    executor = subqueries.planner.wrap(join);
+                }
+                None => residual.push(conjunct.clone()),
+            }
+        }
+        // Keep the filter as written unless a subquery was taken out of it
+        let filter = if residual.len() == conjuncts.len() {
+            Some(predicate.clone())
+        } else {
+            residual.into_iter().reduce(Expression::and)
+        };
+
+        if let Some(filter) = filter {
+            let filter = subqueries.replace(&filter)?;
+            executor = subqueries.join_correlated(executor)?;
+            let bound_expr = filter.bind(&input_schema)?.bind_parameters(params);
+            executor = self.wrap(Box::new(FilterExecutor::new(executor, bound_expr)));
+        }
+        Ok(executor)
+    }
+
+    /// Sorts `executor`, the rows of `input` in its first `width` columns, on `keys`. With a
+    /// `limit`, only the first rows are kept while sorting (a top-N).
+    fn sort(
+        &mut self,
+        executor: BoxedExecutor,
+        width: usize,
+        input: &LogicalPlan,
+        keys: &[SortKey],
+        limit: Option<usize>,
+    ) -> Result<BoxedExecutor> {
+        let params = self.params;
+        let input_schema = leading_columns(executor.as_ref(), width);
+        let mut subqueries = SubqueryPlanner::new(self, input, &input_schema, executor.as_ref());
+
+        // Correlated subqueries in the keys add the columns they read
+        let mut replaced = Vec::with_capacity(keys.len());
+        for key in keys {
+            replaced.push(SortKey {
+                expr: subqueries.replace(&key.expr)?,
+                ..key.clone()
+            });
+        }
+        let executor = subqueries.join_correlated(executor)?;
+
+        let keys = replaced
+            .iter()
+            .map(|key| {
+                let key = key.bind(&input_schema)?;
+                Ok(SortKey {
+                    expr: key.expr.bind_parameters(params),
+                    ..key
+                })
+            })
+            .collect::<Result<Vec<_>>>()?;
+        let executor: BoxedExecutor = match limit {
+            Some(limit) => Box::new(TopNExecutor::new(executor, keys, limit)),
+            None => Box::new(SortExecutor::new(executor, keys, self.catalog.clone())),
+        };
+        Ok(self.wrap(executor))
+    }
+
+    /// Evaluates `exprs` over `executor`, the rows of `input` in its first `width` columns.
+    fn project(
+        &mut self,
+        executor: BoxedExecutor,
+        width: usize,
+        input: &LogicalPlan,
+        exprs: &[(Expression, String)],
+    ) -> Result<BoxedExecutor> {
+        let params = self.params;
+        let input_schema = leading_columns(executor.as_ref(), width);
+        let mut subqueries = SubqueryPlanner::new(self, input, &input_schema, executor.as_ref());
+
+        // Correlated subqueries add the columns they read
+        let mut replaced = Vec::with_capacity(exprs.len());
+        for (expr, _) in exprs {
+            replaced.push(subqueries.replace(expr)?);
+        }
+        let executor = subqueries.join_correlated(executor)?;
+
+        let mut bound = Vec::with_capacity(exprs.len());
+        for expr in &replaced {
+            bound.push(expr.bind(&input_schema)?.bind_parameters(params));
+        }
+        let names = exprs.iter().map(|(_, name)| name.clone()).collect();
+        Ok(self.wrap(Box::new(ProjectionExecutor::new(executor, bound, names)?)))
+    }
+
+    /// Builds the executor for a join whose inputs are planned, choosing its algorithm.
+    ///
+    /// `left_is_smaller` is asked whether the left input has fewer rows when a hash join
+    /// could build its table from either side.
+    fn join_executor(
+        &self,
+        join: PlannedJoin<'_>,
+        left_is_smaller: impl FnOnce() -> Result<bool>,
+    ) -> Result<BoxedExecutor> {
+        let PlannedJoin {
+            left,
+            right,
+            on,
+            join_type,
+            strategy,
+            schema,
+        } = join;
+        let keys = EquiJoinKeys::extract(&on, left.schema().columns.len());
+
+        // A hash join can only answer NOT IN for a single value compared on its own; other
+        // conditions need the nested loop join's row by row three-valued evaluation
+        let null_aware_hashable = |keys: &EquiJoinKeys| {
+            join_type != JoinType::NullAwareAnti
+                || (keys.left.len() == 1 && keys.residual.is_none())
+        };
+
+        let strategy = match (strategy, &keys) {
+            (JoinStrategy::NestedLoop, _) | (JoinStrategy::Auto, None) => JoinStrategy::NestedLoop,
+            (JoinStrategy::Hash, Some(keys)) if !null_aware_hashable(keys) => {
+                return Err(QueryError::ExecutionError(format!(
+                    "Hash join supports NullAwareAnti joins only on a single equality, got {}",
+                    on.display_with(schema)
+                )));
+            }
+            (JoinStrategy::Hash | JoinStrategy::SortMerge, None) => {
+                return Err(QueryError::ExecutionError(format!(
+                    "{:?} join needs an equality condition between the two sides, got {}",
+                    strategy,
+                    on.display_with(schema)
+                )));
+            }
+            (JoinStrategy::SortMerge, Some(_)) if join_type != JoinType::Inner => {
+                return Err(QueryError::ExecutionError(format!(
+                    "SortMerge join only supports inner joins, got a {} join",
+                    join_type.name()
+                )));
+            }
+            (JoinStrategy::Auto, Some(keys)) if !null_aware_hashable(keys) => {
+                JoinStrategy::NestedLoop
+            }
+            (JoinStrategy::Auto, Some(keys)) => {
+                if join_type == JoinType::Inner
+                    && is_sorted_on(left.as_ref(), &keys.left)
+                    && is_sorted_on(right.as_ref(), &keys.right)
+                {
+                    JoinStrategy::SortMerge
+                } else {
+                    JoinStrategy::Hash
+                }
+            }
+            (strategy, Some(_)) => strategy,
+        };
+
+        let executor: BoxedExecutor = match (strategy, keys) {
+            (JoinStrategy::Hash, Some(keys)) => {
+                // Semi and anti joins return probe tuples, so they probe with the left side
+                let build_side = if join_type.includes_right_columns() && left_is_smaller()? {
+                    BuildSide::Left
+                } else {
+                    BuildSide::Right
+                };
+                Box::new(HashJoinExecutor::new(
+                    left,
+                    right,
+                    keys,
+                    join_type,
+                    build_side,
+                    schema.clone(),
+                    self.catalog.clone(),
+                ))
+            }
+            (JoinStrategy::SortMerge, Some(keys)) => {
+                // Sort whichever inputs do not already arrive in key order
+                let sorted = |input: BoxedExecutor, keys: &[Expression]| {
+                    if is_sorted_on(input.as_ref(), keys) {
+                        input
+                    } else {
+                        let keys = keys.iter().cloned().map(SortKey::asc).collect();
+                        self.wrap(Box::new(SortExecutor::new(input, keys, self.catalog.clone())))
+                    }
+                };
+                let left = sorted(left, &keys.left);
+                let right = sorted(right, &keys.right);
+                Box::new(MergeJoinExecutor::new(
+                    left,
+                    right,
+                    keys,
+                    join_type,
+                    schema.clone(),
+                ))
+            }
+            _ => Box::new(NestedLoopJoinExecutor::new(
+                left,
+                right,
+                on,
+                join_type,
+                schema.clone(),
+            )),
+        };
+        Ok(executor)
+    }
+
+    /// Returns whether `plan`, used as a subquery, refers to the outer query's columns in
+    /// one of its filters, including those of the rows it aggregates.
+    fn is_correlated(&self, plan: &LogicalPlan) -> Result<bool> {
+        match plan {
+            LogicalPlan::Filter { input, predicate } => {
+                let schema = input.qualify(input.schema()?);
+                if !split_correlated(predicate, &schema).0.is_empty() {
+                    return Ok(true);
+                }
+                self.is_correlated(input)
+            }
+            LogicalPlan::Aggregate(aggregate) => self.is_correlated(&aggregate.input),
+            plan => match plan.single_input() {
+                Some(input) => self.is_correlated(input),
+                None => Ok(false),
+            },
+        }
+    }
+}
+
+/// Plans the subqueries in the expressions of one plan node.
+///
+/// An uncorrelated subquery is planned on its own, to be run by a [`SubqueryExecutor`]
+/// before the query. A correlated one is turned into a join with the outer rows: an IN
+/// subquery into a semi join, and a scalar subquery over an aggregate into a left join
+/// with the aggregate grouped by the inner columns its filter equates to outer ones.
+struct SubqueryPlanner<'p, 'a> {
+    planner: &'p mut Planner<'a>,
+    /// The node's input columns, qualified as a subquery's filter names them
+    outer_schema: Schema,
+    /// Columns of the outer rows once the pending joins are added
+    width: usize,
+    /// Grouped aggregates of correlated scalar subqueries waiting to be joined to the outer
+    /// rows, each with its columns named for the join and the join condition
+    pending: Vec<(BoxedExecutor, Schema, Expression)>,
+}
+
+impl<'p, 'a> SubqueryPlanner<'p, 'a> {
+    /// Creates a subquery planner for a node over `executor`, the rows of `input` with the
+    /// columns `input_schema` followed by any joined in below the node.
+    fn new(
+        planner: &'p mut Planner<'a>,
+        input: &LogicalPlan,
+        input_schema: &Schema,
+        executor: &dyn Executor,
+    ) -> Self {
+        Self {
+            planner,
+            outer_schema: input.qualify(input_schema.clone()),
+            width: executor.schema().columns.len(),
+            pending: Vec::new(),
+        }
+    }
+
+    /// Replaces the subqueries in `expr` with expressions the executors can evaluate.
+    ///
+    /// Columns read by correlated subqueries are only there once
+    /// [`join_correlated`](Self::join_correlated) has added them.
+    fn replace(&mut self, expr: &Expression) -> Result<Expression> {
+        expr.replace_subqueries(&mut |node| {
+            let correlated = match node {
+                Expression::ScalarSubquery(subquery) | Expression::InSubquery { subquery, .. } => {
+                    self.planner.is_correlated(subquery.dataframe().plan())?
+                }
+                _ => false,
+            };
+            match node {
+                Expression::ScalarSubquery(subquery) if correlated => {
+                    self.join_aggregate(subquery.dataframe().plan())
+                }
+                Expression::ScalarSubquery(subquery) => Ok(Expression::BoundScalarSubquery(
+                    self.fetch(subquery.dataframe().plan(), SubqueryRows::scalar)?,
+                )),
+                Expression::InSubquery { .. } if correlated => {
+                    Err(QueryError::ExecutionError(format!(
+                        "Correlated subqueries are only supported in IN conditions that every \
+                         row of filter() must meet, got {}",
+                        node
+                    )))
+                }
+                Expression::InSubquery { expr, subquery } => Ok(Expression::BoundInSubquery {
+                    expr: expr.clone(),
+                    rows: self.fetch(subquery.dataframe().plan(), SubqueryRows::set)?,
+                }),
+                other => Ok(other.clone()),
+            }
+        })
+    }
+
+    /// Plans an uncorrelated subquery to run before the query, returning the rows it will
+    /// fill in.
+    fn fetch(
+        &mut self,
+        subquery: &LogicalPlan,
+        rows: fn(Column) -> SubqueryRows,
+    ) -> Result<SubqueryRows> {
+        let plan = self.planner.nested().build_root(subquery)?;
+        let rows = rows(subquery_column(plan.schema())?.clone());
+        self.planner.uncorrelated.push((plan, rows.clone()));
+        Ok(rows)
+    }
+
+    /// Plans a correlated scalar subquery as a left join with its aggregate grouped by the
+    /// correlated inner columns, returning the joined column holding its value.
+    fn join_aggregate(&mut self, subquery: &LogicalPlan) -> Result<Expression> {
+        let unsupported = |reason: String| {
+            QueryError::ExecutionError(format!(
+                "Cannot decorrelate scalar subquery: {}",
+                reason
+            ))
+        };
+        let (aggregate, input, filter) = match subquery {
+            LogicalPlan::Aggregate(aggregate)
+                if aggregate.group_by.is_empty() && aggregate.aggregates.len() == 1 =>
+            {
+                match &aggregate.input {
+                    LogicalPlan::Filter { input, predicate } => (aggregate, input, predicate),
+                    _ => {
+                        return Err(unsupported(
+                            "its conditions on the outer query must be in the last filter() \
+                             before group_by()"
+                                .to_string(),
+                        ))
+                    }
+                }
+            }
+            _ => {
+                return Err(unsupported(
+                    "it must be a single aggregate without group_by() over a filtered DataFrame"
+                        .to_string(),
+                ))
+            }
+        };
+        if aggregate.aggregates[0].function == AggregateFunction::Count {
+            return Err(unsupported(
+                "COUNT would be NULL instead of 0 for rows without matches".to_string(),
+            ));
+        }
+
+        // Each correlated condition equates an inner column with an outer one, which become
+        // the grouping and join keys
+        let params = self.planner.params;
+        let inner_schema = input.qualify(input.schema()?);
+        let (correlated, residual) = split_correlated(filter, &inner_schema);
+        let mut inner_keys = Vec::with_capacity(correlated.len());
+        let mut on = None;
+        for conjunct in &correlated {
+            let keys = match conjunct {
+                Expression::BinaryOp {
+                    left,
+                    op: BinaryOperator::Eq,
+                    right,
+                } => [(left, right), (right, left)].into_iter().find_map(|(inner, outer)| {
+                    Some((
+                        inner.bind(&inner_schema).ok()?,
+                        outer.bind(&self.outer_schema).ok()?,
+                    ))
+                }),
+                _ => None,
+            };
+            let Some((inner, outer)) = keys else {
+                return Err(unsupported(format!(
+                    "correlated conditions must equate an inner column with an outer one, got {}",
+                    conjunct
+                )));
+            };
+            let key = Expression::BoundColumn(self.width + inner_keys.len());
+            let condition = outer.bind_parameters(params).eq(key);
+            on = Some(match on {
+                Some(on) => Expression::and(on, condition),
+                None => condition,
+            });
+            inner_keys.push(inner.bind_parameters(params));
+        }
+        let Some(on) = on else {
+            return Err(unsupported("its filter refers to no outer column".to_string()));
+        };
+
+        let value = Expression::BoundColumn(self.width + inner_keys.len());
+        let aggregates = vec![aggregate.aggregates[0]
+            .bind(&inner_schema)?
+            .bind_parameters(params)];
+        let input = self.planner.build_filtered(input, residual.as_ref())?;
+        // Name the keys as the subquery does, to tell them from the outer columns
+        let key_names: Vec<String> = inner_keys
+            .iter()
+            .map(|key| key.display_with(&inner_schema).to_string())
+            .collect();
+        let grouped = self.planner.wrap(Box::new(HashAggregateExecutor::new(
+            input, inner_keys, aggregates,
+        )));
+        let mut schema = grouped.schema().clone();
+        for (column, name) in schema.columns.iter_mut().zip(key_names) {
+            column.name = name;
+        }
+        self.width += schema.columns.len();
+        self.pending.push((grouped, schema, on));
+        Ok(value)
+    }
+
+    /// Left joins the aggregates of the correlated scalar subqueries replaced so far to
+    /// `executor`'s rows.
+    fn join_correlated(&mut self, mut executor: BoxedExecutor) -> Result<BoxedExecutor> {
+        for (grouped, grouped_schema, on) in std::mem::take(&mut self.pending) {
+            let mut columns = executor.schema().columns.clone();
+            columns.extend(grouped_schema.columns);
+            let schema = Schema { columns };
+            let join = PlannedJoin {
+                left: executor,
+                right: grouped,
+                on,
+                join_type: JoinType::Left,
+                strategy: JoinStrategy::Auto,
+                schema: &schema,
+            };
+            // The aggregate has at most one row per outer row, so it is the side to build
+            executor = self.planner.wrap(self.planner.join_executor(join, || Ok(false))?);
+        }
+        Ok(executor)
+    }
+
+    /// Joins `executor`'s rows with `subquery`'s, keeping those whose `expr` is among the
+    /// subquery's values: a semi join for IN, or a null-aware anti join for NOT IN. A
+    /// correlated subquery's conditions on the outer columns are added to the join's.
+    fn semi_join(
+        &mut self,
+        executor: BoxedExecutor,
+        expr: &Expression,
+        subquery: &Subquery,
+        join_type: JoinType,
+    ) -> Result<BoxedExecutor> {
+        let params = self.planner.params;
+        let expr = self.replace(expr)?;
+        let executor = self.join_correlated(executor)?;
+
+        // The outer columns, then any joined in for subqueries
+        let mut columns = self.outer_schema.columns.clone();
+        columns.extend(executor.schema().columns[columns.len()..].iter().cloned());
+        let left_schema = Schema { columns };
+        let left_len = left_schema.columns.len();
+        let expr = expr.bind(&left_schema)?.bind_parameters(params);
+
+        let subquery = subquery.dataframe().plan();
+        let (right, right_schema, value, correlated) = if !self.planner.is_correlated(subquery)? {
+            let right = self.planner.nested().build_root(subquery)?;
+            subquery_column(right.schema())?;
+            let right_schema = subquery.qualify(right.schema().clone());
+            (right, right_schema, Expression::BoundColumn(0), Vec::new())
+        } else {
+            // The rows are joined before the projection, so the value is computed in the
+            // join condition, and before any sort, which does not change which rows match
+            let (projection, mut rest) = match subquery {
+                LogicalPlan::Projection { input, exprs } => (Some(exprs.as_slice()), &**input),
+                plan => (None, plan),
+            };
+            while let LogicalPlan::Sort { input, .. } = rest {
+                rest = input;
+            }
+            let (input, filter) = match rest {
+                LogicalPlan::Filter { input, predicate } => (&**input, predicate),
+                LogicalPlan::Distinct { .. } | LogicalPlan::Limit { .. } => {
+                    return Err(QueryError::ExecutionError(
+                        "Correlated IN subqueries cannot use distinct(), limit() or offset()"
+                            .to_string(),
+                    ))
+                }
+                _ => {
+                    return Err(QueryError::ExecutionError(
+                        "Correlated IN subqueries must have their conditions on the outer query \
+                         in the last filter() before select()"
+                            .to_string(),
+                    ))
+                }
+            };
+            let right_schema = input.qualify(input.schema()?);
+            let (correlated, residual) = split_correlated(filter, &right_schema);
+            let value = match projection {
+                Some([(value, _)]) => value.bind(&right_schema)?,
+                None => {
+                    subquery_column(&right_schema)?;
+                    Expression::BoundColumn(0)
+                }
+                Some(exprs) => {
+                    return Err(QueryError::ExecutionError(format!(
+                        "Subquery must return a single column, got {}",
+                        exprs.len()
+                    )))
+                }
+            };
+            let right = self.planner.build_filtered(input, residual.as_ref())?;
+            (right, right_schema, value, correlated)
+        };
+
+        let mut columns = left_schema.columns;
+        for column in right_schema.columns {
+            if !correlated.is_empty() && columns.iter().any(|other| other.name == column.name) {
+                return Err(QueryError::ExecutionError(format!(
+                    "Both the subquery and the outer query have a column named '{}'; use \
+                     alias() to tell them apart",
+                    column.name
+                )));
+            }
+            columns.push(column);
+        }
+        let schema = Schema { columns };
+        let value = value
+            .map_columns(&|index| index + left_len)
+            .bind_parameters(params);
+        let mut on = expr.eq(value);
+        for conjunct in &correlated {
+            on = on.and(conjunct.bind(&schema)?.bind_parameters(params));
+        }
+
+        let join = PlannedJoin {
+            left: executor,
+            right,
+            on,
+            join_type,
+            strategy: JoinStrategy::Auto,
+            schema: &schema,
+        };
+        self.planner.join_executor(join, || Ok(false))
+    }
+}
+
+/// Returns whether every sort key only reads columns named by `exprs`, a projection's
+/// output, so the sort can come after the projection.
+fn projects_sort_keys(exprs: &[(Expression, String)], keys: &[SortKey]) -> bool {
+    keys.iter().all(|key| {
+        key.expr
+            .column_names()
+            .into_iter()
+            .all(|name| exprs.iter().any(|(_, output)| output == name))
+    })
+}
+
+/// Returns whether every sort key only reads columns of `schema`.
+fn reads_only(schema: &Schema, keys: &[SortKey]) -> bool {
+    keys.iter().all(|key| {
+        key.expr
+            .column_names()
+            .into_iter()
+            .all(|name| column_index(schema, name).is_ok())
+    })
+}
+
+/// Returns the first `width` columns of `executor`'s rows, those of the plan it was built
+/// for, without the columns joined in for correlated subqueries after them.
+fn leading_columns(executor: &dyn Executor, width: usize) -> Schema {
+    Schema {
+        columns: executor.schema().columns[..width].to_vec(),
+    }
+}
+
+/// Splits `filter` into the conjuncts reading columns `schema`, the qualified columns of
+/// the rows it filters, does not have, which in a correlated subquery belong to the outer
+/// query, and the rest.
+fn split_correlated(filter: &Expression, schema: &Schema) -> (Vec<Expression>, Option<Expression>) {
+    let (correlated, own): (Vec<&Expression>, Vec<&Expression>) =
+        filter.conjuncts().into_iter().partition(|conjunct| {
+            conjunct
+                .column_names()
+                .into_iter()
+                .any(|name| column_index(schema, name).is_err())
+        });
+    if correlated.is_empty() {
+        return (Vec::new(), Some(filter.clone()));
+    }
+    (
+        correlated.into_iter().cloned().collect(),
+        own.into_iter().cloned().reduce(Expression::and),
+    )
+}
+
+/// Returns the value, subquery and join type of a filter conjunct that is an IN subquery
+/// (a semi join) or a NOT IN subquery (a null-aware anti join).
+fn in_subquery_join(conjunct: &Expression) -> Option<(&Expression, &Subquery, JoinType)> {
+    match conjunct {
+        Expression::InSubquery { expr, subquery } => Some((expr, subquery, JoinType::Semi)),
+        Expression::UnaryOp {
+            op: UnaryOperator::Not,
+            expr,
+        } => match expr.as_ref() {
+            Expression::InSubquery { expr, subquery } => {
+                Some((expr, subquery, JoinType::NullAwareAnti))
+            }
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+/// Returns the only column of a subquery's rows.
+pub(crate) fn subquery_column(schema: &Schema) -> Result<&Column> {
+    match schema.columns.as_slice() {
+        [column] => Ok(column),
+        columns => Err(QueryError::ExecutionError(format!(
+            "Subquery must return a single column, got {}",
+            columns.len()
+        ))),
+    }
+}
+
+/// Returns whether `executor`'s output is known to be sorted on the columns `keys` read,
+/// in order.
+fn is_sorted_on(executor: &dyn Executor, keys: &[Expression]) -> bool {
+    let order = executor.sort_order();
+    keys.len() <= order.len()
+        && keys.iter().zip(order).all(
+            |(key, column)| matches!(key, Expression::BoundColumn(index) if *index == column),
+        )
+}
+
+/// Appends `tuple` to `heap`.
+fn insert_tuple(heap: &TableHeap, tuple: &Tuple) -> Result<RowId> {
+    heap.insert_tuple(tuple)
+        .ok_or_else(|| QueryError::ExecutionError("Failed to insert tuple".to_string()))
+}
+
+/// Which rows of a table a modification applies to.
+enum RowMatcher {
+    All,
+    Predicate(Expression),
+    /// The values of the rows passing a filter planned as a query. Whether a row passes
+    /// depends only on its values, so a row matches iff its values are among them.
+    Values(HashSet<Vec<Value>>),
+}
+
+/// Scans every row of `table_info`, calling `f` with the row and whether it matches
+/// `filter`.
+///
+/// A filter with subqueries is planned like [`DataFrame::filter`]'s and run first to find
+/// the matching rows; other filters are evaluated on each row as it is scanned.
+fn scan_matching(
+    catalog: &Arc<Catalog>,
+    table_info: &Arc<TableInfo>,
+    filter: Option<&Expression>,
+    mut f: impl FnMut(Row, bool) -> Result<()>,
+) -> Result<()> {
+    let matcher = match filter {
+        None => RowMatcher::All,
+        Some(expr) if expr.subqueries().is_empty() => {
+            RowMatcher::Predicate(expr.bind(&table_info.schema)?)
+        }
+        Some(expr) => {
+            let plan = LogicalPlan::Filter {
+                input: Box::new(LogicalPlan::Scan(table_info.clone())),
+                predicate: expr.clone(),
+            };
+            let rows = DataFrame::new(catalog.clone(), plan).collect()?;
+            RowMatcher::Values(rows.into_iter().map(|row| row.values).collect())
+        }
+    };
+
+    let mut scan = SeqScanExecutor::new(table_info.clone());
+    scan.init()?;
+
+    while let Some(row) = scan.next()? {
+        let matched = match &matcher {
+            RowMatcher::All => true,
+            RowMatcher::Predicate(predicate) => predicate.evaluate(&row)?.is_true(),
+            RowMatcher::Values(values) => values.contains(&row.values),
+        };
+        f(row, matched)?;
+    }
+    Ok(())
+}
+
+/// Converts a row of values into a storage tuple, checking it against the table schema.
+fn to_storage_tuple(schema: &Schema, values: &[Value]) -> Result<Tuple> {
+    if values.len() != schema.columns.len() {
+        return Err(QueryError::ExecutionError(format!(
+            "Expected {} values, got {}",
+            schema.columns.len(),
+            values.len()
+        )));
+    }
+
+    let mut storage_values = Vec::with_capacity(values.len());
+    for (value, column) in values.iter().zip(&schema.columns) {
+        match (value, &column.column_type) {
+            (Value::Null, _) => {
+                return Err(QueryError::ExecutionError(
+                    "Cannot insert NULL values (not supported yet)".to_string(),
+                ))
+            }
+            (Value::Integer(_), Type::Integer) => {}
+            (Value::Varchar(s), Type::Varchar) => {
+                if s.len() > column.length as usize {
+                    return Err(QueryError::ExecutionError(format!(
+                        "Value too long for column '{}' (VARCHAR({}))",
+                        column.name, column.length
+                    )));
+                }
+            }
+            _ => {
+                return Err(QueryError::TypeMismatch(format!(
+                    "Cannot store {:?} in column '{}'",
+                    value, column.name
+                )))
+            }
+        }
+        // NULL was rejected above, so the conversion always succeeds
+        storage_values.extend(value.to_storage());
+    }
+
+    Ok(Tuple {
+        values: storage_values,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::expression::{
+        avg, count, count_star, lag, lit, lit_str, max, param, partition_by, rank, row_number,
+        scalar_subquery, sum,
+    };
+    use crate::Database;
+    use storage_engine::tuple::Type;
+
+    #[test]
+    fn test_dataframe_insert_and_collect() {
+        let path = "test_dataframe_dir";
+        let _ = std::fs::remove_dir_all(path);
+        let db = Database::open(path).unwrap();
+
+        let schema = storage_engine::tuple::Schema {
+            columns: vec![
+                crate::int_column("id"),
+                crate::int_column("age"),
+            ],
+        };
+
+        db.create_table("users", schema).unwrap();
 
         let df = db.table("users").unwrap();
 
@@ -288,7 +2164,1268 @@ mod tests {
 
         assert_eq!(results.len(), 3);
         assert_eq!(results[0].values.len(), 1); // Only column b
-        assert_eq!(results[0].values[0], storage_engine::tuple::Value::Integer(10));
+        assert_eq!(results[0].values[0], Value::Integer(10));
+
+        std::fs::remove_dir_all(path).unwrap();
+    }
+
+    #[test]
+    fn test_dataframe_update_and_delete() {
+        let path = "test_dataframe3_dir";
+        let _ = std::fs::remove_dir_all(path);
+        let db = Database::open(path).unwrap();
+
+        let schema = storage_engine::tuple::Schema {
+            columns: vec![crate::int_column("id"), crate::int_column("age")],
+        };
+        db.create_table("users", schema).unwrap();
+
+        let rows: Vec<Vec<Value>> = (1..=4)
+            .map(|i| vec![Value::Integer(i), Value::Integer(i * 10)])
+            .collect();
+        assert_eq!(db.table("users").unwrap().insert_many(&rows).unwrap(), 4);
+
+        let updated = db
+            .table("users")
+            .unwrap()
+            .filter(col("id").gt(crate::expression::lit(2)))
+            .update(&[("age", col("age").add(crate::expression::lit(1)))])
+            .unwrap();
+        assert_eq!(updated, 2);
+
+        let deleted = db
+            .table("users")
+            .unwrap()
+            .filter(col("age").eq(crate::expression::lit(31)))
+            .delete()
+            .unwrap();
+        assert_eq!(deleted, 1);
+
+        let results = db.table("users").unwrap().collect().unwrap();
+        let ages: Vec<_> = results.iter().map(|t| t.values[1].clone()).collect();
+        assert_eq!(
+            ages,
+            vec![
+                Value::Integer(10),
+                Value::Integer(20),
+                Value::Integer(41),
+            ]
+        );
+
+        // The row count kept for planning follows the rewrites and inserts
+        let row_count = || {
+            let users = db.table("users").unwrap();
+            users.catalog.get_table("users").unwrap().row_count().unwrap()
+        };
+        assert_eq!(row_count(), 3);
+        db.table("users")
+            .unwrap()
+            .insert(&[Value::Integer(5), Value::Integer(50)])
+            .unwrap();
+        assert_eq!(row_count(), 4);
+
+        // Projected DataFrames can't be modified
+        assert!(db.table("users").unwrap().select(&["id"]).delete().is_err());
+
+        std::fs::remove_dir_all(path).unwrap();
+    }
+
+    #[test]
+    fn test_dataframe_handles_outlive_rewrite() {
+        let path = "test_dataframe_rewrite_dir";
+        let _ = std::fs::remove_dir_all(path);
+        let db = Database::open(path).unwrap();
+
+        let schema = storage_engine::tuple::Schema {
+            columns: vec![crate::int_column("id")],
+        };
+        db.create_table("users", schema).unwrap();
+        let rows: Vec<Vec<Value>> = (1..=3).map(|i| vec![Value::Integer(i)]).collect();
+        db.table("users").unwrap().insert_many(&rows).unwrap();
+
+        // Handles taken before a delete write to and read from the rewritten table
+        let users = db.table("users").unwrap();
+        let reader = db.table("users").unwrap().filter(col("id").gt(crate::expression::lit(1)));
+        db.execute("DELETE FROM users WHERE id = 2").unwrap();
+        users.insert(&[Value::Integer(4)]).unwrap();
+
+        let results = reader.collect().unwrap();
+        let ids: Vec<_> = results.iter().map(|t| t.values[0].clone()).collect();
+        assert_eq!(ids, vec![Value::Integer(3), Value::Integer(4)]);
+        assert_eq!(users.filter(col("id").eq(crate::expression::lit(4))).delete().unwrap(), 1);
+        assert_eq!(db.table("users").unwrap().collect().unwrap().len(), 2);
+
+        std::fs::remove_dir_all(path).unwrap();
+    }
+
+    #[test]
+    fn test_dataframe_modify_with_subquery() {
+        let path = "test_dataframe_modify_subquery_dir";
+        let _ = std::fs::remove_dir_all(path);
+        let db = Database::open(path).unwrap();
+
+        db.execute("CREATE TABLE customers (id INTEGER, name VARCHAR(20))")
+            .unwrap();
+        db.execute("CREATE TABLE orders (id INTEGER, customer_id INTEGER)")
+            .unwrap();
+        db.execute("INSERT INTO customers VALUES (1, 'Alice'), (2, 'Bob'), (3, 'Cy'), (4, 'Dan')")
+            .unwrap();
+        db.execute("INSERT INTO orders VALUES (10, 1), (11, 3)").unwrap();
+
+        let customers = || db.table("customers").unwrap();
+        let buyers = || db.table("orders").unwrap().select(&["customer_id"]);
+        let deleted = customers().filter(col("id").in_subquery(buyers())).delete().unwrap();
+        assert_eq!(deleted, 2);
+
+        let last = customers().group_by(&[]).agg(&[max(col("id"))]).unwrap();
+        let updated = customers()
+            .filter(col("id").eq(scalar_subquery(last)))
+            .update(&[("name", lit_str("Max"))])
+            .unwrap();
+        assert_eq!(updated, 1);
+        let deleted = customers()
+            .filter(col("id").not_in_subquery(buyers()).and(col("id").lt(lit(3))))
+            .delete()
+            .unwrap();
+        assert_eq!(deleted, 1);
+
+        let results = db.table("customers").unwrap().collect().unwrap();
+        let rows: Vec<_> = results.into_iter().map(|row| row.values).collect();
+        assert_eq!(rows, vec![vec![Value::Integer(4), Value::Varchar("Max".to_string())]]);
+
+        std::fs::remove_dir_all(path).unwrap();
+    }
+
+    #[test]
+    fn test_dataframe_explain() {
+        let path = "test_dataframe_explain_dir";
+        let _ = std::fs::remove_dir_all(path);
+        let db = Database::open(path).unwrap();
+
+        let schema = storage_engine::tuple::Schema {
+            columns: vec![crate::varchar_column("name", 20), crate::int_column("age")],
+        };
+        db.create_table("users", schema).unwrap();
+        let rows: Vec<Vec<Value>> = [("Alice", 30), ("Bob", 20), ("Carol", 40)]
+            .iter()
+            .map(|(name, age)| vec![Value::Varchar(name.to_string()), Value::Integer(*age)])
+            .collect();
+        db.table("users").unwrap().insert_many(&rows).unwrap();
+
+        let df = db
+            .table("users")
+            .unwrap()
+            .filter(col("age").gt(crate::expression::lit(25)))
+            .select(&["name"])
+            .limit(10);
+        assert_eq!(
+            df.explain().unwrap(),
+            concat!(
+                "Limit(10)\n",
+                "  -> Projection[name]\n",
+                "    -> Filter(age > 25)\n",
+                "      -> SeqScan(users)\n",
+            )
+        );
+
+        let analyzed = df.explain_analyze().unwrap();
+        let lines: Vec<&str> = analyzed.lines().collect();
+        assert_eq!(lines.len(), 5);
+        assert!(lines[0].starts_with("Limit(10)  (rows=2 next_calls=3 time="));
+        assert!(lines[2].starts_with("    -> Filter(age > 25)  (rows=2 next_calls=3 time="));
+        assert!(lines[3].starts_with("      -> SeqScan(users)  (rows=3 next_calls=4 time="));
+        assert!(lines[4].starts_with("Execution time: "));
+
+        std::fs::remove_dir_all(path).unwrap();
+    }
+
+    #[test]
+    fn test_dataframe_join() {
+        let path = "test_dataframe_join_dir";
+        let _ = std::fs::remove_dir_all(path);
+        let db = Database::open(path).unwrap();
+
+        db.execute("CREATE TABLE customers (id INTEGER, name VARCHAR(20), referrer INTEGER)")
+            .unwrap();
+        db.execute("CREATE TABLE orders (id INTEGER, customer_id INTEGER, amount INTEGER)")
+            .unwrap();
+        db.execute("INSERT INTO customers VALUES (1, 'Alice', 0), (2, 'Bob', 1), (3, 'Carol', 1)")
+            .unwrap();
+        db.execute("INSERT INTO orders VALUES (10, 1, 150), (11, 3, 40), (12, 1, 200)")
+            .unwrap();
+
+        let orders = db.table("orders").unwrap().filter(col("amount").gt(lit(100)));
+        let df = db
+            .table("customers")
+            .unwrap()
+            .join(orders, col("customers.id").eq(col("customer_id")), JoinType::Inner)
+            .unwrap()
+            .select(&["name", "orders.id", "amount"]);
+        assert_eq!(
+            df.explain().unwrap(),
+            concat!(
+                "Projection[customers.name AS name, orders.id, orders.amount AS amount]\n",
+                "  -> HashJoin(Inner, customers.id = orders.customer_id, build: right)\n",
+                "    -> SeqScan(customers)\n",
+                "    -> Filter(amount > 100)\n",
+                "      -> SeqScan(orders)\n",
+            )
+        );
+        let results = df.collect().unwrap();
+        assert_eq!(results.len(), 2);
+        assert_eq!(results[0].values[0], Value::Varchar("Alice".to_string()));
+        assert_eq!(results[0].values[1], Value::Integer(10));
+        assert_eq!(results[1].values[1], Value::Integer(12));
+
+        // Names shared by both sides must be qualified
+        let joined = db
+            .table("customers")
+            .unwrap()
+            .join(db.table("orders").unwrap(), lit(1), JoinType::Inner)
+            .unwrap();
+        assert_eq!(joined.schema().unwrap().columns[3].name, "orders.id");
+        assert!(joined.filter(col("id").eq(lit(1))).collect().is_err());
+
+        // A self-join needs an alias for one side
+        let customers = || db.table("customers").unwrap();
+        assert!(customers().join(customers(), lit(1), JoinType::Inner).is_err());
+        let referrals = customers()
+            .join(
+                customers().alias("r"),
+                col("customers.referrer").eq(col("r.id")),
+                JoinType::Inner,
+            )
+            .unwrap()
+            .select(&["customers.name", "r.name"])
+            .collect()
+            .unwrap();
+        assert_eq!(referrals.len(), 2);
+        assert_eq!(referrals[1].values[0], Value::Varchar("Carol".to_string()));
+        assert_eq!(referrals[1].values[1], Value::Varchar("Alice".to_string()));
+
+        // A left join keeps customers without orders, with NULL for the order columns
+        let on = || col("customers.id").eq(col("customer_id"));
+        let with_orders = customers()
+            .join(db.table("orders").unwrap(), on(), JoinType::Left)
+            .unwrap();
+        assert!(with_orders
+            .explain()
+            .unwrap()
+            .starts_with("HashJoin(Left, customers.id = orders.customer_id, build: right)"));
+        let amounts: Vec<_> = with_orders
+            .select(&["name", "amount"])
+            .collect()
+            .unwrap()
+            .into_iter()
+            .map(|row| row.values)
+            .collect();
+        let name = |name: &str| Value::Varchar(name.to_string());
+        assert_eq!(
+            amounts,
+            vec![
+                vec![name("Alice"), Value::Integer(150)],
+                vec![name("Alice"), Value::Integer(200)],
+                vec![name("Bob"), Value::Null],
+                vec![name("Carol"), Value::Integer(40)],
+            ]
+        );
+        let without_orders = customers()
+            .join(db.table("orders").unwrap(), on(), JoinType::Left)
+            .unwrap()
+            .filter(col("orders.id").is_null())
+            .select(&["name"])
+            .collect()
+            .unwrap();
+        assert_eq!(without_orders.len(), 1);
+        assert_eq!(without_orders[0].values[0], name("Bob"));
+
+        // Merge joins only handle inner joins
+        let merged = customers()
+            .join_with(db.table("orders").unwrap(), on(), JoinType::Full, JoinStrategy::SortMerge)
+            .unwrap();
+        assert!(merged.collect().is_err());
+
+        std::fs::remove_dir_all(path).unwrap();
+    }
+
+    #[test]
+    fn test_dataframe_semi_and_anti_join() {
+        let path = "test_dataframe_semi_join_dir";
+        let _ = std::fs::remove_dir_all(path);
+        let db = Database::open(path).unwrap();
+
+        db.execute("CREATE TABLE customers (id INTEGER, name VARCHAR(20))")
+            .unwrap();
+        db.execute("CREATE TABLE orders (id INTEGER, customer_id INTEGER)")
+            .unwrap();
+        db.execute("CREATE TABLE refs (code INTEGER)").unwrap();
+        db.execute("INSERT INTO customers VALUES (1, 'Alice'), (2, 'Bob'), (3, 'Carol'), (4, 'Dave')")
+            .unwrap();
+        db.execute("INSERT INTO orders VALUES (10, 1), (11, 1), (12, 3)")
+            .unwrap();
+        db.execute("INSERT INTO refs VALUES (10), (99)").unwrap();
+
+        let customers = || db.table("customers").unwrap();
+        let orders = || db.table("orders").unwrap();
+        let on = || col("customers.id").eq(col("orders.customer_id"));
+
+        // Runs a join as a nested loop and as a hash join, checks both agree and returns the
+        // first column of the result
+        let run = |left: &dyn Fn() -> DataFrame,
+                   right: &dyn Fn() -> DataFrame,
+                   on: &dyn Fn() -> Expression,
+                   join_type: JoinType| {
+            let results: Vec<Vec<Value>> = [JoinStrategy::NestedLoop, JoinStrategy::Hash]
+                .into_iter()
+                .map(|strategy| {
+                    left()
+                        .join_with(right(), on(), join_type, strategy)
+                        .unwrap()
+                        .collect()
+                        .unwrap()
+                        .into_iter()
+                        .map(|row| row.values[0].clone())
+                        .collect()
+                })
+                .collect();
+            assert_eq!(results[0], results[1], "{:?} join", join_type);
+            results[0].clone()
+        };
+        let ints = |values: &[i32]| values.iter().map(|&i| Value::Integer(i)).collect::<Vec<_>>();
+
+        // Alice has two orders but appears once, without order columns
+        let semi = customers().semi_join(orders(), on()).unwrap();
+        assert!(semi
+            .explain()
+            .unwrap()
+            .starts_with("HashJoin(Semi, customers.id = orders.customer_id, build: right)"));
+        assert_eq!(semi.schema().unwrap().columns.len(), 2);
+        assert_eq!(run(&customers, &orders, &on, JoinType::Semi), ints(&[1, 3]));
+        assert_eq!(run(&customers, &orders, &on, JoinType::Anti), ints(&[2, 4]));
+        assert_eq!(run(&customers, &orders, &on, JoinType::NullAwareAnti), ints(&[2, 4]));
+
+        // A left join supplies NULLs: refs 99 has no order, so its customer_id is NULL
+        let referred = || {
+            db.table("refs")
+                .unwrap()
+                .join(orders(), col("refs.code").eq(col("orders.id")), JoinType::Left)
+                .unwrap()
+        };
+        // NOT IN (1, NULL) is never true, while NOT EXISTS ignores the NULL
+        assert_eq!(run(&customers, &referred, &on, JoinType::NullAwareAnti), ints(&[]));
+        assert_eq!(run(&customers, &referred, &on, JoinType::Anti), ints(&[2, 3, 4]));
+
+        // A NULL on the left is NOT IN an empty set, but unknown against anything else
+        let matches = || col("orders.customer_id").eq(col("o.customer_id"));
+        let no_orders = || orders().filter(col("id").gt(lit(100))).alias("o");
+        let some_orders = || orders().alias("o");
+        assert_eq!(
+            run(&referred, &no_orders, &matches, JoinType::NullAwareAnti),
+            ints(&[10, 99])
+        );
+        assert_eq!(
+            run(&referred, &some_orders, &matches, JoinType::NullAwareAnti),
+            ints(&[])
+        );
+        assert_eq!(run(&referred, &some_orders, &matches, JoinType::Anti), ints(&[99]));
+
+        // NOT IN on more than a single equality is evaluated row by row
+        let plan = customers()
+            .null_aware_anti_join(orders(), on().and(col("orders.id").gt(lit(10))))
+            .unwrap()
+            .explain()
+            .unwrap();
+        assert!(plan.starts_with("NestedLoopJoin(NullAwareAnti, "));
+
+        std::fs::remove_dir_all(path).unwrap();
+    }
+
+    #[test]
+    fn test_dataframe_group_by() {
+        let path = "test_dataframe_group_by_dir";
+        let _ = std::fs::remove_dir_all(path);
+        let db = Database::open(path).unwrap();
+
+        db.execute("CREATE TABLE customers (id INTEGER, name VARCHAR(20))")
+            .unwrap();
+        db.execute("CREATE TABLE orders (id INTEGER, customer_id INTEGER, amount INTEGER)")
+            .unwrap();
+        db.execute("INSERT INTO customers VALUES (1, 'Alice'), (2, 'Bob'), (3, 'Carol')")
+            .unwrap();
+        db.execute("INSERT INTO orders VALUES (10, 1, 100), (11, 1, 50), (12, 3, 20)")
+            .unwrap();
+
+        // Bob has no orders, so the left join gives him a single row of NULLs
+        let per_customer = db
+            .table("customers")
+            .unwrap()
+            .join(
+                db.table("orders").unwrap(),
+                col("customers.id").eq(col("orders.customer_id")),
+                JoinType::Left,
+            )
+            .unwrap()
+            .group_by(&[col("name")])
+            .agg(&[
+                count_star(),
+                count(col("orders.id")),
+                sum(col("amount")).alias("total"),
+            ])
+            .unwrap();
+        let names: Vec<String> = per_customer
+            .schema()
+            .unwrap()
+            .columns
+            .into_iter()
+            .map(|column| column.name)
+            .collect();
+        assert_eq!(names, ["customers.name", "count(*)", "count(orders.id)", "total"]);
+
+        let text = |s: &str| Value::Varchar(s.to_string());
+        let results: Vec<Vec<Value>> = per_customer
+            .collect()
+            .unwrap()
+            .into_iter()
+            .map(|row| row.values)
+            .collect();
+        assert_eq!(
+            results,
+            vec![
+                vec![text("Alice"), Value::Integer(2), Value::Integer(2), Value::Integer(150)],
+                vec![text("Bob"), Value::Integer(1), Value::Integer(0), Value::Null],
+                vec![text("Carol"), Value::Integer(1), Value::Integer(1), Value::Integer(20)],
+            ]
+        );
+
+        // A filter on the aggregated rows acts as HAVING
+        let big_spenders = db
+            .table("orders")
+            .unwrap()
+            .filter(col("amount").gt(lit(10)))
+            .group_by(&[col("customer_id")])
+            .agg(&[sum(col("amount")).alias("total")])
+            .unwrap()
+            .filter(col("total").gt(lit(100)));
+        assert_eq!(
+            big_spenders.explain().unwrap(),
+            concat!(
+                "Filter(total > 100)\n",
+                "  -> HashAggregate(keys: [customer_id], aggregates: [sum(amount) AS total])\n",
+                "    -> Filter(amount > 10)\n",
+                "      -> SeqScan(orders)\n",
+            )
+        );
+        let results = big_spenders.collect().unwrap();
+        assert_eq!(results, vec![Row::new(vec![Value::Integer(1), Value::Integer(150)])]);
+
+        // Without keys, an empty input is still summarized in one row
+        let summary = db
+            .table("orders")
+            .unwrap()
+            .filter(col("amount").gt(lit(1000)))
+            .group_by(&[])
+            .agg(&[count_star(), max(col("amount"))])
+            .unwrap()
+            .collect()
+            .unwrap();
+        assert_eq!(summary, vec![Row::new(vec![Value::Integer(0), Value::Null])]);
+
+        // Bad columns and clashing names are reported when the aggregation is built
+        let orders = || db.table("orders").unwrap().group_by(&[]);
+        assert!(matches!(
+            orders().agg(&[sum(col("amont"))]),
+            Err(QueryError::ColumnNotFound { .. })
+        ));
+        assert!(orders().agg(&[count_star(), count_star()]).is_err());
+
+        std::fs::remove_dir_all(path).unwrap();
+    }
+
+    #[test]
+    fn test_dataframe_order_by() {
+        let path = "test_dataframe_order_by_dir";
+        let _ = std::fs::remove_dir_all(path);
+        let db = Database::open(path).unwrap();
+
+        db.execute("CREATE TABLE users (id INTEGER, name VARCHAR(20), age INTEGER)")
+            .unwrap();
+        db.execute("CREATE TABLE logins (user_id INTEGER, day INTEGER)")
+            .unwrap();
+        db.execute("INSERT INTO users VALUES (1, 'Alice', 30), (2, 'Bob', 25), (3, 'Carol', 30)")
+            .unwrap();
+        db.execute("INSERT INTO logins VALUES (1, 5), (3, 2)").unwrap();
+
+        // Sorts by a column that is not selected, ties keeping their input order
+        let df = db
+            .table("users")
+            .unwrap()
+            .order_by(&[col("age").desc()])
+            .select(&["name"]);
+        assert_eq!(
+            df.explain().unwrap(),
+            concat!(
+                "Projection[name]\n",
+                "  -> Sort(age DESC)\n",
+                "    -> SeqScan(users)\n",
+            )
+        );
+        let names: Vec<Value> = df
+            .collect()
+            .unwrap()
+            .into_iter()
+            .map(|row| row.values[0].clone())
+            .collect();
+        let text = |s: &str| Value::Varchar(s.to_string());
+        assert_eq!(names, vec![text("Alice"), text("Carol"), text("Bob")]);
+
+        // With a limit, only the first rows are kept instead of sorting everything
+        let oldest = db
+            .table("users")
+            .unwrap()
+            .order_by(&[col("age").desc()])
+            .select(&["name"])
+            .limit(2);
+        assert_eq!(
+            oldest.explain().unwrap(),
+            concat!(
+                "Projection[name]\n",
+                "  -> TopN(2, age DESC)\n",
+                "    -> SeqScan(users)\n",
+            )
+        );
+        let results = oldest.collect().unwrap();
+        assert_eq!(
+            results,
+            vec![Row::new(vec![text("Alice")]), Row::new(vec![text("Carol")])]
+        );
+
+        // Limits too large to hold in memory sort everything, which may spill, instead
+        let everyone = db
+            .table("users")
+            .unwrap()
+            .order_by(&[col("age").desc()])
+            .limit(usize::MAX);
+        assert_eq!(
+            everyone.explain().unwrap(),
+            format!(
+                "Limit({})\n  -> Sort(age DESC)\n    -> SeqScan(users)\n",
+                usize::MAX
+            )
+        );
+        assert_eq!(everyone.collect().unwrap().len(), 3);
+
+        // Bob never logged in, so his day is NULL: first when descending unless moved
+        let by_day = |key: SortKey| -> Vec<Value> {
+            db.table("users")
+                .unwrap()
+                .join(
+                    db.table("logins").unwrap(),
+                    col("users.id").eq(col("logins.user_id")),
+                    JoinType::Left,
+                )
+                .unwrap()
+                .order_by(&[key])
+                .collect()
+                .unwrap()
+                .into_iter()
+                .map(|row| row.values[1].clone())
+                .collect()
+        };
+        assert_eq!(by_day(col("day").desc()), vec![text("Bob"), text("Alice"), text("Carol")]);
+        assert_eq!(
+            by_day(col("day").desc().nulls_last()),
+            vec![text("Alice"), text("Carol"), text("Bob")]
+        );
+        assert_eq!(by_day(col("day").asc()), vec![text("Carol"), text("Alice"), text("Bob")]);
+        assert_eq!(
+            by_day(col("day").asc().nulls_first()),
+            vec![text("Bob"), text("Carol"), text("Alice")]
+        );
+
+        std::fs::remove_dir_all(path).unwrap();
+    }
+
+    #[test]
+    fn test_dataframe_distinct() {
+        let path = "test_dataframe_distinct_dir";
+        let _ = std::fs::remove_dir_all(path);
+        let db = Database::open(path).unwrap();
+
+        db.execute("CREATE TABLE customers (id INTEGER, city VARCHAR(20))")
+            .unwrap();
+        db.execute("CREATE TABLE orders (id INTEGER, customer_id INTEGER, amount INTEGER)")
+            .unwrap();
+        db.execute("INSERT INTO customers VALUES (1, 'Oslo'), (2, 'Rome'), (3, 'Oslo'), (4, 'Lima')")
+            .unwrap();
+        db.execute("INSERT INTO orders VALUES (10, 1, 5), (11, 3, 40), (12, 1, 25), (13, 2, 40)")
+            .unwrap();
+
+        let text = |s: &str| Value::Varchar(s.to_string());
+        let column = |df: DataFrame, index: usize| -> Vec<Value> {
+            df.collect()
+                .unwrap()
+                .into_iter()
+                .map(|row| row.values[index].clone())
+                .collect()
+        };
+
+        let cities = db.table("customers").unwrap().select(&["city"]).distinct();
+        assert_eq!(
+            cities.explain().unwrap(),
+            concat!(
+                "Distinct(city)\n",
+                "  -> Projection[city]\n",
+                "    -> SeqScan(customers)\n",
+            )
+        );
+        assert_eq!(column(cities, 0), vec![text("Oslo"), text("Rome"), text("Lima")]);
+
+        // Lima has no orders: NULL amounts count as one value
+        let amounts = db
+            .table("customers")
+            .unwrap()
+            .join(
+                db.table("orders").unwrap(),
+                col("customers.id").eq(col("orders.customer_id")),
+                JoinType::Left,
+            )
+            .unwrap()
+            .select(&["amount"])
+            .distinct();
+        assert_eq!(
+            column(amounts, 0),
+            vec![Value::Integer(5), Value::Integer(25), Value::Integer(40), Value::Null]
+        );
+
+        // Each customer's largest order; the limit applies after duplicates are removed
+        let largest = db
+            .table("orders")
+            .unwrap()
+            .order_by(&[col("amount").desc()])
+            .distinct_on(&["customer_id"])
+            .limit(2);
+        assert_eq!(
+            largest.explain().unwrap(),
+            concat!(
+                "Limit(2)\n",
+                "  -> Distinct(customer_id)\n",
+                "    -> Sort(amount DESC)\n",
+                "      -> SeqScan(orders)\n",
+            )
+        );
+        assert_eq!(column(largest, 0), vec![Value::Integer(11), Value::Integer(13)]);
+
+        let unknown = db.table("orders").unwrap().select(&["id"]).distinct_on(&["amount"]);
+        assert!(matches!(
+            unknown.collect(),
+            Err(QueryError::ColumnNotFound { .. })
+        ));
+
+        std::fs::remove_dir_all(path).unwrap();
+    }
+
+    #[test]
+    fn test_dataframe_slice() {
+        let path = "test_dataframe_slice_dir";
+        let _ = std::fs::remove_dir_all(path);
+        let db = Database::open(path).unwrap();
+
+        db.execute("CREATE TABLE orders (id INTEGER, amount INTEGER)")
+            .unwrap();
+        for id in 1..=25 {
+            db.execute(&format!("INSERT INTO orders VALUES ({}, {})", id, id * 7 % 10))
+                .unwrap();
+        }
+        let ids = |df: DataFrame| -> Vec<i32> {
+            df.collect()
+                .unwrap()
+                .into_iter()
+                .map(|row| match row.values[0] {
+                    Value::Integer(id) => id,
+                    _ => panic!("Expected an integer id"),
+                })
+                .collect()
+        };
+        let orders = || db.table("orders").unwrap();
+
+        assert_eq!(ids(orders().slice(10, 3)), vec![11, 12, 13]);
+        assert_eq!(ids(orders().offset(22)), vec![23, 24, 25]);
+        assert_eq!(ids(orders().slice(24, 10)), vec![25]);
+        assert!(ids(orders().slice(30, 10)).is_empty());
+
+        // The top-N keeps the skipped rows too; the page is then cut from them
+        let page = orders()
+            .order_by(&[col("amount").desc(), col("id").asc()])
+            .select(&["id"])
+            .slice(2, 3);
+        assert_eq!(
+            page.explain().unwrap(),
+            concat!(
+                "Limit(3, offset: 2)\n",
+                "  -> Projection[id]\n",
+                "    -> TopN(5, amount DESC, id)\n",
+                "      -> SeqScan(orders)\n",
+            )
+        );
+        // Amount 9 for ids 7, 17; amount 8 for ids 4, 14, 24
+        assert_eq!(ids(page), vec![4, 14, 24]);
+
+        let rows = db.sql("SELECT id FROM orders LIMIT 2 OFFSET 5").unwrap();
+        assert_eq!(
+            rows,
+            vec![Row::new(vec![Value::Integer(6)]), Row::new(vec![Value::Integer(7)])]
+        );
+
+        std::fs::remove_dir_all(path).unwrap();
+    }
+
+    #[test]
+    fn test_dataframe_chained_operations() {
+        let path = "test_dataframe_chained_operations_dir";
+        let _ = std::fs::remove_dir_all(path);
+        let db = Database::open(path).unwrap();
+
+        db.execute("CREATE TABLE orders (id INTEGER, amount INTEGER)")
+            .unwrap();
+        for id in 1..=25 {
+            db.execute(&format!("INSERT INTO orders VALUES ({}, {})", id, id * 7 % 10))
+                .unwrap();
+        }
+        let ids = |df: DataFrame| -> Vec<i32> {
+            df.collect()
+                .unwrap()
+                .into_iter()
+                .map(|row| match row.values[0] {
+                    Value::Integer(id) => id,
+                    _ => panic!("Expected an integer id"),
+                })
+                .collect()
+        };
+        let orders = || db.table("orders").unwrap();
+
+        // Every filter applies, merged into one
+        let between = orders()
+            .filter(col("id").gt(lit(5)))
+            .filter(col("id").lt_eq(lit(8)));
+        assert_eq!(
+            between.explain().unwrap(),
+            concat!("Filter(id > 5 AND id <= 8)\n", "  -> SeqScan(orders)\n")
+        );
+        assert_eq!(ids(between), vec![6, 7, 8]);
+
+        // A filter after a projection sees the projected columns, and only those
+        let doubled = orders()
+            .select_exprs(&[(col("id"), "id"), (col("amount").add(col("amount")), "double")])
+            .filter(col("double").gt(lit(16)));
+        assert_eq!(ids(doubled), vec![7, 17]);
+        let dropped = orders()
+            .select(&["id"])
+            .filter(col("amount").gt(lit(8)))
+            .collect();
+        assert!(matches!(dropped, Err(QueryError::ColumnNotFound { .. })));
+
+        // A filter after a limit only looks at the rows the limit kept
+        let large = || col("amount").gt_eq(lit(8));
+        assert_eq!(ids(orders().limit(10).filter(large())), vec![4, 7]);
+        assert_eq!(ids(orders().filter(large()).limit(10)), vec![4, 7, 14, 17, 24]);
+
+        // A sort after a projection can use the names it gives
+        let by_alias = orders()
+            .select_exprs(&[(col("id"), "id"), (col("amount"), "a")])
+            .order_by(&[col("a").desc(), col("id").asc()])
+            .limit(3);
+        assert_eq!(
+            by_alias.explain().unwrap(),
+            concat!(
+                "TopN(3, a DESC, id)\n",
+                "  -> Projection[id, amount AS a]\n",
+                "    -> SeqScan(orders)\n",
+            )
+        );
+        assert_eq!(ids(by_alias), vec![7, 17, 4]);
+
+        // Keys mixing a projection's names with a column it drops carry the column through
+        // it until after the sort
+        let mixed = orders()
+            .select_exprs(&[(col("id").add(lit(1)), "x")])
+            .order_by(&[col("amount").desc(), col("x").asc()])
+            .limit(3);
+        assert_eq!(mixed.schema().unwrap().columns.len(), 1);
+        assert_eq!(
+            mixed.explain().unwrap(),
+            concat!(
+                "Projection[x]\n",
+                "  -> TopN(3, amount DESC, x)\n",
+                "    -> Projection[id + 1 AS x, amount]\n",
+                "      -> SeqScan(orders)\n",
+            )
+        );
+        assert_eq!(ids(mixed), vec![8, 18, 5]);
+
+        // Limits and offsets apply to what the calls before them kept
+        assert_eq!(ids(orders().limit(10).offset(3)), vec![4, 5, 6, 7, 8, 9, 10]);
+        assert_eq!(ids(orders().offset(3).limit(2)), vec![4, 5]);
+        assert_eq!(ids(orders().limit(2).limit(5)).len(), 2);
+        assert_eq!(
+            orders().limit(10).offset(3).limit(4).explain().unwrap(),
+            concat!("Limit(4, offset: 3)\n", "  -> SeqScan(orders)\n")
+        );
+
+        // Modifications match the rows passing every filter
+        let updated = orders()
+            .filter(col("id").gt(lit(20)))
+            .filter(col("amount").lt(lit(5)))
+            .update(&[("amount", lit(100))])
+            .unwrap();
+        assert_eq!(updated, 2);
+        assert_eq!(ids(orders().filter(col("amount").eq(lit(100)))), vec![22, 23]);
+
+        std::fs::remove_dir_all(path).unwrap();
+    }
+
+    #[test]
+    fn test_dataframe_set_operations() {
+        let path = "test_dataframe_set_operations_dir";
+        let _ = std::fs::remove_dir_all(path);
+        let db = Database::open(path).unwrap();
+
+        db.execute("CREATE TABLE orders (id INTEGER, customer VARCHAR(10))")
+            .unwrap();
+        db.execute("CREATE TABLE archived_orders (id INTEGER, customer VARCHAR(20))")
+            .unwrap();
+        db.execute("INSERT INTO orders VALUES (4, 'ann'), (5, 'bob'), (6, 'ann')")
+            .unwrap();
+        db.execute("INSERT INTO archived_orders VALUES (1, 'cy'), (2, 'bob'), (3, 'ann')")
+            .unwrap();
+
+        let customers = |table: &str| db.table(table).unwrap().select(&["customer"]);
+        let names = |df: DataFrame| -> Vec<String> {
+            df.collect()
+                .unwrap()
+                .into_iter()
+                .map(|row| match &row.values[0] {
+                    Value::Varchar(name) => name.clone(),
+                    _ => panic!("Expected a name"),
+                })
+                .collect()
+        };
+
+        // The result takes the left side's column names and the longer VARCHAR
+        let all = db
+            .table("orders")
+            .unwrap()
+            .union_all(db.table("archived_orders").unwrap())
+            .unwrap();
+        let schema = all.schema().unwrap();
+        assert_eq!(schema.columns[1].name, "customer");
+        assert_eq!(schema.columns[1].length, 20);
+        assert_eq!(all.filter(col("id").lt(lit(3))).collect().unwrap().len(), 2);
+
+        let union = customers("orders").union(customers("archived_orders")).unwrap();
+        assert_eq!(
+            union.explain().unwrap(),
+            concat!(
+                "Union\n",
+                "  -> Projection[customer]\n",
+                "    -> SeqScan(orders)\n",
+                "  -> Projection[customer]\n",
+                "    -> SeqScan(archived_orders)\n",
+            )
+        );
+        assert_eq!(names(union), vec!["ann", "bob", "cy"]);
+        let both = customers("orders").intersect(customers("archived_orders"));
+        assert_eq!(names(both.unwrap()), vec!["ann", "bob"]);
+        let only_archived = customers("archived_orders").except(customers("orders"));
+        assert_eq!(names(only_archived.unwrap()), vec!["cy"]);
+
+        // Set operations can be chained and sorted like any other DataFrame
+        let sorted = customers("orders")
+            .union_all(customers("archived_orders"))
+            .unwrap()
+            .except(
+                db.table("archived_orders")
+                    .unwrap()
+                    .filter(col("id").eq(lit(2)))
+                    .select(&["customer"]),
+            )
+            .unwrap()
+            .order_by(&[col("customer").desc()]);
+        assert_eq!(names(sorted), vec!["cy", "ann"]);
+
+        let columns = db.table("orders").unwrap().union(customers("archived_orders"));
+        assert!(matches!(columns, Err(QueryError::ExecutionError(_))));
+        let types = db
+            .table("orders")
+            .unwrap()
+            .select(&["customer", "id"])
+            .union(db.table("archived_orders").unwrap());
+        assert!(matches!(types, Err(QueryError::TypeMismatch(_))));
+
+        std::fs::remove_dir_all(path).unwrap();
+    }
+
+    #[test]
+    fn test_dataframe_with_window() {
+        let path = "test_dataframe_with_window_dir";
+        let _ = std::fs::remove_dir_all(path);
+        let db = Database::open(path).unwrap();
+
+        db.execute("CREATE TABLE orders (id INTEGER, customer_id INTEGER, amount INTEGER)")
+            .unwrap();
+        db.execute(
+            "INSERT INTO orders VALUES (1, 7, 30), (2, 8, 10), (3, 7, 50), (4, 7, 20), (5, 8, 40)",
+        )
+        .unwrap();
+        let integers = |df: DataFrame| -> Vec<Vec<i32>> {
+            df.collect()
+                .unwrap()
+                .into_iter()
+                .map(|row| {
+                    row.values
+                        .iter()
+                        .map(|value| match value {
+                            Value::Integer(i) => *i,
+                            _ => panic!("Expected an integer"),
+                        })
+                        .collect()
+                })
+                .collect()
+        };
+        let by_amount = || partition_by(&[col("customer_id")]).order_by(&[col("amount").desc()]);
+
+        // Each customer's largest order, keeping the first row of each partition
+        let largest = db
+            .table("orders")
+            .unwrap()
+            .with_window("rn", row_number().over(by_amount()))
+            .unwrap()
+            .filter(col("rn").eq(lit(1)))
+            .select(&["id"]);
+        assert_eq!(
+            largest.explain().unwrap(),
+            concat!(
+                "Projection[id]\n",
+                "  -> Filter(rn = 1)\n",
+                "    -> Window(row_number() OVER (PARTITION BY customer_id ",
+                "ORDER BY amount DESC) AS rn)\n",
+                "      -> Sort(customer_id, amount DESC)\n",
+                "        -> SeqScan(orders)\n",
+            )
+        );
+        assert_eq!(integers(largest), vec![vec![3], vec![5]]);
+
+        // A running total, and the previous order's amount, in order of id
+        let running = db
+            .table("orders")
+            .unwrap()
+            .with_window(
+                "total",
+                sum(col("amount")).over(partition_by(&[]).order_by(&[col("id").asc()])),
+            )
+            .unwrap()
+            .with_window("previous", lag(col("amount"), 1).over(by_amount()))
+            .unwrap()
+            .filter(col("customer_id").eq(lit(7)))
+            .order_by(&[col("id").asc()])
+            .select(&["id", "total", "previous"]);
+        let int = |i: i32| Value::Integer(i);
+        assert_eq!(
+            running.collect().unwrap(),
+            vec![
+                Row::new(vec![int(1), int(30), int(50)]),
+                Row::new(vec![int(3), int(90), Value::Null]),
+                Row::new(vec![int(4), int(110), int(30)]),
+            ]
+        );
+
+        let taken = db
+            .table("orders")
+            .unwrap()
+            .with_window("amount", rank().over(by_amount()));
+        assert!(matches!(taken, Err(QueryError::ExecutionError(_))));
+        let unknown = db
+            .table("orders")
+            .unwrap()
+            .with_window("rn", row_number().over(partition_by(&[col("customer")])));
+        assert!(matches!(unknown, Err(QueryError::ColumnNotFound { .. })));
+
+        std::fs::remove_dir_all(path).unwrap();
+    }
+
+    #[test]
+    fn test_dataframe_subqueries() {
+        let path = "test_dataframe_subquery_dir";
+        let _ = std::fs::remove_dir_all(path);
+        let db = Database::open(path).unwrap();
+
+        db.execute("CREATE TABLE customers (id INTEGER, name VARCHAR(20))")
+            .unwrap();
+        db.execute("CREATE TABLE orders (id INTEGER, customer_id INTEGER, amount INTEGER)")
+            .unwrap();
+        db.execute("INSERT INTO customers VALUES (1, 'Alice'), (2, 'Bob'), (3, 'Carol')")
+            .unwrap();
+        db.execute("INSERT INTO orders VALUES (10, 1, 50), (11, 1, 150), (12, 3, 70), (13, 3, 30)")
+            .unwrap();
+
+        let customers = || db.table("customers").unwrap();
+        let orders = || db.table("orders").unwrap();
+        let buyers = || orders().select(&["customer_id"]);
+        let column = |df: DataFrame, i: usize| -> Vec<Value> {
+            df.collect()
+                .unwrap()
+                .into_iter()
+                .map(|row| row.values[i].clone())
+                .collect()
+        };
+        let ints = |values: &[i32]| values.iter().map(|&i| Value::Integer(i)).collect::<Vec<_>>();
+
+        // IN and NOT IN conditions of a filter become semi and anti joins
+        let with_orders = customers().filter(col("id").in_subquery(buyers()));
+        assert!(with_orders.explain().unwrap().starts_with(
+            "HashJoin(Semi, customers.id = orders.customer_id, build: right)"
+        ));
+        assert_eq!(column(with_orders, 0), ints(&[1, 3]));
+        let without = customers().filter(col("id").not_in_subquery(buyers()));
+        assert_eq!(column(without, 0), ints(&[2]));
+        let not_alice = customers().filter(
+            col("name")
+                .not_eq(lit_str("Alice"))
+                .and(col("id").in_subquery(buyers())),
+        );
+        assert_eq!(column(not_alice, 0), ints(&[3]));
+
+        // Elsewhere, an uncorrelated subquery's rows are fetched once before the query runs
+        let flagged = customers().select_exprs(&[(col("id").in_subquery(buyers()), "buyer")]);
+        assert!(flagged.explain().unwrap().starts_with("Subqueries(customer_id)\n"));
+        assert_eq!(column(flagged, 0), ints(&[1, 0, 1]));
+        let average = || orders().group_by(&[]).agg(&[avg(col("amount"))]).unwrap();
+        let above_average = orders().filter(col("amount").gt(scalar_subquery(average())));
+        assert_eq!(column(above_average, 0), ints(&[11]));
+        // Arithmetic on a scalar subquery is an INTEGER column
+        let shifted =
+            orders().select_exprs(&[(col("amount").add(scalar_subquery(average())), "shifted")]);
+        let shifted_column = shifted.schema().unwrap().columns[0].clone();
+        assert!(matches!(shifted_column.column_type, Type::Integer));
+        assert_eq!((shifted_column.name.as_str(), shifted_column.length), ("shifted", 4));
+        // Nested subqueries are checked against their plans' schemas too
+        let wide = customers().select_exprs(&[(col("id").in_subquery(customers()), "wide")]);
+        assert!(wide.schema().is_err());
+        let too_many = customers().select_exprs(&[(scalar_subquery(buyers()), "buyer")]);
+        assert!(matches!(too_many.collect(), Err(QueryError::ExecutionError(_))));
+
+        // A correlated IN subquery joins on its conditions on the outer columns too
+        let orders_of_customer = || orders().filter(col("customer_id").eq(col("customers.id")));
+        let spent_150 =
+            customers().filter(lit(150).in_subquery(orders_of_customer().select(&["amount"])));
+        assert_eq!(column(spent_150, 0), ints(&[1]));
+        let never_150 =
+            customers().filter(lit(150).not_in_subquery(orders_of_customer().select(&["amount"])));
+        assert_eq!(column(never_150, 0), ints(&[2, 3]));
+
+        // A correlated scalar aggregate becomes a left join with the aggregate per key
+        let largest = || {
+            orders_of_customer()
+                .group_by(&[])
+                .agg(&[max(col("amount"))])
+                .unwrap()
+        };
+        let largest_orders =
+            customers().select_exprs(&[(col("name"), "name"), (scalar_subquery(largest()), "max")]);
+        assert_eq!(
+            concat!(
+                "Projection[name, max(amount) AS max]\n",
+                "  -> HashJoin(Left, id = orders.customer_id, build: right)\n",
+                "    -> SeqScan(customers)\n",
+                "    -> HashAggregate(keys: [customer_id], aggregates: [max(amount)])\n",
+                "      -> SeqScan(orders)\n"
+            ),
+            largest_orders.explain().unwrap()
+        );
+        assert_eq!(
+            column(largest_orders, 1),
+            vec![Value::Integer(150), Value::Null, Value::Integer(70)]
+        );
+        // Orders larger than their customer's average, with the joined columns dropped
+        let customer_average = orders()
+            .alias("o")
+            .filter(col("o.customer_id").eq(col("orders.customer_id")))
+            .group_by(&[])
+            .agg(&[avg(col("amount"))])
+            .unwrap();
+        let above = orders().filter(col("amount").gt(scalar_subquery(customer_average)));
+        assert_eq!(above.schema().unwrap().columns.len(), 3);
+        assert_eq!(column(above, 0), ints(&[11, 12]));
+        let counted = orders_of_customer().group_by(&[]).agg(&[count_star()]).unwrap();
+        let counts = customers().select_exprs(&[(scalar_subquery(counted), "orders")]);
+        assert!(matches!(counts.collect(), Err(QueryError::ExecutionError(_))));
+
+        // Parameters inside a subquery are supplied with the outer query's
+        let big_spenders = customers().filter(
+            col("id").in_subquery(
+                orders()
+                    .filter(col("amount").gt(param(1)))
+                    .select(&["customer_id"]),
+            ),
+        );
+        let mut prepared = big_spenders.prepare().unwrap();
+        let ids = |rows: Vec<Row>| rows.into_iter().map(|row| row.values[0].clone()).collect();
+        let run = |prepared: &mut PreparedQuery, min: i32| -> Vec<Value> {
+            ids(prepared.execute(&[Value::Integer(min)]).unwrap())
+        };
+        assert_eq!(run(&mut prepared, 100), ints(&[1]));
+        assert_eq!(run(&mut prepared, 40), ints(&[1, 3]));
+
+        std::fs::remove_dir_all(path).unwrap();
+    }
+
+    #[test]
+    fn test_dataframe_common_tables() {
+        let path = "test_dataframe_cte_dir";
+        let _ = std::fs::remove_dir_all(path);
+        let db = Database::open(path).unwrap();
+
+        db.execute("CREATE TABLE employees (id INTEGER, name VARCHAR(20), manager_id INTEGER)")
+            .unwrap();
+        db.execute(
+            "INSERT INTO employees VALUES (1, 'Ann', 0), (2, 'Bob', 1), (3, 'Cid', 1), \
+             (4, 'Dee', 2), (5, 'Eve', 4), (6, 'Fay', 3)",
+        )
+        .unwrap();
+        db.execute("CREATE TABLE links (src INTEGER, dst INTEGER)")
+            .unwrap();
+        db.execute("INSERT INTO links VALUES (1, 2), (2, 3), (3, 1), (3, 4)")
+            .unwrap();
+
+        let employees = || db.table("employees").unwrap();
+        let column = |df: DataFrame, i: usize| -> Vec<Value> {
+            df.collect()
+                .unwrap()
+                .into_iter()
+                .map(|row| row.values[i].clone())
+                .collect()
+        };
+        let ints = |values: &[i32]| values.iter().map(|&i| Value::Integer(i)).collect::<Vec<_>>();
+
+        // A common table read twice is computed once, before the query runs
+        let managers = employees()
+            .filter(col("id").in_subquery(employees().select(&["manager_id"])))
+            .cte("managers")
+            .unwrap();
+        let with_boss = managers
+            .table()
+            .alias("m")
+            .join(
+                managers.table().alias("b"),
+                col("m.manager_id").eq(col("b.id")),
+                JoinType::Inner,
+            )
+            .unwrap()
+            .select(&["m.name", "b.name"]);
+        let plan = with_boss.explain().unwrap();
+        assert!(plan.starts_with("With(managers)\n"), "{}", plan);
+        assert_eq!(plan.matches("CteScan(managers)").count(), 2, "{}", plan);
+        assert_eq!(plan.matches("SeqScan(employees)").count(), 2, "{}", plan);
+        let names = |df: DataFrame| -> Vec<(Value, Value)> {
+            df.collect()
+                .unwrap()
+                .into_iter()
+                .map(|row| (row.values[0].clone(), row.values[1].clone()))
+                .collect()
+        };
+        let name = |s: &str| Value::Varchar(s.to_string());
+        assert_eq!(
+            names(with_boss),
+            vec![
+                (name("Bob"), name("Ann")),
+                (name("Cid"), name("Ann")),
+                (name("Dee"), name("Bob"))
+            ]
+        );
+        assert!(managers.table().delete().is_err());
+
+        // Everyone under an employee: the step joins the employees reporting to the rows
+        // found in the previous round
+        let reports = |top: Expression| {
+            employees()
+                .filter(col("id").eq(top))
+                .select(&["id", "name"])
+                .recursive_union_all("reports", |reports| {
+                    Ok(employees()
+                        .join(
+                            reports,
+                            col("manager_id").eq(col("reports.id")),
+                            JoinType::Inner,
+                        )?
+                        .select(&["employees.id", "employees.name"]))
+                })
+                .unwrap()
+        };
+        let under_bob = reports(lit(2));
+        assert_eq!(under_bob.schema().columns.len(), 2);
+        assert_eq!(
+            concat!(
+                "With(reports)\n",
+                "  -> CteScan(reports)\n",
+                "  -> RecursiveUnionAll(reports)\n",
+                "    -> Projection[id, name]\n",
+                "      -> Filter(id = 2)\n",
+                "        -> SeqScan(employees)\n",
+                "    -> Projection[employees.id, employees.name]\n",
+                "      -> HashJoin(Inner, employees.manager_id = reports.id, build: right)\n",
+                "        -> SeqScan(employees)\n",
+                "        -> CteScan(reports)\n"
+            ),
+            under_bob.table().explain().unwrap()
+        );
+        assert_eq!(column(under_bob.table(), 0), ints(&[2, 4, 5]));
+
+        // UNION drops the rows found before, so following a cycle ends
+        let reachable = db
+            .table("links")
+            .unwrap()
+            .filter(col("src").eq(lit(1)))
+            .select(&["src"])
+            .recursive_union("reachable", |reachable| {
+                Ok(db
+                    .table("links")?
+                    .join(reachable, col("links.src").eq(col("reachable.src")), JoinType::Inner)?
+                    .select_exprs(&[(col("dst"), "src")]))
+            })
+            .unwrap();
+        assert_eq!(column(reachable.table(), 0), ints(&[1, 2, 3, 4]));
+
+        // The step must return the base's columns
+        let mismatch = employees().select(&["id"]).recursive_union("bad", |bad| {
+            Ok(bad.select_exprs(&[(col("id"), "id"), (lit(1), "x")]))
+        });
+        assert!(matches!(mismatch, Err(QueryError::ExecutionError(_))));
+
+        // A prepared query recomputes its common tables on every run, from the current rows
+        let mut prepared = reports(param(1)).table().prepare().unwrap();
+        let ids = |rows: Vec<Row>| rows.into_iter().map(|row| row.values[0].clone()).collect();
+        let run = |prepared: &mut PreparedQuery, top: i32| -> Vec<Value> {
+            ids(prepared.execute(&[Value::Integer(top)]).unwrap())
+        };
+        assert_eq!(run(&mut prepared, 2), ints(&[2, 4, 5]));
+        assert_eq!(run(&mut prepared, 3), ints(&[3, 6]));
+        db.execute("DELETE FROM employees WHERE id = 4").unwrap();
+        assert_eq!(run(&mut prepared, 2), ints(&[2]));
+
+        // Executions of the same common tables at the same time each keep their own rows
+        let shared = reports(param(1));
+        std::thread::scope(|scope| {
+            for top in [1, 3] {
+                let table = shared.table();
+                scope.spawn(move || {
+                    let mut prepared = table.prepare().unwrap();
+                    let expected = if top == 1 { 4 } else { 2 };
+                    for _ in 0..50 {
+                        let rows = prepared.execute(&[Value::Integer(top)]).unwrap();
+                        assert_eq!(rows.len(), expected);
+                    }
+                });
+            }
+        });
 
         std::fs::remove_dir_all(path).unwrap();
     }
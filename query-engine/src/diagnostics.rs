@@ -0,0 +1,119 @@
+//! Helpers for making errors point at their cause: source locations with a caret snippet,
+//! and "did you mean" suggestions for misspelled names.
+
+use std::fmt;
+
+/// The place in SQL text that an error refers to.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SourceLocation {
+    /// Line of the start of the span, counting from 1
+    pub line: usize,
+    /// Column of the start of the span in characters, counting from 1
+    pub column: usize,
+    /// The line containing the span, followed by a line of carets underneath it
+    pub snippet: String,
+}
+
+impl SourceLocation {
+    /// Locates the `len` bytes at `offset` in `sql`.
+    pub fn new(sql: &str, offset: usize, len: usize) -> Self {
+        let offset = offset.min(sql.len());
+        let (line, column) = crate::sql::lexer::line_col(sql, offset);
+
+        let line_start = sql[..offset].rfind('\n').map_or(0, |i| i + 1);
+        let line_end = sql[offset..].find('\n').map_or(sql.len(), |i| offset + i);
+        let source_line = sql[line_start..line_end].trim_end_matches('\r');
+
+        // Keep tabs in the padding so the carets line up however the terminal renders them
+        let padding: String = sql[line_start..offset]
+            .chars()
+            .map(|c| if c == '\t' { '\t' } else { ' ' })
+            .collect();
+        let end = (offset + len).min(line_end);
+        let carets = "^".repeat(sql[offset..end].chars().count().max(1));
+
+        Self {
+            line,
+            column,
+            snippet: format!("{}\n{}{}", source_line, padding, carets),
+        }
+    }
+}
+
+impl fmt::Display for SourceLocation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.snippet)
+    }
+}
+
+/// Returns the candidate closest to `name`, if any is close enough to be a likely typo.
+///
+/// Names are compared case-insensitively by edit distance, counting a swap of adjacent
+/// characters as one edit. Up to one edit per three characters is tolerated.
+pub(crate) fn closest_match<'a>(
+    name: &str,
+    candidates: impl IntoIterator<Item = &'a str>,
+) -> Option<String> {
+    let name = name.to_lowercase();
+    let max_distance = (name.chars().count() / 3).max(1);
+
+    candidates
+        .into_iter()
+        .map(|candidate| (edit_distance(&name, &candidate.to_lowercase()), candidate))
+        .filter(|(distance, _)| *distance <= max_distance)
+        .min_by_key(|(distance, _)| *distance)
+        .map(|(_, candidate)| candidate.to_string())
+}
+
+/// Optimal string alignment distance: insertions, deletions, substitutions and adjacent
+/// transpositions each cost one.
+fn edit_distance(a: &str, b: &str) -> usize {
+    let a: Vec<char> = a.chars().collect();
+    let b: Vec<char> = b.chars().collect();
+
+    // Three rolling rows of the dynamic programming table
+    let mut before_previous = vec![0; b.len() + 1];
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    let mut current = vec![0; b.len() + 1];
+
+    for i in 1..=a.len() {
+        current[0] = i;
+        for j in 1..=b.len() {
+            let cost = usize::from(a[i - 1] != b[j - 1]);
+            current[j] = (previous[j] + 1)
+                .min(current[j - 1] + 1)
+                .min(previous[j - 1] + cost);
+            if i > 1 && j > 1 && a[i - 1] == b[j - 2] && a[i - 2] == b[j - 1] {
+                current[j] = current[j].min(before_previous[j - 2] + 1);
+            }
+        }
+        std::mem::swap(&mut before_previous, &mut previous);
+        std::mem::swap(&mut previous, &mut current);
+    }
+    previous[b.len()]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_closest_match() {
+        let columns = ["id", "name", "age"];
+        assert_eq!(closest_match("agee", columns), Some("age".to_string()));
+        assert_eq!(closest_match("nmae", columns), Some("name".to_string()));
+        assert_eq!(closest_match("NAME", columns), Some("name".to_string()));
+        assert_eq!(closest_match("salary", columns), None);
+    }
+
+    #[test]
+    fn test_source_location_snippet() {
+        let sql = "SELECT name\nFROM users WHERE agee > 25";
+        let location = SourceLocation::new(sql, sql.find("agee").unwrap(), 4);
+        assert_eq!((location.line, location.column), (2, 18));
+        assert_eq!(
+            location.snippet,
+            "FROM users WHERE agee > 25\n                 ^^^^"
+        );
+    }
+}
@@ -0,0 +1,264 @@
+//! Common table executors for WITH queries.
+//!
+//! The rows of a common table are computed once per execution, however many times the query
+//! reads them. A [`CteExecutor`] at the root of the plan runs each common table's plan on
+//! init and writes its rows to a temporary table, before initializing the query itself.
+//! Every reference to the common table is a [`CteScanExecutor`] reading that table back.
+
+use super::{BoxedExecutor, Executor};
+use crate::catalog::temp::{TempTable, TempTableScan};
+use crate::catalog::Catalog;
+use crate::types::Row;
+use crate::{QueryError, Result};
+use std::sync::{Arc, RwLock};
+use storage_engine::tuple::Schema;
+
+/// Where the rows of a common table are kept once computed.
+///
+/// Cloning shares the same rows, so the scans reading them see what the executor computing
+/// them stored. Logical plans use a `CteRows` only to identify the common table; each
+/// executor tree is given slots of its own.
+#[derive(Clone)]
+pub struct CteRows {
+    name: String,
+    table: Arc<RwLock<Option<Arc<TempTable>>>>,
+}
+
+impl CteRows {
+    /// Creates an empty slot for the rows of the common table `name`.
+    pub fn new(name: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            table: Arc::new(RwLock::new(None)),
+        }
+    }
+
+    /// Returns the name of the common table.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Returns whether `other` is a clone of this slot, sharing its rows.
+    pub(crate) fn same_table(&self, other: &CteRows) -> bool {
+        Arc::ptr_eq(&self.table, &other.table)
+    }
+
+    /// Replaces the rows with those of `table`.
+    pub fn set(&self, table: TempTable) {
+        *self.table.write().unwrap() = Some(Arc::new(table));
+    }
+
+    /// Drops the rows. Scans still reading them keep them alive until they are done.
+    pub fn clear(&self) {
+        *self.table.write().unwrap() = None;
+    }
+
+    /// Returns the current rows, or an error if none have been stored.
+    fn get(&self) -> Result<Arc<TempTable>> {
+        self.table.read().unwrap().clone().ok_or_else(|| {
+            QueryError::ExecutionError(format!(
+                "The rows of common table '{}' have not been computed",
+                self.name
+            ))
+        })
+    }
+}
+
+/// Executor that computes the rows of common tables before its input runs, returning the
+/// input's tuples unchanged.
+pub struct CteExecutor {
+    child: BoxedExecutor,
+    /// Each common table's plan, with where its rows go, dependencies first
+    tables: Vec<(BoxedExecutor, CteRows)>,
+    catalog: Arc<Catalog>,
+}
+
+impl CteExecutor {
+    /// Creates a new common table executor.
+    ///
+    /// # Arguments
+    /// * `child` - The child executor whose scans read the common tables
+    /// * `tables` - The plan of each common table, with the rows it fills in, ordered so
+    ///   that a plan only reads the tables before it
+    /// * `catalog` - Where the temporary tables holding the rows are created
+    pub fn new(
+        child: BoxedExecutor,
+        tables: Vec<(BoxedExecutor, CteRows)>,
+        catalog: Arc<Catalog>,
+    ) -> Self {
+        Self {
+            child,
+            tables,
+            catalog,
+        }
+    }
+}
+
+impl Executor for CteExecutor {
+    fn schema(&self) -> &Schema {
+        self.child.schema()
+    }
+
+    fn init(&mut self) -> Result<()> {
+        // Free the previous execution's rows before computing new ones
+        for (_, rows) in &self.tables {
+            rows.clear();
+        }
+        for (plan, rows) in &mut self.tables {
+            plan.init()?;
+            let mut table = self.catalog.create_temp_table(plan.schema().clone())?;
+            while let Some(tuple) = plan.next()? {
+                table.insert(&tuple)?;
+            }
+            rows.set(table);
+        }
+        self.child.init()
+    }
+
+    fn describe(&self) -> String {
+        let names: Vec<&str> = self.tables.iter().map(|(_, rows)| rows.name()).collect();
+        format!("With({})", names.join(", "))
+    }
+
+    fn children(&self) -> Vec<&dyn Executor> {
+        let mut children = vec![self.child.as_ref()];
+        children.extend(self.tables.iter().map(|(plan, _)| plan.as_ref()));
+        children
+    }
+
+    fn sort_order(&self) -> Vec<usize> {
+        self.child.sort_order()
+    }
+
+    fn next(&mut self) -> Result<Option<Row>> {
+        self.child.next()
+    }
+}
+
+/// Executor that reads the rows of a common table, as they were when it was initialized.
+pub struct CteScanExecutor {
+    rows: CteRows,
+    schema: Schema,
+    /// The scan, with the table it reads kept alive
+    scan: Option<(TempTableScan, Arc<TempTable>)>,
+}
+
+impl CteScanExecutor {
+    /// Creates a new common table scan.
+    ///
+    /// # Arguments
+    /// * `rows` - Where the common table's rows are stored
+    /// * `schema` - The columns of the rows
+    pub fn new(rows: CteRows, schema: Schema) -> Self {
+        Self {
+            rows,
+            schema,
+            scan: None,
+        }
+    }
+}
+
+impl Executor for CteScanExecutor {
+    fn schema(&self) -> &Schema {
+        &self.schema
+    }
+
+    fn init(&mut self) -> Result<()> {
+        let table = self.rows.get()?;
+        let mut scan = table.scan();
+        scan.init()?;
+        self.scan = Some((scan, table));
+        Ok(())
+    }
+
+    fn describe(&self) -> String {
+        format!("CteScan({})", self.rows.name())
+    }
+
+    fn next(&mut self) -> Result<Option<Row>> {
+        match &mut self.scan {
+            Some((scan, _)) => scan.next(),
+            None => Ok(None),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::executor::tests::collect_results;
+    use crate::executor::{FilterExecutor, JoinType, NestedLoopJoinExecutor};
+    use crate::expression::{Expression, UnaryOperator};
+    use crate::types::Value;
+    use crate::{int_column, Database};
+
+    #[test]
+    fn test_cte_executor() {
+        let path = "test_cte_dir";
+        let _ = std::fs::remove_dir_all(path);
+        let db = Database::open(path).unwrap();
+
+        let schema = Schema {
+            columns: vec![int_column("x")],
+        };
+        let mut input = db.catalog().create_temp_table(schema.clone()).unwrap();
+        for value in [Some(1), None, Some(2)] {
+            let value = value.map_or(Value::Null, Value::Integer);
+            input.insert(&Row::new(vec![value])).unwrap();
+        }
+
+        // The common table is every non-NULL x, read twice by a self join
+        let rows = CteRows::new("t");
+        let plan = FilterExecutor::new(
+            Box::new(input.scan()),
+            Expression::UnaryOp {
+                op: UnaryOperator::IsNotNull,
+                expr: Box::new(Expression::BoundColumn(0)),
+            },
+        );
+        let scan = || Box::new(CteScanExecutor::new(rows.clone(), schema.clone()));
+        let join_schema = Schema {
+            columns: vec![int_column("a.x"), int_column("b.x")],
+        };
+        let join = NestedLoopJoinExecutor::new(
+            scan(),
+            scan(),
+            Expression::BoundColumn(0).lt_eq(Expression::BoundColumn(1)),
+            JoinType::Inner,
+            join_schema,
+        );
+        let mut executor = CteExecutor::new(
+            Box::new(join),
+            vec![(Box::new(plan), rows.clone())],
+            db.catalog().clone(),
+        );
+        let pairs = |executor: &mut CteExecutor| -> Vec<Vec<Value>> {
+            collect_results(executor)
+                .unwrap()
+                .into_iter()
+                .map(|row| row.values)
+                .collect()
+        };
+        let expected = vec![
+            vec![Value::Integer(1), Value::Integer(1)],
+            vec![Value::Integer(1), Value::Integer(2)],
+            vec![Value::Integer(2), Value::Integer(2)],
+        ];
+        assert_eq!(pairs(&mut executor), expected);
+        assert_eq!(executor.describe(), "With(t)");
+
+        // Running again computes the rows afresh
+        input.insert(&Row::new(vec![Value::Integer(0)])).unwrap();
+        assert_eq!(pairs(&mut executor).len(), 6);
+
+        // Scans only read rows computed by a CteExecutor
+        let mut unfilled = CteScanExecutor::new(CteRows::new("u"), schema);
+        assert!(matches!(
+            collect_results(&mut unfilled),
+            Err(QueryError::ExecutionError(_))
+        ));
+
+        drop((executor, input));
+        std::fs::remove_dir_all(path).unwrap();
+    }
+}
@@ -0,0 +1,261 @@
+//! Distinct executor for DISTINCT and DISTINCT ON.
+//!
+//! Returns the first tuple of each distinct combination of key values, in input order.
+//! Tuples stream through a hash set of the keys seen so far, so a duplicate is dropped as
+//! soon as it arrives. NULL keys count as equal to each other here, as SQL's DISTINCT
+//! requires, even though NULL = NULL is unknown in a predicate.
+//!
+//! If the set grows past the memory limit, the executor falls back to sorting. The keys
+//! already seen stay in the set, and the rest of the input, minus tuples matching them, is
+//! written to a temporary table tagged with input positions, split across two INTEGER
+//! columns so that they do not wrap past 2^31 tuples. An external sort on the keys
+//! brings duplicates together so the first of each can be kept, and a second sort on the
+//! positions restores input order.
+
+use super::sort::DEFAULT_MEMORY_LIMIT;
+use super::{BoxedExecutor, Executor, SortExecutor};
+use crate::catalog::temp::TempTable;
+use crate::catalog::Catalog;
+use crate::expression::{Expression, SortKey};
+use crate::types::{Row, Value};
+use crate::{int_column, QueryError, Result};
+use std::collections::HashSet;
+use std::sync::Arc;
+use storage_engine::tuple::Schema;
+
+/// Distinct executor that drops tuples whose key values have been seen before.
+pub struct DistinctExecutor {
+    child: BoxedExecutor,
+    keys: Vec<Expression>,
+    catalog: Arc<Catalog>,
+    memory_limit: usize,
+    /// Key values of the tuples returned so far
+    seen: HashSet<Vec<Value>>,
+    /// The remaining distinct tuples in input order, each followed by the two columns of
+    /// its position, once the set has outgrown memory
+    fallback: Option<BoxedExecutor>,
+    /// The temporary table `fallback` reads
+    spilled: Option<TempTable>,
+}
+
+impl DistinctExecutor {
+    /// Creates a new distinct executor.
+    ///
+    /// # Arguments
+    /// * `child` - The child executor to pull tuples from
+    /// * `keys` - Expressions bound against the child's schema that tuples must differ in;
+    ///   every column for a plain DISTINCT
+    /// * `catalog` - Where temporary tables are created if the executor falls back to
+    ///   sorting
+    pub fn new(child: BoxedExecutor, keys: Vec<Expression>, catalog: Arc<Catalog>) -> Self {
+        Self {
+            child,
+            keys,
+            catalog,
+            memory_limit: DEFAULT_MEMORY_LIMIT,
+            seen: HashSet::new(),
+            fallback: None,
+            spilled: None,
+        }
+    }
+
+    /// Sets how many distinct keys may be held in memory before falling back to sorting.
+    pub fn with_memory_limit(mut self, keys: usize) -> Self {
+        self.memory_limit = keys.max(1);
+        self
+    }
+
+    /// Returns whether the last run outgrew memory and fell back to sorting.
+    pub fn fell_back(&self) -> bool {
+        self.fallback.is_some()
+    }
+
+    fn key_values(&self, tuple: &Row) -> Result<Vec<Value>> {
+        self.keys.iter().map(|expr| expr.evaluate(tuple)).collect()
+    }
+
+    /// Sets up the sort-based path for the rest of the input, starting with `first`, a
+    /// tuple with an unseen key.
+    fn fall_back(&mut self, first: Row) -> Result<()> {
+        // The position's high bits, then its low 31 bits, both non-negative
+        let mut schema = self.child.schema().clone();
+        schema.columns.push(int_column("position_high"));
+        schema.columns.push(int_column("position_low"));
+        let position_column = schema.columns.len() - 2;
+        let tagged = |mut tuple: Row, position: usize| -> Result<Row> {
+            let high = i32::try_from(position >> 31).map_err(|_| {
+                QueryError::ExecutionError("Too many rows for DISTINCT".to_string())
+            })?;
+            let low = (position & 0x7fff_ffff) as i32;
+            tuple.values.push(Value::Integer(high));
+            tuple.values.push(Value::Integer(low));
+            Ok(tuple)
+        };
+
+        // Tuples whose key was not returned already, tagged with their position
+        let mut remaining = self.catalog.create_temp_table(schema.clone())?;
+        remaining.insert(&tagged(first, 0)?)?;
+        let mut position = 1;
+        while let Some(tuple) = self.child.next()? {
+            if !self.seen.contains(&self.key_values(&tuple)?) {
+                remaining.insert(&tagged(tuple, position)?)?;
+            }
+            position += 1;
+        }
+        self.seen = HashSet::new();
+
+        // The sort is stable, so the first tuple of each run of equal keys came first
+        let sort_keys = self.keys.iter().cloned().map(SortKey::asc).collect();
+        let mut by_key =
+            SortExecutor::new(Box::new(remaining.scan()), sort_keys, self.catalog.clone())
+                .with_memory_limit(self.memory_limit);
+        by_key.init()?;
+        let mut firsts = self.catalog.create_temp_table(schema)?;
+        let mut previous = None;
+        while let Some(tuple) = by_key.next()? {
+            let key = self.key_values(&tuple)?;
+            if previous.as_ref() != Some(&key) {
+                firsts.insert(&tuple)?;
+                previous = Some(key);
+            }
+        }
+        drop(by_key);
+        drop(remaining);
+
+        let position_keys = (position_column..position_column + 2)
+            .map(|column| SortKey::asc(Expression::BoundColumn(column)))
+            .collect();
+        let mut by_position = SortExecutor::new(
+            Box::new(firsts.scan()),
+            position_keys,
+            self.catalog.clone(),
+        )
+        .with_memory_limit(self.memory_limit);
+        by_position.init()?;
+        self.fallback = Some(Box::new(by_position));
+        self.spilled = Some(firsts);
+        Ok(())
+    }
+}
+
+impl Executor for DistinctExecutor {
+    fn schema(&self) -> &Schema {
+        self.child.schema()
+    }
+
+    fn init(&mut self) -> Result<()> {
+        self.seen.clear();
+        self.fallback = None;
+        self.spilled = None;
+        self.child.init()
+    }
+
+    fn describe(&self) -> String {
+        let keys: Vec<String> = self
+            .keys
+            .iter()
+            .map(|expr| expr.display_with(self.child.schema()).to_string())
+            .collect();
+        format!("Distinct({})", keys.join(", "))
+    }
+
+    fn children(&self) -> Vec<&dyn Executor> {
+        vec![self.child.as_ref()]
+    }
+
+    /// Both paths return tuples in input order.
+    fn sort_order(&self) -> Vec<usize> {
+        self.child.sort_order()
+    }
+
+    fn next(&mut self) -> Result<Option<Row>> {
+        if let Some(fallback) = &mut self.fallback {
+            return Ok(fallback.next()?.map(|mut tuple| {
+                tuple.values.truncate(tuple.values.len() - 2);
+                tuple
+            }));
+        }
+
+        while let Some(tuple) = self.child.next()? {
+            let key = self.key_values(&tuple)?;
+            if self.seen.contains(&key) {
+                continue;
+            }
+            if self.seen.len() < self.memory_limit {
+                self.seen.insert(key);
+                return Ok(Some(tuple));
+            }
+            self.fall_back(tuple)?;
+            return self.next();
+        }
+        Ok(None)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::executor::tests::collect_results;
+    use crate::expression::col;
+    use crate::Database;
+
+    #[test]
+    fn test_distinct_executor() {
+        let path = "test_distinct_dir";
+        let _ = std::fs::remove_dir_all(path);
+        let db = Database::open(path).unwrap();
+
+        // Temporary tables can hold NULLs, which stored tables cannot
+        let schema = Schema {
+            columns: vec![int_column("a"), int_column("b")],
+        };
+        let mut input = db.catalog().create_temp_table(schema.clone()).unwrap();
+        let value = |i: i32| {
+            if i == 0 {
+                Value::Null
+            } else {
+                Value::Integer(i)
+            }
+        };
+        let mut rows = Vec::new();
+        for i in 0..60 {
+            rows.push(Row::new(vec![value(i * 7 % 5), value(i % 3)]));
+        }
+        for row in &rows {
+            input.insert(row).unwrap();
+        }
+
+        // The first row of each key, found by comparing with every earlier row
+        let expected = |key: &dyn Fn(&Row) -> Vec<Value>| -> Vec<Row> {
+            rows.iter()
+                .enumerate()
+                .filter(|(i, row)| rows[..*i].iter().all(|earlier| key(earlier) != key(row)))
+                .map(|(_, row)| row.clone())
+                .collect()
+        };
+        let both = expected(&|row| row.values.clone());
+        let first = expected(&|row| vec![row.values[0].clone()]);
+        // NULLs are equal for DISTINCT: 5 values of a times 3 of b
+        assert_eq!((both.len(), first.len()), (15, 5));
+
+        for (names, expected) in [(vec!["a", "b"], both), (vec!["a"], first)] {
+            let keys: Vec<Expression> = names
+                .iter()
+                .map(|name| col(name).bind(&schema).unwrap())
+                .collect();
+            for (memory_limit, fell_back) in [(DEFAULT_MEMORY_LIMIT, false), (2, true)] {
+                let mut distinct = DistinctExecutor::new(
+                    Box::new(input.scan()),
+                    keys.clone(),
+                    db.catalog().clone(),
+                )
+                .with_memory_limit(memory_limit);
+                assert_eq!(collect_results(&mut distinct).unwrap(), expected);
+                assert_eq!(distinct.fell_back(), fell_back);
+            }
+        }
+
+        drop(input);
+        std::fs::remove_dir_all(path).unwrap();
+    }
+}
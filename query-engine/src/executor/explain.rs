@@ -0,0 +1,130 @@
+//! Plan printing and per-operator instrumentation for EXPLAIN / EXPLAIN ANALYZE.
+//!
+//! [`format_plan`] renders an executor tree one operator per line, children indented under
+//! their parent. [`InstrumentedExecutor`] wraps a node to count the rows it produces, the
+//! `next()` calls made on it and the time spent inside it, so the same rendering can
+//! report where a query spends its time.
+
+use super::{BoxedExecutor, Executor};
+use crate::types::Row;
+use crate::Result;
+use std::fmt::Write;
+use std::time::{Duration, Instant};
+use storage_engine::tuple::Schema;
+
+/// Runtime statistics collected for one operator.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct OperatorStats {
+    /// Tuples the operator returned
+    pub rows: u64,
+    /// Calls made to its `next()`, including the final one that returned `None`
+    pub next_calls: u64,
+    /// Time spent in `init()` and `next()`, including time spent in its children
+    pub elapsed: Duration,
+}
+
+/// Wraps an executor and records [`OperatorStats`] for it.
+///
+/// Describes itself and its children exactly as the wrapped executor does, so an
+/// instrumented tree prints the same plan as the plain one.
+pub struct InstrumentedExecutor {
+    inner: BoxedExecutor,
+    stats: OperatorStats,
+}
+
+impl InstrumentedExecutor {
+    pub fn new(inner: BoxedExecutor) -> Self {
+        Self {
+            inner,
+            stats: OperatorStats::default(),
+        }
+    }
+}
+
+impl Executor for InstrumentedExecutor {
+    fn schema(&self) -> &Schema {
+        self.inner.schema()
+    }
+
+    fn init(&mut self) -> Result<()> {
+        self.stats = OperatorStats::default();
+        let start = Instant::now();
+        let result = self.inner.init();
+        self.stats.elapsed += start.elapsed();
+        result
+    }
+
+    fn next(&mut self) -> Result<Option<Row>> {
+        let start = Instant::now();
+        let result = self.inner.next();
+        self.stats.elapsed += start.elapsed();
+        self.stats.next_calls += 1;
+        if let Ok(Some(_)) = result {
+            self.stats.rows += 1;
+        }
+        result
+    }
+
+    /// Restarts the inner executor without clearing the statistics, so an operator that is
+    /// rescanned (such as the inner side of a nested loop join) reports its totals.
+    fn reset(&mut self) -> Result<()> {
+        let start = Instant::now();
+        let result = self.inner.reset();
+        self.stats.elapsed += start.elapsed();
+        result
+    }
+
+    fn describe(&self) -> String {
+        self.inner.describe()
+    }
+
+    fn children(&self) -> Vec<&dyn Executor> {
+        self.inner.children()
+    }
+
+    fn sort_order(&self) -> Vec<usize> {
+        self.inner.sort_order()
+    }
+
+    fn stats(&self) -> Option<OperatorStats> {
+        Some(self.stats)
+    }
+}
+
+/// Renders an executor tree, one operator per line:
+///
+/// ```text
+/// Limit(10)
+///   -> Projection[name]
+///     -> Filter(age > 25)
+///       -> SeqScan(users)
+/// ```
+///
+/// Instrumented operators are followed by their statistics.
+pub fn format_plan(root: &dyn Executor) -> String {
+    let mut out = String::new();
+    format_node(root, 0, &mut out);
+    out
+}
+
+fn format_node(node: &dyn Executor, depth: usize, out: &mut String) {
+    if depth > 0 {
+        out.push_str(&"  ".repeat(depth));
+        out.push_str("-> ");
+    }
+    out.push_str(&node.describe());
+    if let Some(stats) = node.stats() {
+        let _ = write!(
+            out,
+            "  (rows={} next_calls={} time={:.3} ms)",
+            stats.rows,
+            stats.next_calls,
+            stats.elapsed.as_secs_f64() * 1000.0
+        );
+    }
+    out.push('\n');
+
+    for child in node.children() {
+        format_node(child, depth + 1, out);
+    }
+}
@@ -4,9 +4,9 @@
 
 use super::{BoxedExecutor, Executor};
 use crate::expression::Expression;
-use crate::types::Value;
+use crate::types::Row;
 use crate::Result;
-use storage_engine::tuple::{Schema, Tuple};
+use storage_engine::tuple::Schema;
 
 /// Filter executor that applies a predicate to tuples.
 ///
@@ -32,21 +32,27 @@ impl Executor for FilterExecutor {
         self.child.init()
     }
 
-    fn next(&mut self) -> Result<Option<Tuple>> {
+    fn describe(&self) -> String {
+        format!("Filter({})", self.predicate.display_with(self.child.schema()))
+    }
+
+    fn children(&self) -> Vec<&dyn Executor> {
+        vec![self.child.as_ref()]
+    }
+
+    fn sort_order(&self) -> Vec<usize> {
+        self.child.sort_order()
+    }
+
+    fn next(&mut self) -> Result<Option<Row>> {
         loop {
             match self.child.next()? {
                 None => return Ok(None),
                 Some(tuple) => {
-                    // Evaluate predicate
+                    // Evaluate predicate; NULL and 0 are both treated as FALSE
                     let result = self.predicate.evaluate(&tuple)?;
 
-                    // Check if predicate is TRUE (non-zero integer, following SQL semantics)
-                    let is_true = match result {
-                        Value::Integer(i) if i != 0 => true,
-                        _ => false, // NULL or 0 are both FALSE
-                    };
-
-                    if is_true {
+                    if result.is_true() {
                         return Ok(Some(tuple));
                     }
                     // Otherwise continue to next tuple
@@ -62,12 +68,13 @@ mod tests {
     use crate::catalog::TableInfo;
     use crate::executor::SeqScanExecutor;
     use crate::expression::col;
+    use crate::types::Value;
     use buffer_pool_manager::api::BufferPoolManager;
     use buffer_pool_manager::concurrent::ConcurrentBufferPoolManager;
     use buffer_pool_manager::disk_manager::DiskManager;
     use std::path::Path;
     use storage_engine::table::TableHeap;
-    use storage_engine::tuple::{Column, Type, Value as StorageValue};
+    use storage_engine::tuple::{Column, Tuple, Type, Value as StorageValue};
     use std::sync::Arc;
 
     #[test]
@@ -117,8 +124,8 @@ mod tests {
 
         // Should return tuples with age > 22 (id=1 with age=25, id=2 with age=30)
         assert_eq!(results.len(), 2);
-        assert_eq!(results[0].values[1], StorageValue::Integer(25));
-        assert_eq!(results[1].values[1], StorageValue::Integer(30));
+        assert_eq!(results[0].values[1], Value::Integer(25));
+        assert_eq!(results[1].values[1], Value::Integer(30));
 
         std::fs::remove_dir_all(dir).unwrap();
     }
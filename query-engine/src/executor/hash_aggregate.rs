@@ -0,0 +1,423 @@
+//! Hash aggregate executor for GROUP BY with COUNT, SUM, AVG, MIN and MAX.
+//!
+//! Reads its whole input on init into a hash table keyed by the group-by values, folding
+//! each row into its group's accumulators, then returns one row per group in the order the
+//! groups were first seen. Only the accumulators are kept, not the rows themselves.
+
+use super::projection::infer_column;
+use super::{BoxedExecutor, Executor};
+use crate::expression::{AggregateExpr, AggregateFunction, Expression};
+use crate::types::{Row, Value};
+use crate::{QueryError, Result};
+use std::collections::HashMap;
+use storage_engine::tuple::{Column, Schema, Type};
+
+/// Hash aggregate executor that groups tuples and computes aggregates for each group.
+pub struct HashAggregateExecutor {
+    child: BoxedExecutor,
+    group_by: Vec<Expression>,
+    aggregates: Vec<AggregateExpr>,
+    output_schema: Schema,
+    /// Finished groups not yet returned, in reverse order so they can be popped
+    results: Vec<Row>,
+}
+
+impl HashAggregateExecutor {
+    /// Creates a new hash aggregate executor.
+    ///
+    /// The output has a column for each group-by expression followed by one for each
+    /// aggregate, named after its alias or, failing that, its SQL form.
+    ///
+    /// # Arguments
+    /// * `child` - The child executor to pull tuples from
+    /// * `group_by` - Expressions bound against the child's schema whose values form a group
+    /// * `aggregates` - Aggregates bound against the child's schema
+    pub fn new(
+        child: BoxedExecutor,
+        group_by: Vec<Expression>,
+        aggregates: Vec<AggregateExpr>,
+    ) -> Self {
+        let output_schema = output_schema(child.schema(), &group_by, &aggregates);
+
+        Self {
+            child,
+            group_by,
+            aggregates,
+            output_schema,
+            results: Vec::new(),
+        }
+    }
+}
+
+/// Returns the schema of the rows a hash aggregate produces from `input_schema`, the schema
+/// its group-by expressions and aggregates are bound against.
+pub(crate) fn output_schema(
+    input_schema: &Schema,
+    group_by: &[Expression],
+    aggregates: &[AggregateExpr],
+) -> Schema {
+    let keys = group_by.iter().map(|expr| {
+        infer_column(
+            expr,
+            expr.display_with(input_schema).to_string(),
+            input_schema,
+        )
+    });
+    let values = aggregates.iter().map(|aggregate| {
+        let name = aggregate
+            .alias
+            .clone()
+            .unwrap_or_else(|| aggregate.display_with(input_schema));
+        aggregate_column(aggregate, name, input_schema)
+    });
+    Schema {
+        columns: keys.chain(values).collect(),
+    }
+}
+
+/// Returns the column `aggregate` produces, named `name`: MIN and MAX keep their argument's
+/// type, and the other aggregates are INTEGERs.
+pub(crate) fn aggregate_column(
+    aggregate: &AggregateExpr,
+    name: String,
+    input_schema: &Schema,
+) -> Column {
+    match (aggregate.function, &aggregate.arg) {
+        (AggregateFunction::Min | AggregateFunction::Max, Some(arg)) => {
+            infer_column(arg, name, input_schema)
+        }
+        _ => Column {
+            name,
+            column_type: Type::Integer,
+            length: 4, // Size of integer
+        },
+    }
+}
+
+/// The running state of one aggregate within one group.
+pub(crate) enum Accumulator {
+    Count(i64),
+    /// Running total, or None until a non-NULL value arrives
+    Sum(Option<i64>),
+    Avg {
+        sum: i64,
+        count: i64,
+    },
+    Min(Value),
+    Max(Value),
+}
+
+impl Accumulator {
+    pub(crate) fn new(function: AggregateFunction) -> Self {
+        match function {
+            AggregateFunction::Count => Accumulator::Count(0),
+            AggregateFunction::Sum => Accumulator::Sum(None),
+            AggregateFunction::Avg => Accumulator::Avg { sum: 0, count: 0 },
+            AggregateFunction::Min => Accumulator::Min(Value::Null),
+            AggregateFunction::Max => Accumulator::Max(Value::Null),
+        }
+    }
+
+    /// Folds in one row's argument value, or None for `COUNT(*)`. NULLs are skipped.
+    pub(crate) fn update(&mut self, value: Option<Value>) -> Result<()> {
+        let value = match value {
+            None => {
+                if let Accumulator::Count(count) = self {
+                    *count += 1;
+                }
+                return Ok(());
+            }
+            Some(Value::Null) => return Ok(()),
+            Some(value) => value,
+        };
+
+        match (self, value) {
+            (Accumulator::Count(count), _) => *count += 1,
+            (Accumulator::Sum(sum), Value::Integer(i)) => {
+                *sum = Some(sum.unwrap_or(0) + i as i64);
+            }
+            (Accumulator::Avg { sum, count }, Value::Integer(i)) => {
+                *sum += i as i64;
+                *count += 1;
+            }
+            (Accumulator::Sum(_) | Accumulator::Avg { .. }, value) => {
+                return Err(QueryError::TypeMismatch(format!(
+                    "Cannot sum or average {:?}",
+                    value
+                )));
+            }
+            (Accumulator::Min(min), value) => {
+                if min.is_null() || value.compare(min).is_some_and(|o| o.is_lt()) {
+                    *min = value;
+                }
+            }
+            (Accumulator::Max(max), value) => {
+                if max.is_null() || value.compare(max).is_some_and(|o| o.is_gt()) {
+                    *max = value;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns the aggregate's value for the rows seen so far.
+    pub(crate) fn finish(&self) -> Result<Value> {
+        let integer = |value: i64| {
+            i32::try_from(value).map(Value::Integer).map_err(|_| {
+                QueryError::ExecutionError(format!("Aggregate result {} is out of range", value))
+            })
+        };
+        match self {
+            Accumulator::Count(count) => integer(*count),
+            Accumulator::Sum(None) => Ok(Value::Null),
+            Accumulator::Sum(Some(sum)) => integer(*sum),
+            Accumulator::Avg { count: 0, .. } => Ok(Value::Null),
+            Accumulator::Avg { sum, count } => integer(sum / count),
+            Accumulator::Min(value) | Accumulator::Max(value) => Ok(value.clone()),
+        }
+    }
+}
+
+impl Executor for HashAggregateExecutor {
+    fn schema(&self) -> &Schema {
+        &self.output_schema
+    }
+
+    fn init(&mut self) -> Result<()> {
+        self.child.init()?;
+
+        // Groups in first-seen order, with an index into them by key. NULL keys are equal to
+        // each other here, so all of them form a single group as in SQL.
+        let mut groups: Vec<(Vec<Value>, Vec<Accumulator>)> = Vec::new();
+        let mut index: HashMap<Vec<Value>, usize> = HashMap::new();
+        while let Some(tuple) = self.child.next()? {
+            let key = self
+                .group_by
+                .iter()
+                .map(|expr| expr.evaluate(&tuple))
+                .collect::<Result<Vec<_>>>()?;
+            let position = match index.get(&key) {
+                Some(&position) => position,
+                None => {
+                    let accumulators = self
+                        .aggregates
+                        .iter()
+                        .map(|aggregate| Accumulator::new(aggregate.function))
+                        .collect();
+                    index.insert(key.clone(), groups.len());
+                    groups.push((key, accumulators));
+                    groups.len() - 1
+                }
+            };
+
+            for (aggregate, accumulator) in self.aggregates.iter().zip(&mut groups[position].1) {
+                let value = match &aggregate.arg {
+                    Some(arg) => Some(arg.evaluate(&tuple)?),
+                    None => None,
+                };
+                accumulator.update(value)?;
+            }
+        }
+
+        // Without GROUP BY the whole input is one group, even when it is empty
+        if groups.is_empty() && self.group_by.is_empty() {
+            let accumulators = self
+                .aggregates
+                .iter()
+                .map(|aggregate| Accumulator::new(aggregate.function))
+                .collect();
+            groups.push((Vec::new(), accumulators));
+        }
+
+        let mut results = Vec::with_capacity(groups.len());
+        for (mut values, accumulators) in groups {
+            for accumulator in accumulators {
+                values.push(accumulator.finish()?);
+            }
+            results.push(Row::new(values));
+        }
+        results.reverse();
+        self.results = results;
+        Ok(())
+    }
+
+    fn describe(&self) -> String {
+        let input_schema = self.child.schema();
+        let keys: Vec<String> = self
+            .group_by
+            .iter()
+            .map(|expr| expr.display_with(input_schema).to_string())
+            .collect();
+        let aggregates: Vec<String> = self
+            .aggregates
+            .iter()
+            .map(|aggregate| {
+                let expr = aggregate.display_with(input_schema);
+                match &aggregate.alias {
+                    Some(alias) if *alias != expr => format!("{} AS {}", expr, alias),
+                    _ => expr,
+                }
+            })
+            .collect();
+        format!(
+            "HashAggregate(keys: [{}], aggregates: [{}])",
+            keys.join(", "),
+            aggregates.join(", ")
+        )
+    }
+
+    fn children(&self) -> Vec<&dyn Executor> {
+        vec![self.child.as_ref()]
+    }
+
+    fn next(&mut self) -> Result<Option<Row>> {
+        Ok(self.results.pop())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::executor::tests::collect_results;
+    use crate::expression::{avg, col, count, count_star, max, min, sum};
+    use crate::{int_column, varchar_column, Database};
+
+    #[test]
+    fn test_hash_aggregate_executor() {
+        let path = "test_hash_aggregate_dir";
+        let _ = std::fs::remove_dir_all(path);
+        let db = Database::open(path).unwrap();
+
+        // Temporary tables can hold NULLs, which stored tables cannot
+        let schema = Schema {
+            columns: vec![
+                varchar_column("city", 10),
+                int_column("amount"),
+                varchar_column("name", 10),
+            ],
+        };
+        let mut table = db.catalog().create_temp_table(schema.clone()).unwrap();
+        let city = |s: &str| Value::Varchar(s.to_string());
+        for (c, amount, name) in [
+            (city("NYC"), Value::Integer(10), city("ann")),
+            (city("LA"), Value::Null, city("bob")),
+            (city("NYC"), Value::Null, Value::Null),
+            (Value::Null, Value::Integer(5), city("cat")),
+            (city("NYC"), Value::Integer(-3), city("dan")),
+            (Value::Null, Value::Integer(7), city("eve")),
+        ] {
+            table.insert(&Row::new(vec![c, amount, name])).unwrap();
+        }
+
+        let aggregates = vec![
+            count_star(),
+            count(col("amount")),
+            sum(col("amount")).alias("total"),
+            avg(col("amount")),
+            min(col("name")),
+            max(col("amount")),
+        ];
+        let bind = |aggregates: &[AggregateExpr]| -> Vec<AggregateExpr> {
+            aggregates
+                .iter()
+                .map(|a| a.bind(&schema).unwrap())
+                .collect()
+        };
+
+        let mut grouped = HashAggregateExecutor::new(
+            Box::new(table.scan()),
+            vec![col("city").bind(&schema).unwrap()],
+            bind(&aggregates),
+        );
+        let names: Vec<&str> = grouped
+            .schema()
+            .columns
+            .iter()
+            .map(|c| c.name.as_str())
+            .collect();
+        assert_eq!(
+            names,
+            [
+                "city",
+                "count(*)",
+                "count(amount)",
+                "total",
+                "avg(amount)",
+                "min(name)",
+                "max(amount)"
+            ]
+        );
+        assert!(matches!(
+            grouped.schema().columns[5].column_type,
+            Type::Varchar
+        ));
+        assert_eq!(
+            grouped.describe(),
+            "HashAggregate(keys: [city], aggregates: [count(*), count(amount), \
+             sum(amount) AS total, avg(amount), min(name), max(amount)])"
+        );
+
+        let row = |values: Vec<Value>| Row::new(values);
+        let (int, null) = (Value::Integer, || Value::Null);
+        assert_eq!(
+            collect_results(&mut grouped).unwrap(),
+            vec![
+                // The NULL amount is counted by COUNT(*) only, and averaging truncates
+                row(vec![
+                    city("NYC"),
+                    int(3),
+                    int(2),
+                    int(7),
+                    int(3),
+                    city("ann"),
+                    int(10)
+                ]),
+                // A group of only NULLs sums to NULL but counts to 0
+                row(vec![
+                    city("LA"),
+                    int(1),
+                    int(0),
+                    null(),
+                    null(),
+                    city("bob"),
+                    null()
+                ]),
+                // NULL keys form one group
+                row(vec![
+                    null(),
+                    int(2),
+                    int(2),
+                    int(12),
+                    int(6),
+                    city("cat"),
+                    int(7)
+                ]),
+            ]
+        );
+
+        // Without keys an empty input still produces a row
+        let mut empty = db.catalog().create_temp_table(schema.clone()).unwrap();
+        let mut total =
+            HashAggregateExecutor::new(Box::new(empty.scan()), Vec::new(), bind(&aggregates));
+        assert_eq!(
+            collect_results(&mut total).unwrap(),
+            vec![row(vec![int(0), int(0), null(), null(), null(), null()])]
+        );
+        empty
+            .insert(&Row::new(vec![city("x"), int(1), city("y")]))
+            .unwrap();
+        let mut grouped = HashAggregateExecutor::new(
+            Box::new(empty.scan()),
+            Vec::new(),
+            bind(&[sum(col("name"))]),
+        );
+        assert!(matches!(
+            collect_results(&mut grouped),
+            Err(QueryError::TypeMismatch(_))
+        ));
+
+        drop(table);
+        drop(empty);
+        std::fs::remove_dir_all(path).unwrap();
+    }
+}
@@ -0,0 +1,807 @@
+//! Hash join executor for equality joins.
+//!
+//! Reads the build side into a hash table keyed by its join columns, then streams the
+//! probe side through it. When the build side holds more rows than the memory limit, both
+//! inputs are hash partitioned into temporary tables and the partitions are joined pair by
+//! pair (a grace hash join). A partition that is still too big is partitioned again with a
+//! different hash, up to [`MAX_PARTITION_DEPTH`] times; past that, the rows all share a few
+//! keys and splitting further cannot help, so the partition is joined in memory as is.
+//!
+//! For outer joins, each probe tuple remembers whether it matched, and each build tuple in
+//! the hash table carries a matched flag. Unmatched build tuples are returned once the
+//! probe input of their table (or partition) has been fully read. Rows with a NULL key go
+//! to the first partition when spilled, since they cannot match anything.
+//!
+//! Semi and anti joins always build from the right input and return probe tuples alone. A
+//! null-aware anti join (`NOT IN`) also has to know whether the build side held a NULL key
+//! or nothing at all, as either changes the answer for every probe tuple; both are settled
+//! once the build input has been read in full, before probing starts.
+
+use super::nested_loop_join::{combine, null_row};
+use super::{BoxedExecutor, Executor, JoinType};
+use crate::catalog::temp::TempTable;
+use crate::catalog::Catalog;
+use crate::expression::{BinaryOperator, Expression};
+use crate::types::{Row, Value};
+use crate::Result;
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
+use std::hash::{Hash, Hasher};
+use std::sync::Arc;
+use storage_engine::tuple::Schema;
+
+/// Default number of build rows kept in memory before the join spills to disk.
+pub const DEFAULT_MEMORY_LIMIT: usize = 100_000;
+
+/// Number of partitions each spilled input is split into.
+const PARTITION_FANOUT: usize = 8;
+
+/// How many times a partition may be split again before it is joined in memory regardless.
+const MAX_PARTITION_DEPTH: usize = 3;
+
+/// Which input of a hash join the hash table is built from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BuildSide {
+    Left,
+    Right,
+}
+
+/// The equality conditions of a join predicate, split by the side they read.
+pub(crate) struct EquiJoinKeys {
+    /// Key expressions bound against the left input
+    pub left: Vec<Expression>,
+    /// Key expressions bound against the right input
+    pub right: Vec<Expression>,
+    /// The remaining conditions, bound against the joined schema
+    pub residual: Option<Expression>,
+}
+
+impl EquiJoinKeys {
+    /// Finds the `left_expr = right_expr` conjuncts of a bound join predicate, where the left
+    /// input has `left_len` columns. Returns None if there are none.
+    pub(crate) fn extract(on: &Expression, left_len: usize) -> Option<Self> {
+        let mut keys = EquiJoinKeys {
+            left: Vec::new(),
+            right: Vec::new(),
+            residual: None,
+        };
+        for conjunct in on.conjuncts() {
+            if let Expression::BinaryOp {
+                left,
+                op: BinaryOperator::Eq,
+                right,
+            } = conjunct
+            {
+                let side = |expr: &Expression| {
+                    let columns = expr.referenced_columns();
+                    if columns.is_empty() {
+                        None
+                    } else if columns.iter().all(|&index| index < left_len) {
+                        Some(BuildSide::Left)
+                    } else if columns.iter().all(|&index| index >= left_len) {
+                        Some(BuildSide::Right)
+                    } else {
+                        None
+                    }
+                };
+                match (side(left), side(right)) {
+                    (Some(BuildSide::Left), Some(BuildSide::Right)) => {
+                        keys.left.push((**left).clone());
+                        keys.right.push(right.shift_columns(left_len));
+                        continue;
+                    }
+                    (Some(BuildSide::Right), Some(BuildSide::Left)) => {
+                        keys.left.push((**right).clone());
+                        keys.right.push(left.shift_columns(left_len));
+                        continue;
+                    }
+                    _ => {}
+                }
+            }
+            keys.residual = Some(match keys.residual.take() {
+                Some(residual) => residual.and(conjunct.clone()),
+                None => conjunct.clone(),
+            });
+        }
+
+        if keys.left.is_empty() {
+            None
+        } else {
+            Some(keys)
+        }
+    }
+}
+
+/// Evaluates join key expressions against a tuple. Returns None if any key is NULL, since
+/// NULL never equals anything.
+pub(crate) fn evaluate_key(keys: &[Expression], row: &Row) -> Result<Option<Vec<Value>>> {
+    let mut values = Vec::with_capacity(keys.len());
+    for key in keys {
+        let value = key.evaluate(row)?;
+        if value.is_null() {
+            return Ok(None);
+        }
+        values.push(value);
+    }
+    Ok(Some(values))
+}
+
+/// Formats an equi-join condition for EXPLAIN, naming columns after the joined schema so
+/// they appear qualified.
+pub(crate) fn describe_condition(
+    left_keys: &[Expression],
+    right_keys: &[Expression],
+    residual: Option<&Expression>,
+    output_schema: &Schema,
+    left_len: usize,
+) -> String {
+    let (left_columns, right_columns) = output_schema.columns.split_at(left_len);
+    let left_schema = Schema {
+        columns: left_columns.to_vec(),
+    };
+    let right_schema = Schema {
+        columns: right_columns.to_vec(),
+    };
+    let mut conditions: Vec<String> = left_keys
+        .iter()
+        .zip(right_keys)
+        .map(|(left, right)| {
+            format!(
+                "{} = {}",
+                left.display_with(&left_schema),
+                right.display_with(&right_schema)
+            )
+        })
+        .collect();
+    conditions.extend(residual.map(|residual| residual.display_with(output_schema).to_string()));
+    conditions.join(" AND ")
+}
+
+/// Picks the partition of a key at a given depth of partitioning.
+fn partition_of(key: &[Value], depth: usize) -> usize {
+    let mut hasher = DefaultHasher::new();
+    depth.hash(&mut hasher);
+    key.hash(&mut hasher);
+    (hasher.finish() % PARTITION_FANOUT as u64) as usize
+}
+
+/// A pair of spilled partitions still waiting to be joined.
+struct PartitionPair {
+    build: TempTable,
+    probe: TempTable,
+    depth: usize,
+}
+
+/// Where the join reads probe tuples from.
+enum ProbeInput {
+    /// The probe child, when the build side fit in memory
+    Child,
+    /// A scan of the current partition's probe tuples
+    Partition(BoxedExecutor),
+    /// Every probe tuple has been read
+    Done,
+}
+
+/// The hash table of a join and the partitions it spilled.
+struct HashTable {
+    build_keys: Vec<Expression>,
+    probe_keys: Vec<Expression>,
+    build_schema: Schema,
+    probe_schema: Schema,
+    catalog: Arc<Catalog>,
+    memory_limit: usize,
+    /// Whether build tuples that match nothing are part of the result
+    keep_build: bool,
+    /// Whether probe tuples that match nothing are part of the result
+    keep_probe: bool,
+    /// Whether a NULL key on either side makes the outcome unknown (`NOT IN`)
+    null_aware: bool,
+    /// Whether any build tuple read since init had a NULL key
+    null_key_seen: bool,
+    /// Build tuples by key, each with whether it has matched, for the input or partition
+    /// being joined
+    tuples: HashMap<Vec<Value>, Vec<(Row, bool)>>,
+    /// Build tuples with a NULL key, held only when `keep_build` is set
+    null_keys: Vec<Row>,
+    /// Spilled partitions not yet joined
+    pending: Vec<PartitionPair>,
+    /// The spilled partition being probed, kept alive while its scan runs
+    current: Option<PartitionPair>,
+    /// Number of partition pairs written to disk since init
+    spilled_partitions: usize,
+}
+
+impl HashTable {
+    fn clear(&mut self) {
+        self.tuples.clear();
+        self.null_keys.clear();
+        self.null_key_seen = false;
+        self.pending.clear();
+        self.current = None;
+        self.spilled_partitions = 0;
+    }
+
+    /// Reads build tuples from `input` until it ends or, if `allow_spill` is set, until the
+    /// table outgrows the memory limit. Returns whether the whole input was read.
+    fn load(&mut self, input: &mut dyn Executor, allow_spill: bool) -> Result<bool> {
+        let mut rows = 0;
+        while let Some(tuple) = input.next()? {
+            match evaluate_key(&self.build_keys, &tuple)? {
+                Some(key) => self.tuples.entry(key).or_default().push((tuple, false)),
+                None => {
+                    self.null_key_seen = true;
+                    if !self.keep_build {
+                        continue;
+                    }
+                    self.null_keys.push(tuple);
+                }
+            }
+            rows += 1;
+            if allow_spill && rows > self.memory_limit {
+                return Ok(false);
+            }
+        }
+        Ok(true)
+    }
+
+    /// Removes the build tuples from the table, returning those that matched nothing.
+    fn take_unmatched(&mut self) -> Vec<Row> {
+        let mut unmatched: Vec<Row> = self.null_keys.drain(..).collect();
+        for (_, tuples) in self.tuples.drain() {
+            unmatched.extend(
+                tuples
+                    .into_iter()
+                    .filter(|(_, matched)| !matched)
+                    .map(|(tuple, _)| tuple),
+            );
+        }
+        unmatched
+    }
+
+    /// Creates `PARTITION_FANOUT` empty temporary tables with the given schema.
+    fn create_partitions(&self, schema: &Schema) -> Result<Vec<TempTable>> {
+        (0..PARTITION_FANOUT)
+            .map(|_| self.catalog.create_temp_table(schema.clone()))
+            .collect()
+    }
+
+    /// Moves the table's tuples and the rest of `build_input` into build partitions, and
+    /// all of `probe_input` into probe partitions, queueing every pair that can match.
+    fn spill(
+        &mut self,
+        build_input: &mut dyn Executor,
+        probe_input: &mut dyn Executor,
+        depth: usize,
+    ) -> Result<()> {
+        let mut build_parts = self.create_partitions(&self.build_schema)?;
+        for (key, tuples) in self.tuples.drain() {
+            let part = &mut build_parts[partition_of(&key, depth)];
+            for (tuple, _) in tuples {
+                part.insert(&tuple)?;
+            }
+        }
+        for tuple in self.null_keys.drain(..) {
+            build_parts[0].insert(&tuple)?;
+        }
+        while let Some(tuple) = build_input.next()? {
+            match evaluate_key(&self.build_keys, &tuple)? {
+                Some(key) => build_parts[partition_of(&key, depth)].insert(&tuple)?,
+                None => {
+                    self.null_key_seen = true;
+                    if self.keep_build {
+                        build_parts[0].insert(&tuple)?;
+                    }
+                }
+            }
+        }
+
+        let mut probe_parts = self.create_partitions(&self.probe_schema)?;
+        while let Some(tuple) = probe_input.next()? {
+            match evaluate_key(&self.probe_keys, &tuple)? {
+                Some(key) => probe_parts[partition_of(&key, depth)].insert(&tuple)?,
+                // Against a build side with rows, a NULL key is never NOT IN it
+                None if self.keep_probe && !self.null_aware => probe_parts[0].insert(&tuple)?,
+                None => {}
+            }
+        }
+
+        for (build, probe) in build_parts.into_iter().zip(probe_parts) {
+            // A partition produces nothing unless both sides have tuples, or one side has
+            // tuples that are returned even without a match
+            let matches = !build.is_empty() && !probe.is_empty();
+            let unmatched =
+                (self.keep_build && !build.is_empty()) || (self.keep_probe && !probe.is_empty());
+            if !matches && !unmatched {
+                continue;
+            }
+            self.spilled_partitions += 1;
+            self.pending.push(PartitionPair {
+                build,
+                probe,
+                depth: depth + 1,
+            });
+        }
+        Ok(())
+    }
+
+    /// Loads the next spilled partition pair, splitting it again while it is too big, and
+    /// returns a scan of its probe tuples. Returns None when no partitions are left.
+    fn next_partition(&mut self) -> Result<Option<BoxedExecutor>> {
+        self.current = None;
+        while let Some(pair) = self.pending.pop() {
+            self.tuples.clear();
+            let mut build_scan = pair.build.scan();
+            build_scan.init()?;
+            let mut probe_scan = pair.probe.scan();
+            probe_scan.init()?;
+
+            if self.load(&mut build_scan, pair.depth < MAX_PARTITION_DEPTH)? {
+                self.current = Some(pair);
+                return Ok(Some(Box::new(probe_scan)));
+            }
+            self.spill(&mut build_scan, &mut probe_scan, pair.depth)?;
+        }
+        Ok(None)
+    }
+}
+
+/// Joins two inputs on equal keys using a hash table, spilling to disk when needed.
+///
+/// Output tuples hold the left tuple's values followed by the right tuple's, whichever side
+/// the hash table is built from. Rows with a NULL key never match, though outer joins still
+/// return them padded with NULLs.
+pub struct HashJoinExecutor {
+    left: BoxedExecutor,
+    right: BoxedExecutor,
+    residual: Option<Expression>,
+    join_type: JoinType,
+    build_side: BuildSide,
+    /// The left columns followed by the right columns, which the residual is bound against
+    joined_schema: Schema,
+    output_schema: Schema,
+    table: HashTable,
+    probe_input: ProbeInput,
+    /// The probe tuple being matched
+    probe_tuple: Option<ProbeTuple>,
+    /// Build tuples that matched nothing, waiting to be returned padded with NULLs
+    unmatched_build: Vec<Row>,
+    /// Whether the build input had no tuples at all
+    build_empty: bool,
+}
+
+/// A probe tuple and its progress through the matching build tuples.
+struct ProbeTuple {
+    tuple: Row,
+    key: Vec<Value>,
+    /// The next build tuple with this key to try
+    position: usize,
+    matched: bool,
+}
+
+impl HashJoinExecutor {
+    /// Creates a new hash join.
+    ///
+    /// # Arguments
+    /// * `left` - The left input
+    /// * `right` - The right input
+    /// * `keys` - Equality conditions and any residual predicate
+    /// * `join_type` - Which rows to return
+    /// * `build_side` - The input to build the hash table from, ideally the smaller one;
+    ///   semi and anti joins must build from the right
+    /// * `joined_schema` - The left columns followed by the right columns
+    /// * `catalog` - Where temporary tables are created if the join spills
+    ///
+    /// A null-aware anti join must have a single key and no residual condition.
+    pub(crate) fn new(
+        left: BoxedExecutor,
+        right: BoxedExecutor,
+        keys: EquiJoinKeys,
+        join_type: JoinType,
+        build_side: BuildSide,
+        joined_schema: Schema,
+        catalog: Arc<Catalog>,
+    ) -> Self {
+        let output_schema = join_type.output_schema(&joined_schema, left.schema().columns.len());
+        let (build_keys, probe_keys, build_schema, probe_schema) = match build_side {
+            BuildSide::Left => (keys.left, keys.right, left.schema(), right.schema()),
+            BuildSide::Right => (keys.right, keys.left, right.schema(), left.schema()),
+        };
+        let (keep_build, keep_probe) = match build_side {
+            BuildSide::Left => (join_type.preserves_left(), join_type.preserves_right()),
+            BuildSide::Right => (join_type.preserves_right(), join_type.preserves_left()),
+        };
+        let table = HashTable {
+            build_keys,
+            probe_keys,
+            build_schema: build_schema.clone(),
+            probe_schema: probe_schema.clone(),
+            catalog,
+            memory_limit: DEFAULT_MEMORY_LIMIT,
+            keep_build,
+            keep_probe,
+            null_aware: join_type == JoinType::NullAwareAnti,
+            null_key_seen: false,
+            tuples: HashMap::new(),
+            null_keys: Vec::new(),
+            pending: Vec::new(),
+            current: None,
+            spilled_partitions: 0,
+        };
+        Self {
+            left,
+            right,
+            residual: keys.residual,
+            join_type,
+            build_side,
+            joined_schema,
+            output_schema,
+            table,
+            probe_input: ProbeInput::Done,
+            probe_tuple: None,
+            unmatched_build: Vec::new(),
+            build_empty: false,
+        }
+    }
+
+    /// Sets how many build rows may be held in memory before the join spills to disk.
+    pub fn with_memory_limit(mut self, rows: usize) -> Self {
+        self.table.memory_limit = rows.max(1);
+        self
+    }
+
+    /// Returns how many pairs of partitions the last run wrote to disk; 0 if it fit in
+    /// memory.
+    pub fn spilled_partitions(&self) -> usize {
+        self.table.spilled_partitions
+    }
+
+    /// Pads a build tuple with NULLs for the probe side's columns.
+    fn pad_build(&self, build: &Row) -> Row {
+        let probe = null_row(self.table.probe_schema.columns.len());
+        match self.build_side {
+            BuildSide::Left => combine(build, &probe),
+            BuildSide::Right => combine(&probe, build),
+        }
+    }
+
+    /// Returns the output row for a probe tuple that matched nothing: the tuple itself for
+    /// anti joins, padded with NULLs for the build side's columns otherwise.
+    fn unmatched_probe(&self, probe: Row) -> Row {
+        if !self.join_type.includes_right_columns() {
+            return probe;
+        }
+        let build = null_row(self.table.build_schema.columns.len());
+        match self.build_side {
+            BuildSide::Left => combine(&build, &probe),
+            BuildSide::Right => combine(&probe, &build),
+        }
+    }
+
+    /// Reads the next probe tuple, moving on to the next spilled partition as each one ends.
+    fn next_probe(&mut self) -> Result<Option<Row>> {
+        loop {
+            let tuple = match &mut self.probe_input {
+                ProbeInput::Child => match self.build_side {
+                    BuildSide::Left => self.right.next()?,
+                    BuildSide::Right => self.left.next()?,
+                },
+                ProbeInput::Partition(scan) => scan.next()?,
+                ProbeInput::Done => return Ok(None),
+            };
+            if tuple.is_some() {
+                return Ok(tuple);
+            }
+
+            // Every probe tuple of this table has been seen, so its unmatched build tuples
+            // are final
+            if self.table.keep_build {
+                self.unmatched_build.extend(self.table.take_unmatched());
+            }
+            self.probe_input = ProbeInput::Done;
+            if let Some(scan) = self.table.next_partition()? {
+                self.probe_input = ProbeInput::Partition(scan);
+            }
+        }
+    }
+}
+
+impl Executor for HashJoinExecutor {
+    fn schema(&self) -> &Schema {
+        &self.output_schema
+    }
+
+    fn init(&mut self) -> Result<()> {
+        self.probe_input = ProbeInput::Done;
+        self.probe_tuple = None;
+        self.unmatched_build.clear();
+        self.table.clear();
+
+        let (build_input, probe_input) = match self.build_side {
+            BuildSide::Left => (self.left.as_mut(), self.right.as_mut()),
+            BuildSide::Right => (self.right.as_mut(), self.left.as_mut()),
+        };
+        build_input.init()?;
+        probe_input.init()?;
+        let in_memory = self.table.load(build_input, true)?;
+        if !in_memory {
+            self.table.spill(build_input, probe_input, 0)?;
+        }
+
+        // The whole build input has been read by now
+        if self.table.null_aware && self.table.null_key_seen {
+            // x NOT IN (..., NULL) is never true, so no probe tuple can qualify
+            self.table.clear();
+            return Ok(());
+        }
+        self.build_empty = in_memory && self.table.tuples.is_empty() && !self.table.null_key_seen;
+
+        if in_memory {
+            self.probe_input = ProbeInput::Child;
+        } else if let Some(scan) = self.table.next_partition()? {
+            self.probe_input = ProbeInput::Partition(scan);
+        }
+        Ok(())
+    }
+
+    fn describe(&self) -> String {
+        let (left_keys, right_keys) = match self.build_side {
+            BuildSide::Left => (&self.table.build_keys, &self.table.probe_keys),
+            BuildSide::Right => (&self.table.probe_keys, &self.table.build_keys),
+        };
+        let build = match self.build_side {
+            BuildSide::Left => "left",
+            BuildSide::Right => "right",
+        };
+        format!(
+            "HashJoin({}, {}, build: {})",
+            self.join_type.name(),
+            describe_condition(
+                left_keys,
+                right_keys,
+                self.residual.as_ref(),
+                &self.joined_schema,
+                self.left.schema().columns.len(),
+            ),
+            build
+        )
+    }
+
+    fn children(&self) -> Vec<&dyn Executor> {
+        vec![self.left.as_ref(), self.right.as_ref()]
+    }
+
+    fn next(&mut self) -> Result<Option<Row>> {
+        loop {
+            if let Some(build) = self.unmatched_build.pop() {
+                return Ok(Some(self.pad_build(&build)));
+            }
+
+            if let Some(probe) = &mut self.probe_tuple {
+                let build = self
+                    .table
+                    .tuples
+                    .get_mut(&probe.key)
+                    .and_then(|tuples| tuples.get_mut(probe.position));
+                let Some((build, build_matched)) = build else {
+                    let probe = self.probe_tuple.take().unwrap();
+                    if !probe.matched && self.table.keep_probe {
+                        return Ok(Some(self.unmatched_probe(probe.tuple)));
+                    }
+                    continue;
+                };
+                probe.position += 1;
+                let joined = match self.build_side {
+                    BuildSide::Left => combine(build, &probe.tuple),
+                    BuildSide::Right => combine(&probe.tuple, build),
+                };
+                if let Some(residual) = &self.residual {
+                    if !residual.evaluate(&joined)?.is_true() {
+                        continue;
+                    }
+                }
+                *build_matched = true;
+                probe.matched = true;
+                if !self.join_type.includes_right_columns() {
+                    // One match settles a semi or anti join for this probe tuple
+                    let probe = self.probe_tuple.take().unwrap();
+                    if self.join_type == JoinType::Semi {
+                        return Ok(Some(probe.tuple));
+                    }
+                    continue;
+                }
+                return Ok(Some(joined));
+            }
+
+            match self.next_probe()? {
+                Some(tuple) => match evaluate_key(&self.table.probe_keys, &tuple)? {
+                    Some(key) => {
+                        self.probe_tuple = Some(ProbeTuple {
+                            tuple,
+                            key,
+                            position: 0,
+                            matched: false,
+                        })
+                    }
+                    // NOT IN is unknown for a NULL unless there is nothing to compare it with
+                    None if self.table.keep_probe
+                        && (!self.table.null_aware || self.build_empty) =>
+                    {
+                        return Ok(Some(self.unmatched_probe(tuple)))
+                    }
+                    None => {}
+                },
+                // The last probe tuple may have left unmatched build tuples to return
+                None if self.unmatched_build.is_empty() => return Ok(None),
+                None => {}
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::executor::tests::collect_results;
+    use crate::expression::{col, lit, ParameterValues};
+    use crate::types::Value;
+    use crate::Database;
+
+    /// A (l.id, r.amount) pair from a join result, where None is NULL or, for semi and anti
+    /// joins, missing.
+    type Pair = (Option<i32>, Option<i32>);
+
+    /// Runs a hash join of `l` and `r` on `on`, returning sorted (l.id, r.amount) pairs and
+    /// the number of partitions spilled.
+    fn run_join(
+        db: &Database,
+        on: Expression,
+        join_type: JoinType,
+        build_side: BuildSide,
+        memory_limit: usize,
+    ) -> (Vec<Pair>, usize) {
+        let params = ParameterValues::default();
+        let left = db.table("l").unwrap().build_executor(&params).unwrap();
+        let right = db.table("r").unwrap().build_executor(&params).unwrap();
+        let schema = Schema {
+            columns: left
+                .schema()
+                .columns
+                .iter()
+                .chain(&right.schema().columns)
+                .cloned()
+                .collect(),
+        };
+        let on = on.bind(&schema).unwrap();
+        let keys = EquiJoinKeys::extract(&on, left.schema().columns.len()).unwrap();
+        let mut join = HashJoinExecutor::new(
+            left,
+            right,
+            keys,
+            join_type,
+            build_side,
+            schema,
+            db.catalog().clone(),
+        )
+        .with_memory_limit(memory_limit);
+
+        let integer = |value: &Value| match value {
+            Value::Integer(i) => Some(*i),
+            Value::Null => None,
+            _ => panic!("unexpected value"),
+        };
+        let mut pairs: Vec<Pair> = collect_results(&mut join)
+            .unwrap()
+            .iter()
+            .map(|t| (integer(&t.values[0]), t.values.get(3).and_then(integer)))
+            .collect();
+        pairs.sort();
+        (pairs, join.spilled_partitions())
+    }
+
+    #[test]
+    fn test_hash_join_executor() {
+        let path = "test_hash_join_dir";
+        let _ = std::fs::remove_dir_all(path);
+        let db = Database::open(path).unwrap();
+        db.execute("CREATE TABLE l (id INTEGER, name VARCHAR(10))")
+            .unwrap();
+        db.execute("CREATE TABLE r (l_id INTEGER, amount INTEGER)")
+            .unwrap();
+        let left_rows: Vec<String> = (1..=20).map(|i| format!("({}, 'n{}')", i, i)).collect();
+        db.execute(&format!("INSERT INTO l VALUES {}", left_rows.join(", ")))
+            .unwrap();
+        // Every id up to 24 once, so some rows match nothing, plus a skewed run on id 1
+        let right_rows: Vec<String> = (0..25)
+            .map(|i| format!("({}, {})", i, i * 10))
+            .chain((0..30).map(|i| format!("(1, {})", 1000 + i)))
+            .collect();
+        db.execute(&format!("INSERT INTO r VALUES {}", right_rows.join(", ")))
+            .unwrap();
+
+        let mut expected: Vec<Pair> = (1..=20)
+            .map(|i| (Some(i), Some(i * 10)))
+            .chain((0..30).map(|i| (Some(1), Some(1000 + i))))
+            .collect();
+        expected.sort();
+
+        // Fits in memory
+        let on = || col("id").eq(col("l_id"));
+        let (pairs, spilled) = run_join(
+            &db,
+            on(),
+            JoinType::Inner,
+            BuildSide::Right,
+            DEFAULT_MEMORY_LIMIT,
+        );
+        assert_eq!(pairs, expected);
+        assert_eq!(spilled, 0);
+
+        // Spills from either side, including the partition holding the skewed key
+        for build_side in [BuildSide::Left, BuildSide::Right] {
+            let (pairs, spilled) = run_join(&db, on(), JoinType::Inner, build_side, 4);
+            assert_eq!(pairs, expected);
+            assert!(spilled > 0);
+        }
+
+        // Conditions that are not equalities are checked on each match
+        let below_100 = || on().and(col("amount").lt(lit(100)));
+        let (pairs, _) = run_join(&db, below_100(), JoinType::Inner, BuildSide::Left, 4);
+        let matched: Vec<Pair> = (1..=9).map(|i| (Some(i), Some(i * 10))).collect();
+        assert_eq!(pairs, matched);
+
+        // Outer joins pad the rows that matched nothing, whichever side the table is built
+        // from and whether or not the join spills
+        let left_only: Vec<Pair> = (10..=20).map(|i| (Some(i), None)).collect();
+        let right_only: Vec<Pair> = (0..1)
+            .chain(10..25)
+            .map(|i| i * 10)
+            .chain(1000..1030)
+            .map(|amount| (None, Some(amount)))
+            .collect();
+        let cases = [
+            (JoinType::Left, vec![&matched, &left_only]),
+            (JoinType::Right, vec![&matched, &right_only]),
+            (JoinType::Full, vec![&matched, &left_only, &right_only]),
+        ];
+        for (join_type, parts) in cases {
+            let mut expected: Vec<Pair> = parts.into_iter().flatten().copied().collect();
+            expected.sort();
+            for build_side in [BuildSide::Left, BuildSide::Right] {
+                for memory_limit in [DEFAULT_MEMORY_LIMIT, 4] {
+                    let (pairs, _) =
+                        run_join(&db, below_100(), join_type, build_side, memory_limit);
+                    assert_eq!(
+                        pairs, expected,
+                        "{:?} join, build {:?}",
+                        join_type, build_side
+                    );
+                }
+            }
+        }
+
+        // Semi and anti joins return left rows alone, each at most once
+        let keys_only = |pairs: &[Pair]| {
+            let mut ids: Vec<Pair> = pairs.iter().map(|(id, _)| (*id, None)).collect();
+            ids.dedup();
+            ids
+        };
+        for memory_limit in [DEFAULT_MEMORY_LIMIT, 4] {
+            let run =
+                |join_type| run_join(&db, below_100(), join_type, BuildSide::Right, memory_limit).0;
+            assert_eq!(run(JoinType::Semi), keys_only(&matched));
+            assert_eq!(run(JoinType::Anti), left_only);
+        }
+
+        // Temporary tables are gone once the join is dropped
+        let leftovers = std::fs::read_dir(path)
+            .unwrap()
+            .filter(|entry| {
+                let name = entry.as_ref().unwrap().file_name();
+                name.to_string_lossy().starts_with("rose_temp_")
+            })
+            .count();
+        assert_eq!(leftovers, 0);
+
+        std::fs::remove_dir_all(path).unwrap();
+    }
+}
@@ -1,16 +1,21 @@
-//! Limit executor for LIMIT clauses.
+//! Limit executor for LIMIT and OFFSET clauses.
 //!
-//! Returns only the first N tuples from the child executor.
+//! Skips the first `offset` tuples from the child executor, then returns at most N.
 
 use super::{BoxedExecutor, Executor};
+use crate::types::Row;
 use crate::Result;
-use storage_engine::tuple::{Schema, Tuple};
+use storage_engine::tuple::Schema;
 
-/// Limit executor that returns at most N tuples.
+/// Limit executor that skips `offset` tuples and returns at most N after them.
 pub struct LimitExecutor {
     child: BoxedExecutor,
-    limit: usize,
+    /// Maximum number of tuples to return, or None for no maximum
+    limit: Option<usize>,
+    offset: usize,
     count: usize,
+    /// Whether the offset tuples have been skipped since init
+    skipped: bool,
 }
 
 impl LimitExecutor {
@@ -18,10 +23,26 @@ impl LimitExecutor {
     pub fn new(child: BoxedExecutor, limit: usize) -> Self {
         Self {
             child,
-            limit,
+            limit: Some(limit),
+            offset: 0,
             count: 0,
+            skipped: false,
         }
     }
+
+    /// Creates an executor that skips the first `offset` tuples and returns all the rest.
+    pub fn offset(child: BoxedExecutor, offset: usize) -> Self {
+        Self {
+            limit: None,
+            ..Self::new(child, 0).with_offset(offset)
+        }
+    }
+
+    /// Skips the first `offset` tuples before counting towards the limit.
+    pub fn with_offset(mut self, offset: usize) -> Self {
+        self.offset = offset;
+        self
+    }
 }
 
 impl Executor for LimitExecutor {
@@ -31,14 +52,41 @@ impl Executor for LimitExecutor {
 
     fn init(&mut self) -> Result<()> {
         self.count = 0;
+        self.skipped = false;
         self.child.init()
     }
 
-    fn next(&mut self) -> Result<Option<Tuple>> {
-        if self.count >= self.limit {
+    fn describe(&self) -> String {
+        match (self.limit, self.offset) {
+            (Some(limit), 0) => format!("Limit({})", limit),
+            (Some(limit), offset) => format!("Limit({}, offset: {})", limit, offset),
+            (None, offset) => format!("Offset({})", offset),
+        }
+    }
+
+    fn children(&self) -> Vec<&dyn Executor> {
+        vec![self.child.as_ref()]
+    }
+
+    fn sort_order(&self) -> Vec<usize> {
+        self.child.sort_order()
+    }
+
+    fn next(&mut self) -> Result<Option<Row>> {
+        if self.limit.is_some_and(|limit| self.count >= limit) {
             return Ok(None);
         }
 
+        // Skip the offset lazily, after the limit check, so LIMIT 0 reads nothing
+        if !self.skipped {
+            self.skipped = true;
+            for _ in 0..self.offset {
+                if self.child.next()?.is_none() {
+                    return Ok(None);
+                }
+            }
+        }
+
         match self.child.next()? {
             None => Ok(None),
             Some(tuple) => {
@@ -59,7 +107,8 @@ mod tests {
     use buffer_pool_manager::disk_manager::DiskManager;
     use std::path::Path;
     use storage_engine::table::TableHeap;
-    use storage_engine::tuple::{Column, Schema, Type, Value};
+    use crate::types::Value;
+    use storage_engine::tuple::{Column, Schema, Tuple, Type, Value as StorageValue};
     use std::sync::Arc;
 
     #[test]
@@ -78,7 +127,7 @@ mod tests {
 
         for i in 1..=5 {
             table_heap.insert_tuple(&Tuple {
-                values: vec![Value::Integer(i)],
+                values: vec![StorageValue::Integer(i)],
             });
         }
 
@@ -87,7 +136,7 @@ mod tests {
         let table_info = Arc::new(TableInfo::new(1, "test".to_string(), schema, table_heap));
 
         // Create executor: SELECT * FROM test LIMIT 3
-        let scan = Box::new(SeqScanExecutor::new(table_info));
+        let scan = Box::new(SeqScanExecutor::new(table_info.clone()));
         let mut limit = LimitExecutor::new(scan, 3);
         limit.init().unwrap();
 
@@ -103,6 +152,25 @@ mod tests {
         assert_eq!(results[1].values[0], Value::Integer(2));
         assert_eq!(results[2].values[0], Value::Integer(3));
 
+        // SELECT * FROM test LIMIT 2 OFFSET 3, and pages running past the end
+        let ids = |mut limit: LimitExecutor| {
+            limit.init().unwrap();
+            let mut ids = Vec::new();
+            while let Some(tuple) = limit.next().unwrap() {
+                ids.push(tuple.values[0].clone());
+            }
+            ids
+        };
+        let scan = || Box::new(SeqScanExecutor::new(table_info.clone()));
+        let page = LimitExecutor::new(scan(), 2).with_offset(3);
+        assert_eq!(page.describe(), "Limit(2, offset: 3)");
+        assert_eq!(ids(page), vec![Value::Integer(4), Value::Integer(5)]);
+        assert_eq!(ids(LimitExecutor::new(scan(), 2).with_offset(4)), vec![Value::Integer(5)]);
+        assert!(ids(LimitExecutor::new(scan(), 2).with_offset(9)).is_empty());
+        let rest = LimitExecutor::offset(scan(), 1);
+        assert_eq!(rest.describe(), "Offset(1)");
+        assert_eq!(ids(rest).len(), 4);
+
         std::fs::remove_dir_all(dir).unwrap();
     }
 }
@@ -0,0 +1,270 @@
+//! Sort-merge join executor for equality joins over sorted inputs.
+//!
+//! Both inputs must arrive sorted on their join keys in [`Value::sort_cmp`] order, as a
+//! [`SortExecutor`](super::SortExecutor) with ascending keys produces. The join walks the
+//! two inputs in step, so it needs no hash table and reads each input once. To handle keys
+//! repeated on both sides, the right tuples sharing the current key are buffered and
+//! replayed for every left tuple with that key.
+
+use super::hash_join::{describe_condition, evaluate_key, EquiJoinKeys};
+use super::nested_loop_join::combine;
+use super::{BoxedExecutor, Executor, JoinType};
+use crate::expression::Expression;
+use crate::types::{Row, Value};
+use crate::Result;
+use std::cmp::Ordering;
+use storage_engine::tuple::Schema;
+
+/// Compares two join keys column by column.
+fn compare_keys(a: &[Value], b: &[Value]) -> Ordering {
+    a.iter()
+        .zip(b)
+        .map(|(a, b)| a.sort_cmp(b))
+        .find(|ordering| ordering.is_ne())
+        .unwrap_or(Ordering::Equal)
+}
+
+/// Joins two inputs sorted on their join keys by merging them.
+///
+/// Output tuples hold the left tuple's values followed by the right tuple's, in left key
+/// order. Rows with a NULL key never match.
+pub struct MergeJoinExecutor {
+    left: BoxedExecutor,
+    right: BoxedExecutor,
+    keys: EquiJoinKeys,
+    join_type: JoinType,
+    output_schema: Schema,
+    /// The left tuple being matched
+    current_left: Option<Row>,
+    /// The next right tuple not yet buffered, with its key
+    next_right: Option<(Row, Vec<Value>)>,
+    /// The right tuples sharing `run_key`
+    run: Vec<Row>,
+    run_key: Option<Vec<Value>>,
+    /// The next tuple in `run` to pair with `current_left`
+    run_position: usize,
+}
+
+impl MergeJoinExecutor {
+    /// Creates a new merge join.
+    ///
+    /// # Arguments
+    /// * `left` - The left input, sorted on `keys.left`
+    /// * `right` - The right input, sorted on `keys.right`
+    /// * `keys` - Equality conditions and any residual predicate
+    /// * `join_type` - Which rows to return
+    /// * `output_schema` - The left columns followed by the right columns
+    pub(crate) fn new(
+        left: BoxedExecutor,
+        right: BoxedExecutor,
+        keys: EquiJoinKeys,
+        join_type: JoinType,
+        output_schema: Schema,
+    ) -> Self {
+        Self {
+            left,
+            right,
+            keys,
+            join_type,
+            output_schema,
+            current_left: None,
+            next_right: None,
+            run: Vec::new(),
+            run_key: None,
+            run_position: 0,
+        }
+    }
+
+    /// Reads the next tuple with a non-NULL key from an input.
+    fn next_keyed(
+        input: &mut BoxedExecutor,
+        keys: &[Expression],
+    ) -> Result<Option<(Row, Vec<Value>)>> {
+        while let Some(tuple) = input.next()? {
+            if let Some(key) = evaluate_key(keys, &tuple)? {
+                return Ok(Some((tuple, key)));
+            }
+        }
+        Ok(None)
+    }
+
+    /// Buffers the run of right tuples matching `key`, skipping smaller keys. Leaves the run
+    /// empty if the right input has no such key.
+    fn seek_run(&mut self, key: &[Value]) -> Result<()> {
+        self.run.clear();
+        self.run_key = None;
+        while let Some((_, right_key)) = &self.next_right {
+            if compare_keys(right_key, key).is_ge() {
+                break;
+            }
+            self.next_right = Self::next_keyed(&mut self.right, &self.keys.right)?;
+        }
+        while let Some((_, right_key)) = &self.next_right {
+            if compare_keys(right_key, key).is_ne() {
+                break;
+            }
+            let (tuple, right_key) = self.next_right.take().unwrap();
+            self.run.push(tuple);
+            self.run_key = Some(right_key);
+            self.next_right = Self::next_keyed(&mut self.right, &self.keys.right)?;
+        }
+        Ok(())
+    }
+}
+
+impl Executor for MergeJoinExecutor {
+    fn schema(&self) -> &Schema {
+        &self.output_schema
+    }
+
+    fn init(&mut self) -> Result<()> {
+        self.current_left = None;
+        self.run.clear();
+        self.run_key = None;
+        self.run_position = 0;
+        self.left.init()?;
+        self.right.init()?;
+        self.next_right = Self::next_keyed(&mut self.right, &self.keys.right)?;
+        Ok(())
+    }
+
+    fn describe(&self) -> String {
+        format!(
+            "MergeJoin({}, {})",
+            self.join_type.name(),
+            describe_condition(
+                &self.keys.left,
+                &self.keys.right,
+                self.keys.residual.as_ref(),
+                &self.output_schema,
+                self.left.schema().columns.len(),
+            )
+        )
+    }
+
+    fn children(&self) -> Vec<&dyn Executor> {
+        vec![self.left.as_ref(), self.right.as_ref()]
+    }
+
+    fn sort_order(&self) -> Vec<usize> {
+        self.keys
+            .left
+            .iter()
+            .map_while(|key| match key {
+                Expression::BoundColumn(index) => Some(*index),
+                _ => None,
+            })
+            .collect()
+    }
+
+    fn next(&mut self) -> Result<Option<Row>> {
+        loop {
+            if let Some(left) = &self.current_left {
+                if let Some(right) = self.run.get(self.run_position) {
+                    self.run_position += 1;
+                    let joined = combine(left, right);
+                    match &self.keys.residual {
+                        Some(residual) if !residual.evaluate(&joined)?.is_true() => continue,
+                        _ => return Ok(Some(joined)),
+                    }
+                }
+            }
+
+            let Some((left, key)) = Self::next_keyed(&mut self.left, &self.keys.left)? else {
+                self.current_left = None;
+                return Ok(None);
+            };
+            // A repeated left key pairs with the run already buffered
+            let same_run =
+                matches!(&self.run_key, Some(run_key) if compare_keys(run_key, &key).is_eq());
+            if !same_run {
+                self.seek_run(&key)?;
+            }
+            self.run_position = 0;
+            self.current_left = Some(left);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::expression::{col, Expression};
+    use crate::types::Value;
+    use crate::{DataFrame, Database, JoinStrategy, JoinType};
+
+    #[test]
+    fn test_merge_join_executor() {
+        let path = "test_merge_join_dir";
+        let _ = std::fs::remove_dir_all(path);
+        let db = Database::open(path).unwrap();
+        db.execute("CREATE TABLE l (id INTEGER, name VARCHAR(10))")
+            .unwrap();
+        db.execute("CREATE TABLE r (l_id INTEGER, amount INTEGER)")
+            .unwrap();
+        db.execute("CREATE TABLE t (l_id INTEGER, tag VARCHAR(10))")
+            .unwrap();
+        // Unsorted, with key 2 repeated on both sides and keys that match nothing
+        db.execute("INSERT INTO l VALUES (3, 'c'), (2, 'b1'), (1, 'a'), (2, 'b2'), (5, 'e')")
+            .unwrap();
+        db.execute("INSERT INTO r VALUES (2, 20), (4, 40), (3, 30), (2, 21), (2, 22), (0, 0)")
+            .unwrap();
+        db.execute("INSERT INTO t VALUES (3, 'x'), (2, 'y')")
+            .unwrap();
+
+        let merge = |left: &str, right: DataFrame, on: Expression| {
+            db.table(left)
+                .unwrap()
+                .join_with(right, on, JoinType::Inner, JoinStrategy::SortMerge)
+                .unwrap()
+        };
+        let df = || merge("l", db.table("r").unwrap(), col("id").eq(col("l_id")));
+        assert_eq!(
+            df().explain().unwrap(),
+            concat!(
+                "MergeJoin(Inner, l.id = r.l_id)\n",
+                "  -> Sort(id)\n",
+                "    -> SeqScan(l)\n",
+                "  -> Sort(l_id)\n",
+                "    -> SeqScan(r)\n",
+            )
+        );
+
+        // Each of the two left rows with key 2 pairs with all three right rows, in key order
+        let pairs: Vec<_> = df()
+            .collect()
+            .unwrap()
+            .into_iter()
+            .map(|t| (t.values[1].clone(), t.values[3].clone()))
+            .collect();
+        let row = |name: &str, amount| (Value::Varchar(name.to_string()), Value::Integer(amount));
+        assert_eq!(
+            pairs,
+            vec![
+                row("b1", 20),
+                row("b1", 21),
+                row("b1", 22),
+                row("b2", 20),
+                row("b2", 21),
+                row("b2", 22),
+                row("c", 30),
+            ]
+        );
+
+        // Merge joins come out in key order, so joining two of them on their keys needs no
+        // further sort, and the planner picks a merge join by itself
+        let tagged = merge(
+            "t",
+            db.table("l").unwrap().alias("l2"),
+            col("t.l_id").eq(col("l2.id")),
+        );
+        let nested = df()
+            .join(tagged, col("l.id").eq(col("t.l_id")), JoinType::Inner)
+            .unwrap();
+        let plan = nested.explain().unwrap();
+        assert!(plan.starts_with("MergeJoin(Inner, l.id = t.l_id)\n  -> MergeJoin"));
+        assert_eq!(plan.matches("Sort(").count(), 4);
+        assert_eq!(nested.collect().unwrap().len(), 6 * 2 + 1);
+
+        std::fs::remove_dir_all(path).unwrap();
+    }
+}
@@ -2,18 +2,47 @@
 //!
 //! Each executor implements the Executor trait and can be composed to form query plans.
 
+use crate::types::Row;
 use crate::Result;
-use storage_engine::tuple::{Schema, Tuple};
+use storage_engine::tuple::Schema;
 
 pub mod seq_scan;
 pub mod filter;
 pub mod projection;
 pub mod limit;
+pub mod nested_loop_join;
+pub mod hash_join;
+pub mod merge_join;
+pub mod sort;
+pub mod distinct;
+pub mod top_n;
+pub mod hash_aggregate;
+pub mod set_operation;
+pub mod window;
+pub mod subquery;
+pub mod cte;
+pub mod recursive_cte;
+pub mod system_scan;
+pub mod explain;
 
 pub use seq_scan::SeqScanExecutor;
 pub use filter::FilterExecutor;
 pub use projection::ProjectionExecutor;
 pub use limit::LimitExecutor;
+pub use nested_loop_join::{JoinType, NestedLoopJoinExecutor};
+pub use hash_join::{BuildSide, HashJoinExecutor};
+pub use merge_join::MergeJoinExecutor;
+pub use sort::SortExecutor;
+pub use distinct::DistinctExecutor;
+pub use top_n::TopNExecutor;
+pub use hash_aggregate::HashAggregateExecutor;
+pub use set_operation::{SetOperation, SetOperationExecutor};
+pub use window::WindowExecutor;
+pub use subquery::SubqueryExecutor;
+pub use cte::{CteExecutor, CteRows, CteScanExecutor};
+pub use recursive_cte::RecursiveCteExecutor;
+pub use system_scan::SystemScanExecutor;
+pub use explain::{InstrumentedExecutor, OperatorStats};
 
 /// The core executor trait for the Volcano iterator model.
 ///
@@ -26,23 +55,42 @@ pub trait Executor {
     fn init(&mut self) -> Result<()>;
 
     /// Get the next tuple, or None if exhausted.
-    fn next(&mut self) -> Result<Option<Tuple>>;
+    fn next(&mut self) -> Result<Option<Row>>;
 
     /// Reset the executor to its initial state for re-execution.
     fn reset(&mut self) -> Result<()> {
         self.init()
     }
+
+    /// One-line description of this operator for EXPLAIN output, e.g. `Filter(age > 25)`.
+    fn describe(&self) -> String;
+
+    /// The executors this one pulls tuples from.
+    fn children(&self) -> Vec<&dyn Executor> {
+        Vec::new()
+    }
+
+    /// Columns the output is known to be sorted on, most significant first, each ascending
+    /// in [`Value::sort_cmp`](crate::types::Value::sort_cmp) order. Empty if unknown.
+    fn sort_order(&self) -> Vec<usize> {
+        Vec::new()
+    }
+
+    /// Runtime statistics, if this executor is instrumented for EXPLAIN ANALYZE.
+    fn stats(&self) -> Option<OperatorStats> {
+        None
+    }
 }
 
 /// A boxed executor for dynamic dispatch.
 pub type BoxedExecutor = Box<dyn Executor>;
 
 #[cfg(test)]
-mod tests {
+pub(crate) mod tests {
     use super::*;
 
     // Helper to collect all results from an executor
-    pub fn collect_results(executor: &mut dyn Executor) -> Result<Vec<Tuple>> {
+    pub fn collect_results(executor: &mut dyn Executor) -> Result<Vec<Row>> {
         let mut results = Vec::new();
         executor.init()?;
         while let Some(tuple) = executor.next()? {
@@ -0,0 +1,425 @@
+//! Nested loop join executor.
+//!
+//! For every tuple from the left (outer) child, rescans the right (inner) child and returns
+//! each combined tuple that satisfies the join predicate. Works with any predicate, at the
+//! cost of reading the inner side once per outer tuple.
+//!
+//! Outer joins also return the rows that matched nothing, padded with NULLs. Unmatched left
+//! rows are known once their pass over the inner side ends. Unmatched right rows are
+//! tracked by their position in the inner scan and returned by one final pass after the
+//! outer side is exhausted, which relies on the inner side producing its rows in the same
+//! order on every scan.
+//!
+//! Semi and anti joins return outer tuples alone, and stop scanning the inner side as soon
+//! as one match decides the outcome.
+
+use super::{BoxedExecutor, Executor};
+use crate::expression::Expression;
+use crate::types::{Row, Value};
+use crate::Result;
+use storage_engine::tuple::Schema;
+
+/// How the rows of two inputs are combined by a join.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum JoinType {
+    /// Only pairs of rows that satisfy the join predicate
+    Inner,
+    /// Every inner join pair, plus each left row that matched nothing, padded with NULLs
+    Left,
+    /// Every inner join pair, plus each right row that matched nothing, padded with NULLs
+    Right,
+    /// Every inner join pair, plus the unmatched rows of both sides
+    Full,
+    /// Each left row that matches at least one right row, once and without right columns
+    /// (`EXISTS`, `IN (subquery)`)
+    Semi,
+    /// Each left row that matches no right row, without right columns (`NOT EXISTS`)
+    Anti,
+    /// Each left row for which the predicate is FALSE against every right row (`NOT IN`).
+    ///
+    /// Unlike [`Anti`](Self::Anti), a predicate that is NULL (unknown) for some right row
+    /// excludes the left row, so `x NOT IN (1, NULL)` is never true.
+    NullAwareAnti,
+}
+
+impl JoinType {
+    /// Name of the join type in EXPLAIN output.
+    pub fn name(&self) -> &'static str {
+        match self {
+            JoinType::Inner => "Inner",
+            JoinType::Left => "Left",
+            JoinType::Right => "Right",
+            JoinType::Full => "Full",
+            JoinType::Semi => "Semi",
+            JoinType::Anti => "Anti",
+            JoinType::NullAwareAnti => "NullAwareAnti",
+        }
+    }
+
+    /// Returns true if left rows without a match are part of the result.
+    pub fn preserves_left(&self) -> bool {
+        matches!(
+            self,
+            JoinType::Left | JoinType::Full | JoinType::Anti | JoinType::NullAwareAnti
+        )
+    }
+
+    /// Returns true if right rows without a match are part of the result.
+    pub fn preserves_right(&self) -> bool {
+        matches!(self, JoinType::Right | JoinType::Full)
+    }
+
+    /// Returns true if result rows include the right input's columns; semi and anti joins
+    /// return left rows alone.
+    pub fn includes_right_columns(&self) -> bool {
+        !matches!(
+            self,
+            JoinType::Semi | JoinType::Anti | JoinType::NullAwareAnti
+        )
+    }
+
+    /// Returns the schema of the join's result, given the left columns followed by the
+    /// right columns and the number of left columns.
+    pub(crate) fn output_schema(&self, joined_schema: &Schema, left_len: usize) -> Schema {
+        if self.includes_right_columns() {
+            joined_schema.clone()
+        } else {
+            Schema {
+                columns: joined_schema.columns[..left_len].to_vec(),
+            }
+        }
+    }
+}
+
+/// Joins two inputs by evaluating a predicate on every pair of tuples.
+///
+/// Output tuples hold the left tuple's values followed by the right tuple's. The predicate
+/// is bound against that combined layout, and pairs for which it is NULL or FALSE are
+/// dropped.
+pub struct NestedLoopJoinExecutor {
+    left: BoxedExecutor,
+    right: BoxedExecutor,
+    predicate: Expression,
+    join_type: JoinType,
+    /// The left columns followed by the right columns, which the predicate is bound against
+    joined_schema: Schema,
+    output_schema: Schema,
+    /// The outer tuple currently being matched against the inner side
+    current_left: Option<Row>,
+    /// Whether `current_left` has matched any inner tuple yet
+    left_matched: bool,
+    /// Position of the next inner tuple within the current inner scan
+    right_position: usize,
+    /// Which inner tuples have matched, by position; only kept for right and full joins
+    right_matched: Vec<bool>,
+    /// Set once the outer side is exhausted and unmatched inner tuples are being returned
+    emitting_right: bool,
+}
+
+impl NestedLoopJoinExecutor {
+    /// Creates a new nested loop join.
+    ///
+    /// # Arguments
+    /// * `left` - The outer input, read once
+    /// * `right` - The inner input, rescanned for every outer tuple
+    /// * `predicate` - Join condition, bound against `joined_schema`
+    /// * `join_type` - Which rows to return
+    /// * `joined_schema` - The left columns followed by the right columns
+    pub fn new(
+        left: BoxedExecutor,
+        right: BoxedExecutor,
+        predicate: Expression,
+        join_type: JoinType,
+        joined_schema: Schema,
+    ) -> Self {
+        let output_schema = join_type.output_schema(&joined_schema, left.schema().columns.len());
+        Self {
+            left,
+            right,
+            predicate,
+            join_type,
+            joined_schema,
+            output_schema,
+            current_left: None,
+            left_matched: false,
+            right_position: 0,
+            right_matched: Vec::new(),
+            emitting_right: false,
+        }
+    }
+
+    /// Restarts the inner side for another pass.
+    fn rescan_right(&mut self) -> Result<()> {
+        self.right_position = 0;
+        self.right.reset()
+    }
+}
+
+/// Concatenates a left and a right row into one output row.
+pub(crate) fn combine(left: &Row, right: &Row) -> Row {
+    let mut values = Vec::with_capacity(left.values.len() + right.values.len());
+    values.extend(left.values.iter().cloned());
+    values.extend(right.values.iter().cloned());
+    Row { values }
+}
+
+/// Returns a row of `columns` NULLs, standing in for the missing side of an outer join row.
+pub(crate) fn null_row(columns: usize) -> Row {
+    Row::new(vec![Value::Null; columns])
+}
+
+impl Executor for NestedLoopJoinExecutor {
+    fn schema(&self) -> &Schema {
+        &self.output_schema
+    }
+
+    fn init(&mut self) -> Result<()> {
+        self.current_left = None;
+        self.left_matched = false;
+        self.right_position = 0;
+        self.right_matched.clear();
+        self.emitting_right = false;
+        self.left.init()
+    }
+
+    fn describe(&self) -> String {
+        format!(
+            "NestedLoopJoin({}, {})",
+            self.join_type.name(),
+            self.predicate.display_with(&self.joined_schema)
+        )
+    }
+
+    fn children(&self) -> Vec<&dyn Executor> {
+        vec![self.left.as_ref(), self.right.as_ref()]
+    }
+
+    fn next(&mut self) -> Result<Option<Row>> {
+        loop {
+            if self.emitting_right {
+                let Some(right) = self.right.next()? else {
+                    return Ok(None);
+                };
+                let position = self.right_position;
+                self.right_position += 1;
+                if !self.right_matched.get(position).copied().unwrap_or(false) {
+                    let left = null_row(self.left.schema().columns.len());
+                    return Ok(Some(combine(&left, &right)));
+                }
+                continue;
+            }
+
+            let Some(left) = &self.current_left else {
+                match self.left.next()? {
+                    None => {
+                        if !self.join_type.preserves_right() {
+                            return Ok(None);
+                        }
+                        // One last pass to return the inner tuples nothing matched
+                        self.rescan_right()?;
+                        self.emitting_right = true;
+                    }
+                    Some(left) => {
+                        // Start a fresh pass over the inner side for this outer tuple
+                        self.rescan_right()?;
+                        self.current_left = Some(left);
+                        self.left_matched = false;
+                    }
+                }
+                continue;
+            };
+
+            match self.right.next()? {
+                None => {
+                    let left = self.current_left.take();
+                    if let Some(left) = left {
+                        if !self.left_matched && self.join_type.preserves_left() {
+                            if !self.join_type.includes_right_columns() {
+                                return Ok(Some(left));
+                            }
+                            let right = null_row(self.right.schema().columns.len());
+                            return Ok(Some(combine(&left, &right)));
+                        }
+                    }
+                }
+                Some(right) => {
+                    let position = self.right_position;
+                    self.right_position += 1;
+                    let joined = combine(left, &right);
+                    let result = self.predicate.evaluate(&joined)?;
+                    // For NOT IN, an unknown comparison rules the left row out as surely as
+                    // a match does
+                    let matched = match self.join_type {
+                        JoinType::NullAwareAnti => result.is_true() || result.is_null(),
+                        _ => result.is_true(),
+                    };
+                    if !matched {
+                        continue;
+                    }
+                    self.left_matched = true;
+                    if self.join_type.preserves_right() {
+                        if self.right_matched.len() <= position {
+                            self.right_matched.resize(position + 1, false);
+                        }
+                        self.right_matched[position] = true;
+                    }
+                    if !self.join_type.includes_right_columns() {
+                        // One match settles a semi or anti join for this outer tuple
+                        let left = self.current_left.take();
+                        if self.join_type == JoinType::Semi {
+                            return Ok(left);
+                        }
+                        continue;
+                    }
+                    return Ok(Some(joined));
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::executor::tests::collect_results;
+    use crate::expression::ParameterValues;
+    use crate::expression::{col, Expression};
+    use crate::types::Value;
+    use crate::Database;
+
+    #[test]
+    fn test_nested_loop_join_executor() {
+        let path = "test_nested_loop_join_dir";
+        let _ = std::fs::remove_dir_all(path);
+        let db = Database::open(path).unwrap();
+        db.execute("CREATE TABLE l (id INTEGER, name VARCHAR(10))")
+            .unwrap();
+        db.execute("CREATE TABLE r (l_id INTEGER, amount INTEGER)")
+            .unwrap();
+        db.execute("INSERT INTO l VALUES (1, 'a'), (2, 'b'), (3, 'c')")
+            .unwrap();
+        db.execute("INSERT INTO r VALUES (1, 10), (3, 30), (1, 11), (4, 40)")
+            .unwrap();
+
+        let params = ParameterValues::default();
+        let left = db.table("l").unwrap().build_executor(&params).unwrap();
+        let right = db.table("r").unwrap().build_executor(&params).unwrap();
+        let schema = Schema {
+            columns: left
+                .schema()
+                .columns
+                .iter()
+                .chain(&right.schema().columns)
+                .cloned()
+                .collect(),
+        };
+        let predicate = col("id").eq(col("l_id")).bind(&schema).unwrap();
+
+        let mut join = NestedLoopJoinExecutor::new(left, right, predicate, JoinType::Inner, schema);
+        assert_eq!(join.describe(), "NestedLoopJoin(Inner, id = l_id)");
+
+        // Matches come out in outer order, then inner order
+        let results = collect_results(&mut join).unwrap();
+        let pairs: Vec<_> = results
+            .iter()
+            .map(|t| (t.values[0].clone(), t.values[3].clone()))
+            .collect();
+        assert_eq!(
+            pairs,
+            vec![
+                (Value::Integer(1), Value::Integer(10)),
+                (Value::Integer(1), Value::Integer(11)),
+                (Value::Integer(3), Value::Integer(30)),
+            ]
+        );
+
+        // Re-running the join gives the same rows
+        assert_eq!(collect_results(&mut join).unwrap().len(), 3);
+
+        // A predicate that is never true (NULL) joins nothing
+        let never = Expression::Literal(Value::Null);
+        let left = db.table("l").unwrap().build_executor(&params).unwrap();
+        let right = db.table("r").unwrap().build_executor(&params).unwrap();
+        let schema = join.schema().clone();
+        let mut join = NestedLoopJoinExecutor::new(left, right, never, JoinType::Inner, schema);
+        assert!(collect_results(&mut join).unwrap().is_empty());
+
+        std::fs::remove_dir_all(path).unwrap();
+    }
+
+    #[test]
+    fn test_nested_loop_outer_joins() {
+        let path = "test_nested_loop_outer_join_dir";
+        let _ = std::fs::remove_dir_all(path);
+        let db = Database::open(path).unwrap();
+        db.execute("CREATE TABLE l (id INTEGER, name VARCHAR(10))")
+            .unwrap();
+        db.execute("CREATE TABLE r (l_id INTEGER, amount INTEGER)")
+            .unwrap();
+        db.execute("INSERT INTO l VALUES (1, 'a'), (2, 'b'), (3, 'c')")
+            .unwrap();
+        db.execute("INSERT INTO r VALUES (1, 10), (3, 30), (1, 11), (4, 40)")
+            .unwrap();
+
+        // Returns (l.id, r.amount) for each output row, in output order
+        let run = |join_type: JoinType| {
+            let params = ParameterValues::default();
+            let left = db.table("l").unwrap().build_executor(&params).unwrap();
+            let right = db.table("r").unwrap().build_executor(&params).unwrap();
+            let schema = Schema {
+                columns: left
+                    .schema()
+                    .columns
+                    .iter()
+                    .chain(&right.schema().columns)
+                    .cloned()
+                    .collect(),
+            };
+            let predicate = col("id").eq(col("l_id")).bind(&schema).unwrap();
+            let mut join = NestedLoopJoinExecutor::new(left, right, predicate, join_type, schema);
+            collect_results(&mut join)
+                .unwrap()
+                .iter()
+                .map(|t| (t.values[0].clone(), t.values[3].clone()))
+                .collect::<Vec<_>>()
+        };
+        let row = |id: Option<i32>, amount: Option<i32>| {
+            (
+                id.map_or(Value::Null, Value::Integer),
+                amount.map_or(Value::Null, Value::Integer),
+            )
+        };
+
+        // Unmatched left rows appear in outer order, unmatched right rows at the end
+        assert_eq!(
+            run(JoinType::Left),
+            vec![
+                row(Some(1), Some(10)),
+                row(Some(1), Some(11)),
+                row(Some(2), None),
+                row(Some(3), Some(30)),
+            ]
+        );
+        assert_eq!(
+            run(JoinType::Right),
+            vec![
+                row(Some(1), Some(10)),
+                row(Some(1), Some(11)),
+                row(Some(3), Some(30)),
+                row(None, Some(40)),
+            ]
+        );
+        assert_eq!(
+            run(JoinType::Full),
+            vec![
+                row(Some(1), Some(10)),
+                row(Some(1), Some(11)),
+                row(Some(2), None),
+                row(Some(3), Some(30)),
+                row(None, Some(40)),
+            ]
+        );
+
+        std::fs::remove_dir_all(path).unwrap();
+    }
+}
@@ -4,8 +4,9 @@
 
 use super::{BoxedExecutor, Executor};
 use crate::expression::Expression;
+use crate::types::{Row, Value};
 use crate::{QueryError, Result};
-use storage_engine::tuple::{Column, Schema, Tuple, Type, Value as StorageValue};
+use storage_engine::tuple::{Column, Schema, Type};
 
 /// Projection executor that evaluates expressions to produce output columns.
 pub struct ProjectionExecutor {
@@ -32,16 +33,12 @@ impl ProjectionExecutor {
             ));
         }
 
-        // Build output schema
-        // For now, assume all projected columns are integers
-        // TODO: Infer types from expressions
-        let columns = output_column_names
-            .into_iter()
-            .map(|name| Column {
-                name,
-                column_type: Type::Integer,
-                length: 4, // Size of integer
-            })
+        // Build output schema, inferring each column's type from its expression
+        let input_schema = child.schema();
+        let columns = projections
+            .iter()
+            .zip(output_column_names)
+            .map(|(expr, name)| infer_column(expr, name, input_schema))
             .collect();
 
         let output_schema = Schema { columns };
@@ -54,6 +51,34 @@ impl ProjectionExecutor {
     }
 }
 
+/// Infers the output column for a projected expression.
+///
+/// Column references keep the input column's type and length, as do scalar subqueries their
+/// column's, string literals become VARCHARs, and everything else (arithmetic, comparisons,
+/// NULL) is an INTEGER.
+pub(crate) fn infer_column(expr: &Expression, name: String, input_schema: &Schema) -> Column {
+    match expr {
+        Expression::BoundColumn(index) if *index < input_schema.columns.len() => Column {
+            name,
+            ..input_schema.columns[*index].clone()
+        },
+        Expression::BoundScalarSubquery(rows) => Column {
+            name,
+            ..rows.column().clone()
+        },
+        Expression::Literal(Value::Varchar(s)) => Column {
+            name,
+            column_type: Type::Varchar,
+            length: s.len() as u32,
+        },
+        _ => Column {
+            name,
+            column_type: Type::Integer,
+            length: 4, // Size of integer
+        },
+    }
+}
+
 impl Executor for ProjectionExecutor {
     fn schema(&self) -> &Schema {
         &self.output_schema
@@ -63,28 +88,51 @@ impl Executor for ProjectionExecutor {
         self.child.init()
     }
 
-    fn next(&mut self) -> Result<Option<Tuple>> {
+    fn describe(&self) -> String {
+        let items: Vec<String> = self
+            .projections
+            .iter()
+            .zip(&self.output_schema.columns)
+            .map(|(expr, column)| {
+                let expr = expr.display_with(self.child.schema()).to_string();
+                if expr == column.name {
+                    expr
+                } else {
+                    format!("{} AS {}", expr, column.name)
+                }
+            })
+            .collect();
+        format!("Projection[{}]", items.join(", "))
+    }
+
+    fn children(&self) -> Vec<&dyn Executor> {
+        vec![self.child.as_ref()]
+    }
+
+    /// The child's order survives for as long a prefix of its sort columns as is projected.
+    fn sort_order(&self) -> Vec<usize> {
+        self.child
+            .sort_order()
+            .into_iter()
+            .map_while(|column| {
+                self.projections
+                    .iter()
+                    .position(|expr| matches!(expr, Expression::BoundColumn(index) if *index == column))
+            })
+            .collect()
+    }
+
+    fn next(&mut self) -> Result<Option<Row>> {
         match self.child.next()? {
             None => Ok(None),
-            Some(tuple) => {
+            Some(row) => {
                 // Evaluate each projection expression
-                let mut output_values = Vec::new();
+                let mut output_values = Vec::with_capacity(self.projections.len());
                 for expr in &self.projections {
-                    let value = expr.evaluate(&tuple)?;
-
-                    // Convert back to storage Value
-                    // Skip NULL values for now (storage engine doesn't support them yet)
-                    if let Some(storage_val) = value.to_storage() {
-                        output_values.push(storage_val);
-                    } else {
-                        // For NULL, use a placeholder (0 for integers)
-                        output_values.push(StorageValue::Integer(0));
-                    }
+                    output_values.push(expr.evaluate(&row)?);
                 }
 
-                Ok(Some(Tuple {
-                    values: output_values,
-                }))
+                Ok(Some(Row::new(output_values)))
             }
         }
     }
@@ -101,7 +149,7 @@ mod tests {
     use buffer_pool_manager::disk_manager::DiskManager;
     use std::path::Path;
     use storage_engine::table::TableHeap;
-    use storage_engine::tuple::Value as StorageValue;
+    use storage_engine::tuple::{Tuple, Value as StorageValue};
     use std::sync::Arc;
 
     #[test]
@@ -167,11 +215,11 @@ mod tests {
         // Check results
         assert_eq!(results.len(), 2);
         // First tuple: b=10, a+c=1+100=101
-        assert_eq!(results[0].values[0], StorageValue::Integer(10));
-        assert_eq!(results[0].values[1], StorageValue::Integer(101));
+        assert_eq!(results[0].values[0], Value::Integer(10));
+        assert_eq!(results[0].values[1], Value::Integer(101));
         // Second tuple: b=20, a+c=2+200=202
-        assert_eq!(results[1].values[0], StorageValue::Integer(20));
-        assert_eq!(results[1].values[1], StorageValue::Integer(202));
+        assert_eq!(results[1].values[0], Value::Integer(20));
+        assert_eq!(results[1].values[1], Value::Integer(202));
 
         std::fs::remove_dir_all(dir).unwrap();
     }
@@ -4,6 +4,7 @@
 
 use super::Executor;
 use crate::catalog::TableInfo;
+use crate::types::Row;
 use crate::Result;
 use buffer_pool_manager::api::{PageId, INVALID_PAGE_ID};
 use buffer_pool_manager::page::SlottedPage;
@@ -42,7 +43,11 @@ impl Executor for SeqScanExecutor {
         Ok(())
     }
 
-    fn next(&mut self) -> Result<Option<Tuple>> {
+    fn describe(&self) -> String {
+        format!("SeqScan({})", self.table_info.name)
+    }
+
+    fn next(&mut self) -> Result<Option<Row>> {
         loop {
             if self.current_page_id == INVALID_PAGE_ID {
                 return Ok(None);
@@ -63,7 +68,7 @@ impl Executor for SeqScanExecutor {
                 // Get the record data and deserialize
                 let record = slotted_page.get_record(slot);
                 let tuple = Tuple::deserialize(record, &self.table_info.schema);
-                return Ok(Some(tuple));
+                return Ok(Some(Row::from_storage(tuple)));
             }
 
             // Exhausted current page, move to next page
@@ -82,7 +87,8 @@ mod tests {
     use buffer_pool_manager::disk_manager::DiskManager;
     use std::path::Path;
     use storage_engine::table::TableHeap;
-    use storage_engine::tuple::Value;
+    use crate::types::Value;
+    use storage_engine::tuple::Value as StorageValue;
 
     const TABLE_ID: u32 = 1;
 
@@ -127,13 +133,13 @@ mod tests {
         let table_heap = Arc::new(TableHeap::new(bpm.clone(), schema.clone(), TABLE_ID));
 
         table_heap.insert_tuple(&Tuple {
-            values: vec![Value::Integer(1), Value::Varchar("Alice".to_string())],
+            values: vec![StorageValue::Integer(1), StorageValue::Varchar("Alice".to_string())],
         });
         table_heap.insert_tuple(&Tuple {
-            values: vec![Value::Integer(2), Value::Varchar("Bob".to_string())],
+            values: vec![StorageValue::Integer(2), StorageValue::Varchar("Bob".to_string())],
         });
         table_heap.insert_tuple(&Tuple {
-            values: vec![Value::Integer(3), Value::Varchar("Charlie".to_string())],
+            values: vec![StorageValue::Integer(3), StorageValue::Varchar("Charlie".to_string())],
         });
 
         bpm.flush_all_pages().unwrap();
@@ -0,0 +1,260 @@
+//! Set operation executor for UNION, UNION ALL, INTERSECT and EXCEPT.
+//!
+//! UNION ALL returns the left input's tuples followed by the right's. The other operations
+//! return each distinct tuple once, comparing whole tuples with NULLs equal to each other as
+//! SQL does for set operations. UNION streams both inputs past a hash set of the tuples
+//! returned so far. INTERSECT and EXCEPT first load the right input into a hash set, then
+//! stream the left input through it. Output follows the left input's order, then the right's.
+
+use super::{BoxedExecutor, Executor};
+use crate::types::Row;
+use crate::{QueryError, Result};
+use std::collections::HashSet;
+use storage_engine::tuple::{Column, Schema, Type};
+
+/// How a set operation combines its inputs.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SetOperation {
+    /// Tuples in either input, each once
+    Union,
+    /// Every tuple of both inputs, duplicates included
+    UnionAll,
+    /// Tuples in both inputs, each once
+    Intersect,
+    /// Tuples in the left input but not the right, each once
+    Except,
+}
+
+impl SetOperation {
+    /// The operation's name as shown in EXPLAIN output.
+    pub fn name(&self) -> &'static str {
+        match self {
+            SetOperation::Union => "Union",
+            SetOperation::UnionAll => "UnionAll",
+            SetOperation::Intersect => "Intersect",
+            SetOperation::Except => "Except",
+        }
+    }
+}
+
+/// Checks that `left` and `right` have the same number of columns with matching types, and
+/// returns the schema of the combined rows.
+///
+/// Columns are named after the left input's, and VARCHAR columns are as long as the longer
+/// of the two.
+pub(crate) fn output_schema(
+    left: &Schema,
+    right: &Schema,
+    operation: SetOperation,
+) -> Result<Schema> {
+    if left.columns.len() != right.columns.len() {
+        return Err(QueryError::ExecutionError(format!(
+            "{} inputs must have the same number of columns, got {} and {}",
+            operation.name(),
+            left.columns.len(),
+            right.columns.len()
+        )));
+    }
+
+    let type_name = |column: &Column| match column.column_type {
+        Type::Integer => "INTEGER",
+        Type::Varchar => "VARCHAR",
+    };
+    let mut columns = Vec::with_capacity(left.columns.len());
+    for (l, r) in left.columns.iter().zip(&right.columns) {
+        if type_name(l) != type_name(r) {
+            return Err(QueryError::TypeMismatch(format!(
+                "{} column '{}' is {} on the left but '{}' is {} on the right",
+                operation.name(),
+                l.name,
+                type_name(l),
+                r.name,
+                type_name(r)
+            )));
+        }
+        columns.push(Column {
+            length: l.length.max(r.length),
+            ..l.clone()
+        });
+    }
+    Ok(Schema { columns })
+}
+
+/// Set operation executor that combines the tuples of two inputs with the same columns.
+pub struct SetOperationExecutor {
+    left: BoxedExecutor,
+    right: BoxedExecutor,
+    operation: SetOperation,
+    output_schema: Schema,
+    /// Whether the left input has been exhausted
+    left_done: bool,
+    /// For UNION, the tuples returned so far; for INTERSECT, the right tuples not yet
+    /// returned; for EXCEPT, the right tuples and those returned so far
+    tuples: HashSet<Row>,
+}
+
+impl SetOperationExecutor {
+    /// Creates a new set operation executor, or an error if the inputs' schemas do not
+    /// match.
+    ///
+    /// # Arguments
+    /// * `left` - The left input, whose column names the output takes
+    /// * `right` - The right input
+    /// * `operation` - How to combine the two
+    pub fn new(left: BoxedExecutor, right: BoxedExecutor, operation: SetOperation) -> Result<Self> {
+        let output_schema = output_schema(left.schema(), right.schema(), operation)?;
+        Ok(Self {
+            left,
+            right,
+            operation,
+            output_schema,
+            left_done: false,
+            tuples: HashSet::new(),
+        })
+    }
+}
+
+impl Executor for SetOperationExecutor {
+    fn schema(&self) -> &Schema {
+        &self.output_schema
+    }
+
+    fn init(&mut self) -> Result<()> {
+        self.left_done = false;
+        self.tuples.clear();
+        self.left.init()?;
+        self.right.init()?;
+
+        if matches!(
+            self.operation,
+            SetOperation::Intersect | SetOperation::Except
+        ) {
+            while let Some(tuple) = self.right.next()? {
+                self.tuples.insert(tuple);
+            }
+        }
+        Ok(())
+    }
+
+    fn describe(&self) -> String {
+        self.operation.name().to_string()
+    }
+
+    fn children(&self) -> Vec<&dyn Executor> {
+        vec![self.left.as_ref(), self.right.as_ref()]
+    }
+
+    fn next(&mut self) -> Result<Option<Row>> {
+        while !self.left_done {
+            let Some(tuple) = self.left.next()? else {
+                self.left_done = true;
+                break;
+            };
+            let keep = match self.operation {
+                SetOperation::UnionAll => true,
+                SetOperation::Union => self.tuples.insert(tuple.clone()),
+                SetOperation::Intersect => self.tuples.remove(&tuple),
+                SetOperation::Except => self.tuples.insert(tuple.clone()),
+            };
+            if keep {
+                return Ok(Some(tuple));
+            }
+        }
+
+        // Only the unions return tuples from the right input
+        while let Some(tuple) = self.right.next()? {
+            let keep = match self.operation {
+                SetOperation::UnionAll => true,
+                SetOperation::Union => self.tuples.insert(tuple.clone()),
+                SetOperation::Intersect | SetOperation::Except => return Ok(None),
+            };
+            if keep {
+                return Ok(Some(tuple));
+            }
+        }
+        Ok(None)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::executor::tests::collect_results;
+    use crate::types::Value;
+    use crate::{int_column, varchar_column, Database};
+
+    #[test]
+    fn test_set_operations() {
+        let path = "test_set_operation_dir";
+        let _ = std::fs::remove_dir_all(path);
+        let db = Database::open(path).unwrap();
+
+        // Temporary tables can hold NULLs, which stored tables cannot
+        let table = |values: &[Option<i32>]| {
+            let schema = Schema {
+                columns: vec![int_column("x")],
+            };
+            let mut table = db.catalog().create_temp_table(schema).unwrap();
+            for value in values {
+                let value = value.map_or(Value::Null, Value::Integer);
+                table.insert(&Row::new(vec![value])).unwrap();
+            }
+            table
+        };
+        let left = table(&[Some(1), Some(2), Some(2), None, Some(3), None]);
+        let right = table(&[Some(3), None, Some(4), Some(3)]);
+
+        let run = |operation| -> Vec<Option<i32>> {
+            let mut executor =
+                SetOperationExecutor::new(Box::new(left.scan()), Box::new(right.scan()), operation)
+                    .unwrap();
+            collect_results(&mut executor)
+                .unwrap()
+                .into_iter()
+                .map(|row| match row.values[0] {
+                    Value::Integer(i) => Some(i),
+                    _ => None,
+                })
+                .collect()
+        };
+        assert_eq!(
+            run(SetOperation::UnionAll),
+            vec![
+                Some(1),
+                Some(2),
+                Some(2),
+                None,
+                Some(3),
+                None,
+                Some(3),
+                None,
+                Some(4),
+                Some(3)
+            ]
+        );
+        // NULLs are equal to each other, so each appears once
+        assert_eq!(
+            run(SetOperation::Union),
+            vec![Some(1), Some(2), None, Some(3), Some(4)]
+        );
+        assert_eq!(run(SetOperation::Intersect), vec![None, Some(3)]);
+        assert_eq!(run(SetOperation::Except), vec![Some(1), Some(2)]);
+
+        // Inputs must line up column by column
+        let names = db
+            .catalog()
+            .create_temp_table(Schema {
+                columns: vec![varchar_column("name", 10)],
+            })
+            .unwrap();
+        let mismatch = SetOperationExecutor::new(
+            Box::new(left.scan()),
+            Box::new(names.scan()),
+            SetOperation::Union,
+        );
+        assert!(matches!(mismatch, Err(QueryError::TypeMismatch(_))));
+
+        drop((left, right, names));
+        std::fs::remove_dir_all(path).unwrap();
+    }
+}
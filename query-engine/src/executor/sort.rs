@@ -0,0 +1,337 @@
+//! Sort executor for ORDER BY and for feeding order-dependent operators.
+//!
+//! Reads its whole input on init, sorts it by the key values, and returns the tuples in
+//! order. The sort is stable, so tuples with equal keys keep their input order.
+//!
+//! When the input holds more rows than the memory limit, it is sorted in chunks (runs) of
+//! at most that many rows, each written to a temporary table in the buffer pool except the
+//! last, which stays in memory. The runs are then merged by repeatedly taking the smallest
+//! head of all runs from a binary heap (a k-way merge). If there are more than
+//! [`MERGE_FAN_IN`] runs, the oldest are merged into a single longer run first, so no more
+//! than that many scans are open at once.
+
+use super::{BoxedExecutor, Executor};
+use crate::catalog::temp::{TempTable, TempTableScan};
+use crate::catalog::Catalog;
+use crate::expression::SortKey;
+use crate::types::{Row, Value};
+use crate::Result;
+use std::cmp::Ordering;
+use std::collections::BinaryHeap;
+use std::sync::Arc;
+use storage_engine::tuple::Schema;
+
+/// Default number of rows sorted in memory before a run is written to disk.
+pub const DEFAULT_MEMORY_LIMIT: usize = 100_000;
+
+/// Maximum number of runs merged at once.
+const MERGE_FAN_IN: usize = 16;
+
+/// Sort executor that orders tuples by a list of keys, spilling sorted runs to disk when
+/// the input does not fit in memory.
+pub struct SortExecutor {
+    child: BoxedExecutor,
+    keys: Arc<[SortKey]>,
+    catalog: Arc<Catalog>,
+    memory_limit: usize,
+    /// Sorted tuples not yet returned, in reverse order so they can be popped, when the
+    /// input fit in memory
+    sorted: Vec<Row>,
+    /// The merge of the sorted runs, when the input did not fit in memory
+    merge: Option<Merge>,
+    /// Spilled runs, kept alive while `merge` scans them
+    runs: Vec<TempTable>,
+    /// Number of runs written to disk since init, including those of intermediate merges
+    spilled_runs: usize,
+}
+
+impl SortExecutor {
+    /// Creates a new sort executor.
+    ///
+    /// # Arguments
+    /// * `child` - The child executor to pull tuples from
+    /// * `keys` - Keys bound against the child's schema, most significant first
+    /// * `catalog` - Where temporary tables are created if the sort spills
+    pub fn new(child: BoxedExecutor, keys: Vec<SortKey>, catalog: Arc<Catalog>) -> Self {
+        Self {
+            child,
+            keys: keys.into(),
+            catalog,
+            memory_limit: DEFAULT_MEMORY_LIMIT,
+            sorted: Vec::new(),
+            merge: None,
+            runs: Vec::new(),
+            spilled_runs: 0,
+        }
+    }
+
+    /// Sets how many rows may be held in memory before a sorted run is written to disk.
+    pub fn with_memory_limit(mut self, rows: usize) -> Self {
+        self.memory_limit = rows.max(1);
+        self
+    }
+
+    /// Returns how many sorted runs the last run wrote to disk; 0 if it fit in memory.
+    pub fn spilled_runs(&self) -> usize {
+        self.spilled_runs
+    }
+
+    /// Sorts `rows` by their key values, returning the rows alone.
+    fn sort_run(&self, mut rows: Vec<(Vec<Value>, Row)>) -> Vec<Row> {
+        rows.sort_by(|(a, _), (b, _)| compare_rows(&self.keys, a, b));
+        rows.into_iter().map(|(_, tuple)| tuple).collect()
+    }
+
+    /// Writes `rows` to a new temporary table.
+    fn spill_run(&mut self, rows: impl IntoIterator<Item = Row>) -> Result<TempTable> {
+        let mut run = self
+            .catalog
+            .create_temp_table(self.child.schema().clone())?;
+        for tuple in rows {
+            run.insert(&tuple)?;
+        }
+        self.spilled_runs += 1;
+        Ok(run)
+    }
+
+    /// Merges the oldest spilled runs into one until at most `MERGE_FAN_IN` runs, counting
+    /// the one in memory, are left.
+    fn reduce_runs(&mut self) -> Result<()> {
+        while self.runs.len() >= MERGE_FAN_IN {
+            let oldest: Vec<TempTable> = self.runs.drain(..MERGE_FAN_IN).collect();
+            let inputs = oldest
+                .iter()
+                .map(|run| RunReader::Spilled(run.scan()))
+                .collect();
+            let mut merge = Merge::new(self.keys.clone(), inputs)?;
+            let mut merged = self
+                .catalog
+                .create_temp_table(self.child.schema().clone())?;
+            while let Some(tuple) = merge.next()? {
+                merged.insert(&tuple)?;
+            }
+            self.spilled_runs += 1;
+            // The merged rows came before all the remaining runs' rows
+            self.runs.insert(0, merged);
+        }
+        Ok(())
+    }
+}
+
+/// Compares two rows' key values under `keys`.
+pub(crate) fn compare_rows(keys: &[SortKey], a: &[Value], b: &[Value]) -> Ordering {
+    keys.iter()
+        .zip(a.iter().zip(b))
+        .map(|(key, (a, b))| key.compare(a, b))
+        .find(|ordering| ordering.is_ne())
+        .unwrap_or(Ordering::Equal)
+}
+
+/// Evaluates the sort keys of `tuple`.
+fn key_values(keys: &[SortKey], tuple: &Row) -> Result<Vec<Value>> {
+    keys.iter().map(|key| key.expr.evaluate(tuple)).collect()
+}
+
+/// The source of one sorted run being merged.
+enum RunReader {
+    Spilled(TempTableScan),
+    Memory(std::vec::IntoIter<Row>),
+}
+
+impl RunReader {
+    fn next(&mut self) -> Result<Option<Row>> {
+        match self {
+            RunReader::Spilled(scan) => scan.next(),
+            RunReader::Memory(rows) => Ok(rows.next()),
+        }
+    }
+}
+
+/// The current head of one run in a merge.
+struct MergeEntry {
+    keys: Arc<[SortKey]>,
+    values: Vec<Value>,
+    tuple: Row,
+    /// Index of the run, which breaks ties so the merge stays stable
+    run: usize,
+}
+
+impl Ord for MergeEntry {
+    /// Orders entries in reverse, so the max-heap yields the smallest first.
+    fn cmp(&self, other: &Self) -> Ordering {
+        compare_rows(&self.keys, &other.values, &self.values).then(other.run.cmp(&self.run))
+    }
+}
+
+impl PartialOrd for MergeEntry {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl PartialEq for MergeEntry {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other).is_eq()
+    }
+}
+
+impl Eq for MergeEntry {}
+
+/// A k-way merge of sorted runs, given oldest first.
+struct Merge {
+    keys: Arc<[SortKey]>,
+    inputs: Vec<RunReader>,
+    heap: BinaryHeap<MergeEntry>,
+}
+
+impl Merge {
+    fn new(keys: Arc<[SortKey]>, mut inputs: Vec<RunReader>) -> Result<Self> {
+        let mut heap = BinaryHeap::with_capacity(inputs.len());
+        for (run, input) in inputs.iter_mut().enumerate() {
+            if let RunReader::Spilled(scan) = input {
+                scan.init()?;
+            }
+            if let Some(tuple) = input.next()? {
+                heap.push(MergeEntry {
+                    keys: keys.clone(),
+                    values: key_values(&keys, &tuple)?,
+                    tuple,
+                    run,
+                });
+            }
+        }
+        Ok(Self { keys, inputs, heap })
+    }
+
+    /// Returns the smallest head, replacing it with the next tuple of its run.
+    fn next(&mut self) -> Result<Option<Row>> {
+        let Some(entry) = self.heap.pop() else {
+            return Ok(None);
+        };
+        if let Some(tuple) = self.inputs[entry.run].next()? {
+            self.heap.push(MergeEntry {
+                keys: self.keys.clone(),
+                values: key_values(&self.keys, &tuple)?,
+                tuple,
+                run: entry.run,
+            });
+        }
+        Ok(Some(entry.tuple))
+    }
+}
+
+impl Executor for SortExecutor {
+    fn schema(&self) -> &Schema {
+        self.child.schema()
+    }
+
+    fn init(&mut self) -> Result<()> {
+        self.merge = None;
+        self.runs.clear();
+        self.spilled_runs = 0;
+        self.child.init()?;
+
+        let mut rows = Vec::new();
+        while let Some(tuple) = self.child.next()? {
+            if rows.len() == self.memory_limit {
+                let run = self.sort_run(std::mem::take(&mut rows));
+                let run = self.spill_run(run)?;
+                self.runs.push(run);
+            }
+            rows.push((key_values(&self.keys, &tuple)?, tuple));
+        }
+        let last = self.sort_run(rows);
+
+        if self.runs.is_empty() {
+            self.sorted = last.into_iter().rev().collect();
+            return Ok(());
+        }
+        self.sorted = Vec::new();
+        self.reduce_runs()?;
+        let mut inputs: Vec<RunReader> = self
+            .runs
+            .iter()
+            .map(|run| RunReader::Spilled(run.scan()))
+            .collect();
+        inputs.push(RunReader::Memory(last.into_iter()));
+        self.merge = Some(Merge::new(self.keys.clone(), inputs)?);
+        Ok(())
+    }
+
+    fn describe(&self) -> String {
+        let keys: Vec<String> = self
+            .keys
+            .iter()
+            .map(|key| key.display_with(self.child.schema()))
+            .collect();
+        format!("Sort({})", keys.join(", "))
+    }
+
+    fn children(&self) -> Vec<&dyn Executor> {
+        vec![self.child.as_ref()]
+    }
+
+    fn sort_order(&self) -> Vec<usize> {
+        self.keys
+            .iter()
+            .map_while(SortKey::ascending_column)
+            .collect()
+    }
+
+    fn next(&mut self) -> Result<Option<Row>> {
+        match &mut self.merge {
+            Some(merge) => merge.next(),
+            None => Ok(self.sorted.pop()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::executor::tests::collect_results;
+    use crate::expression::col;
+    use crate::{int_column, Database};
+
+    #[test]
+    fn test_external_sort() {
+        let path = "test_external_sort_dir";
+        let _ = std::fs::remove_dir_all(path);
+        let db = Database::open(path).unwrap();
+
+        // Temporary tables can hold NULLs, which stored tables cannot
+        let schema = Schema {
+            columns: vec![int_column("age"), int_column("id")],
+        };
+        let mut input = db.catalog().create_temp_table(schema.clone()).unwrap();
+        let age = |id: i32| match id % 7 {
+            0 => Value::Null,
+            _ => Value::Integer(id * 37 % 11),
+        };
+        for id in 0..200 {
+            input
+                .insert(&Row::new(vec![age(id), Value::Integer(id)]))
+                .unwrap();
+        }
+
+        // Descending with NULLs last, ties broken by input order
+        let keys = vec![col("age").desc().nulls_last().bind(&schema).unwrap()];
+        let mut expected: Vec<Row> = (0..200)
+            .map(|id| Row::new(vec![age(id), Value::Integer(id)]))
+            .collect();
+        expected.sort_by(|a, b| keys[0].compare(&a.values[0], &b.values[0]));
+
+        // 5 rows per run spills 39 runs, more than can be merged at once
+        for (memory_limit, spilled_runs) in [(DEFAULT_MEMORY_LIMIT, 0), (5, 39 + 2)] {
+            let mut sort =
+                SortExecutor::new(Box::new(input.scan()), keys.clone(), db.catalog().clone())
+                    .with_memory_limit(memory_limit);
+            assert_eq!(collect_results(&mut sort).unwrap(), expected);
+            assert_eq!(sort.spilled_runs(), spilled_runs);
+            assert_eq!(sort.describe(), "Sort(age DESC NULLS LAST)");
+        }
+
+        drop(input);
+        std::fs::remove_dir_all(path).unwrap();
+    }
+}
@@ -0,0 +1,160 @@
+//! Subquery executor for uncorrelated subqueries.
+//!
+//! Runs the plan of every subquery that does not refer to the outer query once, on init,
+//! and stores its rows where the expressions using it read them. Its input is initialized
+//! afterwards, so the values are in place before the first of its tuples is evaluated.
+//! Subqueries run again on every init, so a prepared query sees the current data.
+
+use super::{BoxedExecutor, Executor};
+use crate::expression::SubqueryRows;
+use crate::types::Row;
+use crate::Result;
+use storage_engine::tuple::Schema;
+
+/// Subquery executor that fetches the rows of uncorrelated subqueries before its input
+/// runs, returning the input's tuples unchanged.
+pub struct SubqueryExecutor {
+    child: BoxedExecutor,
+    /// Each subquery's plan, with where its rows go
+    subqueries: Vec<(BoxedExecutor, SubqueryRows)>,
+}
+
+impl SubqueryExecutor {
+    /// Creates a new subquery executor.
+    ///
+    /// # Arguments
+    /// * `child` - The child executor whose expressions read the subqueries' rows
+    /// * `subqueries` - The plan of each subquery, with the rows it fills in
+    pub fn new(child: BoxedExecutor, subqueries: Vec<(BoxedExecutor, SubqueryRows)>) -> Self {
+        Self { child, subqueries }
+    }
+}
+
+impl Executor for SubqueryExecutor {
+    fn schema(&self) -> &Schema {
+        self.child.schema()
+    }
+
+    fn init(&mut self) -> Result<()> {
+        for (plan, rows) in &mut self.subqueries {
+            rows.fetch(plan.as_mut())?;
+        }
+        self.child.init()
+    }
+
+    fn describe(&self) -> String {
+        let columns: Vec<&str> = self
+            .subqueries
+            .iter()
+            .map(|(_, rows)| rows.column().name.as_str())
+            .collect();
+        format!("Subqueries({})", columns.join(", "))
+    }
+
+    fn children(&self) -> Vec<&dyn Executor> {
+        let mut children = vec![self.child.as_ref()];
+        children.extend(self.subqueries.iter().map(|(plan, _)| plan.as_ref()));
+        children
+    }
+
+    fn sort_order(&self) -> Vec<usize> {
+        self.child.sort_order()
+    }
+
+    fn next(&mut self) -> Result<Option<Row>> {
+        self.child.next()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::catalog::temp::TempTable;
+    use crate::executor::tests::collect_results;
+    use crate::executor::FilterExecutor;
+    use crate::expression::{col, Expression, UnaryOperator};
+    use crate::types::Value;
+    use crate::{int_column, Database, QueryError};
+
+    #[test]
+    fn test_subquery_executor() {
+        let path = "test_subquery_dir";
+        let _ = std::fs::remove_dir_all(path);
+        let db = Database::open(path).unwrap();
+
+        // Temporary tables can hold NULLs, which stored tables cannot
+        let schema = Schema {
+            columns: vec![int_column("x")],
+        };
+        let table = |values: &[Option<i32>]| {
+            let mut table = db.catalog().create_temp_table(schema.clone()).unwrap();
+            for value in values {
+                let value = value.map_or(Value::Null, Value::Integer);
+                table.insert(&Row::new(vec![value])).unwrap();
+            }
+            table
+        };
+        let input = table(&[Some(1), Some(2), None, Some(3)]);
+        let x = col("x").bind(&schema).unwrap();
+
+        // Runs `predicate` over the input, with `subquery`'s rows fetched into `rows` first
+        let run = |predicate: Expression, subquery: &TempTable, rows| {
+            let filter = FilterExecutor::new(Box::new(input.scan()), predicate);
+            let mut executor =
+                SubqueryExecutor::new(Box::new(filter), vec![(Box::new(subquery.scan()), rows)]);
+            collect_results(&mut executor).map(|rows| {
+                rows.into_iter()
+                    .map(|row| match row.values[0] {
+                        Value::Integer(i) => Some(i),
+                        _ => None,
+                    })
+                    .collect::<Vec<_>>()
+            })
+        };
+        let in_rows = |values: &[Option<i32>]| {
+            let subquery = &table(values);
+            let rows = SubqueryRows::set(int_column("x"));
+            let predicate = Expression::BoundInSubquery {
+                expr: Box::new(x.clone()),
+                rows: rows.clone(),
+            };
+            let not_in = Expression::UnaryOp {
+                op: UnaryOperator::Not,
+                expr: Box::new(predicate.clone()),
+            };
+            (
+                run(predicate, subquery, rows.clone()).unwrap(),
+                run(not_in, subquery, rows).unwrap(),
+            )
+        };
+
+        // A NULL in the subquery makes NOT IN unknown for every value not found
+        assert_eq!(
+            in_rows(&[Some(3), Some(1)]),
+            (vec![Some(1), Some(3)], vec![Some(2)])
+        );
+        assert_eq!(in_rows(&[Some(3), None]), (vec![Some(3)], vec![]));
+        // Nothing is in an empty subquery, not even NULL
+        assert_eq!(
+            in_rows(&[]),
+            (vec![], vec![Some(1), Some(2), None, Some(3)])
+        );
+
+        // Scalar subqueries are NULL without rows, and an error with more than one
+        let scalar = |values: &[Option<i32>]| {
+            let subquery = &table(values);
+            let rows = SubqueryRows::scalar(int_column("x"));
+            let predicate = x.clone().gt(Expression::BoundScalarSubquery(rows.clone()));
+            run(predicate, subquery, rows)
+        };
+        assert_eq!(scalar(&[Some(1)]).unwrap(), vec![Some(2), Some(3)]);
+        assert_eq!(scalar(&[]).unwrap(), vec![]);
+        assert!(matches!(
+            scalar(&[Some(1), Some(2)]),
+            Err(QueryError::ExecutionError(_))
+        ));
+
+        drop(input);
+        std::fs::remove_dir_all(path).unwrap();
+    }
+}
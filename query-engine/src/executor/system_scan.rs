@@ -0,0 +1,63 @@
+//! Scan executor for the catalog-backed system tables.
+//!
+//! Snapshots the system table's rows from the catalog on `init()` and returns them one at
+//! a time, so it composes with filters, projections and limits like a sequential scan.
+
+use super::Executor;
+use crate::catalog::system::SystemTable;
+use crate::catalog::Catalog;
+use crate::types::{Row, Value};
+use crate::Result;
+use std::sync::Arc;
+use storage_engine::tuple::Schema;
+
+/// Scan over a [`SystemTable`].
+pub struct SystemScanExecutor {
+    catalog: Arc<Catalog>,
+    table: SystemTable,
+    schema: Schema,
+    rows: Vec<Vec<Value>>,
+    position: usize,
+}
+
+impl SystemScanExecutor {
+    /// Creates a new system table scan.
+    pub fn new(catalog: Arc<Catalog>, table: SystemTable) -> Self {
+        Self {
+            catalog,
+            table,
+            schema: table.schema(),
+            rows: Vec::new(),
+            position: 0,
+        }
+    }
+}
+
+impl Executor for SystemScanExecutor {
+    fn schema(&self) -> &Schema {
+        &self.schema
+    }
+
+    fn init(&mut self) -> Result<()> {
+        self.rows = self
+            .table
+            .rows(&self.catalog)
+            .into_iter()
+            .map(|row| row.into_iter().map(Value::from_storage).collect())
+            .collect();
+        self.position = 0;
+        Ok(())
+    }
+
+    fn next(&mut self) -> Result<Option<Row>> {
+        let Some(values) = self.rows.get(self.position) else {
+            return Ok(None);
+        };
+        self.position += 1;
+        Ok(Some(Row::new(values.clone())))
+    }
+
+    fn describe(&self) -> String {
+        format!("SystemScan({})", self.table.name())
+    }
+}
@@ -0,0 +1,209 @@
+//! Top-N executor for ORDER BY ... LIMIT N.
+//!
+//! Reads its whole input on init like a sort, but keeps only the N smallest tuples seen so
+//! far in a bounded max-heap: each new tuple either replaces the largest one held or is
+//! discarded. Memory stays at N tuples however large the input is, and no tuple is
+//! written to disk. Tuples with equal keys keep their input order, as with a full sort.
+
+use super::sort::compare_rows;
+use super::{BoxedExecutor, Executor};
+use crate::expression::SortKey;
+use crate::types::{Row, Value};
+use crate::Result;
+use std::cmp::Ordering;
+use std::collections::BinaryHeap;
+use std::sync::Arc;
+use storage_engine::tuple::Schema;
+
+/// Top-N executor that returns the first N tuples in sort order.
+pub struct TopNExecutor {
+    child: BoxedExecutor,
+    keys: Arc<[SortKey]>,
+    limit: usize,
+    /// The first tuples in sort order not yet returned, in reverse order so they can be
+    /// popped
+    sorted: Vec<Row>,
+}
+
+impl TopNExecutor {
+    /// Creates a new top-N executor.
+    ///
+    /// # Arguments
+    /// * `child` - The child executor to pull tuples from
+    /// * `keys` - Keys bound against the child's schema, most significant first
+    /// * `limit` - How many tuples to return
+    pub fn new(child: BoxedExecutor, keys: Vec<SortKey>, limit: usize) -> Self {
+        Self {
+            child,
+            keys: keys.into(),
+            limit,
+            sorted: Vec::new(),
+        }
+    }
+}
+
+/// A tuple held in the heap, ordered by its key values and then by input position.
+struct HeapEntry {
+    keys: Arc<[SortKey]>,
+    values: Vec<Value>,
+    position: usize,
+    tuple: Row,
+}
+
+impl Ord for HeapEntry {
+    fn cmp(&self, other: &Self) -> Ordering {
+        compare_rows(&self.keys, &self.values, &other.values)
+            .then(self.position.cmp(&other.position))
+    }
+}
+
+impl PartialOrd for HeapEntry {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl PartialEq for HeapEntry {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other).is_eq()
+    }
+}
+
+impl Eq for HeapEntry {}
+
+impl Executor for TopNExecutor {
+    fn schema(&self) -> &Schema {
+        self.child.schema()
+    }
+
+    fn init(&mut self) -> Result<()> {
+        self.child.init()?;
+        self.sorted = Vec::new();
+        if self.limit == 0 {
+            return Ok(());
+        }
+
+        // The heap's top is the largest tuple held, the first to go when a smaller arrives.
+        // It grows with the input rather than being reserved up front: the limit may be far
+        // larger than the input.
+        let mut heap = BinaryHeap::new();
+        let mut position = 0;
+        while let Some(tuple) = self.child.next()? {
+            let values = self
+                .keys
+                .iter()
+                .map(|key| key.expr.evaluate(&tuple))
+                .collect::<Result<Vec<_>>>()?;
+            let entry = HeapEntry {
+                keys: self.keys.clone(),
+                values,
+                position,
+                tuple,
+            };
+            position += 1;
+
+            if heap.len() < self.limit {
+                heap.push(entry);
+            } else if heap.peek().is_some_and(|largest| entry < *largest) {
+                heap.pop();
+                heap.push(entry);
+            }
+        }
+
+        self.sorted = heap
+            .into_sorted_vec()
+            .into_iter()
+            .rev()
+            .map(|entry| entry.tuple)
+            .collect();
+        Ok(())
+    }
+
+    fn describe(&self) -> String {
+        let keys: Vec<String> = self
+            .keys
+            .iter()
+            .map(|key| key.display_with(self.child.schema()))
+            .collect();
+        format!("TopN({}, {})", self.limit, keys.join(", "))
+    }
+
+    fn children(&self) -> Vec<&dyn Executor> {
+        vec![self.child.as_ref()]
+    }
+
+    fn sort_order(&self) -> Vec<usize> {
+        self.keys
+            .iter()
+            .map_while(SortKey::ascending_column)
+            .collect()
+    }
+
+    fn next(&mut self) -> Result<Option<Row>> {
+        Ok(self.sorted.pop())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::executor::tests::collect_results;
+    use crate::executor::{LimitExecutor, SortExecutor};
+    use crate::expression::col;
+    use crate::{int_column, Database};
+
+    #[test]
+    fn test_top_n_matches_sort_and_limit() {
+        let path = "test_top_n_dir";
+        let _ = std::fs::remove_dir_all(path);
+        let db = Database::open(path).unwrap();
+
+        // Temporary tables can hold NULLs, which stored tables cannot
+        let schema = Schema {
+            columns: vec![int_column("day"), int_column("id")],
+        };
+        let mut input = db.catalog().create_temp_table(schema.clone()).unwrap();
+        for id in 0..100 {
+            let day = match id % 9 {
+                0 => Value::Null,
+                _ => Value::Integer(id * 13 % 17),
+            };
+            input
+                .insert(&Row::new(vec![day, Value::Integer(id)]))
+                .unwrap();
+        }
+
+        for key in [
+            col("day").desc(),
+            col("day").asc(),
+            col("day").asc().nulls_first(),
+        ] {
+            let keys = vec![
+                key.bind(&schema).unwrap(),
+                col("id").asc().bind(&schema).unwrap(),
+            ];
+            // Ties on the first key only, to check they keep their input order
+            let first_key = keys[..1].to_vec();
+            for keys in [keys, first_key] {
+                for limit in [0, 1, 7, 100, 150, usize::MAX] {
+                    let mut top_n = TopNExecutor::new(Box::new(input.scan()), keys.clone(), limit);
+                    let sort = SortExecutor::new(
+                        Box::new(input.scan()),
+                        keys.clone(),
+                        db.catalog().clone(),
+                    );
+                    let mut expected = LimitExecutor::new(Box::new(sort), limit);
+                    assert_eq!(
+                        collect_results(&mut top_n).unwrap(),
+                        collect_results(&mut expected).unwrap(),
+                        "{}",
+                        top_n.describe()
+                    );
+                }
+            }
+        }
+
+        drop(input);
+        std::fs::remove_dir_all(path).unwrap();
+    }
+}
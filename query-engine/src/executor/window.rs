@@ -0,0 +1,561 @@
+//! Window executor for window functions such as ROW_NUMBER, RANK, LAG and running SUMs.
+//!
+//! Expects its input sorted by the PARTITION BY keys and then the ORDER BY keys, which the
+//! planner arranges with a sort when needed. Rows are read one partition at a time, the
+//! function is computed for every row of the partition, and the rows are returned in the
+//! same order with the result appended as a new column. Only the current partition is held
+//! in memory.
+//!
+//! Aggregates read the rows of each row's frame. Frames starting at UNBOUNDED PRECEDING
+//! only grow from one row to the next, so they share a single accumulator; other frames
+//! are folded from scratch for every row.
+
+use super::hash_aggregate::{aggregate_column, Accumulator};
+use super::projection::infer_column;
+use super::sort::compare_rows;
+use super::{BoxedExecutor, Executor};
+use crate::expression::{
+    AggregateExpr, FrameBound, FrameUnits, SortKey, WindowExpr, WindowFrame, WindowFunction,
+};
+use crate::types::{Row, Value};
+use crate::{QueryError, Result};
+use storage_engine::tuple::{Column, Schema, Type};
+
+/// Window executor that appends the value of a window function to every row.
+pub struct WindowExecutor {
+    child: BoxedExecutor,
+    window: WindowExpr,
+    name: String,
+    output_schema: Schema,
+    /// The first row of the next partition, read while looking for the end of the current one
+    pending: Option<Row>,
+    /// Rows of the current partition not yet returned, in reverse order so they can be popped
+    results: Vec<Row>,
+}
+
+impl WindowExecutor {
+    /// Creates a new window executor.
+    ///
+    /// # Arguments
+    /// * `child` - The child executor to pull tuples from, sorted by the window's PARTITION
+    ///   BY keys in ascending order with NULLs last and then by its ORDER BY keys
+    /// * `window` - The window function, bound against the child's schema
+    /// * `name` - Name of the output column holding the function's value
+    pub fn new(child: BoxedExecutor, window: WindowExpr, name: &str) -> Self {
+        let output_schema = output_schema(child.schema(), &window, name);
+        Self {
+            child,
+            window,
+            name: name.to_string(),
+            output_schema,
+            pending: None,
+            results: Vec::new(),
+        }
+    }
+
+    fn partition_key(&self, tuple: &Row) -> Result<Vec<Value>> {
+        let partition_by = &self.window.window.partition_by;
+        partition_by
+            .iter()
+            .map(|expr| expr.evaluate(tuple))
+            .collect()
+    }
+
+    /// Computes the window function for every row of `partition`.
+    fn evaluate(&self, partition: &[Row]) -> Result<Vec<Value>> {
+        let order_by = &self.window.window.order_by;
+        let order = partition
+            .iter()
+            .map(|tuple| {
+                order_by
+                    .iter()
+                    .map(|key| key.expr.evaluate(tuple))
+                    .collect()
+            })
+            .collect::<Result<Vec<Vec<Value>>>>()?;
+        let peers = |a: usize, b: usize| compare_rows(order_by, &order[a], &order[b]).is_eq();
+        let len = partition.len();
+
+        match &self.window.function {
+            WindowFunction::RowNumber => Ok((1..=len).map(|n| Value::Integer(n as i32)).collect()),
+            WindowFunction::Rank | WindowFunction::DenseRank => {
+                let dense = matches!(self.window.function, WindowFunction::DenseRank);
+                let mut values = Vec::with_capacity(len);
+                let mut rank = 0;
+                for i in 0..len {
+                    if i == 0 || !peers(i - 1, i) {
+                        rank = if dense { rank + 1 } else { i + 1 };
+                    }
+                    values.push(Value::Integer(rank as i32));
+                }
+                Ok(values)
+            }
+            WindowFunction::Lag { expr, offset } => (0..len)
+                .map(|i| match i.checked_sub(*offset) {
+                    Some(j) => expr.evaluate(&partition[j]),
+                    None => Ok(Value::Null),
+                })
+                .collect(),
+            WindowFunction::Lead { expr, offset } => (0..len)
+                .map(|i| match i.checked_add(*offset).filter(|&j| j < len) {
+                    Some(j) => expr.evaluate(&partition[j]),
+                    None => Ok(Value::Null),
+                })
+                .collect(),
+            WindowFunction::Aggregate(aggregate) => {
+                let frame = self.window.window.effective_frame();
+                let frames = Frames::new(frame, order_by, &order)?;
+                aggregate_frames(aggregate, partition, &frames)
+            }
+        }
+    }
+}
+
+/// Returns the schema of the rows a window executor produces from `input_schema`: its
+/// columns followed by the function's, named `name`.
+pub(crate) fn output_schema(input_schema: &Schema, window: &WindowExpr, name: &str) -> Schema {
+    let name = name.to_string();
+    let column = match &window.function {
+        WindowFunction::Lag { expr, .. } | WindowFunction::Lead { expr, .. } => {
+            infer_column(expr, name, input_schema)
+        }
+        WindowFunction::Aggregate(aggregate) => aggregate_column(aggregate, name, input_schema),
+        WindowFunction::RowNumber | WindowFunction::Rank | WindowFunction::DenseRank => Column {
+            name,
+            column_type: Type::Integer,
+            length: 4, // Size of integer
+        },
+    };
+    let mut schema = input_schema.clone();
+    schema.columns.push(column);
+    schema
+}
+
+/// The frame of every row of a partition, as ranges of row indices.
+struct Frames {
+    frame: WindowFrame,
+    /// The ranges, end exclusive
+    bounds: Vec<(usize, usize)>,
+}
+
+impl Frames {
+    /// Works out the frames of a partition whose rows have the ORDER BY values `order` under
+    /// `order_by`.
+    fn new(frame: WindowFrame, order_by: &[SortKey], order: &[Vec<Value>]) -> Result<Self> {
+        let len = order.len();
+
+        // The first and one past the last row tying with each row
+        let mut peers = vec![(0, 0); len];
+        let mut first = 0;
+        for i in 1..=len {
+            if i == len || compare_rows(order_by, &order[first], &order[i]).is_ne() {
+                peers[first..i].fill((first, i));
+                first = i;
+            }
+        }
+
+        let positions = match (frame.units, order_by) {
+            (FrameUnits::Range, [key]) if frame.has_offset() => {
+                Some(RangePositions::new(key, order)?)
+            }
+            _ => None,
+        };
+
+        let mut bounds = Vec::with_capacity(len);
+        for (i, &(peers_start, peers_end)) in peers.iter().enumerate() {
+            let (start, end) = if frame.units == FrameUnits::Rows {
+                // The index of the row each bound names, which may lie outside the partition
+                let row = |bound| match bound {
+                    FrameBound::UnboundedPreceding => 0,
+                    FrameBound::Preceding(n) => (i as i64).saturating_sub(offset(n)),
+                    FrameBound::CurrentRow => i as i64,
+                    FrameBound::Following(n) => (i as i64).saturating_add(offset(n)),
+                    FrameBound::UnboundedFollowing => len as i64 - 1,
+                };
+                let clamp = |row: i64| row.clamp(0, len as i64) as usize;
+                (
+                    clamp(row(frame.start)),
+                    clamp(row(frame.end).saturating_add(1)),
+                )
+            } else {
+                let bound = |bound, is_start| {
+                    let distance = match bound {
+                        FrameBound::UnboundedPreceding => return 0,
+                        FrameBound::UnboundedFollowing => return len,
+                        FrameBound::Preceding(n) => -offset(n),
+                        FrameBound::Following(n) => offset(n),
+                        FrameBound::CurrentRow => 0,
+                    };
+                    // Only offsets need the values themselves; NULLs tie with each other
+                    let offset_row = positions
+                        .as_ref()
+                        .filter(|_| bound != FrameBound::CurrentRow)
+                        .and_then(|positions| positions.bound(i, distance, is_start));
+                    match offset_row {
+                        Some(row) => row,
+                        None if is_start => peers_start,
+                        None => peers_end,
+                    }
+                };
+                (bound(frame.start, true), bound(frame.end, false))
+            };
+            bounds.push((start.min(end), end));
+        }
+        Ok(Self { frame, bounds })
+    }
+}
+
+/// Converts a frame offset to a distance, saturating rather than wrapping.
+fn offset(n: usize) -> i64 {
+    i64::try_from(n).unwrap_or(i64::MAX)
+}
+
+/// The ORDER BY values of a partition's rows, for RANGE frames with offsets.
+struct RangePositions {
+    /// Each row's value, negated for a descending key so they always increase, or None for
+    /// NULL
+    positions: Vec<Option<i64>>,
+    /// The rows with non-NULL values, which the NULLs sort before or after
+    non_null: (usize, usize),
+}
+
+impl RangePositions {
+    fn new(key: &SortKey, order: &[Vec<Value>]) -> Result<Self> {
+        let positions = order
+            .iter()
+            .map(|values| match &values[0] {
+                Value::Integer(i) if key.descending => Ok(Some(-(*i as i64))),
+                Value::Integer(i) => Ok(Some(*i as i64)),
+                Value::Null => Ok(None),
+                value => Err(QueryError::TypeMismatch(format!(
+                    "RANGE frames with offsets need an INTEGER ORDER BY key, got {:?}",
+                    value
+                ))),
+            })
+            .collect::<Result<Vec<_>>>()?;
+        let nulls = positions.iter().filter(|p| p.is_none()).count();
+        let non_null = if key.nulls_first {
+            (nulls, positions.len())
+        } else {
+            (0, positions.len() - nulls)
+        };
+        Ok(Self {
+            positions,
+            non_null,
+        })
+    }
+
+    /// Returns the frame bound `distance` past row `i`'s value: the first row at or past it
+    /// for a start, one past the last row at or before it for an end. Returns None if row
+    /// `i`'s value is NULL, as then only its ties are in range.
+    ///
+    /// A non-NULL row's frame never reaches the NULLs.
+    fn bound(&self, i: usize, distance: i64, is_start: bool) -> Option<usize> {
+        let target = self.positions[i]?.saturating_add(distance);
+        let (start, end) = self.non_null;
+        let rows = &self.positions[start..end];
+        let found = if is_start {
+            rows.partition_point(|p| p.is_some_and(|p| p < target))
+        } else {
+            rows.partition_point(|p| p.is_some_and(|p| p <= target))
+        };
+        Some(start + found)
+    }
+}
+
+/// Folds the rows of each frame into the aggregate.
+fn aggregate_frames(
+    aggregate: &AggregateExpr,
+    partition: &[Row],
+    frames: &Frames,
+) -> Result<Vec<Value>> {
+    let args = partition
+        .iter()
+        .map(|tuple| {
+            aggregate
+                .arg
+                .as_ref()
+                .map(|arg| arg.evaluate(tuple))
+                .transpose()
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    let mut values = Vec::with_capacity(partition.len());
+    if frames.frame.start == FrameBound::UnboundedPreceding {
+        // Every frame starts at the first row and ends no earlier than the previous one
+        let mut accumulator = Accumulator::new(aggregate.function);
+        let mut added = 0;
+        for &(_, end) in &frames.bounds {
+            while added < end {
+                accumulator.update(args[added].clone())?;
+                added += 1;
+            }
+            values.push(accumulator.finish()?);
+        }
+    } else {
+        for &(start, end) in &frames.bounds {
+            let mut accumulator = Accumulator::new(aggregate.function);
+            for arg in &args[start..end] {
+                accumulator.update(arg.clone())?;
+            }
+            values.push(accumulator.finish()?);
+        }
+    }
+    Ok(values)
+}
+
+impl Executor for WindowExecutor {
+    fn schema(&self) -> &Schema {
+        &self.output_schema
+    }
+
+    fn init(&mut self) -> Result<()> {
+        self.results.clear();
+        self.child.init()?;
+        self.pending = self.child.next()?;
+        Ok(())
+    }
+
+    fn describe(&self) -> String {
+        format!(
+            "Window({} AS {})",
+            self.window.display_with(self.child.schema()),
+            self.name
+        )
+    }
+
+    fn children(&self) -> Vec<&dyn Executor> {
+        vec![self.child.as_ref()]
+    }
+
+    fn sort_order(&self) -> Vec<usize> {
+        self.child.sort_order()
+    }
+
+    fn next(&mut self) -> Result<Option<Row>> {
+        if let Some(tuple) = self.results.pop() {
+            return Ok(Some(tuple));
+        }
+        let Some(first) = self.pending.take() else {
+            return Ok(None);
+        };
+
+        // NULL keys are equal here, so all rows with a NULL key form one partition
+        let key = self.partition_key(&first)?;
+        let mut partition = vec![first];
+        while let Some(tuple) = self.child.next()? {
+            if self.partition_key(&tuple)? != key {
+                self.pending = Some(tuple);
+                break;
+            }
+            partition.push(tuple);
+        }
+
+        let values = self.evaluate(&partition)?;
+        self.results = partition
+            .into_iter()
+            .zip(values)
+            .map(|(mut tuple, value)| {
+                tuple.values.push(value);
+                tuple
+            })
+            .rev()
+            .collect();
+        Ok(self.results.pop())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::executor::tests::collect_results;
+    use crate::executor::SortExecutor;
+    use crate::expression::{
+        col, count_star, dense_rank, lag, lead, partition_by, rank, row_number, sum,
+    };
+    use crate::{int_column, Database};
+
+    #[test]
+    fn test_window_functions() {
+        let path = "test_window_dir";
+        let _ = std::fs::remove_dir_all(path);
+        let db = Database::open(path).unwrap();
+
+        // Temporary tables can hold NULLs, which stored tables cannot
+        let schema = Schema {
+            columns: vec![int_column("g"), int_column("x")],
+        };
+        let mut input = db.catalog().create_temp_table(schema.clone()).unwrap();
+        let value = |v: Option<i32>| v.map_or(Value::Null, Value::Integer);
+        for (g, x) in [
+            (Some(2), Some(5)),
+            (None, Some(3)),
+            (Some(1), Some(2)),
+            (Some(1), None),
+            (Some(1), Some(4)),
+            (None, Some(3)),
+            (Some(1), Some(1)),
+            (Some(1), Some(2)),
+        ] {
+            input.insert(&Row::new(vec![value(g), value(x)])).unwrap();
+        }
+
+        // Sorts the input as the planner does, then returns the function's values
+        let run = |window: WindowExpr| -> Vec<Option<i32>> {
+            let window = window.bind(&schema).unwrap();
+            let keys = window
+                .window
+                .partition_by
+                .iter()
+                .cloned()
+                .map(SortKey::asc)
+                .chain(window.window.order_by.iter().cloned())
+                .collect();
+            let sort = SortExecutor::new(Box::new(input.scan()), keys, db.catalog().clone());
+            let mut executor = WindowExecutor::new(Box::new(sort), window, "w");
+            collect_results(&mut executor)
+                .unwrap()
+                .into_iter()
+                .map(|row| match row.values[2] {
+                    Value::Integer(i) => Some(i),
+                    _ => None,
+                })
+                .collect()
+        };
+        let by_x = || partition_by(&[col("g")]).order_by(&[col("x").asc()]);
+
+        // Partitions g = 1: x = 1, 2, 2, 4, NULL; g = 2: x = 5; g = NULL: x = 3, 3
+        let n = None;
+        let ranks = |values: [i32; 8]| values.map(Some).to_vec();
+        assert_eq!(
+            run(row_number().over(by_x())),
+            ranks([1, 2, 3, 4, 5, 1, 1, 2])
+        );
+        assert_eq!(run(rank().over(by_x())), ranks([1, 2, 2, 4, 5, 1, 1, 1]));
+        assert_eq!(
+            run(dense_rank().over(by_x())),
+            ranks([1, 2, 2, 3, 4, 1, 1, 1])
+        );
+        assert_eq!(
+            run(lag(col("x"), 1).over(by_x())),
+            vec![n, Some(1), Some(2), Some(2), Some(4), n, n, Some(3)]
+        );
+        assert_eq!(
+            run(lead(col("x"), 2).over(by_x())),
+            vec![Some(2), Some(4), n, n, n, n, n, n]
+        );
+
+        // The default frame ends with the last row tying with the current one
+        assert_eq!(
+            run(sum(col("x")).over(by_x())),
+            vec![
+                Some(1),
+                Some(5),
+                Some(5),
+                Some(9),
+                Some(9),
+                Some(5),
+                Some(6),
+                Some(6)
+            ]
+        );
+        // Without ORDER BY, every row of the partition ties
+        assert_eq!(
+            run(sum(col("x")).over(partition_by(&[col("g")]))),
+            vec![
+                Some(9),
+                Some(9),
+                Some(9),
+                Some(9),
+                Some(9),
+                Some(5),
+                Some(6),
+                Some(6)
+            ]
+        );
+
+        let rows = by_x().rows_between(FrameBound::Preceding(1), FrameBound::Following(1));
+        assert_eq!(
+            run(count_star().over(rows)),
+            ranks([2, 3, 3, 3, 2, 1, 2, 2])
+        );
+        let rest = by_x().rows_between(FrameBound::CurrentRow, FrameBound::UnboundedFollowing);
+        assert_eq!(
+            run(sum(col("x")).over(rest)),
+            vec![
+                Some(9),
+                Some(8),
+                Some(6),
+                Some(4),
+                n,
+                Some(5),
+                Some(6),
+                Some(3)
+            ]
+        );
+
+        // RANGE offsets compare values; NULL rows only see each other
+        let range = by_x().range_between(FrameBound::Preceding(1), FrameBound::CurrentRow);
+        assert_eq!(
+            run(sum(col("x")).over(range)),
+            vec![
+                Some(1),
+                Some(5),
+                Some(5),
+                Some(4),
+                n,
+                Some(5),
+                Some(6),
+                Some(6)
+            ]
+        );
+        let ahead = by_x().range_between(FrameBound::Following(1), FrameBound::Following(2));
+        assert_eq!(
+            run(sum(col("x")).over(ahead)),
+            vec![Some(4), Some(4), Some(4), n, n, n, n, n]
+        );
+        // Descending, the preceding values are the larger ones: x = NULL, 4, 2, 2, 1
+        let descending = partition_by(&[col("g")])
+            .order_by(&[col("x").desc()])
+            .range_between(FrameBound::Preceding(1), FrameBound::CurrentRow);
+        assert_eq!(
+            run(sum(col("x")).over(descending))[..5],
+            [n, Some(4), Some(4), Some(4), Some(5)]
+        );
+
+        let bad_frame = by_x().rows_between(FrameBound::UnboundedFollowing, FrameBound::CurrentRow);
+        assert!(row_number().over(bad_frame).bind(&schema).is_err());
+        let two_keys = partition_by(&[])
+            .order_by(&[col("g").asc(), col("x").asc()])
+            .range_between(FrameBound::Preceding(1), FrameBound::CurrentRow);
+        assert!(sum(col("x")).over(two_keys).bind(&schema).is_err());
+
+        drop(input);
+        std::fs::remove_dir_all(path).unwrap();
+    }
+
+    #[test]
+    fn test_window_frame_out_of_order() {
+        let schema = Schema {
+            columns: vec![int_column("x")],
+        };
+        let by_x = || partition_by(&[]).order_by(&[col("x").asc()]);
+        let bind = |frame| sum(col("x")).over(frame).bind(&schema);
+
+        for (start, end) in [
+            (FrameBound::CurrentRow, FrameBound::Preceding(1)),
+            (FrameBound::Following(2), FrameBound::Following(1)),
+            (FrameBound::Preceding(1), FrameBound::Preceding(2)),
+            (FrameBound::Following(1), FrameBound::CurrentRow),
+        ] {
+            assert!(bind(by_x().rows_between(start, end)).is_err());
+            assert!(bind(by_x().range_between(start, end)).is_err());
+        }
+
+        // Frames ending where they start, or starting before the current row, are fine
+        let single = by_x().rows_between(FrameBound::Following(1), FrameBound::Following(1));
+        assert!(bind(single).is_ok());
+        let earlier = by_x().range_between(FrameBound::Preceding(2), FrameBound::Preceding(1));
+        assert!(bind(earlier).is_ok());
+    }
+}
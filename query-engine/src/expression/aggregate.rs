@@ -0,0 +1,141 @@
+//! Aggregate functions for grouped queries, e.g. `sum(col("amount"))`.
+
+use super::{Expression, ParameterValues};
+use crate::Result;
+use std::fmt;
+use storage_engine::tuple::Schema;
+
+/// A function that folds the rows of a group into one value.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AggregateFunction {
+    /// Number of rows, or of non-NULL values when given an argument
+    Count,
+    /// Sum of the non-NULL values
+    Sum,
+    /// Mean of the non-NULL values, truncated to an integer
+    Avg,
+    /// Smallest non-NULL value
+    Min,
+    /// Largest non-NULL value
+    Max,
+}
+
+impl AggregateFunction {
+    /// The function's name as written in SQL, in lower case.
+    pub fn name(&self) -> &'static str {
+        match self {
+            AggregateFunction::Count => "count",
+            AggregateFunction::Sum => "sum",
+            AggregateFunction::Avg => "avg",
+            AggregateFunction::Min => "min",
+            AggregateFunction::Max => "max",
+        }
+    }
+}
+
+/// An aggregate function applied to an expression, optionally named with
+/// [`alias`](Self::alias).
+#[derive(Debug, Clone)]
+pub struct AggregateExpr {
+    pub function: AggregateFunction,
+    /// The aggregated expression, or None for `COUNT(*)`
+    pub arg: Option<Expression>,
+    /// Name of the output column; defaults to the expression as SQL, e.g. `sum(amount)`
+    pub alias: Option<String>,
+}
+
+impl AggregateExpr {
+    fn new(function: AggregateFunction, arg: Expression) -> Self {
+        Self {
+            function,
+            arg: Some(arg),
+            alias: None,
+        }
+    }
+
+    /// Names the output column.
+    pub fn alias(mut self, name: &str) -> Self {
+        self.alias = Some(name.to_string());
+        self
+    }
+
+    /// Binds the argument's column names to column indices.
+    pub fn bind(&self, schema: &Schema) -> Result<AggregateExpr> {
+        Ok(AggregateExpr {
+            arg: self.arg.as_ref().map(|arg| arg.bind(schema)).transpose()?,
+            ..self.clone()
+        })
+    }
+
+    /// Connects the argument's parameter placeholders to the argument list.
+    pub fn bind_parameters(&self, values: &ParameterValues) -> AggregateExpr {
+        AggregateExpr {
+            arg: self.arg.as_ref().map(|arg| arg.bind_parameters(values)),
+            ..self.clone()
+        }
+    }
+
+    /// Returns the highest parameter number referenced in the argument, or 0 if none.
+    pub fn parameter_count(&self) -> usize {
+        self.arg.as_ref().map_or(0, Expression::parameter_count)
+    }
+
+    /// Returns the name of the output column: the alias, or the expression as SQL.
+    pub fn output_name(&self) -> String {
+        match &self.alias {
+            Some(alias) => alias.clone(),
+            None => self.to_string(),
+        }
+    }
+
+    /// Displays the aggregate as SQL, e.g. `count(*)`, naming bound columns after `schema`.
+    pub fn display_with(&self, schema: &Schema) -> String {
+        match &self.arg {
+            Some(arg) => format!("{}({})", self.function.name(), arg.display_with(schema)),
+            None => format!("{}(*)", self.function.name()),
+        }
+    }
+}
+
+impl fmt::Display for AggregateExpr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.arg {
+            Some(arg) => write!(f, "{}({})", self.function.name(), arg),
+            None => write!(f, "{}(*)", self.function.name()),
+        }
+    }
+}
+
+/// Counts the rows where `expr` is not NULL, like SQL's `COUNT(expr)`.
+pub fn count(expr: Expression) -> AggregateExpr {
+    AggregateExpr::new(AggregateFunction::Count, expr)
+}
+
+/// Counts every row, NULLs included, like SQL's `COUNT(*)`.
+pub fn count_star() -> AggregateExpr {
+    AggregateExpr {
+        function: AggregateFunction::Count,
+        arg: None,
+        alias: None,
+    }
+}
+
+/// Sums the non-NULL values of `expr`; NULL if there are none.
+pub fn sum(expr: Expression) -> AggregateExpr {
+    AggregateExpr::new(AggregateFunction::Sum, expr)
+}
+
+/// Averages the non-NULL values of `expr`, rounding toward zero; NULL if there are none.
+pub fn avg(expr: Expression) -> AggregateExpr {
+    AggregateExpr::new(AggregateFunction::Avg, expr)
+}
+
+/// Returns the smallest non-NULL value of `expr`; NULL if there are none.
+pub fn min(expr: Expression) -> AggregateExpr {
+    AggregateExpr::new(AggregateFunction::Min, expr)
+}
+
+/// Returns the largest non-NULL value of `expr`; NULL if there are none.
+pub fn max(expr: Expression) -> AggregateExpr {
+    AggregateExpr::new(AggregateFunction::Max, expr)
+}
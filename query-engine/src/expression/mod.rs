@@ -2,10 +2,24 @@
 //!
 //! Provides a fluent API for building expressions like `col("id").eq(42)`.
 
-use crate::types::Value;
+use crate::diagnostics::closest_match;
+use crate::types::{Row, Value};
 use crate::{QueryError, Result};
-use storage_engine::tuple::{Schema, Tuple};
+use storage_engine::tuple::Schema;
 use std::cmp::Ordering;
+use std::fmt;
+use std::sync::{Arc, RwLock};
+
+mod aggregate;
+mod subquery;
+mod window;
+
+pub use aggregate::{avg, count, count_star, max, min, sum, AggregateExpr, AggregateFunction};
+pub use subquery::{scalar_subquery, Subquery, SubqueryRows};
+pub use window::{
+    dense_rank, lag, lead, partition_by, rank, row_number, FrameBound, FrameUnits, WindowExpr,
+    WindowFrame, WindowFunction, WindowSpec,
+};
 
 /// An expression that can be evaluated against a tuple.
 #[derive(Debug, Clone)]
@@ -16,6 +30,10 @@ pub enum Expression {
     BoundColumn(usize),
     /// Literal value
     Literal(Value),
+    /// Positional parameter supplied at execution time (`$1`, `$2`, ... in SQL), numbered from 1
+    Parameter(usize),
+    /// Parameter reading its value from a shared argument list (after binding)
+    BoundParameter(usize, ParameterValues),
     /// Binary operation (e.g., a + b, a > b)
     BinaryOp {
         left: Box<Expression>,
@@ -27,6 +45,40 @@ pub enum Expression {
         op: UnaryOperator,
         expr: Box<Expression>,
     },
+    /// Value of a DataFrame with one column and at most one row (scalar subquery)
+    ScalarSubquery(Subquery),
+    /// Scalar subquery reading its value from rows fetched before execution (after planning)
+    BoundScalarSubquery(SubqueryRows),
+    /// Whether `expr` is among the values of a DataFrame with one column (`expr IN (...)`)
+    InSubquery {
+        expr: Box<Expression>,
+        subquery: Subquery,
+    },
+    /// IN subquery reading its values from rows fetched before execution (after planning)
+    BoundInSubquery {
+        expr: Box<Expression>,
+        rows: SubqueryRows,
+    },
+}
+
+/// The argument list that bound parameters read from when evaluated.
+///
+/// Cloning shares the same list, so a plan built once can be re-run with new arguments by
+/// calling [`set`](Self::set) before each execution.
+#[derive(Debug, Clone, Default)]
+pub struct ParameterValues(Arc<RwLock<Vec<Value>>>);
+
+impl ParameterValues {
+    /// Replaces the current arguments.
+    pub fn set(&self, values: Vec<Value>) {
+        *self.0.write().unwrap() = values;
+    }
+
+    /// Returns the value of parameter `n` (numbered from 1), if one has been supplied.
+    fn get(&self, n: usize) -> Option<Value> {
+        let values = self.0.read().unwrap();
+        n.checked_sub(1).and_then(|i| values.get(i)).cloned()
+    }
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -55,20 +107,134 @@ pub enum UnaryOperator {
     IsNotNull,
 }
 
+/// An expression to sort by, with its direction and where NULLs go.
+#[derive(Debug, Clone)]
+pub struct SortKey {
+    pub expr: Expression,
+    pub descending: bool,
+    /// Whether NULLs come before all other values
+    pub nulls_first: bool,
+}
+
+impl SortKey {
+    /// Sorts by `expr` in ascending order with NULLs last.
+    pub fn asc(expr: Expression) -> Self {
+        Self {
+            expr,
+            descending: false,
+            nulls_first: false,
+        }
+    }
+
+    /// Sorts by `expr` in descending order with NULLs first, as NULL sorts above every
+    /// other value.
+    pub fn desc(expr: Expression) -> Self {
+        Self {
+            expr,
+            descending: true,
+            nulls_first: true,
+        }
+    }
+
+    /// Puts NULLs before all other values.
+    pub fn nulls_first(mut self) -> Self {
+        self.nulls_first = true;
+        self
+    }
+
+    /// Puts NULLs after all other values.
+    pub fn nulls_last(mut self) -> Self {
+        self.nulls_first = false;
+        self
+    }
+
+    /// Binds the key's expression to column indices.
+    pub fn bind(&self, schema: &Schema) -> Result<SortKey> {
+        Ok(SortKey {
+            expr: self.expr.bind(schema)?,
+            ..self.clone()
+        })
+    }
+
+    /// Compares two values of this key in output order.
+    pub fn compare(&self, a: &Value, b: &Value) -> Ordering {
+        match (a.is_null(), b.is_null()) {
+            (true, true) => Ordering::Equal,
+            (true, false) if self.nulls_first => Ordering::Less,
+            (true, false) => Ordering::Greater,
+            (false, true) if self.nulls_first => Ordering::Greater,
+            (false, true) => Ordering::Less,
+            (false, false) if self.descending => b.sort_cmp(a),
+            (false, false) => a.sort_cmp(b),
+        }
+    }
+
+    /// Returns the bound column this key sorts by, if it is a plain ascending column with
+    /// NULLs last, i.e. in [`Value::sort_cmp`] order.
+    pub(crate) fn ascending_column(&self) -> Option<usize> {
+        match self.expr {
+            Expression::BoundColumn(index) if !self.descending && !self.nulls_first => Some(index),
+            _ => None,
+        }
+    }
+
+    /// Displays the key as SQL, e.g. `age DESC NULLS LAST`, naming columns after `schema`.
+    pub fn display_with(&self, schema: &Schema) -> String {
+        let mut out = self.expr.display_with(schema).to_string();
+        if self.descending {
+            out.push_str(" DESC");
+        }
+        // Spell out NULL placement only where it differs from the default for the direction
+        if self.nulls_first != self.descending {
+            out.push_str(if self.nulls_first { " NULLS FIRST" } else { " NULLS LAST" });
+        }
+        out
+    }
+}
+
+/// Finds the column `name` refers to in `schema`.
+///
+/// An exact match wins. Otherwise an unqualified name such as `id` matches a qualified column
+/// such as `users.id`, as produced by joins, provided only one column has that name.
+pub(crate) fn column_index(schema: &Schema, name: &str) -> Result<usize> {
+    if let Some(index) = schema.columns.iter().position(|col| col.name == name) {
+        return Ok(index);
+    }
+
+    let qualified: Vec<usize> = schema
+        .columns
+        .iter()
+        .enumerate()
+        .filter(|(_, col)| matches!(col.name.rsplit_once('.'), Some((_, column)) if column == name))
+        .map(|(index, _)| index)
+        .collect();
+    match qualified.as_slice() {
+        [index] => Ok(*index),
+        [] => Err(QueryError::ColumnNotFound {
+            name: name.to_string(),
+            suggestion: closest_match(name, schema.columns.iter().map(|col| col.name.as_str())),
+            location: None,
+        }),
+        candidates => Err(QueryError::ExecutionError(format!(
+            "Column reference '{}' is ambiguous; use one of {}",
+            name,
+            candidates
+                .iter()
+                .map(|index| format!("'{}'", schema.columns[*index].name))
+                .collect::<Vec<_>>()
+                .join(", ")
+        ))),
+    }
+}
+
 impl Expression {
     /// Binds column names to column indices based on a schema.
     pub fn bind(&self, schema: &Schema) -> Result<Expression> {
         match self {
-            Expression::Column(name) => {
-                let index = schema
-                    .columns
-                    .iter()
-                    .position(|col| &col.name == name)
-                    .ok_or_else(|| QueryError::ColumnNotFound(name.clone()))?;
-                Ok(Expression::BoundColumn(index))
-            }
+            Expression::Column(name) => Ok(Expression::BoundColumn(column_index(schema, name)?)),
             Expression::BoundColumn(_) => Ok(self.clone()),
             Expression::Literal(_) => Ok(self.clone()),
+            Expression::Parameter(_) | Expression::BoundParameter(..) => Ok(self.clone()),
             Expression::BinaryOp { left, op, right } => Ok(Expression::BinaryOp {
                 left: Box::new(left.bind(schema)?),
                 op: *op,
@@ -78,35 +244,269 @@ impl Expression {
                 op: *op,
                 expr: Box::new(expr.bind(schema)?),
             }),
+            Expression::ScalarSubquery(_) | Expression::InSubquery { .. } => {
+                Err(QueryError::ExecutionError(format!(
+                    "Subqueries are only supported in filter(), select_exprs() and order_by(), \
+                     got {}",
+                    self
+                )))
+            }
+            Expression::BoundScalarSubquery(_) => Ok(self.clone()),
+            Expression::BoundInSubquery { expr, rows } => Ok(Expression::BoundInSubquery {
+                expr: Box::new(expr.bind(schema)?),
+                rows: rows.clone(),
+            }),
+        }
+    }
+
+    /// Connects parameter placeholders to the argument list they are read from.
+    pub fn bind_parameters(&self, values: &ParameterValues) -> Expression {
+        match self {
+            Expression::Parameter(n) | Expression::BoundParameter(n, _) => {
+                Expression::BoundParameter(*n, values.clone())
+            }
+            Expression::BinaryOp { left, op, right } => Expression::BinaryOp {
+                left: Box::new(left.bind_parameters(values)),
+                op: *op,
+                right: Box::new(right.bind_parameters(values)),
+            },
+            Expression::UnaryOp { op, expr } => Expression::UnaryOp {
+                op: *op,
+                expr: Box::new(expr.bind_parameters(values)),
+            },
+            // A subquery's own parameters are connected when it is planned
+            Expression::InSubquery { expr, subquery } => Expression::InSubquery {
+                expr: Box::new(expr.bind_parameters(values)),
+                subquery: subquery.clone(),
+            },
+            Expression::BoundInSubquery { expr, rows } => Expression::BoundInSubquery {
+                expr: Box::new(expr.bind_parameters(values)),
+                rows: rows.clone(),
+            },
+            Expression::Column(_)
+            | Expression::BoundColumn(_)
+            | Expression::Literal(_)
+            | Expression::ScalarSubquery(_)
+            | Expression::BoundScalarSubquery(_) => self.clone(),
+        }
+    }
+
+    /// Returns the highest parameter number referenced in this expression, or 0 if none.
+    pub fn parameter_count(&self) -> usize {
+        match self {
+            Expression::Parameter(n) | Expression::BoundParameter(n, _) => *n,
+            Expression::BinaryOp { left, right, .. } => {
+                left.parameter_count().max(right.parameter_count())
+            }
+            Expression::UnaryOp { expr, .. } => expr.parameter_count(),
+            Expression::ScalarSubquery(subquery) => subquery.dataframe().parameter_count(),
+            Expression::InSubquery { expr, subquery } => expr
+                .parameter_count()
+                .max(subquery.dataframe().parameter_count()),
+            Expression::BoundInSubquery { expr, .. } => expr.parameter_count(),
+            Expression::Column(_)
+            | Expression::BoundColumn(_)
+            | Expression::Literal(_)
+            | Expression::BoundScalarSubquery(_) => 0,
+        }
+    }
+
+    /// Splits an AND chain into its operands, e.g. `a AND (b AND c)` into `[a, b, c]`.
+    pub(crate) fn conjuncts(&self) -> Vec<&Expression> {
+        match self {
+            Expression::BinaryOp {
+                left,
+                op: BinaryOperator::And,
+                right,
+            } => {
+                let mut conjuncts = left.conjuncts();
+                conjuncts.extend(right.conjuncts());
+                conjuncts
+            }
+            _ => vec![self],
+        }
+    }
+
+    /// Returns the indices of the bound columns this expression reads.
+    pub(crate) fn referenced_columns(&self) -> Vec<usize> {
+        match self {
+            Expression::BoundColumn(index) => vec![*index],
+            Expression::BinaryOp { left, right, .. } => {
+                let mut columns = left.referenced_columns();
+                columns.extend(right.referenced_columns());
+                columns
+            }
+            Expression::UnaryOp { expr, .. }
+            | Expression::InSubquery { expr, .. }
+            | Expression::BoundInSubquery { expr, .. } => expr.referenced_columns(),
+            Expression::Column(_)
+            | Expression::Literal(_)
+            | Expression::Parameter(_)
+            | Expression::BoundParameter(..)
+            | Expression::ScalarSubquery(_)
+            | Expression::BoundScalarSubquery(_) => Vec::new(),
+        }
+    }
+
+    /// Returns the names of the unbound columns this expression reads, leaving out those
+    /// read inside subqueries.
+    pub(crate) fn column_names(&self) -> Vec<&str> {
+        match self {
+            Expression::Column(name) => vec![name.as_str()],
+            Expression::BinaryOp { left, right, .. } => {
+                let mut names = left.column_names();
+                names.extend(right.column_names());
+                names
+            }
+            Expression::UnaryOp { expr, .. }
+            | Expression::InSubquery { expr, .. }
+            | Expression::BoundInSubquery { expr, .. } => expr.column_names(),
+            Expression::BoundColumn(_)
+            | Expression::Literal(_)
+            | Expression::Parameter(_)
+            | Expression::BoundParameter(..)
+            | Expression::ScalarSubquery(_)
+            | Expression::BoundScalarSubquery(_) => Vec::new(),
+        }
+    }
+
+    /// Returns the subqueries in this expression that have not been planned yet.
+    pub(crate) fn subqueries(&self) -> Vec<&Subquery> {
+        match self {
+            Expression::ScalarSubquery(subquery) => vec![subquery],
+            Expression::InSubquery { expr, subquery } => {
+                let mut subqueries = expr.subqueries();
+                subqueries.push(subquery);
+                subqueries
+            }
+            Expression::BinaryOp { left, right, .. } => {
+                let mut subqueries = left.subqueries();
+                subqueries.extend(right.subqueries());
+                subqueries
+            }
+            Expression::UnaryOp { expr, .. } | Expression::BoundInSubquery { expr, .. } => {
+                expr.subqueries()
+            }
+            Expression::Column(_)
+            | Expression::BoundColumn(_)
+            | Expression::Literal(_)
+            | Expression::Parameter(_)
+            | Expression::BoundParameter(..)
+            | Expression::BoundScalarSubquery(_) => Vec::new(),
         }
     }
 
-    /// Evaluates the expression against a tuple.
-    pub fn evaluate(&self, tuple: &Tuple) -> Result<Value> {
+    /// Renumbers bound columns for a tuple that starts `offset` columns later, e.g. to
+    /// evaluate a join condition's right-hand columns against the right input alone.
+    pub(crate) fn shift_columns(&self, offset: usize) -> Expression {
+        self.map_columns(&|index| index - offset)
+    }
+
+    /// Renumbers every bound column `index` as `f(index)`.
+    pub(crate) fn map_columns(&self, f: &dyn Fn(usize) -> usize) -> Expression {
+        match self {
+            Expression::BoundColumn(index) => Expression::BoundColumn(f(*index)),
+            Expression::BinaryOp { left, op, right } => Expression::BinaryOp {
+                left: Box::new(left.map_columns(f)),
+                op: *op,
+                right: Box::new(right.map_columns(f)),
+            },
+            Expression::UnaryOp { op, expr } => Expression::UnaryOp {
+                op: *op,
+                expr: Box::new(expr.map_columns(f)),
+            },
+            Expression::InSubquery { expr, subquery } => Expression::InSubquery {
+                expr: Box::new(expr.map_columns(f)),
+                subquery: subquery.clone(),
+            },
+            Expression::BoundInSubquery { expr, rows } => Expression::BoundInSubquery {
+                expr: Box::new(expr.map_columns(f)),
+                rows: rows.clone(),
+            },
+            Expression::Column(_)
+            | Expression::Literal(_)
+            | Expression::Parameter(_)
+            | Expression::BoundParameter(..)
+            | Expression::ScalarSubquery(_)
+            | Expression::BoundScalarSubquery(_) => self.clone(),
+        }
+    }
+
+    /// Rebuilds the expression with every unplanned subquery replaced by `f`'s result. The
+    /// value an IN subquery compares has its own subqueries replaced first.
+    pub(crate) fn replace_subqueries(
+        &self,
+        f: &mut dyn FnMut(&Expression) -> Result<Expression>,
+    ) -> Result<Expression> {
+        Ok(match self {
+            Expression::ScalarSubquery(_) => f(self)?,
+            Expression::InSubquery { expr, subquery } => {
+                let expr = expr.replace_subqueries(f)?;
+                f(&Expression::InSubquery {
+                    expr: Box::new(expr),
+                    subquery: subquery.clone(),
+                })?
+            }
+            Expression::BinaryOp { left, op, right } => Expression::BinaryOp {
+                left: Box::new(left.replace_subqueries(f)?),
+                op: *op,
+                right: Box::new(right.replace_subqueries(f)?),
+            },
+            Expression::UnaryOp { op, expr } => Expression::UnaryOp {
+                op: *op,
+                expr: Box::new(expr.replace_subqueries(f)?),
+            },
+            Expression::BoundInSubquery { expr, rows } => Expression::BoundInSubquery {
+                expr: Box::new(expr.replace_subqueries(f)?),
+                rows: rows.clone(),
+            },
+            Expression::Column(_)
+            | Expression::BoundColumn(_)
+            | Expression::Literal(_)
+            | Expression::Parameter(_)
+            | Expression::BoundParameter(..)
+            | Expression::BoundScalarSubquery(_) => self.clone(),
+        })
+    }
+
+    /// Evaluates the expression against a row.
+    pub fn evaluate(&self, row: &Row) -> Result<Value> {
         match self {
             Expression::Column(name) => Err(QueryError::ExecutionError(format!(
                 "Unbound column: {}. Call bind() first.",
                 name
             ))),
             Expression::BoundColumn(index) => {
-                if *index >= tuple.values.len() {
-                    return Err(QueryError::ExecutionError(format!(
-                        "Column index {} out of bounds",
-                        index
-                    )));
-                }
-                Ok(Value::from_storage(tuple.values[*index].clone()))
+                row.values.get(*index).cloned().ok_or_else(|| {
+                    QueryError::ExecutionError(format!("Column index {} out of bounds", index))
+                })
             }
             Expression::Literal(val) => Ok(val.clone()),
+            Expression::Parameter(n) => Err(QueryError::ExecutionError(format!(
+                "No value supplied for parameter ${}",
+                n
+            ))),
+            Expression::BoundParameter(n, values) => values.get(*n).ok_or_else(|| {
+                QueryError::ExecutionError(format!("No value supplied for parameter ${}", n))
+            }),
             Expression::BinaryOp { left, op, right } => {
-                let left_val = left.evaluate(tuple)?;
-                let right_val = right.evaluate(tuple)?;
+                let left_val = left.evaluate(row)?;
+                let right_val = right.evaluate(row)?;
                 self.evaluate_binary_op(&left_val, *op, &right_val)
             }
             Expression::UnaryOp { op, expr } => {
-                let val = expr.evaluate(tuple)?;
+                let val = expr.evaluate(row)?;
                 self.evaluate_unary_op(*op, &val)
             }
+            Expression::ScalarSubquery(_) | Expression::InSubquery { .. } => {
+                Err(QueryError::ExecutionError(format!(
+                    "Unplanned subquery: {}. Use it in a DataFrame's filter(), select_exprs() or \
+                     order_by().",
+                    self
+                )))
+            }
+            Expression::BoundScalarSubquery(rows) => Ok(rows.value()),
+            Expression::BoundInSubquery { expr, rows } => Ok(rows.contains(&expr.evaluate(row)?)),
         }
     }
 
@@ -286,10 +686,139 @@ impl Expression {
             expr: Box::new(self),
         }
     }
+
+    /// Sorts by this expression in ascending order with NULLs last: `self ASC`
+    pub fn asc(self) -> SortKey {
+        SortKey::asc(self)
+    }
+
+    /// Sorts by this expression in descending order with NULLs first: `self DESC`
+    pub fn desc(self) -> SortKey {
+        SortKey::desc(self)
+    }
 }
 
 // ===== Helper Functions for Building Expressions =====
 
+impl BinaryOperator {
+    /// Returns the SQL spelling of the operator.
+    pub fn symbol(&self) -> &'static str {
+        match self {
+            BinaryOperator::Add => "+",
+            BinaryOperator::Subtract => "-",
+            BinaryOperator::Multiply => "*",
+            BinaryOperator::Divide => "/",
+            BinaryOperator::Eq => "=",
+            BinaryOperator::NotEq => "<>",
+            BinaryOperator::Lt => "<",
+            BinaryOperator::LtEq => "<=",
+            BinaryOperator::Gt => ">",
+            BinaryOperator::GtEq => ">=",
+            BinaryOperator::And => "AND",
+            BinaryOperator::Or => "OR",
+        }
+    }
+
+    /// Binding strength, used to decide where parentheses are needed when printing.
+    fn precedence(&self) -> u8 {
+        match self {
+            BinaryOperator::Or => 1,
+            BinaryOperator::And => 2,
+            BinaryOperator::Eq
+            | BinaryOperator::NotEq
+            | BinaryOperator::Lt
+            | BinaryOperator::LtEq
+            | BinaryOperator::Gt
+            | BinaryOperator::GtEq => 3,
+            BinaryOperator::Add | BinaryOperator::Subtract => 4,
+            BinaryOperator::Multiply | BinaryOperator::Divide => 5,
+        }
+    }
+}
+
+/// Displays an expression as SQL, printing bound columns by their name in a schema.
+///
+/// Created by [`Expression::display_with`].
+pub struct DisplayWith<'a> {
+    expr: &'a Expression,
+    schema: Option<&'a Schema>,
+}
+
+impl Expression {
+    /// Returns a displayable form of the expression that names bound columns after
+    /// `schema`, the schema it was bound against.
+    pub fn display_with<'a>(&'a self, schema: &'a Schema) -> DisplayWith<'a> {
+        DisplayWith {
+            expr: self,
+            schema: Some(schema),
+        }
+    }
+
+    fn fmt_sql(&self, f: &mut fmt::Formatter<'_>, schema: Option<&Schema>) -> fmt::Result {
+        // Operands bind tighter than the operator only if they are not themselves looser ops
+        let operand = |expr: &Expression, f: &mut fmt::Formatter<'_>, min: u8| match expr {
+            Expression::BinaryOp { op, .. } if op.precedence() < min => {
+                write!(f, "(")?;
+                expr.fmt_sql(f, schema)?;
+                write!(f, ")")
+            }
+            _ => expr.fmt_sql(f, schema),
+        };
+
+        match self {
+            Expression::Column(name) => write!(f, "{}", name),
+            Expression::BoundColumn(index) => match schema.and_then(|s| s.columns.get(*index)) {
+                Some(column) => write!(f, "{}", column.name),
+                None => write!(f, "#{}", index),
+            },
+            Expression::Literal(Value::Integer(i)) => write!(f, "{}", i),
+            Expression::Literal(Value::Varchar(s)) => write!(f, "'{}'", s.replace('\'', "''")),
+            Expression::Literal(Value::Null) => write!(f, "NULL"),
+            Expression::Parameter(n) | Expression::BoundParameter(n, _) => write!(f, "${}", n),
+            Expression::BinaryOp { left, op, right } => {
+                operand(left, f, op.precedence())?;
+                write!(f, " {} ", op.symbol())?;
+                // Operators are left-associative, so an equal-precedence right operand needs
+                // parentheses: a - (b - c)
+                operand(right, f, op.precedence() + 1)
+            }
+            Expression::UnaryOp { op, expr } => match op {
+                UnaryOperator::Not => {
+                    write!(f, "NOT ")?;
+                    operand(expr, f, u8::MAX)
+                }
+                UnaryOperator::IsNull => {
+                    operand(expr, f, u8::MAX)?;
+                    write!(f, " IS NULL")
+                }
+                UnaryOperator::IsNotNull => {
+                    operand(expr, f, u8::MAX)?;
+                    write!(f, " IS NOT NULL")
+                }
+            },
+            Expression::ScalarSubquery(_) | Expression::BoundScalarSubquery(_) => {
+                write!(f, "(subquery)")
+            }
+            Expression::InSubquery { expr, .. } | Expression::BoundInSubquery { expr, .. } => {
+                operand(expr, f, u8::MAX)?;
+                write!(f, " IN (subquery)")
+            }
+        }
+    }
+}
+
+impl fmt::Display for Expression {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.fmt_sql(f, None)
+    }
+}
+
+impl fmt::Display for DisplayWith<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.expr.fmt_sql(f, self.schema)
+    }
+}
+
 /// Creates a column reference expression.
 pub fn col(name: &str) -> Expression {
     Expression::Column(name.to_string())
@@ -305,6 +834,11 @@ pub fn lit_str(value: &str) -> Expression {
     Expression::Literal(Value::Varchar(value.to_string()))
 }
 
+/// Creates a positional parameter placeholder, numbered from 1.
+pub fn param(n: usize) -> Expression {
+    Expression::Parameter(n)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -333,33 +867,50 @@ mod tests {
 
     #[test]
     fn test_expression_evaluation() {
-        let tuple = Tuple {
-            values: vec![
-                storage_engine::tuple::Value::Integer(42),
-                storage_engine::tuple::Value::Varchar("Alice".to_string()),
-            ],
-        };
+        let row = Row::new(vec![Value::Integer(42), Value::Varchar("Alice".to_string())]);
 
         // Test: column 0 == 42
         let expr = Expression::BoundColumn(0).eq(lit(42));
-        let result = expr.evaluate(&tuple).unwrap();
+        let result = expr.evaluate(&row).unwrap();
         assert_eq!(result, Value::Integer(1)); // TRUE
 
         // Test: column 0 > 50
         let expr = Expression::BoundColumn(0).gt(lit(50));
-        let result = expr.evaluate(&tuple).unwrap();
+        let result = expr.evaluate(&row).unwrap();
         assert_eq!(result, Value::Integer(0)); // FALSE
     }
 
     #[test]
     fn test_arithmetic_expressions() {
-        let tuple = Tuple {
-            values: vec![storage_engine::tuple::Value::Integer(10)],
-        };
+        let row = Row::new(vec![Value::Integer(10)]);
 
         // Test: column 0 + 5
         let expr = Expression::BoundColumn(0).add(lit(5));
-        let result = expr.evaluate(&tuple).unwrap();
+        let result = expr.evaluate(&row).unwrap();
         assert_eq!(result, Value::Integer(15));
     }
+
+    #[test]
+    fn test_expression_display() {
+        let schema = Schema {
+            columns: vec![crate::int_column("age"), crate::varchar_column("city", 20)],
+        };
+
+        let expr = col("age")
+            .gt(lit(25))
+            .and(col("age").lt(lit(50)))
+            .or(col("city").eq(lit_str("O'Hare")));
+        assert_eq!(
+            expr.to_string(),
+            "age > 25 AND age < 50 OR city = 'O''Hare'"
+        );
+
+        let expr = col("age").gt(lit(1).or(lit(0))).and(col("city").is_null());
+        let bound = expr.bind(&schema).unwrap();
+        assert_eq!(bound.to_string(), "#0 > (1 OR 0) AND #1 IS NULL");
+        assert_eq!(
+            bound.display_with(&schema).to_string(),
+            "age > (1 OR 0) AND city IS NULL"
+        );
+    }
 }
@@ -0,0 +1,208 @@
+//! Subqueries: DataFrames nested in an expression, e.g. `col("id").in_subquery(orders)` or
+//! `col("amount").gt(scalar_subquery(average))`.
+//!
+//! The DataFrame planner replaces every subquery before the expression is evaluated. One
+//! that does not refer to the outer query's columns is run once before the query, and its
+//! rows are cached in a [`SubqueryRows`] the expression reads. A correlated one is turned
+//! into a join with the outer query.
+
+use super::Expression;
+use crate::dataframe::DataFrame;
+use crate::executor::Executor;
+use crate::types::Value;
+use crate::{QueryError, Result};
+use std::collections::HashSet;
+use std::fmt;
+use std::sync::{Arc, RwLock};
+use storage_engine::tuple::Column;
+
+/// A DataFrame nested in an expression.
+///
+/// Cloning shares the same DataFrame.
+#[derive(Clone)]
+pub struct Subquery(Arc<DataFrame>);
+
+impl Subquery {
+    /// Returns the nested DataFrame.
+    pub(crate) fn dataframe(&self) -> &DataFrame {
+        &self.0
+    }
+}
+
+impl fmt::Debug for Subquery {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("Subquery")
+    }
+}
+
+/// The rows of an uncorrelated subquery, fetched before each run of the query using it.
+///
+/// Cloning shares the same rows, so the expressions reading them see what the executor
+/// fetching them stored.
+#[derive(Clone)]
+pub struct SubqueryRows {
+    /// The subquery's only column
+    column: Column,
+    fetched: Arc<RwLock<Fetched>>,
+}
+
+/// What a subquery returned, in the form its expression reads.
+enum Fetched {
+    /// A scalar subquery's value, or NULL if it returned no row
+    Scalar(Value),
+    /// An IN subquery's distinct non-NULL values, and whether it also returned a NULL
+    Set {
+        values: HashSet<Value>,
+        has_null: bool,
+    },
+}
+
+impl SubqueryRows {
+    /// Creates the rows of a scalar subquery whose only column is `column`.
+    pub(crate) fn scalar(column: Column) -> Self {
+        Self {
+            column,
+            fetched: Arc::new(RwLock::new(Fetched::Scalar(Value::Null))),
+        }
+    }
+
+    /// Creates the rows of an IN subquery whose only column is `column`.
+    pub(crate) fn set(column: Column) -> Self {
+        Self {
+            column,
+            fetched: Arc::new(RwLock::new(Fetched::Set {
+                values: HashSet::new(),
+                has_null: false,
+            })),
+        }
+    }
+
+    /// Returns the column the subquery returns.
+    pub(crate) fn column(&self) -> &Column {
+        &self.column
+    }
+
+    /// Runs `plan`, the subquery's executor tree, and replaces the rows with its result.
+    ///
+    /// A scalar subquery returning more than one row is an error.
+    pub(crate) fn fetch(&self, plan: &mut dyn Executor) -> Result<()> {
+        plan.init()?;
+        let mut fetched = self.fetched.write().unwrap();
+        match &mut *fetched {
+            Fetched::Scalar(value) => {
+                *value = match plan.next()? {
+                    Some(mut row) => row.values.swap_remove(0),
+                    None => Value::Null,
+                };
+                if plan.next()?.is_some() {
+                    return Err(QueryError::ExecutionError(
+                        "Scalar subquery returned more than one row".to_string(),
+                    ));
+                }
+            }
+            Fetched::Set { values, has_null } => {
+                values.clear();
+                *has_null = false;
+                while let Some(mut row) = plan.next()? {
+                    match row.values.swap_remove(0) {
+                        Value::Null => *has_null = true,
+                        value => {
+                            values.insert(value);
+                        }
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns a scalar subquery's value.
+    pub(crate) fn value(&self) -> Value {
+        match &*self.fetched.read().unwrap() {
+            Fetched::Scalar(value) => value.clone(),
+            Fetched::Set { .. } => Value::Null,
+        }
+    }
+
+    /// Returns whether an IN subquery returned `value`: TRUE if it did, and otherwise NULL
+    /// if `value` or any of the subquery's values is NULL, or FALSE.
+    pub(crate) fn contains(&self, value: &Value) -> Value {
+        let Fetched::Set { values, has_null } = &*self.fetched.read().unwrap() else {
+            return Value::Null;
+        };
+        if value.is_null() {
+            // Nothing is in an empty set, not even NULL
+            if values.is_empty() && !has_null {
+                return Value::Integer(0);
+            }
+            return Value::Null;
+        }
+        if values.contains(value) {
+            Value::Integer(1)
+        } else if *has_null {
+            Value::Null
+        } else {
+            Value::Integer(0)
+        }
+    }
+}
+
+impl fmt::Debug for SubqueryRows {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("SubqueryRows")
+            .field(&self.column.name)
+            .finish()
+    }
+}
+
+impl Expression {
+    /// Checks whether this expression is among the values of `subquery`, a DataFrame with a
+    /// single column (`self IN (subquery)`).
+    ///
+    /// As in SQL, the result is NULL rather than FALSE when the value is not found but it or
+    /// one of the subquery's values is NULL. The subquery may refer to the outer query's
+    /// columns in its filter.
+    ///
+    /// # Example
+    /// ```ignore
+    /// // Customers who have placed an order
+    /// customers.filter(col("id").in_subquery(orders.select(&["customer_id"])))
+    /// ```
+    pub fn in_subquery(self, subquery: DataFrame) -> Expression {
+        Expression::InSubquery {
+            expr: Box::new(self),
+            subquery: Subquery(Arc::new(subquery)),
+        }
+    }
+
+    /// Checks whether this expression is not among the values of `subquery`
+    /// (`self NOT IN (subquery)`).
+    ///
+    /// A single NULL among the subquery's values makes the result NULL for every value not
+    /// found, so no row passes a filter on it.
+    pub fn not_in_subquery(self, subquery: DataFrame) -> Expression {
+        Expression::UnaryOp {
+            op: super::UnaryOperator::Not,
+            expr: Box::new(self.in_subquery(subquery)),
+        }
+    }
+}
+
+/// Creates an expression whose value is that of `subquery`, a DataFrame with a single column
+/// and at most one row, or NULL if it has no rows.
+///
+/// The subquery may refer to the outer query's columns when it is an aggregate without
+/// `group_by`, filtered on equalities between its own columns and the outer query's.
+///
+/// # Example
+/// ```ignore
+/// // Orders larger than their customer's average
+/// let average = db.table("orders")?.alias("o")
+///     .filter(col("o.customer_id").eq(col("orders.customer_id")))
+///     .group_by(&[])
+///     .agg(&[avg(col("amount")).alias("average")])?;
+/// db.table("orders")?.filter(col("amount").gt(scalar_subquery(average)))
+/// ```
+pub fn scalar_subquery(subquery: DataFrame) -> Expression {
+    Expression::ScalarSubquery(Subquery(Arc::new(subquery)))
+}
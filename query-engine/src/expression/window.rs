@@ -0,0 +1,371 @@
+//! Window functions, e.g.
+//! `row_number().over(partition_by(&[col("customer_id")]).order_by(&[col("amount").asc()]))`.
+
+use super::{AggregateExpr, Expression, ParameterValues, SortKey};
+use crate::{QueryError, Result};
+use std::fmt;
+use storage_engine::tuple::Schema;
+
+/// A function computed for each row from the other rows of its partition.
+#[derive(Debug, Clone)]
+pub enum WindowFunction {
+    /// Position of the row within its partition, from 1
+    RowNumber,
+    /// Position of the first row that ties with this one on the ORDER BY keys, from 1
+    Rank,
+    /// Number of distinct ORDER BY values up to and including this row's
+    DenseRank,
+    /// Value of `expr` in the row `offset` rows before this one, or NULL if there is none
+    Lag { expr: Expression, offset: usize },
+    /// Value of `expr` in the row `offset` rows after this one, or NULL if there is none
+    Lead { expr: Expression, offset: usize },
+    /// An aggregate over the rows of the window frame
+    Aggregate(AggregateExpr),
+}
+
+impl WindowFunction {
+    /// Computes the function over the window `window`.
+    pub fn over(self, window: WindowSpec) -> WindowExpr {
+        WindowExpr {
+            function: self,
+            window,
+        }
+    }
+
+    fn map_expressions(&self, f: impl Fn(&Expression) -> Result<Expression>) -> Result<Self> {
+        Ok(match self {
+            WindowFunction::Lag { expr, offset } => WindowFunction::Lag {
+                expr: f(expr)?,
+                offset: *offset,
+            },
+            WindowFunction::Lead { expr, offset } => WindowFunction::Lead {
+                expr: f(expr)?,
+                offset: *offset,
+            },
+            WindowFunction::Aggregate(aggregate) => WindowFunction::Aggregate(AggregateExpr {
+                arg: aggregate.arg.as_ref().map(f).transpose()?,
+                ..aggregate.clone()
+            }),
+            function => function.clone(),
+        })
+    }
+
+    fn display(&self, expr: impl Fn(&Expression) -> String) -> String {
+        match self {
+            WindowFunction::RowNumber => "row_number()".to_string(),
+            WindowFunction::Rank => "rank()".to_string(),
+            WindowFunction::DenseRank => "dense_rank()".to_string(),
+            WindowFunction::Lag { expr: arg, offset } => format!("lag({}, {})", expr(arg), offset),
+            WindowFunction::Lead { expr: arg, offset } => {
+                format!("lead({}, {})", expr(arg), offset)
+            }
+            WindowFunction::Aggregate(aggregate) => match &aggregate.arg {
+                Some(arg) => format!("{}({})", aggregate.function.name(), expr(arg)),
+                None => format!("{}(*)", aggregate.function.name()),
+            },
+        }
+    }
+}
+
+impl AggregateExpr {
+    /// Computes the aggregate over the window frame of each row instead of over a group,
+    /// e.g. `sum(col("amount")).over(...)` for a running total.
+    pub fn over(self, window: WindowSpec) -> WindowExpr {
+        WindowFunction::Aggregate(self).over(window)
+    }
+}
+
+/// Whether a window frame counts rows, or compares ORDER BY values.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FrameUnits {
+    /// Bounds are a number of rows away from the current row
+    Rows,
+    /// Bounds are a distance in ORDER BY value from the current row's, and rows that tie
+    /// with the current row are always in the frame together
+    Range,
+}
+
+/// One end of a window frame.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FrameBound {
+    /// The first row of the partition
+    UnboundedPreceding,
+    /// This many rows, or this much ORDER BY value, before the current row
+    Preceding(usize),
+    /// The current row, or for RANGE its first or last tying row
+    CurrentRow,
+    /// This many rows, or this much ORDER BY value, after the current row
+    Following(usize),
+    /// The last row of the partition
+    UnboundedFollowing,
+}
+
+impl FrameBound {
+    /// Returns where the bound lies relative to the current row, counting rows or ORDER BY
+    /// value back (negative) or forward (positive), for comparing a frame's two ends.
+    fn position(&self) -> i128 {
+        match self {
+            FrameBound::UnboundedPreceding => i128::MIN,
+            FrameBound::Preceding(n) => -(*n as i128),
+            FrameBound::CurrentRow => 0,
+            FrameBound::Following(n) => *n as i128,
+            FrameBound::UnboundedFollowing => i128::MAX,
+        }
+    }
+}
+
+impl fmt::Display for FrameBound {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FrameBound::UnboundedPreceding => write!(f, "UNBOUNDED PRECEDING"),
+            FrameBound::Preceding(n) => write!(f, "{} PRECEDING", n),
+            FrameBound::CurrentRow => write!(f, "CURRENT ROW"),
+            FrameBound::Following(n) => write!(f, "{} FOLLOWING", n),
+            FrameBound::UnboundedFollowing => write!(f, "UNBOUNDED FOLLOWING"),
+        }
+    }
+}
+
+/// The rows of a partition an aggregate window function reads for each row.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WindowFrame {
+    pub units: FrameUnits,
+    pub start: FrameBound,
+    pub end: FrameBound,
+}
+
+impl WindowFrame {
+    /// Returns whether either bound is an offset, which RANGE measures on the ORDER BY key.
+    pub(crate) fn has_offset(&self) -> bool {
+        [self.start, self.end]
+            .iter()
+            .any(|bound| matches!(bound, FrameBound::Preceding(_) | FrameBound::Following(_)))
+    }
+}
+
+impl fmt::Display for WindowFrame {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let units = match self.units {
+            FrameUnits::Rows => "ROWS",
+            FrameUnits::Range => "RANGE",
+        };
+        write!(f, "{} BETWEEN {} AND {}", units, self.start, self.end)
+    }
+}
+
+/// How rows are split into partitions and ordered within them for a window function (the
+/// OVER clause).
+#[derive(Debug, Clone, Default)]
+pub struct WindowSpec {
+    pub partition_by: Vec<Expression>,
+    pub order_by: Vec<SortKey>,
+    /// The frame, or None for the default: every row up to the current one and those tying
+    /// with it, i.e. the whole partition without ORDER BY
+    pub frame: Option<WindowFrame>,
+}
+
+impl WindowSpec {
+    /// Orders the rows within each partition.
+    pub fn order_by(mut self, keys: &[SortKey]) -> Self {
+        self.order_by = keys.to_vec();
+        self
+    }
+
+    /// Limits aggregates to the rows between `start` and `end`, counted in rows from the
+    /// current one (ROWS BETWEEN).
+    pub fn rows_between(mut self, start: FrameBound, end: FrameBound) -> Self {
+        self.frame = Some(WindowFrame {
+            units: FrameUnits::Rows,
+            start,
+            end,
+        });
+        self
+    }
+
+    /// Limits aggregates to the rows whose ORDER BY value lies between `start` and `end`
+    /// relative to the current row's (RANGE BETWEEN). Offsets need a single INTEGER key.
+    pub fn range_between(mut self, start: FrameBound, end: FrameBound) -> Self {
+        self.frame = Some(WindowFrame {
+            units: FrameUnits::Range,
+            start,
+            end,
+        });
+        self
+    }
+
+    /// Returns the frame aggregates read, filling in the default.
+    pub fn effective_frame(&self) -> WindowFrame {
+        self.frame.unwrap_or(WindowFrame {
+            units: FrameUnits::Range,
+            start: FrameBound::UnboundedPreceding,
+            end: FrameBound::CurrentRow,
+        })
+    }
+
+    fn check_frame(&self) -> Result<()> {
+        let Some(frame) = self.frame else {
+            return Ok(());
+        };
+        if frame.start == FrameBound::UnboundedFollowing {
+            return Err(QueryError::ExecutionError(
+                "A window frame cannot start at UNBOUNDED FOLLOWING".to_string(),
+            ));
+        }
+        if frame.end == FrameBound::UnboundedPreceding {
+            return Err(QueryError::ExecutionError(
+                "A window frame cannot end at UNBOUNDED PRECEDING".to_string(),
+            ));
+        }
+        if frame.start.position() > frame.end.position() {
+            return Err(QueryError::ExecutionError(format!(
+                "A window frame cannot start after it ends, got {}",
+                frame
+            )));
+        }
+        if frame.units == FrameUnits::Range && frame.has_offset() && self.order_by.len() != 1 {
+            return Err(QueryError::ExecutionError(format!(
+                "{} needs exactly one ORDER BY key, got {}",
+                frame,
+                self.order_by.len()
+            )));
+        }
+        Ok(())
+    }
+
+    fn display(
+        &self,
+        expr: impl Fn(&Expression) -> String,
+        key: impl Fn(&SortKey) -> String,
+    ) -> String {
+        let mut clauses = Vec::new();
+        if !self.partition_by.is_empty() {
+            let keys: Vec<String> = self.partition_by.iter().map(expr).collect();
+            clauses.push(format!("PARTITION BY {}", keys.join(", ")));
+        }
+        if !self.order_by.is_empty() {
+            let keys: Vec<String> = self.order_by.iter().map(key).collect();
+            clauses.push(format!("ORDER BY {}", keys.join(", ")));
+        }
+        if let Some(frame) = &self.frame {
+            clauses.push(frame.to_string());
+        }
+        format!("OVER ({})", clauses.join(" "))
+    }
+}
+
+/// A window function together with the window it is computed over.
+#[derive(Debug, Clone)]
+pub struct WindowExpr {
+    pub function: WindowFunction,
+    pub window: WindowSpec,
+}
+
+impl WindowExpr {
+    /// Binds the column names of the function's argument and the window's keys to column
+    /// indices, and checks the frame.
+    pub fn bind(&self, schema: &Schema) -> Result<WindowExpr> {
+        self.window.check_frame()?;
+        Ok(WindowExpr {
+            function: self.function.map_expressions(|expr| expr.bind(schema))?,
+            window: WindowSpec {
+                partition_by: self
+                    .window
+                    .partition_by
+                    .iter()
+                    .map(|expr| expr.bind(schema))
+                    .collect::<Result<_>>()?,
+                order_by: self
+                    .window
+                    .order_by
+                    .iter()
+                    .map(|key| key.bind(schema))
+                    .collect::<Result<_>>()?,
+                frame: self.window.frame,
+            },
+        })
+    }
+
+    /// Connects parameter placeholders to the argument list.
+    pub fn bind_parameters(&self, values: &ParameterValues) -> WindowExpr {
+        let bind = |expr: &Expression| expr.bind_parameters(values);
+        WindowExpr {
+            function: self
+                .function
+                .map_expressions(|expr| Ok(bind(expr)))
+                .unwrap(),
+            window: WindowSpec {
+                partition_by: self.window.partition_by.iter().map(bind).collect(),
+                order_by: self
+                    .window
+                    .order_by
+                    .iter()
+                    .map(|key| SortKey {
+                        expr: bind(&key.expr),
+                        ..key.clone()
+                    })
+                    .collect(),
+                frame: self.window.frame,
+            },
+        }
+    }
+
+    /// Returns the highest parameter number referenced, or 0 if none.
+    pub fn parameter_count(&self) -> usize {
+        let argument = match &self.function {
+            WindowFunction::Lag { expr, .. } | WindowFunction::Lead { expr, .. } => Some(expr),
+            WindowFunction::Aggregate(aggregate) => aggregate.arg.as_ref(),
+            _ => None,
+        };
+        argument
+            .into_iter()
+            .chain(&self.window.partition_by)
+            .chain(self.window.order_by.iter().map(|key| &key.expr))
+            .map(Expression::parameter_count)
+            .max()
+            .unwrap_or(0)
+    }
+
+    /// Displays the expression as SQL, naming bound columns after `schema`.
+    pub fn display_with(&self, schema: &Schema) -> String {
+        let expr = |expr: &Expression| expr.display_with(schema).to_string();
+        format!(
+            "{} {}",
+            self.function.display(expr),
+            self.window.display(expr, |key| key.display_with(schema))
+        )
+    }
+}
+
+/// Splits rows into partitions by the values of `keys` (PARTITION BY), to be passed to
+/// `over`. Use [`WindowSpec::default`] for a single partition of all rows.
+pub fn partition_by(keys: &[Expression]) -> WindowSpec {
+    WindowSpec {
+        partition_by: keys.to_vec(),
+        ..WindowSpec::default()
+    }
+}
+
+/// Numbers the rows of each partition from 1 in window order (ROW_NUMBER).
+pub fn row_number() -> WindowFunction {
+    WindowFunction::RowNumber
+}
+
+/// Ranks the rows of each partition in window order, with gaps after ties (RANK).
+pub fn rank() -> WindowFunction {
+    WindowFunction::Rank
+}
+
+/// Ranks the rows of each partition in window order, without gaps after ties (DENSE_RANK).
+pub fn dense_rank() -> WindowFunction {
+    WindowFunction::DenseRank
+}
+
+/// Returns `expr` from `offset` rows earlier in the partition, or NULL (LAG).
+pub fn lag(expr: Expression, offset: usize) -> WindowFunction {
+    WindowFunction::Lag { expr, offset }
+}
+
+/// Returns `expr` from `offset` rows later in the partition, or NULL (LEAD).
+pub fn lead(expr: Expression, offset: usize) -> WindowFunction {
+    WindowFunction::Lead { expr, offset }
+}
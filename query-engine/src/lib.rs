@@ -22,6 +22,9 @@
 //!     .filter(col("id").gt(lit(10)))
 //!     .select(&["name"])
 //!     .collect()?;
+//!
+//! // Or the same query in SQL
+//! let results = db.sql("SELECT name FROM users WHERE id > 10")?;
 //! # Ok(())
 //! # }
 //! ```
@@ -30,13 +33,25 @@ pub mod catalog;
 pub mod types;
 pub mod expression;
 pub mod executor;
+pub mod sql;
 mod database;
 mod dataframe;
+mod diagnostics;
+mod logical_plan;
+mod prepared;
 
 pub use database::Database;
-pub use dataframe::DataFrame;
-pub use expression::{col, lit, lit_str, Expression};
-pub use types::Value;
+pub use dataframe::{CommonTable, DataFrame, GroupedDataFrame, JoinStrategy};
+pub use diagnostics::SourceLocation;
+pub use executor::JoinType;
+pub use expression::{
+    avg, col, count, count_star, dense_rank, lag, lead, lit, lit_str, max, min, param,
+    partition_by, rank, row_number, scalar_subquery, sum, AggregateExpr, AggregateFunction,
+    Expression, FrameBound, SortKey, WindowExpr, WindowSpec,
+};
+pub use prepared::PreparedQuery;
+pub use sql::StatementResult;
+pub use types::{Row, Value};
 
 // Re-export commonly used types from storage_engine
 pub use storage_engine::tuple::{Schema, Column, Type};
@@ -63,19 +78,60 @@ pub fn varchar_column(name: &str, length: u32) -> Column {
 #[derive(Debug)]
 pub enum QueryError {
     /// Table not found in catalog
-    TableNotFound(String),
+    TableNotFound {
+        name: String,
+        /// A similarly named table, if there is one
+        suggestion: Option<String>,
+        /// Where the name appears in the SQL text, for SQL queries
+        location: Option<SourceLocation>,
+    },
     /// Column not found in schema
-    ColumnNotFound(String),
+    ColumnNotFound {
+        name: String,
+        /// A similarly named column, if there is one
+        suggestion: Option<String>,
+        /// Where the name appears in the SQL text, for SQL queries
+        location: Option<SourceLocation>,
+    },
     /// Type mismatch in expression
     TypeMismatch(String),
     /// Execution error
     ExecutionError(String),
+    /// SQL syntax error
+    ParseError {
+        message: String,
+        location: SourceLocation,
+    },
     /// Buffer pool manager error
     BpmError(buffer_pool_manager::api::BpmError),
     /// I/O error
     IoError(std::io::Error),
 }
 
+impl QueryError {
+    /// Returns where in the SQL text the error was found, if known.
+    pub fn location(&self) -> Option<&SourceLocation> {
+        match self {
+            QueryError::TableNotFound { location, .. }
+            | QueryError::ColumnNotFound { location, .. } => location.as_ref(),
+            QueryError::ParseError { location, .. } => Some(location),
+            _ => None,
+        }
+    }
+
+    /// Attaches a source location to a not-found error that does not have one yet.
+    pub(crate) fn with_location(mut self, at: impl FnOnce() -> SourceLocation) -> Self {
+        if let QueryError::TableNotFound { location, .. }
+        | QueryError::ColumnNotFound { location, .. } = &mut self
+        {
+            if location.is_none() {
+                *location = Some(at());
+            }
+        }
+        self
+    }
+}
+
 impl From<buffer_pool_manager::api::BpmError> for QueryError {
     fn from(err: buffer_pool_manager::api::BpmError) -> Self {
         QueryError::BpmError(err)
@@ -91,16 +147,76 @@ impl From<std::io::Error> for QueryError {
 impl std::fmt::Display for QueryError {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
-            QueryError::TableNotFound(name) => write!(f, "Table not found: {}", name),
-            QueryError::ColumnNotFound(name) => write!(f, "Column not found: {}", name),
+            QueryError::TableNotFound {
+                name,
+                suggestion,
+                location,
+            } => write_not_found(f, "table", name, suggestion, location),
+            QueryError::ColumnNotFound {
+                name,
+                suggestion,
+                location,
+            } => write_not_found(f, "column", name, suggestion, location),
             QueryError::TypeMismatch(msg) => write!(f, "Type mismatch: {}", msg),
             QueryError::ExecutionError(msg) => write!(f, "Execution error: {}", msg),
+            QueryError::ParseError { message, location } => write!(
+                f,
+                "Parse error: {} at line {}, col {}",
+                message, location.line, location.column
+            ),
             QueryError::BpmError(err) => write!(f, "Buffer pool error: {:?}", err),
             QueryError::IoError(err) => write!(f, "I/O error: {}", err),
         }
     }
 }
 
+/// Writes e.g. "column `agee` not found at line 1, col 32; did you mean `age`?".
+fn write_not_found(
+    f: &mut std::fmt::Formatter<'_>,
+    kind: &str,
+    name: &str,
+    suggestion: &Option<String>,
+    location: &Option<SourceLocation>,
+) -> std::fmt::Result {
+    write!(f, "{} `{}` not found", kind, name)?;
+    if let Some(location) = location {
+        write!(f, " at line {}, col {}", location.line, location.column)?;
+    }
+    if let Some(suggestion) = suggestion {
+        write!(f, "; did you mean `{}`?", suggestion)?;
+    }
+    Ok(())
+}
+
 impl std::error::Error for QueryError {}
 
 pub type Result<T> = std::result::Result<T, QueryError>;
+
+/// The first failing statement of a script run by [`Database::execute_script`].
+#[derive(Debug)]
+pub struct ScriptError {
+    /// Zero-based index of the statement within the script
+    pub index: usize,
+    /// Line of the script the statement starts on, counting from 1
+    pub line: usize,
+    /// Why the statement failed
+    pub error: QueryError,
+}
+
+impl std::fmt::Display for ScriptError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "statement {} (line {}): {}",
+            self.index + 1,
+            self.line,
+            self.error
+        )
+    }
+}
+
+impl std::error::Error for ScriptError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        Some(&self.error)
+    }
+}
@@ -0,0 +1,507 @@
+//! Logical plans: the tree of operations a DataFrame stands for.
+//!
+//! Every DataFrame method puts a node on top of the plan it is called on, so operations
+//! apply in the order they are chained: a filter after a projection sees the projected
+//! columns, and a filter after a limit only looks at the rows the limit kept. The DataFrame
+//! planner turns the tree into an executor tree when the query is built.
+
+use crate::catalog::system::SystemTable;
+use crate::catalog::{Catalog, TableInfo};
+use crate::dataframe::{subquery_column, JoinStrategy};
+use crate::executor::projection::infer_column;
+use crate::executor::{CteRows, JoinType, SetOperation};
+use crate::expression::{AggregateExpr, Expression, SortKey, SubqueryRows, WindowExpr};
+use crate::{QueryError, Result};
+use std::sync::{Arc, RwLock};
+use storage_engine::tuple::Schema;
+
+/// A node of a logical plan, with the nodes whose rows it reads below it.
+pub(crate) enum LogicalPlan {
+    /// A user table stored in a `TableHeap`
+    Scan(Arc<TableInfo>),
+    /// A read-only table generated from the catalog, with its schema
+    SystemScan(SystemTable, Schema),
+    /// The rows of a common table, computed once per execution
+    CommonTableScan(Arc<CommonTableInfo>),
+    /// The rows of `input` for which `predicate` is true (WHERE clause)
+    Filter {
+        input: Box<LogicalPlan>,
+        predicate: Expression,
+    },
+    /// Expressions evaluated over each row of `input`, each with the name of its output
+    /// column (SELECT clause)
+    Projection {
+        input: Box<LogicalPlan>,
+        exprs: Vec<(Expression, String)>,
+    },
+    /// The rows of `input` sorted on `keys`, most significant first (ORDER BY clause)
+    Sort {
+        input: Box<LogicalPlan>,
+        keys: Vec<SortKey>,
+    },
+    /// The first row of `input` for each distinct value of `on` (DISTINCT ON), or of the
+    /// whole row if `on` is empty (DISTINCT)
+    Distinct {
+        input: Box<LogicalPlan>,
+        on: Vec<Expression>,
+    },
+    /// At most `fetch` rows of `input`, after skipping the first `skip` (LIMIT and OFFSET
+    /// clauses)
+    Limit {
+        input: Box<LogicalPlan>,
+        skip: usize,
+        fetch: Option<usize>,
+    },
+    /// The rows of `input` under another name, which qualifies its columns in a join
+    Alias {
+        input: Box<LogicalPlan>,
+        name: String,
+    },
+    /// Two plans combined by [`DataFrame::join`](crate::DataFrame::join)
+    Join(Box<Join>),
+    /// Groups of a plan's rows folded by [`GroupedDataFrame::agg`](crate::GroupedDataFrame::agg)
+    Aggregate(Box<Aggregate>),
+    /// Two plans' rows combined by [`DataFrame::union`](crate::DataFrame::union) and the like
+    SetOperation(Box<SetOperationInputs>),
+    /// A plan's rows with a window function's value added by
+    /// [`DataFrame::with_window`](crate::DataFrame::with_window)
+    Window(Box<Window>),
+    /// The rows of a recursive common table's base and of each run of its step
+    Recursive(Box<RecursiveInputs>),
+}
+
+/// The inputs and condition of a join.
+pub(crate) struct Join {
+    pub(crate) left: LogicalPlan,
+    pub(crate) right: LogicalPlan,
+    pub(crate) on: Expression,
+    pub(crate) join_type: JoinType,
+    pub(crate) strategy: JoinStrategy,
+    /// Columns of both sides, qualified by the name of the side they come from
+    pub(crate) schema: Schema,
+    /// Columns of the result: `schema`, or only the left side's for semi and anti joins
+    pub(crate) output_schema: Schema,
+}
+
+/// The input, grouping and aggregates of an aggregation.
+pub(crate) struct Aggregate {
+    pub(crate) input: LogicalPlan,
+    pub(crate) group_by: Vec<Expression>,
+    /// Aggregates, each with an alias naming its output column
+    pub(crate) aggregates: Vec<AggregateExpr>,
+    /// Columns of the result: the group-by expressions, then the aggregates
+    pub(crate) schema: Schema,
+}
+
+/// The input and function of a window.
+pub(crate) struct Window {
+    pub(crate) input: LogicalPlan,
+    pub(crate) window: WindowExpr,
+    /// Name of the column holding the function's value
+    pub(crate) name: String,
+    /// Columns of the result: the input's, then the function's
+    pub(crate) schema: Schema,
+}
+
+/// The inputs of a set operation.
+pub(crate) struct SetOperationInputs {
+    pub(crate) left: LogicalPlan,
+    pub(crate) right: LogicalPlan,
+    pub(crate) operation: SetOperation,
+    /// Columns of the result, named after the left side's
+    pub(crate) schema: Schema,
+}
+
+/// The inputs of a recursive common table.
+pub(crate) struct RecursiveInputs {
+    pub(crate) base: LogicalPlan,
+    /// The recursive input, reading the rows `working` holds
+    pub(crate) step: LogicalPlan,
+    /// Identifies the working table holding the rows of the previous run for `step`
+    pub(crate) working: CteRows,
+    pub(crate) operation: SetOperation,
+    /// Columns of the result, named after the base's
+    pub(crate) schema: Schema,
+}
+
+/// A named query shared by the plans reading its rows.
+pub(crate) struct CommonTableInfo {
+    pub(crate) catalog: Arc<Catalog>,
+    /// Identifies the common table's rows, under its name; each executor tree stores them
+    /// in a slot of its own
+    pub(crate) rows: CteRows,
+    pub(crate) schema: Schema,
+    pub(crate) definition: CommonTableDefinition,
+}
+
+/// How the rows of a common table are computed.
+pub(crate) enum CommonTableDefinition {
+    /// By a query, run once before each execution of a query reading the rows
+    Query(RwLock<LogicalPlan>),
+    /// By the executor of a recursive common table, on each run of its step (the working
+    /// table), with a rough number of rows for choosing between plans
+    Working { estimated_rows: usize },
+}
+
+impl LogicalPlan {
+    /// Returns the plan below a node that reads a single input and passes on its columns,
+    /// or `None` for any other node.
+    pub(crate) fn single_input(&self) -> Option<&LogicalPlan> {
+        match self {
+            LogicalPlan::Filter { input, .. }
+            | LogicalPlan::Projection { input, .. }
+            | LogicalPlan::Sort { input, .. }
+            | LogicalPlan::Distinct { input, .. }
+            | LogicalPlan::Limit { input, .. }
+            | LogicalPlan::Alias { input, .. } => Some(input),
+            _ => None,
+        }
+    }
+
+    /// Returns the schema of the table or combined inputs at the bottom of the plan, before
+    /// any filter, projection or other operation on a single input.
+    pub(crate) fn source_schema(&self) -> &Schema {
+        match self {
+            LogicalPlan::Scan(table_info) => &table_info.schema,
+            LogicalPlan::SystemScan(_, schema) => schema,
+            LogicalPlan::CommonTableScan(table) => &table.schema,
+            LogicalPlan::Join(join) => &join.output_schema,
+            LogicalPlan::Aggregate(aggregate) => &aggregate.schema,
+            LogicalPlan::SetOperation(inputs) => &inputs.schema,
+            LogicalPlan::Window(window) => &window.schema,
+            LogicalPlan::Recursive(inputs) => &inputs.schema,
+            LogicalPlan::Filter { input, .. }
+            | LogicalPlan::Projection { input, .. }
+            | LogicalPlan::Sort { input, .. }
+            | LogicalPlan::Distinct { input, .. }
+            | LogicalPlan::Limit { input, .. }
+            | LogicalPlan::Alias { input, .. } => input.source_schema(),
+        }
+    }
+
+    /// Returns the columns of the rows the plan produces, derived from its nodes without
+    /// planning or running anything.
+    pub(crate) fn schema(&self) -> Result<Schema> {
+        let (input, exprs) = match self {
+            LogicalPlan::Projection { input, exprs } => (input, exprs),
+            plan => match plan.single_input() {
+                Some(input) => return input.schema(),
+                None => return Ok(plan.source_schema().clone()),
+            },
+        };
+
+        let input_schema = input.schema()?;
+        let columns = exprs
+            .iter()
+            .map(|(expr, name)| {
+                // Subqueries stand in for the rows they will return, typed from their plans
+                let expr = expr
+                    .replace_subqueries(&mut |node| match node {
+                        Expression::ScalarSubquery(subquery) => {
+                            let schema = subquery.dataframe().plan().schema()?;
+                            let column = subquery_column(&schema)?.clone();
+                            Ok(Expression::BoundScalarSubquery(SubqueryRows::scalar(column)))
+                        }
+                        Expression::InSubquery { expr, subquery } => {
+                            let schema = subquery.dataframe().plan().schema()?;
+                            let column = subquery_column(&schema)?.clone();
+                            Ok(Expression::BoundInSubquery {
+                                expr: expr.clone(),
+                                rows: SubqueryRows::set(column),
+                            })
+                        }
+                        other => Ok(other.clone()),
+                    })?
+                    .bind(&input_schema)?;
+                Ok(infer_column(&expr, name.clone(), &input_schema))
+            })
+            .collect::<Result<_>>()?;
+        Ok(Schema { columns })
+    }
+
+    /// Returns the stored table at the bottom of the plan, as the plan was bound to it, or an
+    /// error if the plan reads anything else.
+    pub(crate) fn writable_table(&self, operation: &str) -> Result<&TableInfo> {
+        match self {
+            LogicalPlan::Scan(table_info) => Ok(table_info),
+            LogicalPlan::SystemScan(table, _) => Err(QueryError::ExecutionError(format!(
+                "Cannot {} system table '{}': it is read-only",
+                operation,
+                table.name()
+            ))),
+            LogicalPlan::Join(_) => Err(QueryError::ExecutionError(format!(
+                "Cannot {} a join",
+                operation
+            ))),
+            LogicalPlan::Aggregate(_) => Err(QueryError::ExecutionError(format!(
+                "Cannot {} an aggregation",
+                operation
+            ))),
+            LogicalPlan::SetOperation(inputs) => Err(QueryError::ExecutionError(format!(
+                "Cannot {} the result of {}",
+                operation,
+                inputs.operation.name()
+            ))),
+            LogicalPlan::Window(_) => Err(QueryError::ExecutionError(format!(
+                "Cannot {} a window",
+                operation
+            ))),
+            LogicalPlan::CommonTableScan(table) => Err(QueryError::ExecutionError(format!(
+                "Cannot {} common table '{}'",
+                operation,
+                table.rows.name()
+            ))),
+            LogicalPlan::Recursive(_) => Err(QueryError::ExecutionError(format!(
+                "Cannot {} a recursive query",
+                operation
+            ))),
+            LogicalPlan::Filter { input, .. }
+            | LogicalPlan::Projection { input, .. }
+            | LogicalPlan::Sort { input, .. }
+            | LogicalPlan::Distinct { input, .. }
+            | LogicalPlan::Limit { input, .. }
+            | LogicalPlan::Alias { input, .. } => input.writable_table(operation),
+        }
+    }
+
+    /// Returns the name qualifying the plan's columns as seen from the other side of a join:
+    /// the innermost alias or table name above anything combining several rows or inputs.
+    pub(crate) fn qualifier(&self) -> Option<&str> {
+        match self {
+            LogicalPlan::Alias { name, .. } => Some(name),
+            LogicalPlan::Scan(table_info) => Some(&table_info.name),
+            LogicalPlan::SystemScan(table, _) => Some(table.name()),
+            LogicalPlan::CommonTableScan(table) => Some(table.rows.name()),
+            plan => plan.single_input()?.qualifier(),
+        }
+    }
+
+    /// Prefixes every unqualified column name in `schema`, the plan's output columns, by the
+    /// plan's alias or table name.
+    pub(crate) fn qualify(&self, mut schema: Schema) -> Schema {
+        let Some(qualifier) = self.qualifier() else {
+            return schema;
+        };
+        for column in &mut schema.columns {
+            if !column.name.contains('.') {
+                column.name = format!("{}.{}", qualifier, column.name);
+            }
+        }
+        schema
+    }
+
+    /// Returns the number of parameters the plan takes, i.e. the highest `param(n)` used.
+    pub(crate) fn parameter_count(&self) -> usize {
+        let exprs = |exprs: &mut dyn Iterator<Item = &Expression>| {
+            exprs.map(Expression::parameter_count).max().unwrap_or(0)
+        };
+        match self {
+            LogicalPlan::Scan(_) | LogicalPlan::SystemScan(..) => 0,
+            LogicalPlan::CommonTableScan(table) => match &table.definition {
+                CommonTableDefinition::Query(definition) => {
+                    definition.read().unwrap().parameter_count()
+                }
+                CommonTableDefinition::Working { .. } => 0,
+            },
+            LogicalPlan::Filter { input, predicate } => {
+                input.parameter_count().max(predicate.parameter_count())
+            }
+            LogicalPlan::Projection {
+                input,
+                exprs: projections,
+            } => input
+                .parameter_count()
+                .max(exprs(&mut projections.iter().map(|(expr, _)| expr))),
+            LogicalPlan::Sort { input, keys } => input
+                .parameter_count()
+                .max(exprs(&mut keys.iter().map(|key| &key.expr))),
+            LogicalPlan::Distinct { input, .. }
+            | LogicalPlan::Limit { input, .. }
+            | LogicalPlan::Alias { input, .. } => input.parameter_count(),
+            LogicalPlan::Join(join) => join
+                .on
+                .parameter_count()
+                .max(join.left.parameter_count())
+                .max(join.right.parameter_count()),
+            LogicalPlan::Aggregate(aggregate) => aggregate
+                .group_by
+                .iter()
+                .map(Expression::parameter_count)
+                .chain(
+                    aggregate
+                        .aggregates
+                        .iter()
+                        .map(AggregateExpr::parameter_count),
+                )
+                .fold(aggregate.input.parameter_count(), usize::max),
+            LogicalPlan::SetOperation(inputs) => inputs
+                .left
+                .parameter_count()
+                .max(inputs.right.parameter_count()),
+            LogicalPlan::Window(window) => window
+                .window
+                .parameter_count()
+                .max(window.input.parameter_count()),
+            LogicalPlan::Recursive(inputs) => inputs
+                .base
+                .parameter_count()
+                .max(inputs.step.parameter_count()),
+        }
+    }
+
+    /// Points every scan at its table's current heap if the table has been rewritten since
+    /// the plan was created. Returns whether anything changed, or an error if a table has
+    /// been replaced by one with different columns.
+    pub(crate) fn refresh(&mut self, catalog: &Catalog) -> Result<bool> {
+        match self {
+            LogicalPlan::Scan(table_info) => {
+                let current = catalog.current_table(table_info)?;
+                if Arc::ptr_eq(&current, table_info) {
+                    return Ok(false);
+                }
+                *table_info = current;
+                Ok(true)
+            }
+            // System tables are regenerated on every scan
+            LogicalPlan::SystemScan(..) => Ok(false),
+            LogicalPlan::CommonTableScan(table) => match &table.definition {
+                CommonTableDefinition::Query(definition) => {
+                    definition.write().unwrap().refresh(catalog)
+                }
+                CommonTableDefinition::Working { .. } => Ok(false),
+            },
+            LogicalPlan::Filter { input, .. }
+            | LogicalPlan::Projection { input, .. }
+            | LogicalPlan::Sort { input, .. }
+            | LogicalPlan::Distinct { input, .. }
+            | LogicalPlan::Limit { input, .. }
+            | LogicalPlan::Alias { input, .. } => input.refresh(catalog),
+            LogicalPlan::Join(join) => {
+                let left = join.left.refresh(catalog)?;
+                let right = join.right.refresh(catalog)?;
+                Ok(left || right)
+            }
+            LogicalPlan::Aggregate(aggregate) => aggregate.input.refresh(catalog),
+            LogicalPlan::Window(window) => window.input.refresh(catalog),
+            LogicalPlan::SetOperation(inputs) => {
+                let left = inputs.left.refresh(catalog)?;
+                let right = inputs.right.refresh(catalog)?;
+                Ok(left || right)
+            }
+            LogicalPlan::Recursive(inputs) => {
+                let base = inputs.base.refresh(catalog)?;
+                let step = inputs.step.refresh(catalog)?;
+                Ok(base || step)
+            }
+        }
+    }
+
+    /// Returns a rough upper bound on the number of rows the plan produces, for choosing
+    /// between plans. Filters are assumed to keep every row. A recursive query's number of
+    /// runs is unknown, so its rows are only a guess.
+    pub(crate) fn estimated_rows(&self, catalog: &Catalog) -> Result<usize> {
+        let rows = match self {
+            LogicalPlan::Scan(table_info) => catalog.current_table(table_info)?.row_count()?,
+            LogicalPlan::SystemScan(table, _) => table.rows(catalog).len(),
+            LogicalPlan::CommonTableScan(table) => match &table.definition {
+                CommonTableDefinition::Query(definition) => {
+                    definition.read().unwrap().estimated_rows(catalog)?
+                }
+                CommonTableDefinition::Working { estimated_rows } => *estimated_rows,
+            },
+            LogicalPlan::Limit { input, skip, fetch } => {
+                let rows = input.estimated_rows(catalog)?.saturating_sub(*skip);
+                fetch.map_or(rows, |fetch| rows.min(fetch))
+            }
+            LogicalPlan::Filter { input, .. }
+            | LogicalPlan::Projection { input, .. }
+            | LogicalPlan::Sort { input, .. }
+            | LogicalPlan::Distinct { input, .. }
+            | LogicalPlan::Alias { input, .. } => input.estimated_rows(catalog)?,
+            LogicalPlan::Join(join) if !join.join_type.includes_right_columns() => {
+                join.left.estimated_rows(catalog)?
+            }
+            // Every pair of rows may match, and outer joins pad the rows that match nothing
+            LogicalPlan::Join(join) => {
+                let left = join.left.estimated_rows(catalog)?;
+                let right = join.right.estimated_rows(catalog)?;
+                left.saturating_mul(right).max(left.saturating_add(right))
+            }
+            // Without GROUP BY there is always exactly one row
+            LogicalPlan::Aggregate(aggregate) if aggregate.group_by.is_empty() => 1,
+            LogicalPlan::Aggregate(aggregate) => aggregate.input.estimated_rows(catalog)?,
+            LogicalPlan::SetOperation(inputs) => {
+                let left = inputs.left.estimated_rows(catalog)?;
+                let right = inputs.right.estimated_rows(catalog)?;
+                match inputs.operation {
+                    SetOperation::Union | SetOperation::UnionAll => left.saturating_add(right),
+                    SetOperation::Intersect => left.min(right),
+                    SetOperation::Except => left,
+                }
+            }
+            LogicalPlan::Window(window) => window.input.estimated_rows(catalog)?,
+            // Each run of the step is assumed to add about as many rows as it reads
+            LogicalPlan::Recursive(inputs) => {
+                let base = inputs.base.estimated_rows(catalog)?;
+                base.saturating_add(inputs.step.estimated_rows(catalog)?)
+            }
+        };
+        Ok(rows)
+    }
+
+    /// Adds the common tables this plan reads, through its nodes or their subqueries, to
+    /// `tables`, each after the ones its own query reads.
+    pub(crate) fn common_tables(&self, tables: &mut Vec<Arc<CommonTableInfo>>) {
+        let mut subqueries = |exprs: &mut dyn Iterator<Item = &Expression>| {
+            for expr in exprs {
+                for subquery in expr.subqueries() {
+                    subquery.dataframe().plan().common_tables(tables);
+                }
+            }
+        };
+        match self {
+            LogicalPlan::Filter { predicate, .. } => {
+                subqueries(&mut std::iter::once(predicate));
+            }
+            LogicalPlan::Projection { exprs, .. } => {
+                subqueries(&mut exprs.iter().map(|(expr, _)| expr));
+            }
+            LogicalPlan::Sort { keys, .. } => subqueries(&mut keys.iter().map(|key| &key.expr)),
+            _ => {}
+        }
+
+        match self {
+            LogicalPlan::Scan(_) | LogicalPlan::SystemScan(..) => {}
+            LogicalPlan::Filter { input, .. }
+            | LogicalPlan::Projection { input, .. }
+            | LogicalPlan::Sort { input, .. }
+            | LogicalPlan::Distinct { input, .. }
+            | LogicalPlan::Limit { input, .. }
+            | LogicalPlan::Alias { input, .. } => input.common_tables(tables),
+            LogicalPlan::Join(join) => {
+                join.left.common_tables(tables);
+                join.right.common_tables(tables);
+            }
+            LogicalPlan::Aggregate(aggregate) => aggregate.input.common_tables(tables),
+            LogicalPlan::SetOperation(inputs) => {
+                inputs.left.common_tables(tables);
+                inputs.right.common_tables(tables);
+            }
+            LogicalPlan::Window(window) => window.input.common_tables(tables),
+            LogicalPlan::Recursive(inputs) => {
+                inputs.base.common_tables(tables);
+                inputs.step.common_tables(tables);
+            }
+            LogicalPlan::CommonTableScan(table) => {
+                // A working table is filled in by its recursive executor instead
+                let CommonTableDefinition::Query(definition) = &table.definition else {
+                    return;
+                };
+                if !tables.iter().any(|other| Arc::ptr_eq(other, table)) {
+                    definition.read().unwrap().common_tables(tables);
+                    tables.push(table.clone());
+                }
+            }
+        }
+    }
+}
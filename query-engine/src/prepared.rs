@@ -0,0 +1,135 @@
+//! Prepared queries: bind and plan once, execute many times.
+//!
+//! Binding column names and building the executor tree happen when the query is prepared.
+//! Each execution only swaps in new parameter values and re-runs the existing tree.
+
+use crate::dataframe::DataFrame;
+use crate::executor::BoxedExecutor;
+use crate::expression::ParameterValues;
+use crate::types::{Row, Value};
+use crate::{QueryError, Result};
+use storage_engine::tuple::Schema;
+
+/// A query planned ahead of time that takes positional parameters.
+///
+/// Created with [`DataFrame::prepare`] or [`Database::prepare`](crate::Database::prepare).
+/// Arguments are passed as [`Value`]s, never spliced into SQL text.
+pub struct PreparedQuery {
+    df: DataFrame,
+    executor: BoxedExecutor,
+    schema: Schema,
+    params: ParameterValues,
+    parameter_count: usize,
+}
+
+impl PreparedQuery {
+    pub(crate) fn new(df: DataFrame) -> Result<Self> {
+        let params = ParameterValues::default();
+        let executor = df.build_executor(&params)?;
+        let schema = df.schema()?;
+        let parameter_count = df.parameter_count();
+        Ok(Self {
+            df,
+            executor,
+            schema,
+            params,
+            parameter_count,
+        })
+    }
+
+    /// Returns the number of arguments each execution expects.
+    pub fn parameter_count(&self) -> usize {
+        self.parameter_count
+    }
+
+    /// Returns the schema of the rows the query produces.
+    pub fn schema(&self) -> &Schema {
+        &self.schema
+    }
+
+    /// Runs the query with `args` as the values of `$1`, `$2`, ..., collecting all results.
+    ///
+    /// The plan is rebuilt only if its table has been rewritten (by an update or delete)
+    /// since it was last run.
+    pub fn execute(&mut self, args: &[Value]) -> Result<Vec<Row>> {
+        if args.len() != self.parameter_count {
+            return Err(QueryError::ExecutionError(format!(
+                "Query takes {} parameters but {} were supplied",
+                self.parameter_count,
+                args.len()
+            )));
+        }
+
+        if self.df.refresh()? {
+            self.executor = self.df.build_executor(&self.params)?;
+        }
+        self.params.set(args.to_vec());
+
+        self.executor.init()?;
+        let mut results = Vec::new();
+        while let Some(tuple) = self.executor.next()? {
+            results.push(tuple);
+        }
+        Ok(results)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::expression::{col, param};
+    use crate::types::Value;
+    use crate::{int_column, varchar_column, Database, Schema};
+
+    #[test]
+    fn test_prepared_query_reruns_with_new_arguments() {
+        let path = "test_prepared_dir";
+        let _ = std::fs::remove_dir_all(path);
+        let db = Database::open(path).unwrap();
+
+        let schema = Schema {
+            columns: vec![
+                int_column("id"),
+                varchar_column("name", 20),
+                int_column("age"),
+            ],
+        };
+        db.create_table("users", schema).unwrap();
+        db.execute("INSERT INTO users VALUES (1, 'Alice', 30), (2, 'Bob', 20), (3, 'Carol', 40)")
+            .unwrap();
+
+        let mut query = db
+            .table("users")
+            .unwrap()
+            .filter(col("age").gt(param(1)))
+            .select(&["name"])
+            .prepare()
+            .unwrap();
+        assert_eq!(query.parameter_count(), 1);
+        assert_eq!(query.execute(&[Value::Integer(25)]).unwrap().len(), 2);
+        assert_eq!(query.execute(&[Value::Integer(35)]).unwrap().len(), 1);
+        assert!(query.execute(&[]).is_err());
+
+        // The plan follows the table after its heap is rewritten
+        db.execute("DELETE FROM users WHERE id = 3").unwrap();
+        assert_eq!(query.execute(&[Value::Integer(35)]).unwrap().len(), 0);
+
+        // SQL placeholders take values, not text, so quotes are just data
+        let mut by_name = db.prepare("SELECT id FROM users WHERE name = $1").unwrap();
+        let rows = by_name
+            .execute(&[Value::Varchar("Bob".to_string())])
+            .unwrap();
+        assert_eq!(rows[0].values[0], Value::Integer(2));
+        let rows = by_name
+            .execute(&[Value::Varchar("x' OR '1' = '1".to_string())])
+            .unwrap();
+        assert!(rows.is_empty());
+
+        // A table replaced by one with other columns no longer fits the plan
+        db.execute("DROP TABLE users").unwrap();
+        db.execute("CREATE TABLE users (name VARCHAR(20), id INTEGER)").unwrap();
+        let err = query.execute(&[Value::Integer(25)]).unwrap_err();
+        assert!(err.to_string().contains("changed since the query was prepared"));
+
+        std::fs::remove_dir_all(path).unwrap();
+    }
+}
@@ -0,0 +1,320 @@
+//! Abstract syntax tree for the supported SQL subset.
+
+use crate::expression::{BinaryOperator, UnaryOperator};
+use crate::types::Value;
+use crate::{QueryError, Result, SourceLocation};
+use std::fmt;
+
+/// A single SQL statement.
+#[derive(Debug, Clone)]
+pub enum Statement {
+    /// `SELECT ...`
+    Select(Select),
+    /// `CREATE TABLE [IF NOT EXISTS] name (column type, ...)`
+    CreateTable(CreateTable),
+    /// `DROP TABLE [IF EXISTS] name`
+    DropTable { name: String, if_exists: bool },
+    /// `INSERT INTO name [(columns)] VALUES ... | SELECT ...`
+    Insert(Insert),
+    /// `UPDATE name SET column = expr, ... [WHERE ...]`
+    Update(Update),
+    /// `DELETE FROM name [WHERE ...]`
+    Delete {
+        table: TableRef,
+        selection: Option<Expr>,
+    },
+}
+
+impl Statement {
+    /// Returns the number of positional parameters, i.e. the highest `$n` referenced.
+    pub fn parameter_count(&self) -> usize {
+        let mut exprs = Vec::new();
+        self.collect_exprs(&mut exprs);
+        exprs
+            .iter()
+            .map(|expr| expr.parameter_count())
+            .max()
+            .unwrap_or(0)
+    }
+
+    /// Replaces every `$n` placeholder with the literal value of `args[n - 1]`.
+    ///
+    /// Values are substituted into the syntax tree rather than the SQL text, so they can never
+    /// change the structure of the statement.
+    pub fn bind_parameters(&mut self, args: &[Value]) -> Result<()> {
+        let expected = self.parameter_count();
+        if args.len() != expected {
+            return Err(QueryError::ExecutionError(format!(
+                "Statement takes {} parameters but {} were supplied",
+                expected,
+                args.len()
+            )));
+        }
+        if expected == 0 {
+            return Ok(());
+        }
+
+        let mut exprs = Vec::new();
+        self.collect_exprs_mut(&mut exprs);
+        for expr in exprs {
+            expr.bind_parameters(args);
+        }
+        Ok(())
+    }
+
+    /// Collects every top-level expression in the statement.
+    pub(crate) fn collect_exprs<'a>(&'a self, out: &mut Vec<&'a Expr>) {
+        match self {
+            Statement::Select(select) => select.collect_exprs(out),
+            Statement::CreateTable(_) | Statement::DropTable { .. } => {}
+            Statement::Insert(insert) => match &insert.source {
+                InsertSource::Values(rows) => out.extend(rows.iter().flatten()),
+                InsertSource::Select(select) => select.collect_exprs(out),
+            },
+            Statement::Update(update) => {
+                out.extend(update.assignments.iter().map(|(_, expr)| expr));
+                out.extend(&update.selection);
+            }
+            Statement::Delete { selection, .. } => out.extend(selection),
+        }
+    }
+
+    fn collect_exprs_mut<'a>(&'a mut self, out: &mut Vec<&'a mut Expr>) {
+        match self {
+            Statement::Select(select) => select.collect_exprs_mut(out),
+            Statement::CreateTable(_) | Statement::DropTable { .. } => {}
+            Statement::Insert(insert) => match &mut insert.source {
+                InsertSource::Values(rows) => out.extend(rows.iter_mut().flatten()),
+                InsertSource::Select(select) => select.collect_exprs_mut(out),
+            },
+            Statement::Update(update) => {
+                out.extend(update.assignments.iter_mut().map(|(_, expr)| expr));
+                out.extend(&mut update.selection);
+            }
+            Statement::Delete { selection, .. } => out.extend(selection),
+        }
+    }
+}
+
+/// An `INSERT` statement.
+#[derive(Debug, Clone)]
+pub struct Insert {
+    pub table: TableRef,
+    /// Explicit target columns, or `None` for all columns in table order
+    pub columns: Option<Vec<String>>,
+    pub source: InsertSource,
+}
+
+/// Where an `INSERT` gets its rows from.
+#[derive(Debug, Clone)]
+pub enum InsertSource {
+    /// `VALUES (...), (...)`
+    Values(Vec<Vec<Expr>>),
+    /// `SELECT ...`
+    Select(Box<Select>),
+}
+
+/// An `UPDATE` statement.
+#[derive(Debug, Clone)]
+pub struct Update {
+    pub table: TableRef,
+    pub assignments: Vec<(String, Expr)>,
+    pub selection: Option<Expr>,
+}
+
+/// A `CREATE TABLE` statement.
+#[derive(Debug, Clone)]
+pub struct CreateTable {
+    pub name: String,
+    pub columns: Vec<ColumnDef>,
+    pub if_not_exists: bool,
+}
+
+/// A column definition inside `CREATE TABLE`.
+#[derive(Debug, Clone)]
+pub struct ColumnDef {
+    pub name: String,
+    pub data_type: DataType,
+}
+
+/// A SQL column type.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DataType {
+    /// `INTEGER` / `INT`
+    Integer,
+    /// `VARCHAR(n)`
+    Varchar(u32),
+}
+
+/// A `SELECT` query.
+#[derive(Debug, Clone)]
+pub struct Select {
+    pub projection: Vec<SelectItem>,
+    pub from: TableRef,
+    pub selection: Option<Expr>,
+    pub limit: Option<u64>,
+    pub offset: Option<u64>,
+}
+
+impl Select {
+    fn collect_exprs<'a>(&'a self, out: &mut Vec<&'a Expr>) {
+        for item in &self.projection {
+            if let SelectItem::Expr { expr, .. } = item {
+                out.push(expr);
+            }
+        }
+        out.extend(&self.selection);
+    }
+
+    fn collect_exprs_mut<'a>(&'a mut self, out: &mut Vec<&'a mut Expr>) {
+        for item in &mut self.projection {
+            if let SelectItem::Expr { expr, .. } = item {
+                out.push(expr);
+            }
+        }
+        out.extend(&mut self.selection);
+    }
+}
+
+/// One entry of a `SELECT` list.
+#[derive(Debug, Clone)]
+pub enum SelectItem {
+    /// `*`
+    Wildcard,
+    /// An expression with an optional `AS` alias
+    Expr { expr: Expr, alias: Option<String> },
+}
+
+/// A table named in a `FROM` clause or as the target of a write.
+#[derive(Debug, Clone)]
+pub struct TableRef {
+    pub name: String,
+    pub alias: Option<String>,
+    /// Where the table name appears in the SQL text
+    pub span: Span,
+}
+
+impl TableRef {
+    /// Returns the name this table is referred to by in the rest of the query.
+    pub fn reference_name(&self) -> &str {
+        self.alias.as_deref().unwrap_or(&self.name)
+    }
+}
+
+/// A byte range of the SQL text that a syntax node was parsed from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Span {
+    pub offset: usize,
+    pub len: usize,
+}
+
+impl Span {
+    /// Resolves the span to a line, column and snippet within `sql`.
+    pub fn locate(&self, sql: &str) -> SourceLocation {
+        SourceLocation::new(sql, self.offset, self.len)
+    }
+}
+
+/// A scalar SQL expression.
+#[derive(Debug, Clone)]
+pub enum Expr {
+    /// Column reference, optionally qualified by a table name or alias
+    Column {
+        table: Option<String>,
+        name: String,
+        span: Span,
+    },
+    /// Integer literal
+    Integer(i64),
+    /// String literal
+    String(String),
+    /// `NULL`
+    Null,
+    /// Positional parameter (`$1`, `$2`, ...), numbered from 1
+    Parameter(usize),
+    /// Binary operation
+    Binary {
+        left: Box<Expr>,
+        op: BinaryOperator,
+        right: Box<Expr>,
+    },
+    /// Unary operation (`NOT`, `IS NULL`, `IS NOT NULL`)
+    Unary { op: UnaryOperator, expr: Box<Expr> },
+    /// Arithmetic negation (`-expr`)
+    Negate(Box<Expr>),
+    /// Parenthesized expression
+    Nested(Box<Expr>),
+}
+
+impl Expr {
+    /// Returns the highest parameter number referenced in this expression, or 0 if none.
+    pub fn parameter_count(&self) -> usize {
+        match self {
+            Expr::Parameter(n) => *n,
+            Expr::Binary { left, right, .. } => left.parameter_count().max(right.parameter_count()),
+            Expr::Unary { expr, .. } | Expr::Negate(expr) | Expr::Nested(expr) => {
+                expr.parameter_count()
+            }
+            Expr::Column { .. } | Expr::Integer(_) | Expr::String(_) | Expr::Null => 0,
+        }
+    }
+
+    /// Replaces `$n` placeholders with literals taken from `args`.
+    ///
+    /// The caller checks that `args` covers every parameter.
+    fn bind_parameters(&mut self, args: &[Value]) {
+        match self {
+            Expr::Parameter(n) => {
+                *self = match &args[*n - 1] {
+                    Value::Integer(i) => Expr::Integer(*i as i64),
+                    Value::Varchar(s) => Expr::String(s.clone()),
+                    Value::Null => Expr::Null,
+                };
+            }
+            Expr::Binary { left, right, .. } => {
+                left.bind_parameters(args);
+                right.bind_parameters(args);
+            }
+            Expr::Unary { expr, .. } | Expr::Negate(expr) | Expr::Nested(expr) => {
+                expr.bind_parameters(args)
+            }
+            Expr::Column { .. } | Expr::Integer(_) | Expr::String(_) | Expr::Null => {}
+        }
+    }
+}
+
+impl fmt::Display for Expr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Expr::Column {
+                table: Some(table),
+                name,
+                ..
+            } => write!(f, "{}.{}", table, name),
+            Expr::Column {
+                table: None, name, ..
+            } => write!(f, "{}", name),
+            Expr::Integer(i) => write!(f, "{}", i),
+            Expr::String(s) => write!(f, "'{}'", s.replace('\'', "''")),
+            Expr::Null => write!(f, "NULL"),
+            Expr::Parameter(n) => write!(f, "${}", n),
+            Expr::Binary { left, op, right } => {
+                write!(f, "{} {} {}", left, op.symbol(), right)
+            }
+            Expr::Unary {
+                op: UnaryOperator::Not,
+                expr,
+            } => write!(f, "NOT {}", expr),
+            Expr::Unary {
+                op: UnaryOperator::IsNull,
+                expr,
+            } => write!(f, "{} IS NULL", expr),
+            Expr::Unary {
+                op: UnaryOperator::IsNotNull,
+                expr,
+            } => write!(f, "{} IS NOT NULL", expr),
+            Expr::Negate(expr) => write!(f, "-{}", expr),
+            Expr::Nested(expr) => write!(f, "({})", expr),
+        }
+    }
+}
@@ -0,0 +1,304 @@
+//! SQL tokenizer.
+//!
+//! Turns SQL text into a flat list of tokens. Keywords are not distinguished from identifiers
+//! here; the parser matches them case-insensitively.
+
+use crate::{QueryError, Result, SourceLocation};
+
+/// The kind of a lexical token.
+#[derive(Debug, Clone, PartialEq)]
+pub enum TokenKind {
+    /// Bare identifier or keyword, as written
+    Ident(String),
+    /// Double-quoted identifier (never treated as a keyword)
+    QuotedIdent(String),
+    /// Integer literal
+    Number(i64),
+    /// Positional parameter placeholder (`$1`, `$2`, ...)
+    Parameter(usize),
+    /// Single-quoted string literal
+    String(String),
+    Comma,
+    Dot,
+    LParen,
+    RParen,
+    Semicolon,
+    Star,
+    Plus,
+    Minus,
+    Slash,
+    Eq,
+    NotEq,
+    Lt,
+    LtEq,
+    Gt,
+    GtEq,
+    /// End of input
+    Eof,
+}
+
+/// A token together with its byte range in the source text.
+#[derive(Debug, Clone)]
+pub struct Token {
+    pub kind: TokenKind,
+    pub offset: usize,
+    pub len: usize,
+}
+
+/// Splits SQL text into tokens, ending with a single `Eof` token.
+pub fn tokenize(sql: &str) -> Result<Vec<Token>> {
+    let bytes = sql.as_bytes();
+    let mut tokens = Vec::new();
+    let mut pos = 0;
+
+    while pos < bytes.len() {
+        let c = bytes[pos];
+        let start = pos;
+
+        // Whitespace and comments
+        if c.is_ascii_whitespace() {
+            pos += 1;
+            continue;
+        }
+        if c == b'-' && bytes.get(pos + 1) == Some(&b'-') {
+            while pos < bytes.len() && bytes[pos] != b'\n' {
+                pos += 1;
+            }
+            continue;
+        }
+        if c == b'/' && bytes.get(pos + 1) == Some(&b'*') {
+            pos += 2;
+            loop {
+                if pos + 1 >= bytes.len() {
+                    return Err(error_at(sql, start, 2, "unterminated block comment"));
+                }
+                if bytes[pos] == b'*' && bytes[pos + 1] == b'/' {
+                    pos += 2;
+                    break;
+                }
+                pos += 1;
+            }
+            continue;
+        }
+
+        let kind = if c.is_ascii_alphabetic() || c == b'_' {
+            while pos < bytes.len() && (bytes[pos].is_ascii_alphanumeric() || bytes[pos] == b'_') {
+                pos += 1;
+            }
+            TokenKind::Ident(sql[start..pos].to_string())
+        } else if c.is_ascii_digit() {
+            while pos < bytes.len() && bytes[pos].is_ascii_digit() {
+                pos += 1;
+            }
+            let number = sql[start..pos]
+                .parse::<i64>()
+                .map_err(|_| error_at(sql, start, pos - start, "integer literal out of range"))?;
+            TokenKind::Number(number)
+        } else if c == b'$' {
+            pos += 1;
+            while pos < bytes.len() && bytes[pos].is_ascii_digit() {
+                pos += 1;
+            }
+            let number = sql[start + 1..pos]
+                .parse::<usize>()
+                .ok()
+                .filter(|n| *n >= 1)
+                .ok_or_else(|| error_at(sql, start, pos - start, "expected a parameter number such as $1"))?;
+            TokenKind::Parameter(number)
+        } else if c == b'\'' {
+            let (value, end) = read_quoted(sql, start, b'\'')?;
+            pos = end;
+            TokenKind::String(value)
+        } else if c == b'"' {
+            let (value, end) = read_quoted(sql, start, b'"')?;
+            pos = end;
+            TokenKind::QuotedIdent(value)
+        } else {
+            let next = bytes.get(pos + 1).copied();
+            let (kind, len) = match (c, next) {
+                (b'<', Some(b'=')) => (TokenKind::LtEq, 2),
+                (b'<', Some(b'>')) => (TokenKind::NotEq, 2),
+                (b'>', Some(b'=')) => (TokenKind::GtEq, 2),
+                (b'!', Some(b'=')) => (TokenKind::NotEq, 2),
+                (b',', _) => (TokenKind::Comma, 1),
+                (b'.', _) => (TokenKind::Dot, 1),
+                (b'(', _) => (TokenKind::LParen, 1),
+                (b')', _) => (TokenKind::RParen, 1),
+                (b';', _) => (TokenKind::Semicolon, 1),
+                (b'*', _) => (TokenKind::Star, 1),
+                (b'+', _) => (TokenKind::Plus, 1),
+                (b'-', _) => (TokenKind::Minus, 1),
+                (b'/', _) => (TokenKind::Slash, 1),
+                (b'=', _) => (TokenKind::Eq, 1),
+                (b'<', _) => (TokenKind::Lt, 1),
+                (b'>', _) => (TokenKind::Gt, 1),
+                _ => {
+                    let ch = sql[start..].chars().next().unwrap();
+                    return Err(error_at(
+                        sql,
+                        start,
+                        ch.len_utf8(),
+                        &format!("unexpected character '{}'", ch),
+                    ));
+                }
+            };
+            pos += len;
+            kind
+        };
+
+        tokens.push(Token {
+            kind,
+            offset: start,
+            len: pos - start,
+        });
+    }
+
+    tokens.push(Token {
+        kind: TokenKind::Eof,
+        offset: sql.len(),
+        len: 0,
+    });
+    Ok(tokens)
+}
+
+/// Reads a quoted string or identifier starting at `start`, where a doubled quote is an
+/// escaped quote. Returns the unescaped contents and the offset just past the closing quote.
+fn read_quoted(sql: &str, start: usize, quote: u8) -> Result<(String, usize)> {
+    let bytes = sql.as_bytes();
+    let mut value = String::new();
+    let mut pos = start + 1;
+    let mut chunk_start = pos;
+
+    loop {
+        if pos >= bytes.len() {
+            return Err(error_at(sql, start, 1, "unterminated quoted string"));
+        }
+        if bytes[pos] == quote {
+            value.push_str(&sql[chunk_start..pos]);
+            if bytes.get(pos + 1) == Some(&quote) {
+                value.push(quote as char);
+                pos += 2;
+                chunk_start = pos;
+                continue;
+            }
+            return Ok((value, pos + 1));
+        }
+        pos += 1;
+    }
+}
+
+/// Splits a script into statements at top-level semicolons.
+///
+/// Semicolons inside string literals, quoted identifiers and comments do not end a statement.
+/// Each statement comes back with the byte offset it starts at and without its terminating
+/// semicolon; empty statements (only whitespace and comments) are dropped. Malformed text
+/// such as an unterminated string is left for the parser to report.
+pub fn split_statements(sql: &str) -> Vec<(usize, &str)> {
+    let bytes = sql.as_bytes();
+    let mut statements = Vec::new();
+    let mut start = None;
+    let mut pos = 0;
+
+    while pos < bytes.len() {
+        let c = bytes[pos];
+        let next = bytes.get(pos + 1).copied();
+
+        if c == b';' {
+            if let Some(start) = start.take() {
+                statements.push((start, &sql[start..pos]));
+            }
+            pos += 1;
+        } else if c.is_ascii_whitespace() {
+            pos += 1;
+        } else if c == b'-' && next == Some(b'-') {
+            while pos < bytes.len() && bytes[pos] != b'\n' {
+                pos += 1;
+            }
+        } else if c == b'/' && next == Some(b'*') {
+            pos = sql[pos + 2..]
+                .find("*/")
+                .map_or(bytes.len(), |end| pos + 2 + end + 2);
+        } else {
+            start.get_or_insert(pos);
+            pos += 1;
+            if c == b'\'' || c == b'"' {
+                // A doubled quote closes and reopens the string, so no escape handling is needed
+                while pos < bytes.len() && bytes[pos] != c {
+                    pos += 1;
+                }
+                pos += 1;
+            }
+        }
+    }
+
+    if let Some(start) = start {
+        statements.push((start, &sql[start..]));
+    }
+    statements
+}
+
+/// Converts a byte offset into a 1-based (line, column) pair.
+pub fn line_col(sql: &str, offset: usize) -> (usize, usize) {
+    let before = &sql[..offset.min(sql.len())];
+    let line = before.matches('\n').count() + 1;
+    let column = before.rsplit('\n').next().unwrap_or("").chars().count() + 1;
+    (line, column)
+}
+
+/// Builds a parse error pointing at the `len` bytes starting at `offset`.
+pub(crate) fn error_at(sql: &str, offset: usize, len: usize, message: &str) -> QueryError {
+    QueryError::ParseError {
+        message: message.to_string(),
+        location: SourceLocation::new(sql, offset, len),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tokenize_select() {
+        let tokens = tokenize("SELECT name FROM users WHERE age >= 25 -- adults\n").unwrap();
+        let kinds: Vec<_> = tokens.into_iter().map(|t| t.kind).collect();
+        assert_eq!(
+            kinds,
+            vec![
+                TokenKind::Ident("SELECT".to_string()),
+                TokenKind::Ident("name".to_string()),
+                TokenKind::Ident("FROM".to_string()),
+                TokenKind::Ident("users".to_string()),
+                TokenKind::Ident("WHERE".to_string()),
+                TokenKind::Ident("age".to_string()),
+                TokenKind::GtEq,
+                TokenKind::Number(25),
+                TokenKind::Eof,
+            ]
+        );
+    }
+
+    #[test]
+    fn test_tokenize_string_escape_and_error_position() {
+        let tokens = tokenize("'it''s'").unwrap();
+        assert_eq!(tokens[0].kind, TokenKind::String("it's".to_string()));
+
+        let err = tokenize("SELECT\n  'oops").unwrap_err();
+        assert!(err.to_string().contains("line 2, col 3"));
+    }
+
+    #[test]
+    fn test_split_statements() {
+        let script = "-- seed\nCREATE TABLE t (s VARCHAR(10));\n\nINSERT INTO t VALUES ('a;b'), ('it''s') /* ; */;\n  ;\nSELECT s FROM t";
+        let statements = split_statements(script);
+        assert_eq!(statements.len(), 3);
+        assert_eq!(statements[0], (8, "CREATE TABLE t (s VARCHAR(10))"));
+        assert_eq!(
+            statements[1].1,
+            "INSERT INTO t VALUES ('a;b'), ('it''s') /* ; */"
+        );
+        assert_eq!(line_col(script, statements[1].0), (4, 1));
+        assert_eq!(statements[2].1, "SELECT s FROM t");
+
+        assert!(split_statements(" ; -- nothing here\n").is_empty());
+    }
+}
@@ -0,0 +1,35 @@
+//! SQL frontend.
+//!
+//! SQL text is tokenized by the [`lexer`], parsed into an AST by the [`parser`], and lowered
+//! into DataFrame calls by the planner. Queries then run through the same executor tree as
+//! hand-built DataFrame queries.
+
+pub mod ast;
+pub mod lexer;
+pub mod parser;
+mod planner;
+
+pub use lexer::split_statements;
+pub use parser::parse;
+pub(crate) use planner::{
+    execute_delete, execute_insert, execute_update, parameter_types, plan_create_table, plan_select,
+};
+
+use crate::types::Row;
+use storage_engine::tuple::Schema;
+
+/// The outcome of executing a single SQL statement.
+pub enum StatementResult {
+    /// Rows produced by a query, with the schema describing their columns
+    Query { schema: Schema, rows: Vec<Row> },
+    /// `CREATE TABLE` finished (including a no-op `IF NOT EXISTS`)
+    CreateTable,
+    /// `DROP TABLE` finished (including a no-op `IF EXISTS`)
+    DropTable,
+    /// Number of rows written by `INSERT`
+    Insert(usize),
+    /// Number of rows changed by `UPDATE`
+    Update(usize),
+    /// Number of rows removed by `DELETE`
+    Delete(usize),
+}
@@ -0,0 +1,698 @@
+//! Recursive-descent SQL parser.
+//!
+//! Parses the token stream produced by the [`lexer`](super::lexer) into a [`Statement`].
+
+use super::ast::{
+    ColumnDef, CreateTable, DataType, Expr, Insert, InsertSource, Select, SelectItem, Span,
+    Statement, TableRef, Update,
+};
+use super::lexer::{error_at, tokenize, Token, TokenKind};
+use crate::expression::{BinaryOperator, UnaryOperator};
+use crate::{QueryError, Result};
+
+/// Keywords that cannot be used as bare identifiers or implicit aliases.
+const RESERVED: &[&str] = &[
+    "SELECT", "FROM", "WHERE", "LIMIT", "OFFSET", "AND", "OR", "NOT", "IS", "NULL", "AS", "SET",
+    "VALUES",
+];
+
+/// Parses a single SQL statement, optionally terminated by a semicolon.
+pub fn parse(sql: &str) -> Result<Statement> {
+    let mut parser = Parser::new(sql)?;
+    let statement = parser.parse_statement()?;
+    parser.consume(&TokenKind::Semicolon);
+    parser.expect_eof()?;
+    Ok(statement)
+}
+
+struct Parser<'a> {
+    sql: &'a str,
+    tokens: Vec<Token>,
+    pos: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn new(sql: &'a str) -> Result<Self> {
+        Ok(Self {
+            sql,
+            tokens: tokenize(sql)?,
+            pos: 0,
+        })
+    }
+
+    fn parse_statement(&mut self) -> Result<Statement> {
+        if self.is_keyword("SELECT") {
+            return Ok(Statement::Select(self.parse_select()?));
+        }
+        if self.parse_keyword("CREATE") {
+            return self.parse_create_table();
+        }
+        if self.parse_keyword("DROP") {
+            return self.parse_drop_table();
+        }
+        if self.parse_keyword("INSERT") {
+            return self.parse_insert();
+        }
+        if self.parse_keyword("UPDATE") {
+            return self.parse_update();
+        }
+        if self.parse_keyword("DELETE") {
+            return self.parse_delete();
+        }
+        Err(self.error("expected a statement"))
+    }
+
+    fn parse_insert(&mut self) -> Result<Statement> {
+        self.expect_keyword("INTO")?;
+        let table = self.parse_table_name()?;
+
+        let columns = if self.consume(&TokenKind::LParen) {
+            let mut columns = vec![self.parse_identifier()?];
+            while self.consume(&TokenKind::Comma) {
+                columns.push(self.parse_identifier()?);
+            }
+            self.expect(&TokenKind::RParen)?;
+            Some(columns)
+        } else {
+            None
+        };
+
+        let source = if self.parse_keyword("VALUES") {
+            let mut rows = vec![self.parse_values_row()?];
+            while self.consume(&TokenKind::Comma) {
+                rows.push(self.parse_values_row()?);
+            }
+            InsertSource::Values(rows)
+        } else if self.is_keyword("SELECT") {
+            InsertSource::Select(Box::new(self.parse_select()?))
+        } else {
+            return Err(self.error("expected VALUES or SELECT"));
+        };
+
+        Ok(Statement::Insert(Insert {
+            table,
+            columns,
+            source,
+        }))
+    }
+
+    fn parse_values_row(&mut self) -> Result<Vec<Expr>> {
+        self.expect(&TokenKind::LParen)?;
+        let mut values = vec![self.parse_expr()?];
+        while self.consume(&TokenKind::Comma) {
+            values.push(self.parse_expr()?);
+        }
+        self.expect(&TokenKind::RParen)?;
+        Ok(values)
+    }
+
+    fn parse_update(&mut self) -> Result<Statement> {
+        let table = self.parse_table_name()?;
+        self.expect_keyword("SET")?;
+
+        let mut assignments = Vec::new();
+        loop {
+            let column = self.parse_identifier()?;
+            self.expect(&TokenKind::Eq)?;
+            assignments.push((column, self.parse_expr()?));
+            if !self.consume(&TokenKind::Comma) {
+                break;
+            }
+        }
+
+        let selection = self.parse_where()?;
+        Ok(Statement::Update(Update {
+            table,
+            assignments,
+            selection,
+        }))
+    }
+
+    fn parse_delete(&mut self) -> Result<Statement> {
+        self.expect_keyword("FROM")?;
+        let table = self.parse_table_name()?;
+        let selection = self.parse_where()?;
+        Ok(Statement::Delete { table, selection })
+    }
+
+    /// Parses an optional `WHERE` clause.
+    fn parse_where(&mut self) -> Result<Option<Expr>> {
+        if self.parse_keyword("WHERE") {
+            Ok(Some(self.parse_expr()?))
+        } else {
+            Ok(None)
+        }
+    }
+
+    fn parse_create_table(&mut self) -> Result<Statement> {
+        self.expect_keyword("TABLE")?;
+        let if_not_exists = if self.parse_keyword("IF") {
+            self.expect_keyword("NOT")?;
+            self.expect_keyword("EXISTS")?;
+            true
+        } else {
+            false
+        };
+        let name = self.parse_identifier()?;
+
+        self.expect(&TokenKind::LParen)?;
+        let mut columns = vec![self.parse_column_def()?];
+        while self.consume(&TokenKind::Comma) {
+            columns.push(self.parse_column_def()?);
+        }
+        self.expect(&TokenKind::RParen)?;
+
+        Ok(Statement::CreateTable(CreateTable {
+            name,
+            columns,
+            if_not_exists,
+        }))
+    }
+
+    fn parse_column_def(&mut self) -> Result<ColumnDef> {
+        let name = self.parse_identifier()?;
+        let data_type = if self.parse_keyword("INTEGER") || self.parse_keyword("INT") {
+            DataType::Integer
+        } else if self.parse_keyword("VARCHAR") {
+            self.expect(&TokenKind::LParen)?;
+            let length = self.parse_unsigned()?;
+            if length == 0 || length > u32::MAX as u64 {
+                return Err(self.error("VARCHAR length must be between 1 and 4294967295"));
+            }
+            self.expect(&TokenKind::RParen)?;
+            DataType::Varchar(length as u32)
+        } else {
+            return Err(self.error("expected a column type (INTEGER or VARCHAR(n))"));
+        };
+        Ok(ColumnDef { name, data_type })
+    }
+
+    fn parse_drop_table(&mut self) -> Result<Statement> {
+        self.expect_keyword("TABLE")?;
+        let if_exists = if self.parse_keyword("IF") {
+            self.expect_keyword("EXISTS")?;
+            true
+        } else {
+            false
+        };
+        let name = self.parse_identifier()?;
+        Ok(Statement::DropTable { name, if_exists })
+    }
+
+    fn parse_select(&mut self) -> Result<Select> {
+        self.expect_keyword("SELECT")?;
+
+        let mut projection = vec![self.parse_select_item()?];
+        while self.consume(&TokenKind::Comma) {
+            projection.push(self.parse_select_item()?);
+        }
+
+        self.expect_keyword("FROM")?;
+        let from = self.parse_table_ref()?;
+
+        let selection = self.parse_where()?;
+
+        // LIMIT and OFFSET may come in either order, as in PostgreSQL
+        let mut limit = None;
+        let mut offset = None;
+        loop {
+            if limit.is_none() && self.parse_keyword("LIMIT") {
+                limit = Some(self.parse_unsigned()?);
+            } else if offset.is_none() && self.parse_keyword("OFFSET") {
+                offset = Some(self.parse_unsigned()?);
+            } else {
+                break;
+            }
+        }
+
+        Ok(Select {
+            projection,
+            from,
+            selection,
+            limit,
+            offset,
+        })
+    }
+
+    fn parse_select_item(&mut self) -> Result<SelectItem> {
+        if self.consume(&TokenKind::Star) {
+            return Ok(SelectItem::Wildcard);
+        }
+        let expr = self.parse_expr()?;
+        let alias = self.parse_alias()?;
+        Ok(SelectItem::Expr { expr, alias })
+    }
+
+    fn parse_table_ref(&mut self) -> Result<TableRef> {
+        let mut table = self.parse_table_name()?;
+        table.alias = self.parse_alias()?;
+        Ok(table)
+    }
+
+    /// Parses a table name that cannot be aliased, as in `INSERT INTO name`.
+    fn parse_table_name(&mut self) -> Result<TableRef> {
+        let start = self.peek().offset;
+        let name = self.parse_identifier()?;
+        Ok(TableRef {
+            name,
+            alias: None,
+            span: self.span_from(start),
+        })
+    }
+
+    /// Parses `AS alias` or a bare non-reserved identifier used as an alias.
+    fn parse_alias(&mut self) -> Result<Option<String>> {
+        if self.parse_keyword("AS") {
+            return Ok(Some(self.parse_identifier()?));
+        }
+        match &self.peek().kind {
+            TokenKind::QuotedIdent(_) => Ok(Some(self.parse_identifier()?)),
+            TokenKind::Ident(word) if !is_reserved(word) => Ok(Some(self.parse_identifier()?)),
+            _ => Ok(None),
+        }
+    }
+
+    // ===== Expressions (lowest to highest precedence) =====
+
+    fn parse_expr(&mut self) -> Result<Expr> {
+        self.parse_or()
+    }
+
+    fn parse_or(&mut self) -> Result<Expr> {
+        let mut left = self.parse_and()?;
+        while self.parse_keyword("OR") {
+            let right = self.parse_and()?;
+            left = binary(left, BinaryOperator::Or, right);
+        }
+        Ok(left)
+    }
+
+    fn parse_and(&mut self) -> Result<Expr> {
+        let mut left = self.parse_not()?;
+        while self.parse_keyword("AND") {
+            let right = self.parse_not()?;
+            left = binary(left, BinaryOperator::And, right);
+        }
+        Ok(left)
+    }
+
+    fn parse_not(&mut self) -> Result<Expr> {
+        if self.parse_keyword("NOT") {
+            let expr = self.parse_not()?;
+            return Ok(Expr::Unary {
+                op: UnaryOperator::Not,
+                expr: Box::new(expr),
+            });
+        }
+        self.parse_comparison()
+    }
+
+    fn parse_comparison(&mut self) -> Result<Expr> {
+        let mut left = self.parse_additive()?;
+
+        let op = match self.peek().kind {
+            TokenKind::Eq => Some(BinaryOperator::Eq),
+            TokenKind::NotEq => Some(BinaryOperator::NotEq),
+            TokenKind::Lt => Some(BinaryOperator::Lt),
+            TokenKind::LtEq => Some(BinaryOperator::LtEq),
+            TokenKind::Gt => Some(BinaryOperator::Gt),
+            TokenKind::GtEq => Some(BinaryOperator::GtEq),
+            _ => None,
+        };
+        if let Some(op) = op {
+            self.advance();
+            let right = self.parse_additive()?;
+            left = binary(left, op, right);
+        }
+
+        while self.parse_keyword("IS") {
+            let op = if self.parse_keyword("NOT") {
+                UnaryOperator::IsNotNull
+            } else {
+                UnaryOperator::IsNull
+            };
+            self.expect_keyword("NULL")?;
+            left = Expr::Unary {
+                op,
+                expr: Box::new(left),
+            };
+        }
+
+        Ok(left)
+    }
+
+    fn parse_additive(&mut self) -> Result<Expr> {
+        let mut left = self.parse_multiplicative()?;
+        loop {
+            let op = match self.peek().kind {
+                TokenKind::Plus => BinaryOperator::Add,
+                TokenKind::Minus => BinaryOperator::Subtract,
+                _ => return Ok(left),
+            };
+            self.advance();
+            let right = self.parse_multiplicative()?;
+            left = binary(left, op, right);
+        }
+    }
+
+    fn parse_multiplicative(&mut self) -> Result<Expr> {
+        let mut left = self.parse_unary()?;
+        loop {
+            let op = match self.peek().kind {
+                TokenKind::Star => BinaryOperator::Multiply,
+                TokenKind::Slash => BinaryOperator::Divide,
+                _ => return Ok(left),
+            };
+            self.advance();
+            let right = self.parse_unary()?;
+            left = binary(left, op, right);
+        }
+    }
+
+    fn parse_unary(&mut self) -> Result<Expr> {
+        if self.consume(&TokenKind::Minus) {
+            return Ok(Expr::Negate(Box::new(self.parse_unary()?)));
+        }
+        if self.consume(&TokenKind::Plus) {
+            return self.parse_unary();
+        }
+        self.parse_primary()
+    }
+
+    fn parse_primary(&mut self) -> Result<Expr> {
+        match self.peek().kind.clone() {
+            TokenKind::Number(n) => {
+                self.advance();
+                Ok(Expr::Integer(n))
+            }
+            TokenKind::Parameter(n) => {
+                self.advance();
+                Ok(Expr::Parameter(n))
+            }
+            TokenKind::String(s) => {
+                self.advance();
+                Ok(Expr::String(s))
+            }
+            TokenKind::LParen => {
+                self.advance();
+                let expr = self.parse_expr()?;
+                self.expect(&TokenKind::RParen)?;
+                Ok(Expr::Nested(Box::new(expr)))
+            }
+            TokenKind::Ident(word) if word.eq_ignore_ascii_case("NULL") => {
+                self.advance();
+                Ok(Expr::Null)
+            }
+            TokenKind::Ident(_) | TokenKind::QuotedIdent(_) => {
+                let start = self.peek().offset;
+                let first = self.parse_identifier()?;
+                if self.consume(&TokenKind::Dot) {
+                    let name = self.parse_identifier()?;
+                    Ok(Expr::Column {
+                        table: Some(first),
+                        name,
+                        span: self.span_from(start),
+                    })
+                } else {
+                    Ok(Expr::Column {
+                        table: None,
+                        name: first,
+                        span: self.span_from(start),
+                    })
+                }
+            }
+            _ => Err(self.error("expected an expression")),
+        }
+    }
+
+    // ===== Token helpers =====
+
+    fn peek(&self) -> &Token {
+        &self.tokens[self.pos]
+    }
+
+    fn advance(&mut self) {
+        if self.pos < self.tokens.len() - 1 {
+            self.pos += 1;
+        }
+    }
+
+    /// Consumes the current token if it matches `kind`.
+    fn consume(&mut self, kind: &TokenKind) -> bool {
+        if &self.peek().kind == kind {
+            self.advance();
+            true
+        } else {
+            false
+        }
+    }
+
+    fn expect(&mut self, kind: &TokenKind) -> Result<()> {
+        if self.consume(kind) {
+            Ok(())
+        } else {
+            Err(self.error(&format!("expected {}", describe(kind))))
+        }
+    }
+
+    fn is_keyword(&self, keyword: &str) -> bool {
+        matches!(&self.peek().kind, TokenKind::Ident(word) if word.eq_ignore_ascii_case(keyword))
+    }
+
+    /// Consumes the current token if it is the given keyword.
+    fn parse_keyword(&mut self, keyword: &str) -> bool {
+        if self.is_keyword(keyword) {
+            self.advance();
+            true
+        } else {
+            false
+        }
+    }
+
+    fn expect_keyword(&mut self, keyword: &str) -> Result<()> {
+        if self.parse_keyword(keyword) {
+            Ok(())
+        } else {
+            Err(self.error(&format!("expected {}", keyword)))
+        }
+    }
+
+    fn parse_identifier(&mut self) -> Result<String> {
+        match self.peek().kind.clone() {
+            TokenKind::Ident(word) if !is_reserved(&word) => {
+                self.advance();
+                Ok(word)
+            }
+            TokenKind::QuotedIdent(word) => {
+                self.advance();
+                Ok(word)
+            }
+            _ => Err(self.error("expected an identifier")),
+        }
+    }
+
+    fn parse_unsigned(&mut self) -> Result<u64> {
+        match self.peek().kind {
+            TokenKind::Number(n) => {
+                self.advance();
+                Ok(n as u64)
+            }
+            _ => Err(self.error("expected a non-negative integer")),
+        }
+    }
+
+    fn expect_eof(&self) -> Result<()> {
+        if self.peek().kind == TokenKind::Eof {
+            Ok(())
+        } else {
+            Err(self.error("expected end of statement"))
+        }
+    }
+
+    /// Returns the span from `start` to the end of the last consumed token.
+    fn span_from(&self, start: usize) -> Span {
+        let last = &self.tokens[self.pos - 1];
+        Span {
+            offset: start,
+            len: last.offset + last.len - start,
+        }
+    }
+
+    /// Builds an error pointing at the current token.
+    fn error(&self, message: &str) -> QueryError {
+        let token = self.peek();
+        error_at(
+            self.sql,
+            token.offset,
+            token.len,
+            &format!("{}, found {}", message, describe(&token.kind)),
+        )
+    }
+}
+
+fn binary(left: Expr, op: BinaryOperator, right: Expr) -> Expr {
+    Expr::Binary {
+        left: Box::new(left),
+        op,
+        right: Box::new(right),
+    }
+}
+
+fn is_reserved(word: &str) -> bool {
+    RESERVED.iter().any(|kw| kw.eq_ignore_ascii_case(word))
+}
+
+/// Describes a token for error messages.
+fn describe(kind: &TokenKind) -> String {
+    match kind {
+        TokenKind::Ident(word) => format!("'{}'", word),
+        TokenKind::QuotedIdent(word) => format!("\"{}\"", word),
+        TokenKind::Number(n) => format!("'{}'", n),
+        TokenKind::Parameter(n) => format!("'${}'", n),
+        TokenKind::String(s) => format!("string '{}'", s),
+        TokenKind::Comma => "','".to_string(),
+        TokenKind::Dot => "'.'".to_string(),
+        TokenKind::LParen => "'('".to_string(),
+        TokenKind::RParen => "')'".to_string(),
+        TokenKind::Semicolon => "';'".to_string(),
+        TokenKind::Star => "'*'".to_string(),
+        TokenKind::Plus => "'+'".to_string(),
+        TokenKind::Minus => "'-'".to_string(),
+        TokenKind::Slash => "'/'".to_string(),
+        TokenKind::Eq => "'='".to_string(),
+        TokenKind::NotEq => "'<>'".to_string(),
+        TokenKind::Lt => "'<'".to_string(),
+        TokenKind::LtEq => "'<='".to_string(),
+        TokenKind::Gt => "'>'".to_string(),
+        TokenKind::GtEq => "'>='".to_string(),
+        TokenKind::Eof => "end of input".to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_select() {
+        let Statement::Select(select) =
+            parse("SELECT name, age + 1 AS next_year FROM users u WHERE age > 25 LIMIT 10;")
+                .unwrap()
+        else {
+            panic!("Expected SELECT");
+        };
+
+        assert_eq!(select.projection.len(), 2);
+        match &select.projection[1] {
+            SelectItem::Expr { expr, alias } => {
+                assert_eq!(expr.to_string(), "age + 1");
+                assert_eq!(alias.as_deref(), Some("next_year"));
+            }
+            _ => panic!("Expected expression item"),
+        }
+        assert_eq!(select.from.name, "users");
+        assert_eq!(select.from.reference_name(), "u");
+        assert_eq!(select.selection.unwrap().to_string(), "age > 25");
+        assert_eq!(select.limit, Some(10));
+        assert_eq!(select.offset, None);
+
+        let Statement::Select(select) = parse("SELECT * FROM users LIMIT 5 OFFSET 20").unwrap()
+        else {
+            panic!("Expected SELECT");
+        };
+        assert_eq!((select.limit, select.offset), (Some(5), Some(20)));
+        let Statement::Select(select) = parse("SELECT * FROM users OFFSET 20 LIMIT 5").unwrap()
+        else {
+            panic!("Expected SELECT");
+        };
+        assert_eq!((select.limit, select.offset), (Some(5), Some(20)));
+        let Statement::Select(select) = parse("SELECT * FROM users OFFSET 20").unwrap() else {
+            panic!("Expected SELECT");
+        };
+        assert_eq!((select.limit, select.offset), (None, Some(20)));
+        assert!(parse("SELECT * FROM users LIMIT 5 OFFSET 20 LIMIT 5").is_err());
+
+        let statement = parse("SELECT * FROM users WHERE id = $2 OR name = $1").unwrap();
+        assert_eq!(statement.parameter_count(), 2);
+        assert!(parse("SELECT * FROM users WHERE id = $0").is_err());
+    }
+
+    #[test]
+    fn test_parse_precedence() {
+        let Statement::Select(select) =
+            parse("select * from t where a = 1 or b = 2 and not c is null").unwrap()
+        else {
+            panic!("Expected SELECT");
+        };
+
+        assert!(matches!(select.projection[0], SelectItem::Wildcard));
+        match select.selection.unwrap() {
+            Expr::Binary { op, right, .. } => {
+                assert_eq!(op, BinaryOperator::Or);
+                assert_eq!(right.to_string(), "b = 2 AND NOT c IS NULL");
+            }
+            _ => panic!("Expected OR at the root"),
+        }
+    }
+
+    #[test]
+    fn test_parse_ddl() {
+        let Statement::CreateTable(create) =
+            parse("CREATE TABLE IF NOT EXISTS t (id INTEGER, name VARCHAR(50))").unwrap()
+        else {
+            panic!("Expected CREATE TABLE");
+        };
+        assert_eq!(create.name, "t");
+        assert!(create.if_not_exists);
+        assert_eq!(create.columns.len(), 2);
+        assert_eq!(create.columns[1].data_type, DataType::Varchar(50));
+
+        assert!(matches!(
+            parse("drop table if exists t").unwrap(),
+            Statement::DropTable {
+                if_exists: true,
+                ..
+            }
+        ));
+        assert!(parse("CREATE TABLE t (id FLOAT)").is_err());
+    }
+
+    #[test]
+    fn test_parse_dml() {
+        let Statement::Insert(insert) =
+            parse("INSERT INTO t (id, name) VALUES (1, 'a'), (2, 'b')").unwrap()
+        else {
+            panic!("Expected INSERT");
+        };
+        assert_eq!(insert.columns.unwrap(), vec!["id", "name"]);
+        assert!(matches!(insert.source, InsertSource::Values(rows) if rows.len() == 2));
+
+        let Statement::Insert(insert) = parse("INSERT INTO t SELECT * FROM s").unwrap() else {
+            panic!("Expected INSERT");
+        };
+        assert!(matches!(insert.source, InsertSource::Select(_)));
+
+        let Statement::Update(update) =
+            parse("UPDATE t SET a = a + 1, b = 'x' WHERE id = 3").unwrap()
+        else {
+            panic!("Expected UPDATE");
+        };
+        assert_eq!(update.assignments.len(), 2);
+        assert!(update.selection.is_some());
+
+        assert!(matches!(
+            parse("DELETE FROM t").unwrap(),
+            Statement::Delete {
+                selection: None,
+                ..
+            }
+        ));
+    }
+
+    #[test]
+    fn test_parse_error_reports_position() {
+        let err = parse("SELECT name FORM users").unwrap_err();
+        assert!(matches!(err, QueryError::ParseError { .. }));
+        assert!(err.to_string().contains("line 1, col 18"), "{}", err);
+    }
+}
@@ -0,0 +1,450 @@
+//! Lowers parsed SQL into DataFrame calls and catalog operations.
+//!
+//! Each clause of a `SELECT` maps onto the equivalent DataFrame method, so SQL queries run
+//! through exactly the same executor tree as hand-built DataFrame queries. DDL statements
+//! become `Schema`s handed to the catalog, and DML runs through the DataFrame write methods.
+
+use super::ast::{
+    CreateTable, DataType, Expr, Insert, InsertSource, Select, SelectItem, Span, Statement,
+    TableRef, Update,
+};
+use crate::database::Database;
+use crate::dataframe::DataFrame;
+use crate::diagnostics::closest_match;
+use crate::expression::{col, BinaryOperator, Expression};
+use crate::types::{Row, Value};
+use crate::{int_column, varchar_column, QueryError, Result};
+use storage_engine::tuple::{Schema, Type};
+
+/// What names in a SQL expression are resolved against.
+///
+/// Column references are checked while lowering, rather than when the DataFrame binds them,
+/// so that errors can point at the reference in `sql`.
+struct Scope<'a> {
+    /// The statement text, for error locations
+    sql: &'a str,
+    table: &'a TableRef,
+    schema: &'a Schema,
+}
+
+/// Builds a DataFrame that evaluates the given `SELECT`, parsed from `sql`.
+pub(crate) fn plan_select(db: &Database, sql: &str, select: &Select) -> Result<DataFrame> {
+    let mut df = open_table(db, sql, &select.from)?;
+    let schema = df.input_schema().clone();
+    let scope = Scope {
+        sql,
+        table: &select.from,
+        schema: &schema,
+    };
+
+    if let Some(selection) = &select.selection {
+        df = df.filter(lower_expr(selection, &scope)?);
+    }
+
+    // A lone `*` keeps every column without adding a projection.
+    if !matches!(select.projection.as_slice(), [SelectItem::Wildcard]) {
+        let mut exprs = Vec::new();
+        for item in &select.projection {
+            match item {
+                SelectItem::Wildcard => {
+                    for column in &df.input_schema().columns {
+                        exprs.push((col(&column.name), column.name.clone()));
+                    }
+                }
+                SelectItem::Expr { expr, alias } => {
+                    let name = match (alias, expr) {
+                        (Some(alias), _) => alias.clone(),
+                        (None, Expr::Column { name, .. }) => name.clone(),
+                        (None, expr) => expr.to_string(),
+                    };
+                    exprs.push((lower_expr(expr, &scope)?, name));
+                }
+            }
+        }
+        let exprs: Vec<(Expression, &str)> = exprs
+            .iter()
+            .map(|(expr, name)| (expr.clone(), name.as_str()))
+            .collect();
+        df = df.select_exprs(&exprs);
+    }
+
+    // OFFSET skips rows before LIMIT counts them, whatever order the clauses are written in
+    if let Some(offset) = select.offset {
+        df = df.offset(offset as usize);
+    }
+    if let Some(limit) = select.limit {
+        df = df.limit(limit as usize);
+    }
+
+    Ok(df)
+}
+
+/// Builds the table schema described by a `CREATE TABLE` statement.
+pub(crate) fn plan_create_table(create: &CreateTable) -> Result<Schema> {
+    let mut columns = Vec::with_capacity(create.columns.len());
+    for (i, def) in create.columns.iter().enumerate() {
+        if create.columns[..i]
+            .iter()
+            .any(|other| other.name == def.name)
+        {
+            return Err(QueryError::ExecutionError(format!(
+                "Column '{}' specified more than once",
+                def.name
+            )));
+        }
+        columns.push(match def.data_type {
+            DataType::Integer => int_column(&def.name),
+            DataType::Varchar(length) => varchar_column(&def.name, length),
+        });
+    }
+    Ok(Schema { columns })
+}
+
+/// Runs an `INSERT` parsed from `sql`, returning the number of rows written.
+pub(crate) fn execute_insert(db: &Database, sql: &str, insert: &Insert) -> Result<usize> {
+    let df = open_table(db, sql, &insert.table)?;
+
+    let rows: Vec<Vec<Value>> = match &insert.source {
+        InsertSource::Values(rows) => {
+            // Values are constants; they cannot refer to columns of the target
+            let empty_schema = Schema { columns: vec![] };
+            let empty_row = Row::new(vec![]);
+            let scope = Scope {
+                sql,
+                table: &insert.table,
+                schema: &empty_schema,
+            };
+            rows.iter()
+                .map(|row| {
+                    row.iter()
+                        .map(|expr| {
+                            lower_expr(expr, &scope)?
+                                .bind(&empty_schema)?
+                                .evaluate(&empty_row)
+                        })
+                        .collect::<Result<Vec<_>>>()
+                })
+                .collect::<Result<_>>()?
+        }
+        InsertSource::Select(select) => {
+            // Run the query to completion first so it never sees the rows being inserted.
+            plan_select(db, sql, select)?
+                .collect()?
+                .into_iter()
+                .map(|row| row.values)
+                .collect()
+        }
+    };
+
+    let rows = match &insert.columns {
+        None => rows,
+        Some(columns) => {
+            let positions = insert_positions(df.input_schema(), columns)?;
+            rows.into_iter()
+                .map(|row| {
+                    if row.len() != positions.len() {
+                        return Err(QueryError::ExecutionError(format!(
+                            "INSERT has {} target columns but {} values",
+                            positions.len(),
+                            row.len()
+                        )));
+                    }
+                    let mut ordered = vec![Value::Null; positions.len()];
+                    for (value, position) in row.into_iter().zip(&positions) {
+                        ordered[*position] = value;
+                    }
+                    Ok(ordered)
+                })
+                .collect::<Result<_>>()?
+        }
+    };
+
+    df.insert_many(&rows)
+}
+
+/// Maps an `INSERT` column list onto table positions.
+///
+/// Every table column must be listed exactly once, since the storage engine has no NULLs or
+/// column defaults to fill the gaps with.
+fn insert_positions(schema: &Schema, columns: &[String]) -> Result<Vec<usize>> {
+    let mut positions = Vec::with_capacity(columns.len());
+    for name in columns {
+        let position = schema
+            .columns
+            .iter()
+            .position(|column| &column.name == name)
+            .ok_or_else(|| QueryError::ColumnNotFound {
+                name: name.clone(),
+                suggestion: closest_match(name, schema.columns.iter().map(|c| c.name.as_str())),
+                location: None,
+            })?;
+        if positions.contains(&position) {
+            return Err(QueryError::ExecutionError(format!(
+                "Column '{}' specified more than once",
+                name
+            )));
+        }
+        positions.push(position);
+    }
+
+    if let Some(missing) = schema
+        .columns
+        .iter()
+        .enumerate()
+        .find(|(i, _)| !positions.contains(i))
+    {
+        return Err(QueryError::ExecutionError(format!(
+            "Column '{}' has no default value and must be given",
+            missing.1.name
+        )));
+    }
+    Ok(positions)
+}
+
+/// Runs an `UPDATE` parsed from `sql`, returning the number of rows changed.
+pub(crate) fn execute_update(db: &Database, sql: &str, update: &Update) -> Result<usize> {
+    let mut df = open_table(db, sql, &update.table)?;
+    let schema = df.input_schema().clone();
+    let scope = Scope {
+        sql,
+        table: &update.table,
+        schema: &schema,
+    };
+
+    if let Some(selection) = &update.selection {
+        df = df.filter(lower_expr(selection, &scope)?);
+    }
+
+    let assignments = update
+        .assignments
+        .iter()
+        .map(|(column, expr)| Ok((column.as_str(), lower_expr(expr, &scope)?)))
+        .collect::<Result<Vec<_>>>()?;
+    df.update(&assignments)
+}
+
+/// Runs a `DELETE` parsed from `sql`, returning the number of rows removed.
+pub(crate) fn execute_delete(
+    db: &Database,
+    sql: &str,
+    table: &TableRef,
+    selection: Option<&Expr>,
+) -> Result<usize> {
+    let mut df = open_table(db, sql, table)?;
+    let schema = df.input_schema().clone();
+    let scope = Scope {
+        sql,
+        table,
+        schema: &schema,
+    };
+
+    if let Some(selection) = selection {
+        df = df.filter(lower_expr(selection, &scope)?);
+    }
+    df.delete()
+}
+
+/// Opens the table a statement reads or writes, pointing a not-found error at its name.
+fn open_table(db: &Database, sql: &str, table: &TableRef) -> Result<DataFrame> {
+    db.table(&table.name)
+        .map_err(|err| err.with_location(|| table.span.locate(sql)))
+}
+
+/// Infers the type of each `$n` parameter in a statement.
+///
+/// A parameter takes the type of the column it is inserted into or assigned to, or of the
+/// column or literal on the other side of an operator. Parameters with no such context are
+/// `None`; the first context found wins.
+pub(crate) fn parameter_types(db: &Database, statement: &Statement) -> Result<Vec<Option<Type>>> {
+    let mut types: Vec<Option<Type>> = (0..statement.parameter_count()).map(|_| None).collect();
+    if types.is_empty() {
+        return Ok(types);
+    }
+
+    let table = match statement {
+        Statement::Select(select) => &select.from.name,
+        Statement::Insert(insert) => &insert.table.name,
+        Statement::Update(update) => &update.table.name,
+        Statement::Delete { table, .. } => &table.name,
+        Statement::CreateTable(_) | Statement::DropTable { .. } => return Ok(types),
+    };
+    let schema = db.table_schema(table)?;
+    let column_type = |name: &str| {
+        schema
+            .columns
+            .iter()
+            .find(|column| column.name == name)
+            .map(|column| copy_type(&column.column_type))
+    };
+
+    // Direct targets: INSERT values and UPDATE assignments
+    match statement {
+        Statement::Insert(Insert {
+            columns,
+            source: InsertSource::Values(rows),
+            ..
+        }) => {
+            for row in rows {
+                for (i, expr) in row.iter().enumerate() {
+                    let target = match columns {
+                        Some(columns) => columns.get(i).and_then(|name| column_type(name)),
+                        None => schema
+                            .columns
+                            .get(i)
+                            .map(|column| copy_type(&column.column_type)),
+                    };
+                    set_parameter_type(&mut types, expr, target);
+                }
+            }
+        }
+        Statement::Update(update) => {
+            for (column, expr) in &update.assignments {
+                set_parameter_type(&mut types, expr, column_type(column));
+            }
+        }
+        _ => {}
+    }
+
+    let mut exprs = Vec::new();
+    statement.collect_exprs(&mut exprs);
+    for expr in exprs {
+        infer_operand_types(expr, &column_type, &mut types);
+    }
+    Ok(types)
+}
+
+/// Types parameters that appear as operands of binary operators within `expr`.
+fn infer_operand_types(
+    expr: &Expr,
+    column_type: &dyn Fn(&str) -> Option<Type>,
+    types: &mut [Option<Type>],
+) {
+    match expr {
+        Expr::Binary { left, op, right } => {
+            let arithmetic = matches!(
+                op,
+                BinaryOperator::Add
+                    | BinaryOperator::Subtract
+                    | BinaryOperator::Multiply
+                    | BinaryOperator::Divide
+            );
+            for (operand, other) in [(left, right), (right, left)] {
+                let other_type = if arithmetic {
+                    Some(Type::Integer)
+                } else {
+                    expr_type(other, column_type)
+                };
+                set_parameter_type(types, operand, other_type);
+            }
+            infer_operand_types(left, column_type, types);
+            infer_operand_types(right, column_type, types);
+        }
+        Expr::Unary { expr, .. } | Expr::Nested(expr) => {
+            infer_operand_types(expr, column_type, types)
+        }
+        Expr::Negate(expr) => {
+            set_parameter_type(types, expr, Some(Type::Integer));
+            infer_operand_types(expr, column_type, types);
+        }
+        Expr::Column { .. }
+        | Expr::Integer(_)
+        | Expr::String(_)
+        | Expr::Null
+        | Expr::Parameter(_) => {}
+    }
+}
+
+/// Records `ty` for `expr` if it is a (possibly parenthesized) parameter not yet typed.
+fn set_parameter_type(types: &mut [Option<Type>], expr: &Expr, ty: Option<Type>) {
+    match expr {
+        Expr::Nested(inner) => set_parameter_type(types, inner, ty),
+        Expr::Parameter(n) if types[*n - 1].is_none() => types[*n - 1] = ty,
+        _ => {}
+    }
+}
+
+/// Returns the type of an expression when it is evident without binding.
+fn expr_type(expr: &Expr, column_type: &dyn Fn(&str) -> Option<Type>) -> Option<Type> {
+    match expr {
+        Expr::Column { name, .. } => column_type(name),
+        Expr::Integer(_) | Expr::Negate(_) => Some(Type::Integer),
+        Expr::String(_) => Some(Type::Varchar),
+        Expr::Nested(inner) => expr_type(inner, column_type),
+        Expr::Binary { .. } | Expr::Unary { .. } => Some(Type::Integer),
+        Expr::Null | Expr::Parameter(_) => None,
+    }
+}
+
+fn copy_type(ty: &Type) -> Type {
+    match ty {
+        Type::Integer => Type::Integer,
+        Type::Varchar => Type::Varchar,
+    }
+}
+
+/// Translates a SQL expression into an [`Expression`] over the columns in `scope`.
+fn lower_expr(expr: &Expr, scope: &Scope) -> Result<Expression> {
+    Ok(match expr {
+        Expr::Column {
+            table: Some(qualifier),
+            name,
+            span,
+        } => {
+            let table = scope.table.reference_name();
+            if qualifier != table {
+                let known = scope.schema.columns.iter().any(|column| &column.name == name);
+                return Err(QueryError::ColumnNotFound {
+                    name: format!("{}.{}", qualifier, name),
+                    suggestion: known.then(|| format!("{}.{}", table, name)),
+                    location: Some(span.locate(scope.sql)),
+                });
+            }
+            resolve_column(name, *span, scope)?
+        }
+        Expr::Column {
+            table: None,
+            name,
+            span,
+        } => resolve_column(name, *span, scope)?,
+        Expr::Integer(i) => Expression::Literal(Value::Integer(to_i32(*i)?)),
+        Expr::String(s) => Expression::Literal(Value::Varchar(s.clone())),
+        Expr::Null => Expression::Literal(Value::Null),
+        Expr::Parameter(n) => Expression::Parameter(*n),
+        Expr::Binary { left, op, right } => Expression::BinaryOp {
+            left: Box::new(lower_expr(left, scope)?),
+            op: *op,
+            right: Box::new(lower_expr(right, scope)?),
+        },
+        Expr::Unary { op, expr } => Expression::UnaryOp {
+            op: *op,
+            expr: Box::new(lower_expr(expr, scope)?),
+        },
+        Expr::Negate(inner) => match inner.as_ref() {
+            Expr::Integer(i) => Expression::Literal(Value::Integer(to_i32(-*i)?)),
+            _ => Expression::BinaryOp {
+                left: Box::new(Expression::Literal(Value::Integer(0))),
+                op: BinaryOperator::Subtract,
+                right: Box::new(lower_expr(inner, scope)?),
+            },
+        },
+        Expr::Nested(inner) => lower_expr(inner, scope)?,
+    })
+}
+
+/// Checks that `name` is a column in scope, suggesting a near miss if it is not.
+fn resolve_column(name: &str, span: Span, scope: &Scope) -> Result<Expression> {
+    let columns = &scope.schema.columns;
+    if !columns.iter().any(|column| column.name == name) {
+        return Err(QueryError::ColumnNotFound {
+            name: name.to_string(),
+            suggestion: closest_match(name, columns.iter().map(|column| column.name.as_str())),
+            location: Some(span.locate(scope.sql)),
+        });
+    }
+    Ok(col(name))
+}
+
+fn to_i32(value: i64) -> Result<i32> {
+    i32::try_from(value)
+        .map_err(|_| QueryError::TypeMismatch(format!("Integer literal {} is out of range", value)))
+}
@@ -3,10 +3,36 @@
 //! Extends storage_engine types with NULL support and comparison operations.
 
 use std::cmp::Ordering;
-use storage_engine::tuple::Value as StorageValue;
+use storage_engine::tuple::{Tuple, Value as StorageValue};
+
+/// A row produced by a query.
+///
+/// Rows are what executors pass between each other. Unlike a storage `Tuple`, a row can
+/// hold NULLs, such as the columns an outer join pads when a row has no match.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Row {
+    pub values: Vec<Value>,
+}
+
+impl Row {
+    /// Creates a row from its values.
+    pub fn new(values: Vec<Value>) -> Self {
+        Self { values }
+    }
+
+    /// Converts a tuple read from storage into a row.
+    pub fn from_storage(tuple: Tuple) -> Self {
+        Self {
+            values: tuple.values.into_iter().map(Value::from_storage).collect(),
+        }
+    }
+}
 
 /// A value that can be stored in a tuple, including NULL.
-#[derive(Debug, Clone, PartialEq)]
+///
+/// `Eq` and `Hash` treat NULL as equal to NULL, which is what grouping and hash tables
+/// want; use [`compare`](Self::compare) for SQL comparison semantics.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub enum Value {
     Integer(i32),
     Varchar(String),
@@ -38,6 +64,14 @@ impl Value {
         matches!(self, Value::Null)
     }
 
+    /// Returns true if this value counts as TRUE in a predicate.
+    ///
+    /// Booleans are represented as integers: any non-zero integer is TRUE, while 0 and NULL
+    /// are not.
+    pub fn is_true(&self) -> bool {
+        matches!(self, Value::Integer(i) if *i != 0)
+    }
+
     /// Compares two values using SQL semantics.
     ///
     /// NULL comparisons always return None (unknown).
@@ -50,6 +84,22 @@ impl Value {
         }
     }
 
+    /// Orders two values for sorting, where every pair must compare.
+    ///
+    /// Agrees with [`compare`](Self::compare) where that gives an answer. NULL sorts after
+    /// every other value and equal to itself, and integers sort before strings.
+    pub fn sort_cmp(&self, other: &Value) -> Ordering {
+        match (self, other) {
+            (Value::Null, Value::Null) => Ordering::Equal,
+            (Value::Null, _) => Ordering::Greater,
+            (_, Value::Null) => Ordering::Less,
+            (Value::Integer(a), Value::Integer(b)) => a.cmp(b),
+            (Value::Varchar(a), Value::Varchar(b)) => a.cmp(b),
+            (Value::Integer(_), Value::Varchar(_)) => Ordering::Less,
+            (Value::Varchar(_), Value::Integer(_)) => Ordering::Greater,
+        }
+    }
+
     /// Adds two values (for arithmetic expressions).
     pub fn add(&self, other: &Value) -> Option<Value> {
         match (self, other) {
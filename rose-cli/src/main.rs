@@ -0,0 +1,134 @@
+//! `rose` - interactive SQL shell and PostgreSQL wire-protocol server for rose-db.
+//!
+//! Opens a database directory with `Database::open` and reads SQL statements or
+//! backslash meta-commands from a line-editing prompt.
+//!
+//! ```text
+//! $ rose mydb
+//! rose> SELECT name FROM users WHERE age > 25;
+//! ```
+//!
+//! With `--listen`, the database is served over TCP to Postgres clients instead:
+//!
+//! ```text
+//! $ rose --listen 127.0.0.1:5432 mydb
+//! $ psql -h 127.0.0.1 -p 5432
+//! ```
+//!
+//! With `--file`, the statements in a script are run in order and the shell exits, stopping
+//! at the first error:
+//!
+//! ```text
+//! $ rose --file fixtures.sql mydb
+//! ```
+
+mod server;
+mod shell;
+mod table;
+
+use query_engine::Database;
+use shell::Shell;
+use std::net::TcpListener;
+use std::process::ExitCode;
+use std::sync::Arc;
+
+const USAGE: &str = "usage: rose [--listen <addr:port> | --file <script.sql>] <database-dir>";
+
+/// What to do with the database once it is open.
+enum Mode<'a> {
+    Shell,
+    Listen(&'a str),
+    Script(&'a str),
+}
+
+fn main() -> ExitCode {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let (mode, path) = match args.as_slice() {
+        [flag] if flag == "-h" || flag == "--help" => {
+            println!("{}", USAGE);
+            return ExitCode::SUCCESS;
+        }
+        [path] if !path.starts_with('-') => (Mode::Shell, path),
+        [flag, addr, path] if flag == "--listen" && !path.starts_with('-') => {
+            (Mode::Listen(addr), path)
+        }
+        [flag, file, path] if (flag == "-f" || flag == "--file") && !path.starts_with('-') => {
+            (Mode::Script(file), path)
+        }
+        _ => {
+            eprintln!("{}", USAGE);
+            return ExitCode::from(2);
+        }
+    };
+
+    let db = match Database::open(path) {
+        Ok(db) => db,
+        Err(err) => {
+            eprintln!("rose: cannot open database '{}': {}", path, err);
+            return ExitCode::FAILURE;
+        }
+    };
+
+    match mode {
+        Mode::Listen(addr) => listen_on(addr, db),
+        Mode::Script(file) => run_script(file, &db),
+        Mode::Shell => match Shell::new(&db).run() {
+            Ok(()) => ExitCode::SUCCESS,
+            Err(err) => {
+                eprintln!("rose: {}", err);
+                ExitCode::FAILURE
+            }
+        },
+    }
+}
+
+/// Runs every statement in a script file, printing results, and flushes what was applied.
+fn run_script(file: &str, db: &Database) -> ExitCode {
+    let script = match std::fs::read_to_string(file) {
+        Ok(script) => script,
+        Err(err) => {
+            eprintln!("rose: cannot read '{}': {}", file, err);
+            return ExitCode::FAILURE;
+        }
+    };
+
+    let outcome = db.execute_script(&script);
+    if let Err(err) = db.flush() {
+        eprintln!("rose: {}", err);
+        return ExitCode::FAILURE;
+    }
+
+    match outcome {
+        Ok(results) => {
+            results.iter().for_each(shell::print_result);
+            ExitCode::SUCCESS
+        }
+        Err(err) => {
+            eprintln!("rose: {}: {}", file, err);
+            if let Some(location) = err.error.location() {
+                eprintln!("{}", location);
+            }
+            ExitCode::FAILURE
+        }
+    }
+}
+
+/// Serves the database to Postgres clients until the process is killed.
+fn listen_on(addr: &str, db: Database) -> ExitCode {
+    let listener = match TcpListener::bind(addr) {
+        Ok(listener) => listener,
+        Err(err) => {
+            eprintln!("rose: cannot listen on {}: {}", addr, err);
+            return ExitCode::FAILURE;
+        }
+    };
+    eprintln!("rose: listening on {}", addr);
+
+    match server::serve(listener, Arc::new(db)) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(err) => {
+            eprintln!("rose: {}", err);
+            ExitCode::FAILURE
+        }
+    }
+}
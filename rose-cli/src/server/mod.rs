@@ -0,0 +1,747 @@
+//! A PostgreSQL wire-protocol (v3) front end for rose-db.
+//!
+//! `rose --listen 127.0.0.1:5432 mydb` serves one database over TCP so `psql` or an
+//! ordinary Postgres driver can run queries against it. Each connection gets its own
+//! thread sharing the same [`Database`]. Queries from different connections run
+//! concurrently, but a statement that writes waits for them to finish and runs alone, so a
+//! query never sees another statement's writes half done.
+//!
+//! Supported:
+//! - startup (SSL and GSSAPI encryption requests are declined, so clients fall back to
+//!   plain text; there is no authentication)
+//! - the simple query protocol (`Query`)
+//! - the extended query protocol (`Parse`, `Bind`, `Describe`, `Execute`, `Close`,
+//!   `Sync`, `Flush`), with text or binary result columns and row limits on `Execute`
+//! - `$n` parameters in text or binary format; untyped parameters take the type of the
+//!   column they are compared with or stored into
+//!
+//! Every statement autocommits and the server always reports itself idle.
+
+mod protocol;
+
+use protocol::{Format, Message, Reader};
+use query_engine::sql::ast::Statement;
+use query_engine::sql::{parse, split_statements};
+use query_engine::{Database, QueryError, StatementResult, Type, Value};
+use std::collections::{HashMap, VecDeque};
+use std::io::{self, BufReader, BufWriter, Write};
+use std::net::{TcpListener, TcpStream};
+use std::sync::{Arc, RwLock};
+use std::thread;
+
+/// Accepts connections forever, serving each one on its own thread.
+pub fn serve(listener: TcpListener, db: Arc<Database>) -> io::Result<()> {
+    let statement_lock = Arc::new(RwLock::new(()));
+    for (id, stream) in listener.incoming().enumerate() {
+        let stream = match stream {
+            Ok(stream) => stream,
+            Err(err) => {
+                eprintln!("rose: accept failed: {}", err);
+                continue;
+            }
+        };
+        let db = Arc::clone(&db);
+        let statement_lock = Arc::clone(&statement_lock);
+        thread::spawn(move || {
+            let peer = stream.peer_addr().ok();
+            let connection = Connection::new(&db, &statement_lock, stream, id as i32);
+            if let Err(err) = connection.and_then(|c| c.run()) {
+                if err.kind() != io::ErrorKind::UnexpectedEof {
+                    eprintln!("rose: connection {:?}: {}", peer, err);
+                }
+            }
+        });
+    }
+    Ok(())
+}
+
+/// A statement created by `Parse`.
+struct PreparedStatement {
+    sql: String,
+    /// Type OID of each `$n` parameter, declared by the client or inferred
+    parameter_types: Vec<i32>,
+}
+
+/// A statement bound by `Bind` and ready to execute.
+struct Portal {
+    sql: String,
+    args: Vec<Value>,
+    result_formats: Vec<i16>,
+    /// Rows still to be sent, once the portal has been executed.
+    pending: Option<VecDeque<Vec<Value>>>,
+    /// Rows sent so far, reported when the last one has been sent
+    rows_sent: usize,
+    /// Command tag of a statement that produced no rows, once the portal has been executed
+    command_tag: Option<String>,
+}
+
+/// The protocol state machine for one client.
+struct Connection<'a> {
+    db: &'a Database,
+    /// Held for reading by queries and for writing by every other statement, across all
+    /// connections
+    statement_lock: &'a RwLock<()>,
+    reader: BufReader<TcpStream>,
+    writer: BufWriter<TcpStream>,
+    id: i32,
+    statements: HashMap<String, PreparedStatement>,
+    portals: HashMap<String, Portal>,
+    /// Set after an error in an extended-query message; everything up to `Sync` is skipped.
+    skip_until_sync: bool,
+}
+
+impl<'a> Connection<'a> {
+    fn new(
+        db: &'a Database,
+        statement_lock: &'a RwLock<()>,
+        stream: TcpStream,
+        id: i32,
+    ) -> io::Result<Self> {
+        Ok(Self {
+            db,
+            statement_lock,
+            reader: BufReader::new(stream.try_clone()?),
+            writer: BufWriter::new(stream),
+            id,
+            statements: HashMap::new(),
+            portals: HashMap::new(),
+            skip_until_sync: false,
+        })
+    }
+
+    fn run(mut self) -> io::Result<()> {
+        if !self.startup()? {
+            return Ok(());
+        }
+
+        while let Some((tag, body)) = protocol::read_message(&mut self.reader)? {
+            if tag == b'X' {
+                break;
+            }
+            if self.skip_until_sync && tag != b'S' {
+                continue;
+            }
+
+            let result = match tag {
+                b'Q' => self.simple_query(&body),
+                b'P' => self.parse(&body),
+                b'B' => self.bind(&body),
+                b'D' => self.describe(&body),
+                b'E' => self.execute(&body),
+                b'C' => self.close(&body),
+                b'H' => self.writer.flush().map(|_| Ok(())),
+                b'S' => {
+                    self.skip_until_sync = false;
+                    self.send(protocol::ready_for_query())?;
+                    self.writer.flush().map(|_| Ok(()))
+                }
+                _ => Ok(Err(protocol::protocol_error(&format!(
+                    "unsupported message type '{}'",
+                    tag as char
+                )))),
+            };
+
+            if let Err(error) = result? {
+                self.send(error)?;
+                if tag == b'Q' {
+                    self.send(protocol::ready_for_query())?;
+                    self.writer.flush()?;
+                } else {
+                    self.skip_until_sync = true;
+                }
+            }
+        }
+
+        self.writer.flush()
+    }
+
+    /// Negotiates the startup packet. Returns `false` if the connection should close.
+    fn startup(&mut self) -> io::Result<bool> {
+        loop {
+            let packet = protocol::read_startup_packet(&mut self.reader)?;
+            let mut body = Reader::new(&packet);
+            match body.i32()? {
+                protocol::SSL_REQUEST | protocol::GSSENC_REQUEST => {
+                    // Decline encryption; the client retries in plain text
+                    self.writer.write_all(b"N")?;
+                    self.writer.flush()?;
+                }
+                protocol::CANCEL_REQUEST => return Ok(false),
+                protocol::PROTOCOL_VERSION => break,
+                version => {
+                    self.send(protocol::protocol_error(&format!(
+                        "unsupported frontend protocol {}.{}",
+                        version >> 16,
+                        version & 0xffff
+                    )))?;
+                    self.writer.flush()?;
+                    return Ok(false);
+                }
+            }
+        }
+
+        self.send(protocol::authentication_ok())?;
+        for (name, value) in [
+            ("server_version", "14.0"),
+            ("server_encoding", "UTF8"),
+            ("client_encoding", "UTF8"),
+            ("DateStyle", "ISO, MDY"),
+            ("integer_datetimes", "on"),
+            ("standard_conforming_strings", "on"),
+        ] {
+            self.send(protocol::parameter_status(name, value))?;
+        }
+        self.send(protocol::backend_key_data(self.id, 0))?;
+        self.send(protocol::ready_for_query())?;
+        self.writer.flush()?;
+        Ok(true)
+    }
+
+    fn send(&mut self, message: Message) -> io::Result<()> {
+        self.writer.write_all(&message.encode())
+    }
+
+    /// Handles `Query`: runs each statement in turn and sends rows in text format.
+    fn simple_query(&mut self, body: &[u8]) -> io::Result<Result<(), Message>> {
+        let sql = Reader::new(body).cstr()?;
+        self.statements.remove("");
+        self.portals.remove("");
+
+        let statements = split_statements(&sql);
+        if statements.is_empty() {
+            self.send(Message::new(b'I'))?;
+        }
+        for (_, sql) in statements {
+            match run_statement(self.db, self.statement_lock, sql, &[]) {
+                Ok(StatementResult::Query { schema, rows }) => {
+                    let formats = vec![Format::Text; schema.columns.len()];
+                    self.send(protocol::row_description(&schema, &formats))?;
+                    let count = rows.len();
+                    for row in rows {
+                        self.send(protocol::data_row(&row.values, &formats))?;
+                    }
+                    self.send(protocol::command_complete(&format!("SELECT {}", count)))?;
+                }
+                Ok(result) => self.send(protocol::command_complete(&command_tag(&result)))?,
+                Err(err) => return Ok(Err(protocol::error_response(&err))),
+            }
+        }
+
+        self.send(protocol::ready_for_query())?;
+        self.writer.flush()?;
+        Ok(Ok(()))
+    }
+
+    /// Handles `Parse`: checks the statement's syntax and stores it under a name.
+    fn parse(&mut self, body: &[u8]) -> io::Result<Result<(), Message>> {
+        let mut body = Reader::new(body);
+        let name = body.cstr()?;
+        let sql = body.cstr()?;
+        let declared_count = body.i16()?;
+        let mut declared = Vec::with_capacity(declared_count.max(0) as usize);
+        for _ in 0..declared_count {
+            declared.push(body.i32()?);
+        }
+
+        let mut parameter_types = Vec::new();
+        if !is_empty_query(&sql) {
+            let inferred = match self.db.parameter_types(&sql) {
+                Ok(inferred) => inferred,
+                Err(err) => return Ok(Err(protocol::error_response(&err))),
+            };
+            // Declared types win; anything left unknown is treated as text, as in Postgres
+            parameter_types = inferred
+                .iter()
+                .enumerate()
+                .map(|(i, ty)| match (declared.get(i), ty) {
+                    (Some(oid), _) if *oid != 0 => *oid,
+                    (_, Some(Type::Integer)) => protocol::INT4_OID,
+                    _ => protocol::VARCHAR_OID,
+                })
+                .collect();
+        }
+
+        self.statements.insert(
+            name,
+            PreparedStatement {
+                sql,
+                parameter_types,
+            },
+        );
+        self.send(Message::new(b'1'))?;
+        Ok(Ok(()))
+    }
+
+    /// Handles `Bind`: creates a portal from a prepared statement.
+    fn bind(&mut self, body: &[u8]) -> io::Result<Result<(), Message>> {
+        let mut body = Reader::new(body);
+        let portal_name = body.cstr()?;
+        let statement_name = body.cstr()?;
+
+        let Some(statement) = self.statements.get(&statement_name) else {
+            return Ok(Err(unknown("prepared statement", &statement_name)));
+        };
+
+        let parameter_format_count = body.i16()?;
+        let mut parameter_formats = Vec::with_capacity(parameter_format_count.max(0) as usize);
+        for _ in 0..parameter_format_count {
+            parameter_formats.push(body.i16()?);
+        }
+        let parameter_count = body.i16()?.max(0) as usize;
+        if parameter_count != statement.parameter_types.len() {
+            return Ok(Err(protocol::protocol_error(&format!(
+                "bind message supplies {} parameters, but prepared statement \"{}\" requires {}",
+                parameter_count,
+                statement_name,
+                statement.parameter_types.len()
+            ))));
+        }
+        let formats = resolve_formats(&parameter_formats, parameter_count);
+        let mut args = Vec::with_capacity(parameter_count);
+        for (type_oid, format) in statement.parameter_types.iter().zip(formats) {
+            let raw = match body.i32()? {
+                -1 => None,
+                len => Some(body.bytes(len.max(0) as usize)?),
+            };
+            match protocol::decode_parameter(raw, *type_oid, format) {
+                Ok(value) => args.push(value),
+                Err(err) => return Ok(Err(protocol::error_response(&err))),
+            }
+        }
+
+        let result_format_count = body.i16()?;
+        let mut result_formats = Vec::with_capacity(result_format_count.max(0) as usize);
+        for _ in 0..result_format_count {
+            let code = body.i16()?;
+            if let Err(err) = Format::from_code(code) {
+                return Ok(Err(protocol::protocol_error(&err.to_string())));
+            }
+            result_formats.push(code);
+        }
+
+        let portal = Portal {
+            sql: statement.sql.clone(),
+            args,
+            result_formats,
+            pending: None,
+            rows_sent: 0,
+            command_tag: None,
+        };
+        self.portals.insert(portal_name, portal);
+        self.send(Message::new(b'2'))?;
+        Ok(Ok(()))
+    }
+
+    /// Handles `Describe` of a statement (`S`) or portal (`P`).
+    fn describe(&mut self, body: &[u8]) -> io::Result<Result<(), Message>> {
+        let mut body = Reader::new(body);
+        let kind = body.u8()?;
+        let name = body.cstr()?;
+
+        let (sql, result_formats) = match kind {
+            b'S' => match self.statements.get(&name) {
+                Some(statement) => {
+                    let mut description =
+                        Message::new(b't').i16(statement.parameter_types.len() as i16);
+                    for type_oid in &statement.parameter_types {
+                        description = description.i32(*type_oid);
+                    }
+                    let sql = statement.sql.clone();
+                    self.send(description)?;
+                    (sql, Vec::new())
+                }
+                None => return Ok(Err(unknown("prepared statement", &name))),
+            },
+            b'P' => match self.portals.get(&name) {
+                Some(portal) => (portal.sql.clone(), portal.result_formats.clone()),
+                None => return Ok(Err(unknown("portal", &name))),
+            },
+            _ => return Ok(Err(protocol::protocol_error("invalid describe kind"))),
+        };
+
+        let schema = if is_empty_query(&sql) {
+            None
+        } else {
+            let _reading = self.statement_lock.read().unwrap();
+            match self.db.describe(&sql) {
+                Ok(schema) => schema,
+                Err(err) => return Ok(Err(protocol::error_response(&err))),
+            }
+        };
+        match schema {
+            Some(schema) => {
+                let formats = resolve_formats(&result_formats, schema.columns.len());
+                self.send(protocol::row_description(&schema, &formats))?;
+            }
+            None => self.send(Message::new(b'n'))?,
+        }
+        Ok(Ok(()))
+    }
+
+    /// Handles `Execute`: runs a portal, sending at most `max_rows` rows (0 means all).
+    fn execute(&mut self, body: &[u8]) -> io::Result<Result<(), Message>> {
+        let mut body = Reader::new(body);
+        let name = body.cstr()?;
+        let max_rows = body.i32()?;
+
+        let Some(portal) = self.portals.get_mut(&name) else {
+            return Ok(Err(unknown("portal", &name)));
+        };
+
+        if let Some(tag) = &portal.command_tag {
+            let message = protocol::command_complete(tag);
+            self.send(message)?;
+            return Ok(Ok(()));
+        }
+
+        let mut messages = Vec::new();
+        if portal.pending.is_none() {
+            if is_empty_query(&portal.sql) {
+                self.send(Message::new(b'I'))?;
+                return Ok(Ok(()));
+            }
+            match run_statement(self.db, self.statement_lock, &portal.sql, &portal.args) {
+                Ok(StatementResult::Query { rows, .. }) => {
+                    portal.pending = Some(
+                        rows.into_iter().map(|row| row.values).collect(),
+                    );
+                }
+                Ok(result) => {
+                    let tag = command_tag(&result);
+                    portal.command_tag = Some(tag.clone());
+                    self.send(protocol::command_complete(&tag))?;
+                    return Ok(Ok(()));
+                }
+                Err(err) => return Ok(Err(protocol::error_response(&err))),
+            }
+        }
+
+        let pending = portal.pending.as_mut().expect("portal was executed above");
+        let batch = if max_rows > 0 {
+            pending.len().min(max_rows as usize)
+        } else {
+            pending.len()
+        };
+        let rows: Vec<Vec<Value>> = pending.drain(..batch).collect();
+        let formats = resolve_formats(&portal.result_formats, rows.first().map_or(0, Vec::len));
+        for row in &rows {
+            messages.push(protocol::data_row(row, &formats));
+        }
+        portal.rows_sent += rows.len();
+        if pending.is_empty() {
+            messages.push(protocol::command_complete(&format!(
+                "SELECT {}",
+                portal.rows_sent
+            )));
+        } else {
+            messages.push(Message::new(b's'));
+        }
+
+        for message in messages {
+            self.send(message)?;
+        }
+        Ok(Ok(()))
+    }
+
+    /// Handles `Close` of a statement (`S`) or portal (`P`).
+    fn close(&mut self, body: &[u8]) -> io::Result<Result<(), Message>> {
+        let mut body = Reader::new(body);
+        let kind = body.u8()?;
+        let name = body.cstr()?;
+        match kind {
+            b'S' => {
+                self.statements.remove(&name);
+            }
+            b'P' => {
+                self.portals.remove(&name);
+            }
+            _ => return Ok(Err(protocol::protocol_error("invalid close kind"))),
+        }
+        self.send(Message::new(b'3'))?;
+        Ok(Ok(()))
+    }
+}
+
+/// Runs one statement, flushing to disk after anything that writes.
+///
+/// Queries hold `statement_lock` for reading and everything else holds it for writing. The
+/// server has no shutdown hook, so changes are made durable as they happen.
+fn run_statement(
+    db: &Database,
+    statement_lock: &RwLock<()>,
+    sql: &str,
+    args: &[Value],
+) -> query_engine::Result<StatementResult> {
+    if matches!(parse(sql), Ok(Statement::Select(_))) {
+        let _reading = statement_lock.read().unwrap();
+        return db.execute_with(sql, args);
+    }
+
+    let _writing = statement_lock.write().unwrap();
+    let result = db.execute_with(sql, args)?;
+    db.flush()?;
+    Ok(result)
+}
+
+/// Whether a query string contains no statement at all.
+fn is_empty_query(sql: &str) -> bool {
+    split_statements(sql).is_empty()
+}
+
+/// Expands `Bind` result format codes to one format per column.
+///
+/// No codes means all text, a single code applies to every column, and otherwise there is
+/// one code per column.
+fn resolve_formats(codes: &[i16], columns: usize) -> Vec<Format> {
+    let code_for = |i: usize| match codes {
+        [] => 0,
+        [code] => *code,
+        codes => codes.get(i).copied().unwrap_or(0),
+    };
+    (0..columns)
+        .map(|i| Format::from_code(code_for(i)).unwrap_or(Format::Text))
+        .collect()
+}
+
+/// The `CommandComplete` tag Postgres sends for a statement.
+fn command_tag(result: &StatementResult) -> String {
+    match result {
+        StatementResult::Query { rows, .. } => format!("SELECT {}", rows.len()),
+        StatementResult::CreateTable => "CREATE TABLE".to_string(),
+        StatementResult::DropTable => "DROP TABLE".to_string(),
+        StatementResult::Insert(count) => format!("INSERT 0 {}", count),
+        StatementResult::Update(count) => format!("UPDATE {}", count),
+        StatementResult::Delete(count) => format!("DELETE {}", count),
+    }
+}
+
+fn unknown(kind: &str, name: &str) -> Message {
+    protocol::error_response(&QueryError::ExecutionError(format!(
+        "{} \"{}\" does not exist",
+        kind, name
+    )))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Read;
+
+    fn send(stream: &mut TcpStream, tag: u8, body: Message) {
+        let mut encoded = body.encode();
+        encoded[0] = tag;
+        stream.write_all(&encoded).unwrap();
+    }
+
+    /// Reads backend messages up to and including `ReadyForQuery`.
+    fn read_until_ready(stream: &mut TcpStream) -> Vec<(u8, Vec<u8>)> {
+        let mut messages = Vec::new();
+        loop {
+            let message = protocol::read_message(stream).unwrap().unwrap();
+            let done = message.0 == b'Z';
+            messages.push(message);
+            if done {
+                return messages;
+            }
+        }
+    }
+
+    fn tags(messages: &[(u8, Vec<u8>)]) -> String {
+        messages.iter().map(|(tag, _)| *tag as char).collect()
+    }
+
+    /// Splits a `DataRow` into its raw column values.
+    fn columns(body: &[u8]) -> Vec<Option<Vec<u8>>> {
+        let mut reader = Reader::new(body);
+        (0..reader.i16().unwrap())
+            .map(|_| match reader.i32().unwrap() {
+                -1 => None,
+                len => Some(reader.bytes(len as usize).unwrap().to_vec()),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_simple_and_extended_query() {
+        let path = "test_server_dir";
+        let _ = std::fs::remove_dir_all(path);
+        let db = Database::open(path).unwrap();
+        db.execute("CREATE TABLE users (id INTEGER, name VARCHAR(20))")
+            .unwrap();
+        db.execute("INSERT INTO users VALUES (1, 'Alice'), (2, 'Bob')")
+            .unwrap();
+
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let db = Arc::new(db);
+        thread::spawn(move || serve(listener, db));
+
+        let mut stream = TcpStream::connect(addr).unwrap();
+
+        // SSLRequest is declined, then a normal startup packet follows
+        stream.write_all(&8i32.to_be_bytes()).unwrap();
+        stream
+            .write_all(&protocol::SSL_REQUEST.to_be_bytes())
+            .unwrap();
+        let mut answer = [0u8; 1];
+        stream.read_exact(&mut answer).unwrap();
+        assert_eq!(&answer, b"N");
+
+        let startup = Message::new(0)
+            .i32(protocol::PROTOCOL_VERSION)
+            .cstr("user")
+            .cstr("rose")
+            .u8(0)
+            .encode();
+        stream.write_all(&startup[1..]).unwrap();
+        let messages = read_until_ready(&mut stream);
+        assert_eq!(messages[0], (b'R', 0i32.to_be_bytes().to_vec()));
+        assert_eq!(messages.last().unwrap().0, b'Z');
+
+        // Simple query
+        send(
+            &mut stream,
+            b'Q',
+            Message::new(0).cstr("SELECT name FROM users WHERE id > 1"),
+        );
+        let messages = read_until_ready(&mut stream);
+        assert_eq!(tags(&messages), "TDCZ");
+        assert_eq!(columns(&messages[1].1), vec![Some(b"Bob".to_vec())]);
+        assert_eq!(messages[2].1, b"SELECT 1\0");
+
+        send(
+            &mut stream,
+            b'Q',
+            Message::new(0).cstr("SELECT * FROM missing"),
+        );
+        let messages = read_until_ready(&mut stream);
+        assert_eq!(tags(&messages), "EZ");
+
+        send(
+            &mut stream,
+            b'Q',
+            Message::new(0).cstr("INSERT INTO users VALUES (3, 'Carol')"),
+        );
+        let messages = read_until_ready(&mut stream);
+        assert_eq!(tags(&messages), "CZ");
+        assert_eq!(messages[0].1, b"INSERT 0 1\0");
+
+        // Several statements in one query run in order until one fails
+        send(
+            &mut stream,
+            b'Q',
+            Message::new(0).cstr(
+                "UPDATE users SET name = 'x' WHERE id = 99; SELECT name FROM users WHERE id = 2; \
+                 SELECT * FROM missing; DELETE FROM users",
+            ),
+        );
+        let messages = read_until_ready(&mut stream);
+        assert_eq!(tags(&messages), "CTDCEZ");
+        assert_eq!(messages[0].1, b"UPDATE 0\0");
+        assert_eq!(columns(&messages[2].1), vec![Some(b"Bob".to_vec())]);
+
+        // Extended query, fetching one row at a time in binary
+        send(
+            &mut stream,
+            b'P',
+            Message::new(0)
+                .cstr("q")
+                .cstr("SELECT id, name FROM users")
+                .i16(0),
+        );
+        send(
+            &mut stream,
+            b'B',
+            Message::new(0)
+                .cstr("")
+                .cstr("q")
+                .i16(0)
+                .i16(0)
+                .i16(1)
+                .i16(1),
+        );
+        send(&mut stream, b'D', Message::new(0).u8(b'P').cstr(""));
+        send(&mut stream, b'E', Message::new(0).cstr("").i32(2));
+        send(&mut stream, b'E', Message::new(0).cstr("").i32(0));
+        send(&mut stream, b'S', Message::new(0));
+        let messages = read_until_ready(&mut stream);
+        assert_eq!(tags(&messages), "12TDDsDCZ");
+        assert_eq!(
+            columns(&messages[3].1),
+            vec![Some(1i32.to_be_bytes().to_vec()), Some(b"Alice".to_vec())]
+        );
+        // The tag counts every row the portal sent, not just the last batch
+        assert_eq!(messages[7].1, b"SELECT 3\0");
+
+        // Executing a finished portal again repeats its tag without running it again
+        send(
+            &mut stream,
+            b'P',
+            Message::new(0)
+                .cstr("")
+                .cstr("INSERT INTO users VALUES (4, 'Dave')")
+                .i16(0),
+        );
+        send(
+            &mut stream,
+            b'B',
+            Message::new(0).cstr("").cstr("").i16(0).i16(0).i16(0),
+        );
+        send(&mut stream, b'E', Message::new(0).cstr("").i32(0));
+        send(&mut stream, b'E', Message::new(0).cstr("").i32(0));
+        send(&mut stream, b'S', Message::new(0));
+        let messages = read_until_ready(&mut stream);
+        assert_eq!(tags(&messages), "12CCZ");
+        assert_eq!(messages[2].1, b"INSERT 0 1\0");
+        assert_eq!(messages[3].1, b"INSERT 0 1\0");
+
+        // Parameters are typed from context and sent as values
+        send(
+            &mut stream,
+            b'P',
+            Message::new(0)
+                .cstr("by_id")
+                .cstr("SELECT name FROM users WHERE id = $1")
+                .i16(0),
+        );
+        send(&mut stream, b'D', Message::new(0).u8(b'S').cstr("by_id"));
+        send(
+            &mut stream,
+            b'B',
+            Message::new(0)
+                .cstr("")
+                .cstr("by_id")
+                .i16(0)
+                .i16(1)
+                .i32(1)
+                .bytes(b"2")
+                .i16(0),
+        );
+        send(&mut stream, b'E', Message::new(0).cstr("").i32(0));
+        send(&mut stream, b'S', Message::new(0));
+        let messages = read_until_ready(&mut stream);
+        assert_eq!(tags(&messages), "1tT2DCZ");
+        let mut description = Reader::new(&messages[1].1);
+        assert_eq!(description.i16().unwrap(), 1);
+        assert_eq!(description.i32().unwrap(), protocol::INT4_OID);
+        assert_eq!(columns(&messages[4].1), vec![Some(b"Bob".to_vec())]);
+
+        // An error skips the rest of the batch until Sync
+        send(
+            &mut stream,
+            b'P',
+            Message::new(0).cstr("").cstr("SELEC 1").i16(0),
+        );
+        send(
+            &mut stream,
+            b'B',
+            Message::new(0).cstr("").cstr("").i16(0).i16(0).i16(0),
+        );
+        send(&mut stream, b'S', Message::new(0));
+        let messages = read_until_ready(&mut stream);
+        assert_eq!(tags(&messages), "EZ");
+
+        send(&mut stream, b'X', Message::new(0));
+        std::fs::remove_dir_all(path).unwrap();
+    }
+}
@@ -0,0 +1,349 @@
+//! Framing and encoding for PostgreSQL v3 protocol messages.
+//!
+//! Every message after startup is a one-byte type tag followed by an `Int32` length that
+//! counts itself and the body. The startup packet has no tag. All integers are big-endian
+//! and strings are NUL-terminated.
+
+use query_engine::{QueryError, Schema, Type, Value};
+use std::io::{self, Read};
+
+/// Protocol version 3.0, sent in a normal startup packet.
+pub const PROTOCOL_VERSION: i32 = 196608;
+/// Request code a client sends to ask for TLS before the real startup packet.
+pub const SSL_REQUEST: i32 = 80877103;
+/// Request code a client sends to ask for GSSAPI encryption.
+pub const GSSENC_REQUEST: i32 = 80877104;
+/// Request code for cancelling a query on another connection.
+pub const CANCEL_REQUEST: i32 = 80877102;
+
+/// Type OID of `int8`.
+pub const INT8_OID: i32 = 20;
+/// Type OID of `int2`.
+pub const INT2_OID: i32 = 21;
+/// Type OID of `int4`.
+pub const INT4_OID: i32 = 23;
+/// Type OID of `varchar`.
+pub const VARCHAR_OID: i32 = 1043;
+
+/// Upper bound on a single message, to stop a bad length from allocating unbounded memory.
+const MAX_MESSAGE_LEN: usize = 64 * 1024 * 1024;
+
+/// Wire format of a parameter or result column.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    Text,
+    Binary,
+}
+
+impl Format {
+    pub fn from_code(code: i16) -> io::Result<Self> {
+        match code {
+            0 => Ok(Format::Text),
+            1 => Ok(Format::Binary),
+            _ => Err(invalid(format!("unknown format code {}", code))),
+        }
+    }
+
+    fn code(self) -> i16 {
+        match self {
+            Format::Text => 0,
+            Format::Binary => 1,
+        }
+    }
+}
+
+/// Reads the untagged startup packet, returning its body after the length word.
+pub fn read_startup_packet(stream: &mut impl Read) -> io::Result<Vec<u8>> {
+    let len = read_i32(stream)?;
+    read_body(stream, len)
+}
+
+/// Reads one tagged frontend message. Returns `None` if the client closed the connection.
+pub fn read_message(stream: &mut impl Read) -> io::Result<Option<(u8, Vec<u8>)>> {
+    let mut tag = [0u8; 1];
+    if stream.read(&mut tag)? == 0 {
+        return Ok(None);
+    }
+    let len = read_i32(stream)?;
+    Ok(Some((tag[0], read_body(stream, len)?)))
+}
+
+fn read_i32(stream: &mut impl Read) -> io::Result<i32> {
+    let mut buf = [0u8; 4];
+    stream.read_exact(&mut buf)?;
+    Ok(i32::from_be_bytes(buf))
+}
+
+fn read_body(stream: &mut impl Read, len: i32) -> io::Result<Vec<u8>> {
+    let len = usize::try_from(len)
+        .ok()
+        .and_then(|len| len.checked_sub(4))
+        .filter(|len| *len <= MAX_MESSAGE_LEN)
+        .ok_or_else(|| invalid(format!("invalid message length {}", len)))?;
+    let mut body = vec![0u8; len];
+    stream.read_exact(&mut body)?;
+    Ok(body)
+}
+
+fn invalid(msg: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg)
+}
+
+/// Cursor over the body of a frontend message.
+pub struct Reader<'a> {
+    buf: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Reader<'a> {
+    pub fn new(buf: &'a [u8]) -> Self {
+        Self { buf, pos: 0 }
+    }
+
+    pub fn u8(&mut self) -> io::Result<u8> {
+        Ok(self.bytes(1)?[0])
+    }
+
+    pub fn i16(&mut self) -> io::Result<i16> {
+        let bytes = self.bytes(2)?;
+        Ok(i16::from_be_bytes([bytes[0], bytes[1]]))
+    }
+
+    pub fn i32(&mut self) -> io::Result<i32> {
+        let bytes = self.bytes(4)?;
+        Ok(i32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+    }
+
+    pub fn bytes(&mut self, n: usize) -> io::Result<&'a [u8]> {
+        if self.buf.len() - self.pos < n {
+            return Err(invalid("message is truncated".to_string()));
+        }
+        let bytes = &self.buf[self.pos..self.pos + n];
+        self.pos += n;
+        Ok(bytes)
+    }
+
+    /// Reads a NUL-terminated string.
+    pub fn cstr(&mut self) -> io::Result<String> {
+        let rest = &self.buf[self.pos..];
+        let end = rest
+            .iter()
+            .position(|b| *b == 0)
+            .ok_or_else(|| invalid("unterminated string".to_string()))?;
+        let s = String::from_utf8(rest[..end].to_vec())
+            .map_err(|_| invalid("string is not valid UTF-8".to_string()))?;
+        self.pos += end + 1;
+        Ok(s)
+    }
+}
+
+/// A backend message being built up before it is framed.
+pub struct Message {
+    tag: u8,
+    body: Vec<u8>,
+}
+
+impl Message {
+    pub fn new(tag: u8) -> Self {
+        Self {
+            tag,
+            body: Vec::new(),
+        }
+    }
+
+    pub fn u8(mut self, value: u8) -> Self {
+        self.body.push(value);
+        self
+    }
+
+    pub fn i16(mut self, value: i16) -> Self {
+        self.body.extend_from_slice(&value.to_be_bytes());
+        self
+    }
+
+    pub fn i32(mut self, value: i32) -> Self {
+        self.body.extend_from_slice(&value.to_be_bytes());
+        self
+    }
+
+    pub fn bytes(mut self, value: &[u8]) -> Self {
+        self.body.extend_from_slice(value);
+        self
+    }
+
+    pub fn cstr(mut self, value: &str) -> Self {
+        self.body.extend_from_slice(value.as_bytes());
+        self.body.push(0);
+        self
+    }
+
+    /// Returns the framed message: tag, length and body.
+    pub fn encode(&self) -> Vec<u8> {
+        let mut out = Vec::with_capacity(self.body.len() + 5);
+        out.push(self.tag);
+        out.extend_from_slice(&(self.body.len() as i32 + 4).to_be_bytes());
+        out.extend_from_slice(&self.body);
+        out
+    }
+}
+
+/// Decodes a `Bind` parameter of the given type OID. `None` is SQL NULL.
+///
+/// Integer types become [`Value::Integer`] (rejecting values outside `i32`); everything else
+/// is taken as text.
+pub fn decode_parameter(
+    raw: Option<&[u8]>,
+    type_oid: i32,
+    format: Format,
+) -> Result<Value, QueryError> {
+    let Some(raw) = raw else {
+        return Ok(Value::Null);
+    };
+    let invalid = |what: &str| QueryError::TypeMismatch(format!("invalid {} parameter", what));
+
+    match (type_oid, format) {
+        (INT2_OID | INT4_OID | INT8_OID, Format::Text) => {
+            let text = std::str::from_utf8(raw).map_err(|_| invalid("integer"))?;
+            let value: i64 = text.trim().parse().map_err(|_| invalid("integer"))?;
+            i32::try_from(value)
+                .map(Value::Integer)
+                .map_err(|_| invalid("integer"))
+        }
+        (INT2_OID | INT4_OID | INT8_OID, Format::Binary) => {
+            let value = match *raw {
+                [a, b] => i16::from_be_bytes([a, b]) as i64,
+                [a, b, c, d] => i32::from_be_bytes([a, b, c, d]) as i64,
+                [a, b, c, d, e, f, g, h] => i64::from_be_bytes([a, b, c, d, e, f, g, h]),
+                _ => return Err(invalid("integer")),
+            };
+            i32::try_from(value)
+                .map(Value::Integer)
+                .map_err(|_| invalid("integer"))
+        }
+        _ => String::from_utf8(raw.to_vec())
+            .map(Value::Varchar)
+            .map_err(|_| invalid("text")),
+    }
+}
+
+/// `AuthenticationOk`.
+pub fn authentication_ok() -> Message {
+    Message::new(b'R').i32(0)
+}
+
+/// `ParameterStatus`, reporting a server setting to the client.
+pub fn parameter_status(name: &str, value: &str) -> Message {
+    Message::new(b'S').cstr(name).cstr(value)
+}
+
+/// `BackendKeyData`, the key a client would use to cancel a query.
+pub fn backend_key_data(process_id: i32, secret: i32) -> Message {
+    Message::new(b'K').i32(process_id).i32(secret)
+}
+
+/// `ReadyForQuery`, reporting that the server is idle outside a transaction.
+pub fn ready_for_query() -> Message {
+    Message::new(b'Z').u8(b'I')
+}
+
+/// `RowDescription` for a result schema, with one format per column.
+pub fn row_description(schema: &Schema, formats: &[Format]) -> Message {
+    let mut msg = Message::new(b'T').i16(schema.columns.len() as i16);
+    for (column, format) in schema.columns.iter().zip(formats) {
+        let (type_oid, type_size) = match column.column_type {
+            Type::Integer => (INT4_OID, 4),
+            Type::Varchar => (VARCHAR_OID, -1),
+        };
+        msg = msg
+            .cstr(&column.name)
+            .i32(0) // table OID
+            .i16(0) // column attribute number
+            .i32(type_oid)
+            .i16(type_size)
+            .i32(-1) // type modifier
+            .i16(format.code());
+    }
+    msg
+}
+
+/// `DataRow`, encoding each value in its column's format. NULL is sent as length -1.
+pub fn data_row(values: &[Value], formats: &[Format]) -> Message {
+    let mut msg = Message::new(b'D').i16(values.len() as i16);
+    for (value, format) in values.iter().zip(formats) {
+        let encoded = match (value, format) {
+            (Value::Null, _) => None,
+            (Value::Integer(i), Format::Binary) => Some(i.to_be_bytes().to_vec()),
+            (Value::Varchar(s), Format::Binary) => Some(s.as_bytes().to_vec()),
+            (value, Format::Text) => Some(value.to_string().into_bytes()),
+        };
+        msg = match encoded {
+            Some(bytes) => msg.i32(bytes.len() as i32).bytes(&bytes),
+            None => msg.i32(-1),
+        };
+    }
+    msg
+}
+
+/// `CommandComplete` with a command tag such as `SELECT 3`.
+pub fn command_complete(tag: &str) -> Message {
+    Message::new(b'C').cstr(tag)
+}
+
+/// `ErrorResponse` for a failed statement.
+pub fn error_response(err: &QueryError) -> Message {
+    Message::new(b'E')
+        .u8(b'S')
+        .cstr("ERROR")
+        .u8(b'V')
+        .cstr("ERROR")
+        .u8(b'C')
+        .cstr(sqlstate(err))
+        .u8(b'M')
+        .cstr(&err.to_string())
+        .u8(0)
+}
+
+/// `ErrorResponse` for a malformed or unsupported message.
+pub fn protocol_error(msg: &str) -> Message {
+    Message::new(b'E')
+        .u8(b'S')
+        .cstr("ERROR")
+        .u8(b'V')
+        .cstr("ERROR")
+        .u8(b'C')
+        .cstr("08P01") // protocol_violation
+        .u8(b'M')
+        .cstr(msg)
+        .u8(0)
+}
+
+/// Maps a query error onto the closest PostgreSQL SQLSTATE code.
+fn sqlstate(err: &QueryError) -> &'static str {
+    match err {
+        QueryError::TableNotFound { .. } => "42P01",  // undefined_table
+        QueryError::ColumnNotFound { .. } => "42703", // undefined_column
+        QueryError::TypeMismatch(_) => "42804",       // datatype_mismatch
+        QueryError::ParseError { .. } => "42601",     // syntax_error
+        _ => "XX000",                                 // internal_error
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_message_framing() {
+        let encoded = command_complete("SELECT 1").encode();
+        assert_eq!(encoded[0], b'C');
+        assert_eq!(&encoded[1..5], &13i32.to_be_bytes());
+        assert_eq!(&encoded[5..], b"SELECT 1\0");
+
+        let body = read_message(&mut encoded.as_slice()).unwrap().unwrap();
+        assert_eq!(body, (b'C', b"SELECT 1\0".to_vec()));
+
+        let mut reader = Reader::new(&body.1);
+        assert_eq!(reader.cstr().unwrap(), "SELECT 1");
+        assert!(reader.u8().is_err());
+    }
+}
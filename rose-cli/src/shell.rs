@@ -0,0 +1,196 @@
+//! The interactive read-eval-print loop.
+
+use crate::table;
+use query_engine::{Database, StatementResult, Type, Value};
+use rustyline::error::ReadlineError;
+use rustyline::DefaultEditor;
+use std::time::Instant;
+
+const PROMPT: &str = "rose> ";
+const CONTINUATION_PROMPT: &str = "   -> ";
+
+const HELP: &str = "\
+SQL statements end with ';' and may span several lines.
+
+Meta-commands:
+  \\dt          list tables
+  \\d TABLE     describe a table's columns
+  \\timing      toggle reporting of statement execution time
+  \\flush       write all dirty pages to disk
+  \\?           show this help
+  \\q           quit";
+
+/// An interactive session against one open database.
+pub struct Shell<'a> {
+    db: &'a Database,
+    timing: bool,
+}
+
+/// What the loop should do after a meta-command.
+enum Control {
+    Continue,
+    Quit,
+}
+
+impl<'a> Shell<'a> {
+    pub fn new(db: &'a Database) -> Self {
+        Self { db, timing: false }
+    }
+
+    /// Runs the prompt loop until `\q` or end of input.
+    pub fn run(&mut self) -> Result<(), ReadlineError> {
+        let mut editor = DefaultEditor::new()?;
+        let mut buffer = String::new();
+
+        println!("rose-db shell. Type \\? for help, \\q to quit.");
+
+        loop {
+            let prompt = if buffer.is_empty() {
+                PROMPT
+            } else {
+                CONTINUATION_PROMPT
+            };
+
+            let line = match editor.readline(prompt) {
+                Ok(line) => line,
+                Err(ReadlineError::Interrupted) => {
+                    // Ctrl-C discards the statement being typed
+                    buffer.clear();
+                    continue;
+                }
+                Err(ReadlineError::Eof) => break,
+                Err(err) => return Err(err),
+            };
+
+            let trimmed = line.trim();
+            if trimmed.is_empty() {
+                continue;
+            }
+            editor.add_history_entry(trimmed)?;
+
+            if buffer.is_empty() && trimmed.starts_with('\\') {
+                match self.run_meta_command(trimmed) {
+                    Control::Continue => continue,
+                    Control::Quit => break,
+                }
+            }
+
+            if !buffer.is_empty() {
+                buffer.push('\n');
+            }
+            buffer.push_str(&line);
+
+            if trimmed.ends_with(';') {
+                self.run_statement(&buffer);
+                buffer.clear();
+            }
+        }
+
+        self.flush();
+        Ok(())
+    }
+
+    /// Executes one SQL statement and prints its result or error.
+    fn run_statement(&self, sql: &str) {
+        let start = Instant::now();
+        let result = self.db.execute(sql);
+        let elapsed = start.elapsed();
+
+        match result {
+            Ok(result) => print_result(&result),
+            Err(err) => {
+                eprintln!("ERROR: {}", err);
+                if let Some(location) = err.location() {
+                    eprintln!("{}", location);
+                }
+            }
+        }
+
+        if self.timing {
+            println!("Time: {:.3} ms", elapsed.as_secs_f64() * 1000.0);
+        }
+    }
+
+    fn run_meta_command(&mut self, line: &str) -> Control {
+        let mut parts = line.split_whitespace();
+        let command = parts.next().unwrap_or_default();
+        let argument = parts.next();
+
+        match (command, argument) {
+            ("\\q", _) => return Control::Quit,
+            ("\\?", _) => println!("{}", HELP),
+            ("\\dt", _) => self.list_tables(),
+            ("\\d", Some(name)) => self.describe_table(name),
+            ("\\d", None) => self.list_tables(),
+            ("\\timing", _) => {
+                self.timing = !self.timing;
+                println!("Timing is {}.", if self.timing { "on" } else { "off" });
+            }
+            ("\\flush", _) => self.flush(),
+            _ => eprintln!("Invalid command {}. Try \\? for help.", command),
+        }
+        Control::Continue
+    }
+
+    fn list_tables(&self) {
+        let mut tables = self.db.list_tables();
+        tables.sort();
+
+        let headers = vec!["name".to_string()];
+        let rows: Vec<Vec<Value>> = tables
+            .into_iter()
+            .map(|t| vec![Value::Varchar(t)])
+            .collect();
+        print!("{}", table::render(&headers, &rows));
+    }
+
+    fn describe_table(&self, name: &str) {
+        let schema = match self.db.table_schema(name) {
+            Ok(schema) => schema,
+            Err(err) => {
+                eprintln!("ERROR: {}", err);
+                return;
+            }
+        };
+
+        let headers = vec!["column".to_string(), "type".to_string()];
+        let rows: Vec<Vec<Value>> = schema
+            .columns
+            .iter()
+            .map(|column| {
+                let type_name = match column.column_type {
+                    Type::Integer => "INTEGER".to_string(),
+                    Type::Varchar => format!("VARCHAR({})", column.length),
+                };
+                vec![
+                    Value::Varchar(column.name.clone()),
+                    Value::Varchar(type_name),
+                ]
+            })
+            .collect();
+        println!("Table \"{}\"", name);
+        print!("{}", table::render(&headers, &rows));
+    }
+
+    fn flush(&self) {
+        if let Err(err) = self.db.flush() {
+            eprintln!("ERROR: {}", err);
+        }
+    }
+}
+
+/// Prints a statement result the way `psql` reports command completion.
+pub fn print_result(result: &StatementResult) {
+    match result {
+        StatementResult::Query { schema, rows } => {
+            let headers: Vec<String> = schema.columns.iter().map(|c| c.name.clone()).collect();
+            let rows: Vec<Vec<Value>> = rows.iter().map(|row| row.values.clone()).collect();
+            print!("{}", table::render(&headers, &rows));
+        }
+        StatementResult::CreateTable => println!("CREATE TABLE"),
+        StatementResult::DropTable => println!("DROP TABLE"),
+        StatementResult::Insert(count) => println!("INSERT 0 {}", count),
+        StatementResult::Update(count) => println!("UPDATE {}", count),
+        StatementResult::Delete(count) => println!("DELETE {}", count),
+    }
+}
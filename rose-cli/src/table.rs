@@ -0,0 +1,78 @@
+//! Aligned plain-text tables for query results.
+
+use query_engine::Value;
+
+/// Renders rows as an aligned table in the style of `psql`.
+///
+/// Integers are right-aligned, everything else is left-aligned. The output ends with a
+/// row-count footer such as `(3 rows)`.
+pub fn render(headers: &[String], rows: &[Vec<Value>]) -> String {
+    let cells: Vec<Vec<String>> = rows
+        .iter()
+        .map(|row| row.iter().map(|value| value.to_string()).collect())
+        .collect();
+
+    let mut widths: Vec<usize> = headers.iter().map(|h| h.chars().count()).collect();
+    for row in &cells {
+        for (width, cell) in widths.iter_mut().zip(row) {
+            *width = (*width).max(cell.chars().count());
+        }
+    }
+
+    let mut out = String::new();
+
+    let header_line: Vec<String> = headers
+        .iter()
+        .zip(&widths)
+        .map(|(header, width)| format!("{:<width$}", header, width = width))
+        .collect();
+    push_line(&mut out, &format!(" {}", header_line.join(" | ")));
+
+    let separator: Vec<String> = widths.iter().map(|width| "-".repeat(width + 2)).collect();
+    push_line(&mut out, &separator.join("+"));
+
+    for (row, values) in cells.iter().zip(rows) {
+        let line: Vec<String> = row
+            .iter()
+            .zip(values)
+            .zip(&widths)
+            .map(|((cell, value), width)| match value {
+                Value::Integer(_) => format!("{:>width$}", cell, width = width),
+                _ => format!("{:<width$}", cell, width = width),
+            })
+            .collect();
+        push_line(&mut out, &format!(" {}", line.join(" | ")));
+    }
+
+    let noun = if rows.len() == 1 { "row" } else { "rows" };
+    out.push_str(&format!("({} {})\n", rows.len(), noun));
+    out
+}
+
+fn push_line(out: &mut String, line: &str) {
+    out.push_str(line.trim_end());
+    out.push('\n');
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render_aligns_columns() {
+        let headers = vec!["id".to_string(), "name".to_string()];
+        let rows = vec![
+            vec![Value::Integer(1), Value::Varchar("Alice".to_string())],
+            vec![Value::Integer(100), Value::Null],
+        ];
+
+        let expected = concat!(
+            " id  | name\n",
+            "-----+-------\n",
+            "   1 | Alice\n",
+            " 100 | NULL\n",
+            "(2 rows)\n",
+        );
+        assert_eq!(render(&headers, &rows), expected);
+    }
+}